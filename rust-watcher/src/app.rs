@@ -0,0 +1,148 @@
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::c_char;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSString;
+use core_foundation::runloop::{CFRunLoop, CFRunLoopRunResult, kCFRunLoopDefaultMode};
+use objc::{class, msg_send, sel, sel_impl};
+
+/// NSApplicationActivationPolicyRegular: apps with a Dock icon and windows
+const ACTIVATION_POLICY_REGULAR: i64 = 0;
+
+/// userInfo key of the NSRunningApplication in NSWorkspace notifications
+const APPLICATION_KEY: &str = "NSWorkspaceApplicationKey";
+
+/// A running application picked by name or bundle identifier rather than by PID
+#[derive(Debug, Clone)]
+pub enum AppSelector {
+    Name(String),
+    BundleId(String),
+}
+
+impl AppSelector {
+    /// PID of a running instance, preferring regular apps over agents sharing the name
+    pub fn find_running(&self) -> Option<u32> {
+        let mut fallback = None;
+        unsafe {
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let apps: id = msg_send![workspace, runningApplications];
+            let count: usize = msg_send![apps, count];
+            for index in 0..count {
+                let app: id = msg_send![apps, objectAtIndex: index];
+                if !self.matches(app) {
+                    continue;
+                }
+
+                let pid: i32 = msg_send![app, processIdentifier];
+                if pid <= 0 {
+                    continue;
+                }
+
+                let policy: i64 = msg_send![app, activationPolicy];
+                if policy == ACTIVATION_POLICY_REGULAR {
+                    return Some(pid as u32);
+                }
+                fallback.get_or_insert(pid as u32);
+            }
+        }
+
+        fallback
+    }
+
+    /// Blocks until the app is running, checking every `interval`
+    pub fn wait_until_running(&self, interval: Duration) -> u32 {
+        let mut announced = false;
+        loop {
+            if let Some(pid) = self.find_running() {
+                return pid;
+            }
+
+            if !announced {
+                println!("Waiting for {} to launch...", self);
+                announced = true;
+            }
+            idle(interval);
+        }
+    }
+
+    unsafe fn matches(&self, app: id) -> bool {
+        match self {
+            AppSelector::Name(name) => {
+                let localized: id = unsafe { msg_send![app, localizedName] };
+                ns_string(localized).is_some_and(|value| value.eq_ignore_ascii_case(name))
+            }
+            AppSelector::BundleId(bundle_id) => {
+                let identifier: id = unsafe { msg_send![app, bundleIdentifier] };
+                ns_string(identifier).is_some_and(|value| value.eq_ignore_ascii_case(bundle_id))
+            }
+        }
+    }
+}
+
+impl fmt::Display for AppSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppSelector::Name(name) => write!(f, "'{}'", name),
+            AppSelector::BundleId(bundle_id) => write!(f, "bundle {}", bundle_id),
+        }
+    }
+}
+
+/// Sleeps on the main run loop, which NSWorkspace needs to refresh its running apps and
+/// deliver notifications
+pub fn idle(timeout: Duration) {
+    let started = Instant::now();
+    let result = CFRunLoop::run_in_mode(unsafe { kCFRunLoopDefaultMode }, timeout, false);
+    // Without any input source the run loop returns immediately
+    if let CFRunLoopRunResult::Finished = result {
+        thread::sleep(timeout.saturating_sub(started.elapsed()));
+    }
+}
+
+/// NSWorkspace's own notification center, which app launches and activations are posted to
+pub unsafe fn notification_center() -> id {
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        msg_send![workspace, notificationCenter]
+    }
+}
+
+/// PID of the application an NSWorkspace notification is about
+pub unsafe fn notification_pid(notification: id) -> Option<u32> {
+    unsafe {
+        let user_info: id = msg_send![notification, userInfo];
+        if user_info == nil {
+            return None;
+        }
+
+        let key = NSString::alloc(nil).init_str(APPLICATION_KEY);
+        let app: id = msg_send![user_info, objectForKey: key];
+        let _: () = msg_send![key, release];
+        if app == nil {
+            return None;
+        }
+
+        let pid: i32 = msg_send![app, processIdentifier];
+        (pid > 0).then_some(pid as u32)
+    }
+}
+
+fn ns_string(value: id) -> Option<String> {
+    if value == nil {
+        return None;
+    }
+
+    let utf8: *const c_char = unsafe { value.UTF8String() };
+    if utf8.is_null() {
+        return None;
+    }
+
+    Some(
+        unsafe { CStr::from_ptr(utf8) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::os::raw::c_void;
+use std::path::Path;
+
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::boolean::CFBoolean;
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::string::{CFString, CFStringRef};
+use serde_json::json;
+
+type AXUIElementRef = *const c_void;
+type AXError = i32;
+
+const AX_ERROR_SUCCESS: AXError = 0;
+
+/// Longest selection kept, so selecting a whole document doesn't bloat every capture
+const MAX_SELECTED_TEXT: usize = 2000;
+
+/// How long an unresponsive app may keep an attribute read waiting, in seconds
+const MESSAGING_TIMEOUT: f32 = 0.5;
+
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn AXIsProcessTrustedWithOptions(options: CFDictionaryRef) -> bool;
+    fn AXUIElementCreateApplication(pid: i32) -> AXUIElementRef;
+    fn AXUIElementSetMessagingTimeout(element: AXUIElementRef, timeout: f32) -> AXError;
+    fn AXUIElementCopyAttributeValue(
+        element: AXUIElementRef,
+        attribute: CFStringRef,
+        value: *mut CFTypeRef,
+    ) -> AXError;
+}
+
+/// What the Accessibility API reports about the tracked app at the time of a capture
+#[derive(Debug, Clone, Default)]
+pub struct AxContext {
+    pub pid: u32,
+    pub window_title: Option<String>,
+    /// e.g. AXTextArea or AXButton
+    pub focused_role: Option<String>,
+    pub focused_subrole: Option<String>,
+    pub selected_text: Option<String>,
+}
+
+impl AxContext {
+    /// Writes the context as JSON next to `image_path`, with its extension replaced
+    pub fn save_next_to(&self, image_path: &Path) -> Result<(), String> {
+        let value = json!({
+            "pid": self.pid,
+            "window_title": self.window_title,
+            "focused_role": self.focused_role,
+            "focused_subrole": self.focused_subrole,
+            "selected_text": self.selected_text,
+        });
+        let text = serde_json::to_string_pretty(&value)
+            .map_err(|err| format!("Unable to serialize AX context: {}", err))?;
+
+        fs::write(image_path.with_extension("json"), text)
+            .map_err(|err| format!("Unable to save AX context: {}", err))
+    }
+}
+
+impl std::fmt::Display for AxContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "window '{}', focused {}",
+            self.window_title.as_deref().unwrap_or("?"),
+            self.focused_role.as_deref().unwrap_or("nothing")
+        )?;
+        if let Some(text) = &self.selected_text {
+            write!(f, ", {} characters selected", text.chars().count())?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether this process may read other apps' UI, asking the user to allow it with
+/// `prompt`
+pub fn ensure_trusted(prompt: bool) -> bool {
+    let options = CFDictionary::from_CFType_pairs(&[(
+        CFString::new("AXTrustedCheckOptionPrompt").as_CFType(),
+        CFBoolean::from(prompt).as_CFType(),
+    )]);
+
+    unsafe { AXIsProcessTrustedWithOptions(options.as_concrete_TypeRef()) }
+}
+
+/// Reads the focused window title, focused element and selection of `pid`; whatever the
+/// app doesn't expose, or everything without Accessibility permission, is left `None`
+pub fn read_context(pid: u32) -> AxContext {
+    let mut context = AxContext {
+        pid,
+        ..Default::default()
+    };
+
+    let raw = unsafe { AXUIElementCreateApplication(pid as i32) };
+    if raw.is_null() {
+        return context;
+    }
+    let app = unsafe { CFType::wrap_under_create_rule(raw as CFTypeRef) };
+    unsafe {
+        AXUIElementSetMessagingTimeout(app.as_CFTypeRef(), MESSAGING_TIMEOUT);
+    }
+
+    if let Some(window) = copy_attribute(&app, "AXFocusedWindow") {
+        context.window_title = copy_string(&window, "AXTitle");
+    }
+
+    if let Some(element) = copy_attribute(&app, "AXFocusedUIElement") {
+        context.focused_role = copy_string(&element, "AXRole");
+        context.focused_subrole = copy_string(&element, "AXSubrole");
+        context.selected_text = copy_string(&element, "AXSelectedText")
+            .filter(|text| !text.is_empty())
+            .map(|text| truncate(text, MAX_SELECTED_TEXT));
+    }
+
+    context
+}
+
+fn copy_attribute(element: &CFType, attribute: &str) -> Option<CFType> {
+    let attribute = CFString::new(attribute);
+    let mut value: CFTypeRef = std::ptr::null();
+    let result = unsafe {
+        AXUIElementCopyAttributeValue(
+            element.as_CFTypeRef(),
+            attribute.as_concrete_TypeRef(),
+            &mut value,
+        )
+    };
+
+    if result != AX_ERROR_SUCCESS || value.is_null() {
+        return None;
+    }
+
+    Some(unsafe { CFType::wrap_under_create_rule(value) })
+}
+
+fn copy_string(element: &CFType, attribute: &str) -> Option<String> {
+    copy_attribute(element, attribute)?
+        .downcast::<CFString>()
+        .map(|value| value.to_string())
+}
+
+fn truncate(mut text: String, max_chars: usize) -> String {
+    if let Some((index, _)) = text.char_indices().nth(max_chars) {
+        text.truncate(index);
+        text.push('…');
+    }
+    text
+}
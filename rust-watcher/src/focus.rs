@@ -0,0 +1,142 @@
+use std::sync::Once;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSString;
+use core_foundation::runloop::{CFRunLoop, CFRunLoopRunResult, kCFRunLoopDefaultMode};
+use objc::declare::ClassDecl;
+use objc::runtime::{Class, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+
+use crate::app;
+
+const ACTIVATION_NOTIFICATION: &str = "NSWorkspaceDidActivateApplicationNotification";
+
+/// PID of the most recently activated application, or 0 before the first activation
+static ACTIVATED_PID: AtomicU32 = AtomicU32::new(0);
+
+/// Follows the frontmost application through NSWorkspace activation notifications.
+///
+/// Notifications are delivered on the main run loop, so `wait` must be called from the
+/// main thread in place of sleeping.
+pub struct FocusTracker {
+    observer: id,
+    pid: u32,
+}
+
+impl FocusTracker {
+    pub fn start() -> Result<Self, String> {
+        let pid = frontmost_pid().ok_or("No application is frontmost")?;
+
+        let observer = unsafe {
+            let observer: id = msg_send![observer_class(), new];
+            let center = app::notification_center();
+            let name = NSString::alloc(nil).init_str(ACTIVATION_NOTIFICATION);
+            let _: () = msg_send![center, addObserver: observer
+                                          selector: sel!(applicationActivated:)
+                                              name: name
+                                            object: nil];
+            let _: () = msg_send![name, release];
+            observer
+        };
+
+        Ok(Self { observer, pid })
+    }
+
+    /// PID of the application currently in front
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Waits up to `timeout` while delivering notifications, returning the new frontmost
+    /// PID as soon as another application is activated
+    pub fn wait(&mut self, timeout: Duration) -> Option<u32> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+
+            let result = CFRunLoop::run_in_mode(unsafe { kCFRunLoopDefaultMode }, remaining, false);
+            if let Some(pid) = self.take_activation() {
+                return Some(pid);
+            }
+
+            match result {
+                CFRunLoopRunResult::TimedOut => break,
+                // Without any input source the run loop returns immediately
+                CFRunLoopRunResult::Finished => {
+                    thread::sleep(deadline.saturating_duration_since(Instant::now()));
+                    break;
+                }
+                // Another observer stopped the run loop to have its event handled
+                CFRunLoopRunResult::Stopped => break,
+                CFRunLoopRunResult::HandledSource => {}
+            }
+        }
+
+        self.take_activation()
+    }
+
+    fn take_activation(&mut self) -> Option<u32> {
+        let pid = ACTIVATED_PID.load(Ordering::SeqCst);
+        if pid == 0 || pid == self.pid {
+            return None;
+        }
+
+        self.pid = pid;
+        Some(pid)
+    }
+}
+
+impl Drop for FocusTracker {
+    fn drop(&mut self) {
+        unsafe {
+            let _: () = msg_send![app::notification_center(), removeObserver: self.observer];
+            let _: () = msg_send![self.observer, release];
+        }
+    }
+}
+
+fn frontmost_pid() -> Option<u32> {
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let app: id = msg_send![workspace, frontmostApplication];
+        if app == nil {
+            return None;
+        }
+
+        let pid: i32 = msg_send![app, processIdentifier];
+        (pid > 0).then_some(pid as u32)
+    }
+}
+
+fn observer_class() -> &'static Class {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        let mut decl = ClassDecl::new("WatcherFocusObserver", class!(NSObject))
+            .expect("WatcherFocusObserver is registered once");
+        unsafe {
+            decl.add_method(
+                sel!(applicationActivated:),
+                application_activated as extern "C" fn(&Object, Sel, id),
+            );
+        }
+        decl.register();
+    });
+
+    class!(WatcherFocusObserver)
+}
+
+extern "C" fn application_activated(_this: &Object, _cmd: Sel, notification: id) {
+    let Some(pid) = (unsafe { app::notification_pid(notification) }) else {
+        return;
+    };
+    ACTIVATED_PID.store(pid, Ordering::SeqCst);
+
+    // Ends the current wait so capture switches to the new app right away
+    CFRunLoop::get_current().stop();
+}
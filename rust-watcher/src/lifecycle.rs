@@ -0,0 +1,254 @@
+use std::fmt;
+use std::sync::{Mutex, Once};
+use std::time::{Duration, SystemTime};
+
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSString;
+use core_foundation::runloop::CFRunLoop;
+use objc::declare::ClassDecl;
+use objc::runtime::{Class, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+
+use crate::{app, format_gap};
+
+const LAUNCH_NOTIFICATION: &str = "NSWorkspaceDidLaunchApplicationNotification";
+const TERMINATE_NOTIFICATION: &str = "NSWorkspaceDidTerminateApplicationNotification";
+const WILL_SLEEP_NOTIFICATION: &str = "NSWorkspaceWillSleepNotification";
+const DID_WAKE_NOTIFICATION: &str = "NSWorkspaceDidWakeNotification";
+const SCREENS_SLEEP_NOTIFICATION: &str = "NSWorkspaceScreensDidSleepNotification";
+const SCREENS_WAKE_NOTIFICATION: &str = "NSWorkspaceScreensDidWakeNotification";
+const SPACE_NOTIFICATION: &str = "NSWorkspaceActiveSpaceDidChangeNotification";
+
+/// Events not yet taken by `LifecycleWatcher::drain`
+static EVENTS: Mutex<Vec<LifecycleEvent>> = Mutex::new(Vec::new());
+
+/// An application starting or quitting, by PID, or the Mac or its displays going to sleep
+/// and waking up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    Launched(u32),
+    Terminated(u32),
+    /// The system is about to sleep or the displays went dark; nothing can be captured
+    Sleeping(SleepSource),
+    /// The system or the displays woke up again
+    Woke(SleepSource),
+    /// The user switched to another Mission Control Space
+    SpaceChanged,
+}
+
+/// What went to sleep or woke up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepSource {
+    System,
+    Displays,
+}
+
+impl fmt::Display for SleepSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SleepSource::System => "System",
+            SleepSource::Displays => "Displays",
+        })
+    }
+}
+
+/// Whether capture is paused for sleep, tracking each source on its own so the displays
+/// waking up don't resume capture while the system is still asleep, or the other way round
+#[derive(Debug, Default)]
+pub struct SleepState {
+    system: bool,
+    displays: bool,
+    /// When the first source went to sleep, while any is asleep
+    since: Option<SystemTime>,
+}
+
+impl SleepState {
+    /// Applies and reports a sleep or wake event, returning whether capture resumes, after
+    /// which windows and displays may have changed; other events are ignored
+    pub fn handle(&mut self, event: LifecycleEvent) -> bool {
+        match event {
+            LifecycleEvent::Sleeping(source) => {
+                if self.sleep(source) {
+                    println!("{} went to sleep; pausing capture", source);
+                }
+                false
+            }
+            LifecycleEvent::Woke(source) => match self.wake(source) {
+                Some(gap) => {
+                    println!(
+                        "{} woke after {}; resuming capture",
+                        source,
+                        format_gap(gap)
+                    );
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Records `source` going to sleep, returning whether this pauses capture
+    fn sleep(&mut self, source: SleepSource) -> bool {
+        *self.source(source) = true;
+        let paused = self.since.is_none();
+        self.since.get_or_insert_with(SystemTime::now);
+        paused
+    }
+
+    /// Records `source` waking up, returning how long capture was paused once nothing is
+    /// asleep anymore
+    fn wake(&mut self, source: SleepSource) -> Option<Duration> {
+        *self.source(source) = false;
+        if self.system || self.displays {
+            return None;
+        }
+        let since = self.since.take()?;
+        Some(since.elapsed().unwrap_or_default())
+    }
+
+    pub fn is_asleep(&self) -> bool {
+        self.since.is_some()
+    }
+
+    fn source(&mut self, source: SleepSource) -> &mut bool {
+        match source {
+            SleepSource::System => &mut self.system,
+            SleepSource::Displays => &mut self.displays,
+        }
+    }
+}
+
+/// Collects application launches and terminations, sleep and wake and Space switches from
+/// NSWorkspace notifications.
+///
+/// Notifications are delivered while the main run loop runs, i.e. during `app::idle` or
+/// `FocusTracker::wait`, either of which returns early when one arrives.
+pub struct LifecycleWatcher {
+    observer: id,
+}
+
+impl LifecycleWatcher {
+    pub fn start() -> Self {
+        let observer = unsafe {
+            let observer: id = msg_send![observer_class(), new];
+            let center = app::notification_center();
+            for (selector, notification) in [
+                (sel!(applicationLaunched:), LAUNCH_NOTIFICATION),
+                (sel!(applicationTerminated:), TERMINATE_NOTIFICATION),
+                (sel!(systemSleeping:), WILL_SLEEP_NOTIFICATION),
+                (sel!(displaysSleeping:), SCREENS_SLEEP_NOTIFICATION),
+                (sel!(systemWoke:), DID_WAKE_NOTIFICATION),
+                (sel!(displaysWoke:), SCREENS_WAKE_NOTIFICATION),
+                (sel!(spaceChanged:), SPACE_NOTIFICATION),
+            ] {
+                let name = NSString::alloc(nil).init_str(notification);
+                let _: () = msg_send![center, addObserver: observer
+                                              selector: selector
+                                                  name: name
+                                                object: nil];
+                let _: () = msg_send![name, release];
+            }
+            observer
+        };
+
+        Self { observer }
+    }
+
+    /// Events since the previous call, oldest first
+    pub fn drain(&self) -> Vec<LifecycleEvent> {
+        std::mem::take(&mut *EVENTS.lock().unwrap_or_else(|err| err.into_inner()))
+    }
+}
+
+impl Drop for LifecycleWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            let _: () = msg_send![app::notification_center(), removeObserver: self.observer];
+            let _: () = msg_send![self.observer, release];
+        }
+    }
+}
+
+fn observer_class() -> &'static Class {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        let mut decl = ClassDecl::new("WatcherLifecycleObserver", class!(NSObject))
+            .expect("WatcherLifecycleObserver is registered once");
+        unsafe {
+            decl.add_method(
+                sel!(applicationLaunched:),
+                application_launched as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(applicationTerminated:),
+                application_terminated as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(systemSleeping:),
+                system_sleeping as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(displaysSleeping:),
+                displays_sleeping as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(systemWoke:),
+                system_woke as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(displaysWoke:),
+                displays_woke as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(spaceChanged:),
+                space_changed as extern "C" fn(&Object, Sel, id),
+            );
+        }
+        decl.register();
+    });
+
+    class!(WatcherLifecycleObserver)
+}
+
+extern "C" fn application_launched(_this: &Object, _cmd: Sel, notification: id) {
+    if let Some(pid) = unsafe { app::notification_pid(notification) } {
+        record(LifecycleEvent::Launched(pid));
+    }
+}
+
+extern "C" fn application_terminated(_this: &Object, _cmd: Sel, notification: id) {
+    if let Some(pid) = unsafe { app::notification_pid(notification) } {
+        record(LifecycleEvent::Terminated(pid));
+    }
+}
+
+extern "C" fn system_sleeping(_this: &Object, _cmd: Sel, _notification: id) {
+    record(LifecycleEvent::Sleeping(SleepSource::System));
+}
+
+extern "C" fn displays_sleeping(_this: &Object, _cmd: Sel, _notification: id) {
+    record(LifecycleEvent::Sleeping(SleepSource::Displays));
+}
+
+extern "C" fn system_woke(_this: &Object, _cmd: Sel, _notification: id) {
+    record(LifecycleEvent::Woke(SleepSource::System));
+}
+
+extern "C" fn displays_woke(_this: &Object, _cmd: Sel, _notification: id) {
+    record(LifecycleEvent::Woke(SleepSource::Displays));
+}
+
+extern "C" fn space_changed(_this: &Object, _cmd: Sel, _notification: id) {
+    record(LifecycleEvent::SpaceChanged);
+}
+
+fn record(event: LifecycleEvent) {
+    EVENTS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .push(event);
+
+    // Ends the current wait so the event is handled right away
+    CFRunLoop::get_current().stop();
+}
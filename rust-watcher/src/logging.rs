@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+/// Name of the daily log files in `--log-dir`, suffixed with the date
+const LOG_FILE_PREFIX: &str = "watcher.log";
+
+/// Sends diagnostics to stderr, or to a log file rotated daily in `log_dir`, e.g. when
+/// running under launchd without a terminal to read them from.
+///
+/// `RUST_LOG` overrides the level picked by `verbose`. The returned guard flushes the log
+/// file when dropped, so it must live as long as the process logs.
+pub fn init(verbose: bool, json: bool, log_dir: Option<&Path>) -> Option<WorkerGuard> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(if verbose {
+            "watcher=debug"
+        } else {
+            "watcher=info"
+        })
+    });
+
+    let (writer, guard) = match log_dir {
+        Some(log_dir) => {
+            let (writer, guard) = tracing_appender::non_blocking(tracing_appender::rolling::daily(
+                log_dir,
+                LOG_FILE_PREFIX,
+            ));
+            (BoxMakeWriter::new(writer), Some(guard))
+        }
+        None => (BoxMakeWriter::new(std::io::stderr), None),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(log_dir.is_none());
+    if json {
+        builder.json().init();
+    } else {
+        builder.init();
+    }
+
+    guard
+}
@@ -1,13 +1,31 @@
 #[cfg(not(target_os = "macos"))]
 compile_error!("watcher currently supports only macOS builds.");
 
+mod app;
+mod ax;
+mod focus;
+mod lifecycle;
+mod logging;
+mod output;
 mod proc;
+mod triggers;
+mod watchlist;
 
-use clap::Parser;
+use app::AppSelector;
+use ax::AxContext;
+use lifecycle::{LifecycleEvent, LifecycleWatcher, SleepState};
+use output::{ImageFormat, ImageOutput, ImageScale};
+use proc::{WindowEvent, WindowTracker};
+use triggers::{ChangeTriggers, MAX_HEARTBEAT, Trigger};
+use watchlist::Schedule;
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use watcher_core::WatcherConfig;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -17,19 +35,209 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 )]
 struct Cli {
     /// Numeric process identifier (PID) to inspect
-    pid: u32,
+    #[arg(required_unless_present_any = ["follow_focus", "app", "bundle_id"])]
+    pid: Option<u32>,
+
+    /// Capture the frontmost application's window, switching as the user switches apps or
+    /// brings another window of the app to front
+    #[arg(long, conflicts_with = "pid")]
+    follow_focus: bool,
+
+    /// Application to capture by name, e.g. Safari; waits for it to launch. Repeat it, or
+    /// combine it with --bundle-id, to watch several apps
+    #[arg(long, conflicts_with_all = ["pid", "follow_focus"])]
+    app: Vec<String>,
+
+    /// Application to capture by bundle identifier, e.g. com.apple.Safari; waits for it to
+    /// launch. Repeatable like --app
+    #[arg(long, conflicts_with_all = ["pid", "follow_focus"])]
+    bundle_id: Vec<String>,
+
+    /// How several watched apps share the capture interval: round-robin or parallel
+    #[arg(long, value_enum, default_value = "round-robin")]
+    schedule: Schedule,
+
+    /// Shortest time between capture rounds when watching several apps, in seconds; each
+    /// app is captured when its window changes or its heartbeat is due
+    #[arg(long, value_name = "SECONDS", default_value_t = 1.0)]
+    interval: f64,
+
+    /// Capture every on-screen window of the app instead of only the frontmost one
+    #[arg(long)]
+    all_windows: bool,
+
+    /// With --all-windows, composite the windows into one contact sheet instead of saving a
+    /// file for each
+    #[arg(long, requires = "all_windows")]
+    sheet: bool,
+
+    /// Capture windows even while they are minimized, hidden, fully covered or on another
+    /// Space, instead of skipping them
+    #[arg(long)]
+    capture_hidden: bool,
+
+    /// Read the focused window title, focused UI element and selected text through the
+    /// Accessibility API and save them as JSON next to each screenshot
+    #[arg(long)]
+    ax_context: bool,
+
+    /// Seconds after which the window is captured again even though it didn't move, resize
+    /// or change its title
+    #[arg(long, value_name = "SECONDS", default_value_t = 10.0)]
+    heartbeat: f64,
+
+    /// Directory screenshots are saved to; watched apps get a subdirectory each
+    #[arg(long, value_name = "PATH", default_value = "output")]
+    output_dir: PathBuf,
+
+    /// Image format of the screenshots
+    #[arg(long, value_enum, default_value = "png")]
+    format: ImageFormat,
+
+    /// JPEG quality from 1 to 100; PNG and WebP are always lossless
+    #[arg(long, default_value_t = 85, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+
+    /// Scale screenshots down so their longest side is at most this many pixels
+    #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u32).range(1..))]
+    max_dimension: Option<u32>,
+
+    /// Save Retina captures at full pixel resolution, or scaled to the window's size in points
+    #[arg(long, value_enum, default_value = "pixels")]
+    scale: ImageScale,
+
+    /// Log debug diagnostics, e.g. every window lookup
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Log diagnostics as JSON lines instead of text
+    #[arg(long)]
+    log_json: bool,
+
+    /// Write diagnostics to a log file in this directory, rotated daily, instead of stderr;
+    /// meant for running in the background, e.g. as a launchd agent
+    #[arg(long, value_name = "PATH")]
+    log_dir: Option<PathBuf>,
+}
+
+impl Cli {
+    fn image_output(&self) -> ImageOutput {
+        ImageOutput {
+            format: self.format,
+            quality: self.quality,
+            max_dimension: self.max_dimension,
+            scale: self.scale,
+        }
+    }
+
+    /// Time after which an unchanged window is captured again
+    fn heartbeat(&self) -> Duration {
+        Duration::try_from_secs_f64(self.heartbeat.max(1.0)).unwrap_or(MAX_HEARTBEAT)
+    }
+
+    /// Fills in the flags not given on the command line from the config file shared with the
+    /// capture binary and the `WATCHER_*` environment variables
+    fn apply_config(&mut self, matches: &ArgMatches, config: &WatcherConfig) -> Result<(), String> {
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+        if unset("interval")
+            && let Some(interval) = config.capture.interval
+        {
+            self.interval = interval;
+        }
+        if unset("output_dir") {
+            self.output_dir = config.output.dir.clone();
+        }
+        if unset("format")
+            && let Some(format) = &config.output.format
+        {
+            self.format = ImageFormat::from_str(format, true)
+                .map_err(|_| format!("output.format: {} can't be saved", format))?;
+        }
+        if unset("quality")
+            && let Some(quality) = config.output.quality
+        {
+            if !(1..=100).contains(&quality) {
+                return Err(format!("output.quality must be 1 to 100, not {}", quality));
+            }
+            self.quality = quality;
+        }
+        if unset("max_dimension") && config.capture.max_edge.is_some() {
+            self.max_dimension = config.capture.max_edge.filter(|&edge| edge > 0);
+        }
+        Ok(())
+    }
 }
 
 fn main() {
-    let args = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let mut args = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let config = WatcherConfig::load().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    if let Err(err) = args.apply_config(&matches, &config) {
+        eprintln!("Invalid config: {}", err);
+        std::process::exit(1);
+    }
+    // The format may come from the config, so this can't be left to clap's `requires`
+    if matches.value_source("quality") == Some(ValueSource::CommandLine)
+        && args.format != ImageFormat::Jpeg
+    {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                format!(
+                    "--quality only applies to JPEG; {} is saved losslessly",
+                    args.format.extension()
+                ),
+            )
+            .exit();
+    }
+    let _log_guard = logging::init(args.verbose, args.log_json, args.log_dir.as_deref());
+
+    let mut focus = if args.follow_focus {
+        match focus::FocusTracker::start() {
+            Ok(tracker) => Some(tracker),
+            Err(err) => {
+                eprintln!("Unable to follow the focused window: {}", err);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    let mut selectors: Vec<AppSelector> = args
+        .app
+        .iter()
+        .cloned()
+        .map(AppSelector::Name)
+        .chain(args.bundle_id.iter().cloned().map(AppSelector::BundleId))
+        .collect();
+    // Started before waiting for the app, so its launch ends the wait right away
+    let lifecycle = LifecycleWatcher::start();
+    if selectors.len() > 1 {
+        if let Err(err) = watchlist::run(&args, selectors, &args.output_dir, &lifecycle) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let app = selectors.pop();
+    let mut pid = match (&focus, &app) {
+        (Some(tracker), _) => tracker.pid(),
+        (_, Some(app)) => app.wait_until_running(Duration::from_secs(1)),
+        _ => args
+            .pid
+            .expect("clap requires a PID without --follow-focus, --app or --bundle-id"),
+    };
 
-    let name = match proc::resolve_app_name(args.pid) {
+    let mut name = match proc::resolve_app_name(pid) {
         Ok(name) => {
             println!("{}", name);
             name
         }
         Err(err) => {
-            eprintln!("Failed to resolve PID {}: {}", args.pid, err);
+            eprintln!("Failed to resolve PID {}: {}", pid, err);
             std::process::exit(1);
         }
     };
@@ -46,19 +254,35 @@ fn main() {
         }
     }
 
-    let output_dir = Path::new("output");
+    if args.ax_context && !ax::ensure_trusted(true) {
+        eprintln!(
+            "Accessibility permission is not granted; AX context stays empty until it is allowed \
+             in System Settings > Privacy & Security > Accessibility"
+        );
+    }
+
+    let output_dir = args.output_dir.as_path();
     if let Err(err) = fs::create_dir_all(output_dir) {
-        eprintln!("Unable to create output directory: {}", err);
+        eprintln!(
+            "Unable to create output directory {}: {}",
+            output_dir.display(),
+            err
+        );
         std::process::exit(1);
     }
 
-    let mut capture_target = match proc::prepare_window_capture(args.pid) {
+    let mut capture_target = match proc::prepare_window_capture(pid) {
         Ok(target) => {
             println!(
                 "Tracking PID {} window '{}' (id={}) owned by {}",
                 target.pid, target.window_title, target.window_id, target.app_name
             );
-            target
+            Some(target)
+        }
+        // The app may have no window yet, or focus moves on before long
+        Err(err) if focus.is_some() || app.is_some() => {
+            eprintln!("Waiting for a captureable window: {}", err);
+            None
         }
         Err(err) => {
             eprintln!("Unable to prepare capture: {}", err);
@@ -66,39 +290,353 @@ fn main() {
         }
     };
 
+    let mut triggers = ChangeTriggers::start(args.heartbeat());
+    triggers.watch(pid);
+    if !triggers.observes_windows() {
+        eprintln!(
+            "Window events are unavailable without Accessibility permission; capturing every {}s",
+            args.heartbeat
+        );
+    }
+
+    let mut window_tracker = WindowTracker::new();
+    let window_events = window_tracker.subscribe();
+    thread::spawn(move || {
+        for (window_id, event) in window_events {
+            tracing::debug!(window_id, ?event, "window changed");
+        }
+    });
+
     println!("Beginning capture loop. Press Ctrl+C to stop.");
 
+    let mut paused = false;
+    // Frontmost window of the followed app at the last poll
+    let mut front_window = None;
+    let mut sleep = SleepState::default();
     loop {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        let screenshot_path = output_dir.join(format!("{}-{}.png", name, timestamp));
-
-        match proc::capture_window(capture_target.window_id, &screenshot_path) {
-            Ok(()) => println!("Saved screenshot to {}", screenshot_path.display()),
-            Err(err) => {
-                eprintln!(
-                    "Capture failed for window {} (id={}): {}",
-                    capture_target.window_title, capture_target.window_id, err
-                );
+        for event in lifecycle.drain() {
+            // Windows and displays may have changed while asleep
+            if sleep.handle(event) {
+                capture_target = None;
+                triggers.force();
+            }
+            match event {
+                // The window may have come into view or gone out of it
+                LifecycleEvent::SpaceChanged => {
+                    println!("Switched Spaces");
+                    triggers.force();
+                }
+                // Focus moves on by itself when the focused app quits
+                LifecycleEvent::Terminated(quit) if quit == pid && focus.is_none() => {
+                    let Some(app) = &app else {
+                        println!("PID {} exited; stopping capture", pid);
+                        return;
+                    };
+                    println!("{} quit; pausing until it launches again", app);
+                    paused = true;
+                    capture_target = None;
+                }
+                // A launch ends the wait below, which then finds the app running
+                _ => {}
+            }
+        }
 
-                match proc::prepare_window_capture(args.pid) {
-                    Ok(new_target) => {
-                        println!(
-                            "Re-acquired PID {} window '{}' (id={})",
-                            new_target.pid, new_target.window_title, new_target.window_id
-                        );
-                        capture_target = new_target;
-                        continue;
+        if sleep.is_asleep() {
+            app::idle(Duration::from_secs(1));
+            continue;
+        }
+
+        if paused {
+            match app.as_ref().and_then(AppSelector::find_running) {
+                Some(running) => {
+                    println!("{} launched as PID {}; resuming", name, running);
+                    pid = running;
+                    paused = false;
+                }
+                None => {
+                    app::idle(Duration::from_secs(1));
+                    continue;
+                }
+            }
+        }
+
+        if capture_target.is_none() {
+            // A relaunched app comes back under a new PID
+            if let Some(app) = &app
+                && let Some(running) = app.find_running()
+            {
+                pid = running;
+            }
+            capture_target = proc::prepare_window_capture(pid).ok();
+        }
+
+        // Polled as well, so changes are seen without Accessibility permission and a closed
+        // window is re-acquired before a capture fails on it
+        let mut closed = false;
+        if let Some(target) = &mut capture_target {
+            window_tracker.track(target.window_id);
+            for event in window_tracker.poll() {
+                match event {
+                    WindowEvent::Moved(_) => triggers.notify(Trigger::WindowMoved),
+                    WindowEvent::Resized(_) | WindowEvent::FullscreenChanged(_) => {
+                        triggers.notify(Trigger::WindowResized)
                     }
-                    Err(prepare_err) => {
-                        eprintln!("Unable to re-acquire window: {}", prepare_err);
+                    WindowEvent::TitleChanged(title) => {
+                        target.window_title = title;
+                        triggers.notify(Trigger::TitleChanged);
                     }
+                    WindowEvent::Closed => closed = true,
                 }
             }
         }
+        if closed {
+            println!("Tracked window closed; re-acquiring");
+            capture_target = proc::prepare_window_capture(pid).ok();
+            triggers.force();
+        }
+        // Another window of the focused app coming to front is also seen without
+        // Accessibility permission
+        if focus.is_some() {
+            let front = proc::front_window_id(pid);
+            if front.is_some() && front_window.is_some() && front != front_window {
+                triggers.notify(Trigger::WindowChanged);
+            }
+            front_window = front;
+        }
 
-        thread::sleep(Duration::from_secs(1));
+        triggers.watch(pid);
+        let trigger = capture_target.as_ref().and_then(|_| triggers.due());
+        // Window ids and bounds may change when displays are added, removed or rearranged
+        if trigger == Some(Trigger::DisplaysChanged) {
+            println!("Displays changed; re-acquiring the window");
+            capture_target = proc::prepare_window_capture(pid).ok();
+        }
+        // Following focus, capture moves along to the window that came to front
+        if trigger == Some(Trigger::WindowChanged)
+            && focus.is_some()
+            && let Ok(target) = proc::prepare_window_capture(pid)
+            && capture_target
+                .as_ref()
+                .is_none_or(|current| current.window_id != target.window_id)
+        {
+            println!(
+                "Focus moved to window '{}' (id={})",
+                target.window_title, target.window_id
+            );
+            capture_target = Some(target);
+        }
+        if let Some(target) = &capture_target
+            && let Some(trigger) = trigger
+        {
+            if trigger != Trigger::Heartbeat {
+                println!("Capturing: {}", trigger);
+            }
+            let result = capture(&args, pid, &name, target, output_dir);
+
+            match result {
+                Ok(()) => {}
+                Err(err) => {
+                    eprintln!(
+                        "Capture failed for window {} (id={}): {}",
+                        target.window_title, target.window_id, err
+                    );
+
+                    match proc::prepare_window_capture(pid) {
+                        Ok(new_target) => {
+                            println!(
+                                "Re-acquired PID {} window '{}' (id={})",
+                                new_target.pid, new_target.window_title, new_target.window_id
+                            );
+                            capture_target = Some(new_target);
+                            triggers.force();
+                            continue;
+                        }
+                        Err(prepare_err) => {
+                            eprintln!("Unable to re-acquire window: {}", prepare_err);
+                            if app.is_some() {
+                                capture_target = None;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Without a target, keep looking for a window once a second
+        let timeout = if capture_target.is_some() {
+            triggers.timeout().min(proc::WINDOW_POLL_INTERVAL)
+        } else {
+            Duration::from_secs(1)
+        };
+        let Some(tracker) = &mut focus else {
+            app::idle(timeout);
+            continue;
+        };
+
+        if let Some(focused_pid) = tracker.wait(timeout) {
+            pid = focused_pid;
+            front_window = None;
+            capture_target = match proc::prepare_window_capture(pid) {
+                Ok(target) => {
+                    println!(
+                        "Focus moved to PID {} window '{}' (id={}) owned by {}",
+                        target.pid, target.window_title, target.window_id, target.app_name
+                    );
+                    name = proc::resolve_app_name(pid).unwrap_or_else(|_| target.app_name.clone());
+                    Some(target)
+                }
+                Err(err) => {
+                    eprintln!("Focus moved to PID {}: {}", pid, err);
+                    None
+                }
+            };
+        }
     }
 }
+
+/// Saves the window of `pid`, or every window with --all-windows, along with its AX context
+/// when enabled
+fn capture(
+    args: &Cli,
+    pid: u32,
+    name: &str,
+    target: &proc::WindowCaptureTarget,
+    output_dir: &Path,
+) -> Result<(), String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let context = args.ax_context.then(|| ax::read_context(pid));
+    let output = args.image_output();
+    if args.all_windows {
+        capture_all_windows(
+            pid,
+            name,
+            timestamp,
+            &output,
+            output_dir,
+            args.sheet,
+            args.capture_hidden,
+            context.as_ref(),
+        )
+    } else if !should_capture(target, args.capture_hidden) {
+        Ok(())
+    } else {
+        let screenshot_path = output.unused_path(output_dir, &format!("{}-{}", name, timestamp));
+        proc::capture_window(target, &output, &screenshot_path).map(|()| {
+            println!("Saved screenshot to {}", screenshot_path.display());
+            save_context(context.as_ref(), &screenshot_path);
+        })
+    }
+}
+
+/// Saves every window of `pid`, a file each or composited into one contact sheet; fails
+/// only when no window could be saved
+fn capture_all_windows(
+    pid: u32,
+    name: &str,
+    timestamp: u64,
+    output: &ImageOutput,
+    output_dir: &Path,
+    sheet: bool,
+    capture_hidden: bool,
+    context: Option<&AxContext>,
+) -> Result<(), String> {
+    let mut targets = proc::prepare_all_window_captures(pid)?;
+    targets.retain(|target| should_capture(target, capture_hidden));
+    if targets.is_empty() {
+        return Ok(());
+    }
+
+    if sheet {
+        let sheet_path = output.unused_path(output_dir, &format!("{}-{}-sheet", name, timestamp));
+        let captured = proc::capture_contact_sheet(&targets, output, &sheet_path)?;
+        println!(
+            "Saved {} of {} windows to {}",
+            captured,
+            targets.len(),
+            sheet_path.display()
+        );
+        save_context(context, &sheet_path);
+        return Ok(());
+    }
+
+    let mut saved = 0;
+    for target in &targets {
+        let screenshot_path = output.unused_path(
+            output_dir,
+            &format!("{}-{}-{}", name, timestamp, target.window_id),
+        );
+        match proc::capture_window(target, output, &screenshot_path) {
+            Ok(()) => {
+                println!(
+                    "Saved window '{}' to {}",
+                    target.window_title,
+                    screenshot_path.display()
+                );
+                save_context(context, &screenshot_path);
+                saved += 1;
+            }
+            Err(err) => eprintln!(
+                "Capture failed for window {} (id={}): {}",
+                target.window_title, target.window_id, err
+            ),
+        }
+    }
+
+    if saved == 0 {
+        return Err(format!(
+            "None of the {} windows could be saved",
+            targets.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Length of a pause in the log, e.g. 1h 05m or 42s
+fn format_gap(gap: Duration) -> String {
+    let seconds = gap.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, seconds) => format!("{}s", seconds),
+        (0, minutes, seconds) => format!("{}m {:02}s", minutes, seconds),
+        (hours, minutes, _) => format!("{}h {:02}m", hours, minutes),
+    }
+}
+
+/// Saves the AX context read for this round next to a screenshot, when enabled
+fn save_context(context: Option<&AxContext>, screenshot_path: &Path) {
+    let Some(context) = context else {
+        return;
+    };
+
+    match context.save_next_to(screenshot_path) {
+        Ok(()) => println!("  AX context: {}", context),
+        Err(err) => eprintln!("{}", err),
+    }
+}
+
+/// Whether the window shows anything worth capturing, logging why when it doesn't; with
+/// `capture_hidden` it is captured anyway and only the log says it can't be seen
+fn should_capture(target: &proc::WindowCaptureTarget, capture_hidden: bool) -> bool {
+    let visibility = proc::window_visibility(target.window_id);
+    // A closed window fails to capture, which re-acquires the target
+    if visibility.is_visible() || visibility == proc::WindowVisibility::Closed {
+        return true;
+    }
+
+    if capture_hidden {
+        println!(
+            "Capturing window '{}' (id={}) although it is {}",
+            target.window_title, target.window_id, visibility
+        );
+        return true;
+    }
+
+    println!(
+        "Skipping window '{}' (id={}): {}",
+        target.window_title, target.window_id, visibility
+    );
+    false
+}
@@ -0,0 +1,135 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, RgbaImage};
+
+/// File format screenshots are saved in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    /// Lossless WebP
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+}
+
+/// Size screenshots are saved at on Retina displays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImageScale {
+    /// Every captured pixel, twice the window's size in points on Retina displays
+    Pixels,
+    /// The window's size in points, as it would be on a non-Retina display
+    Points,
+}
+
+/// How captured images are encoded on disk
+#[derive(Debug, Clone, Copy)]
+pub struct ImageOutput {
+    pub format: ImageFormat,
+    /// JPEG quality from 1 to 100; the other formats are lossless
+    pub quality: u8,
+    /// Longest side in pixels; larger images are scaled down to it
+    pub max_dimension: Option<u32>,
+    pub scale: ImageScale,
+}
+
+impl ImageOutput {
+    /// File name for a screenshot, `stem` plus the format's extension
+    pub fn file_name(&self, stem: &str) -> String {
+        format!("{}.{}", stem, self.format.extension())
+    }
+
+    /// Path in `dir` for a screenshot named after `stem`, numbered `-2`, `-3` and so on
+    /// while a file of that name exists, e.g. for several captures within one second
+    pub fn unused_path(&self, dir: &Path, stem: &str) -> PathBuf {
+        let mut path = dir.join(self.file_name(stem));
+        let mut number = 2;
+        while path.exists() {
+            path = dir.join(self.file_name(&format!("{}-{}", stem, number)));
+            number += 1;
+        }
+        path
+    }
+
+    /// Scales a capture from a display with `scale_factor` pixels per point down to points,
+    /// when saving in points; the factor is only looked up then
+    pub fn in_points(&self, image: RgbaImage, scale_factor: impl FnOnce() -> f64) -> RgbaImage {
+        if self.scale == ImageScale::Pixels {
+            return image;
+        }
+        let scale_factor = scale_factor();
+        if scale_factor <= 1.0 {
+            return image;
+        }
+
+        let (width, height) = image.dimensions();
+        let scaled_width = ((width as f64 / scale_factor).round() as u32).max(1);
+        let scaled_height = ((height as f64 / scale_factor).round() as u32).max(1);
+        imageops::resize(&image, scaled_width, scaled_height, FilterType::Triangle)
+    }
+
+    /// Encodes `image` into a temporary file next to `path` and moves it into place, so a
+    /// failed encode never leaves a truncated screenshot behind
+    pub fn save(&self, image: RgbaImage, path: &Path) -> Result<(), String> {
+        let image = self.fit(image);
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+        let result = self.encode(image, &temp_path).and_then(|()| {
+            fs::rename(&temp_path, path)
+                .map_err(|err| format!("Unable to save {}: {}", path.display(), err))
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    fn encode(&self, image: RgbaImage, path: &Path) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|err| format!("Unable to create {}: {}", path.display(), err))?;
+        let mut writer = BufWriter::new(file);
+
+        match self.format {
+            ImageFormat::Png => image.write_with_encoder(PngEncoder::new(&mut writer)),
+            // JPEG has no alpha channel
+            ImageFormat::Jpeg => DynamicImage::ImageRgba8(image)
+                .to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut writer, self.quality)),
+            ImageFormat::Webp => image.write_with_encoder(WebPEncoder::new_lossless(&mut writer)),
+        }
+        .map_err(|err| err.to_string())?;
+        writer
+            .flush()
+            .map_err(|err| format!("Unable to write {}: {}", path.display(), err))
+    }
+
+    fn fit(&self, image: RgbaImage) -> RgbaImage {
+        let (width, height) = image.dimensions();
+        let Some(max_dimension) = self.max_dimension else {
+            return image;
+        };
+        if width.max(height) <= max_dimension {
+            return image;
+        }
+
+        let scale = max_dimension as f64 / width.max(height) as f64;
+        let scaled_width = ((width as f64 * scale).round() as u32).max(1);
+        let scaled_height = ((height as f64 * scale).round() as u32).max(1);
+        imageops::resize(&image, scaled_width, scaled_height, FilterType::Triangle)
+    }
+}
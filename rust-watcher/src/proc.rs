@@ -2,34 +2,261 @@ use std::collections::HashMap;
 use std::os::raw::c_void;
 use std::path::Path;
 use std::process::Command;
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, mpsc};
+use std::thread;
+use std::time::Duration;
 
 use cocoa::appkit::NSApplication;
 use cocoa::base::{id, nil};
-use core_foundation::array::CFArray;
+use core_foundation::array::{CFArray, CFArrayRef};
 use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::boolean::CFBoolean;
 use core_foundation::dictionary::CFDictionary;
 use core_foundation::number::CFNumber;
 use core_foundation::string::CFString;
-use core_graphics::base::{kCGBitmapByteOrder32Big, kCGImageAlphaPremultipliedLast};
-use core_graphics::color_space::CGColorSpace;
-use core_graphics::context::CGContext;
+use core_graphics::display::CGDisplay;
 use core_graphics::geometry::{CGPoint, CGRect, CGSize};
-use core_graphics::image::CGImage;
 use core_graphics::window::{
-    CGWindowListCopyWindowInfo, create_image, kCGNullWindowID, kCGWindowImageBestResolution,
-    kCGWindowImageBoundsIgnoreFraming, kCGWindowImageDefault, kCGWindowListExcludeDesktopElements,
-    kCGWindowListOptionAll, kCGWindowListOptionIncludingWindow, kCGWindowListOptionOnScreenOnly,
+    CGWindowListCopyWindowInfo, CGWindowListOption, kCGNullWindowID, kCGWindowAlpha,
+    kCGWindowBounds, kCGWindowIsOnscreen, kCGWindowLayer, kCGWindowListExcludeDesktopElements,
+    kCGWindowListOptionAll, kCGWindowListOptionIncludingWindow,
+    kCGWindowListOptionOnScreenAboveWindow, kCGWindowListOptionOnScreenOnly, kCGWindowName,
     kCGWindowNumber, kCGWindowOwnerName, kCGWindowOwnerPID,
 };
-use image::RgbaImage;
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
 use objc::{msg_send, sel, sel_impl};
 use scap::Target;
+use scap::capturer::{Capturer, Options, Resolution};
+use scap::frame::{BGRAFrame, Frame, FrameType, VideoFrame};
+use tracing::{debug, warn};
+
+use crate::output::ImageOutput;
+
+/// Largest size a window takes up in a contact sheet; bigger ones are scaled down to fit
+const SHEET_CELL_WIDTH: u32 = 1280;
+const SHEET_CELL_HEIGHT: u32 = 800;
+const SHEET_PADDING: u32 = 16;
+const SHEET_BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+
+/// How long a window capture may take before it is given up on
+const WINDOW_FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Windows at or above the Dock's layer, like the menu bar, never count as covering
+const DOCK_WINDOW_LAYER: u32 = 20;
+
+/// Points sampled along each side of a window to estimate how much of it is covered
+const COVERAGE_SAMPLES: u32 = 24;
+
+/// kCGSAllSpacesMask: current and other Spaces of every display
+const ALL_SPACES_MASK: i32 = 0x7;
+
+// Private window server calls, the only way to tell which Space a window is on
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGSMainConnectionID() -> i32;
+    fn CGSGetActiveSpace(connection: i32) -> u64;
+    fn CGSCopySpacesForWindows(connection: i32, mask: i32, windows: CFArrayRef) -> CFArrayRef;
+}
+
+/// Whether a window's content can be seen on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowVisibility {
+    Visible,
+    /// Other windows cover this fraction of it
+    PartlyCovered(f64),
+    FullyCovered,
+    /// Minimized or its app is hidden
+    Offscreen,
+    /// On a Mission Control Space that isn't showing
+    OtherSpace,
+    /// The window no longer exists
+    Closed,
+}
+
+impl WindowVisibility {
+    pub fn is_visible(self) -> bool {
+        matches!(
+            self,
+            WindowVisibility::Visible | WindowVisibility::PartlyCovered(_)
+        )
+    }
+}
+
+impl std::fmt::Display for WindowVisibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowVisibility::Visible => write!(f, "visible"),
+            WindowVisibility::PartlyCovered(fraction) => {
+                write!(f, "{:.0}% covered", fraction * 100.0)
+            }
+            WindowVisibility::FullyCovered => write!(f, "fully covered"),
+            WindowVisibility::Offscreen => write!(f, "minimized or hidden"),
+            WindowVisibility::OtherSpace => write!(f, "on another Space"),
+            WindowVisibility::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+/// How often `WindowTracker` is polled by the capture loop
+pub const WINDOW_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Position and size of a window in global points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowGeometry {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl std::fmt::Display for WindowGeometry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{} at ({}, {})",
+            self.width, self.height, self.x, self.y
+        )
+    }
+}
+
+/// A change to the tracked window seen by `WindowTracker`
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowEvent {
+    Moved(WindowGeometry),
+    Resized(WindowGeometry),
+    TitleChanged(String),
+    /// The window now fills a whole display, or no longer does
+    FullscreenChanged(bool),
+    Closed,
+}
+
+/// What the tracker last saw of its window
+#[derive(Debug, Clone, PartialEq)]
+struct WindowState {
+    geometry: WindowGeometry,
+    title: Option<String>,
+    fullscreen: bool,
+}
+
+/// Follows the geometry, title and fullscreen state of one window by polling the window
+/// server, so changes are noticed before a capture fails on them.
+///
+/// Events are returned from `poll` for the caller and sent to every subscriber as well.
+#[derive(Default)]
+pub struct WindowTracker {
+    window_id: Option<u32>,
+    state: Option<WindowState>,
+    subscribers: Vec<mpsc::Sender<(u32, WindowEvent)>>,
+}
+
+impl WindowTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follows `window_id` from now on, starting from its current state
+    pub fn track(&mut self, window_id: u32) {
+        if self.window_id == Some(window_id) {
+            return;
+        }
+        self.window_id = Some(window_id);
+        self.state = window_state(window_id);
+    }
+
+    /// Receives the events of every tracked window along with its id, until the receiver
+    /// is dropped
+    pub fn subscribe(&mut self) -> mpsc::Receiver<(u32, WindowEvent)> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// Re-reads the window and returns what changed since the last poll; after `Closed`
+    /// nothing is tracked until the next `track`
+    pub fn poll(&mut self) -> Vec<WindowEvent> {
+        let Some(window_id) = self.window_id else {
+            return Vec::new();
+        };
+
+        let events = match (self.state.take(), window_state(window_id)) {
+            (Some(_), None) => {
+                self.window_id = None;
+                vec![WindowEvent::Closed]
+            }
+            (Some(old), Some(new)) => {
+                let mut events = Vec::new();
+                if new.geometry.width != old.geometry.width
+                    || new.geometry.height != old.geometry.height
+                {
+                    events.push(WindowEvent::Resized(new.geometry));
+                } else if new.geometry != old.geometry {
+                    events.push(WindowEvent::Moved(new.geometry));
+                }
+                if new.fullscreen != old.fullscreen {
+                    events.push(WindowEvent::FullscreenChanged(new.fullscreen));
+                }
+                if let Some(title) = &new.title
+                    && old.title.as_ref() != Some(title)
+                {
+                    events.push(WindowEvent::TitleChanged(title.clone()));
+                }
+                self.state = Some(new);
+                events
+            }
+            // Not readable when tracking started, e.g. not on screen yet
+            (None, new) => {
+                self.state = new;
+                Vec::new()
+            }
+        };
+
+        self.subscribers.retain(|subscriber| {
+            events
+                .iter()
+                .all(|event| subscriber.send((window_id, event.clone())).is_ok())
+        });
+        events
+    }
+}
+
+fn window_state(window_id: u32) -> Option<WindowState> {
+    let window = window_dict(window_id)?;
+    let bounds = dict_bounds(&window)?;
+    let geometry = WindowGeometry {
+        x: bounds.origin.x,
+        y: bounds.origin.y,
+        width: bounds.size.width,
+        height: bounds.size.height,
+    };
+    // Fullscreen windows get a Space of their own and cover their display exactly
+    let fullscreen = CGDisplay::active_displays()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|display| CGDisplay::new(display).bounds())
+        .any(|display| {
+            display.origin.x == geometry.x
+                && display.origin.y == geometry.y
+                && display.size.width == geometry.width
+                && display.size.height == geometry.height
+        });
+
+    Some(WindowState {
+        geometry,
+        title: dict_string(&window, unsafe { kCGWindowName } as *const c_void)
+            .filter(|title| !title.is_empty()),
+        fullscreen,
+    })
+}
 
 #[derive(Debug, Clone)]
 struct WindowMeta {
     pid: u32,
     app: String,
+    /// Position in the front-to-back window list
+    order: usize,
+    layer: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -38,6 +265,7 @@ pub struct WindowCaptureTarget {
     pub window_id: u32,
     pub window_title: String,
     pub app_name: String,
+    target: Target,
 }
 
 fn ensure_capture_ready() -> Result<(), String> {
@@ -99,6 +327,55 @@ pub fn list_targets() -> Result<Vec<String>, String> {
 }
 
 pub fn prepare_window_capture(pid: u32) -> Result<WindowCaptureTarget, String> {
+    let (_, target) = owned_windows(pid)?.into_iter().next().ok_or_else(|| {
+        format!(
+            "No captureable window found for PID {}. Ensure the app has a visible window.",
+            pid
+        )
+    })?;
+
+    debug!(
+        pid,
+        window_id = target.window_id,
+        title = %target.window_title,
+        "prepared window capture"
+    );
+
+    Ok(target)
+}
+
+/// Every regular on-screen window owned by `pid`, frontmost first
+pub fn prepare_all_window_captures(pid: u32) -> Result<Vec<WindowCaptureTarget>, String> {
+    let targets: Vec<WindowCaptureTarget> = owned_windows(pid)?
+        .into_iter()
+        .filter(|(regular, _)| *regular)
+        .map(|(_, target)| target)
+        .collect();
+
+    if targets.is_empty() {
+        return Err(format!(
+            "No captureable windows found for PID {}. Ensure the app has a visible window.",
+            pid
+        ));
+    }
+
+    Ok(targets)
+}
+
+/// Id of the frontmost regular window of `pid`, from the window server alone, so it is cheap
+/// enough to poll
+pub fn front_window_id(pid: u32) -> Option<u32> {
+    build_window_owner_map()
+        .ok()?
+        .into_iter()
+        .filter(|(_, meta)| meta.pid == pid && meta.layer == 0)
+        .min_by_key(|(_, meta)| meta.order)
+        .map(|(window_id, _)| window_id)
+}
+
+/// Windows owned by `pid` front to back, regular windows ahead of the panels and overlays
+/// the app also owns, each flagged whether it is regular
+fn owned_windows(pid: u32) -> Result<Vec<(bool, WindowCaptureTarget)>, String> {
     ensure_capture_ready()?;
 
     if pid == 0 {
@@ -106,53 +383,287 @@ pub fn prepare_window_capture(pid: u32) -> Result<WindowCaptureTarget, String> {
     }
 
     let window_map = build_window_owner_map()?;
-    eprintln!(
-        "[watcher] window owner map contains {} entries",
-        window_map.len()
+    let targets = scap::get_all_targets();
+    debug!(
+        pid,
+        windows = window_map.len(),
+        targets = targets.len(),
+        "looked up window owners"
     );
 
-    let targets = scap::get_all_targets();
-    eprintln!("[watcher] fetched {} capture targets", targets.len());
-
-    for target in targets.into_iter() {
-        if let Target::Window(window) = target {
-            if let Some(meta) = window_map.get(&window.id) {
-                if meta.pid == pid {
-                    let title = window.title.trim();
-                    let window_title = if title.is_empty() {
-                        meta.app.clone()
-                    } else {
-                        title.to_string()
-                    };
-
-                    eprintln!(
-                        "[watcher] prepared capture for PID {} -> window '{}' (id={})",
-                        pid, window_title, window.id
-                    );
-
-                    return Ok(WindowCaptureTarget {
-                        pid: meta.pid,
-                        window_id: window.id,
-                        window_title,
-                        app_name: meta.app.clone(),
-                    });
-                }
+    let mut windows: Vec<(bool, usize, WindowCaptureTarget)> = targets
+        .into_iter()
+        .filter_map(|target| {
+            let Target::Window(window) = &target else {
+                return None;
+            };
+            let meta = window_map.get(&window.id).filter(|meta| meta.pid == pid)?;
+
+            let title = window.title.trim();
+            let window_title = if title.is_empty() {
+                meta.app.clone()
+            } else {
+                title.to_string()
+            };
+
+            Some((
+                meta.layer == 0,
+                meta.order,
+                WindowCaptureTarget {
+                    pid: meta.pid,
+                    window_id: window.id,
+                    window_title,
+                    app_name: meta.app.clone(),
+                    target: target.clone(),
+                },
+            ))
+        })
+        .collect();
+    windows.sort_by_key(|(regular, order, _)| (!regular, *order));
+
+    Ok(windows
+        .into_iter()
+        .map(|(regular, _, target)| (regular, target))
+        .collect())
+}
+
+pub fn capture_window(
+    target: &WindowCaptureTarget,
+    output: &ImageOutput,
+    output_path: &Path,
+) -> Result<(), String> {
+    let image = capture_window_image(target)?;
+    let image = output.in_points(image, || window_scale_factor(target.window_id));
+
+    output
+        .save(image, output_path)
+        .map_err(|err| format!("Failed to save screenshot: {}", err))
+}
+
+/// Captures the windows side by side into one image, skipping any that can't be captured,
+/// and returns how many made it in
+pub fn capture_contact_sheet(
+    targets: &[WindowCaptureTarget],
+    output: &ImageOutput,
+    output_path: &Path,
+) -> Result<usize, String> {
+    let images: Vec<RgbaImage> = targets
+        .iter()
+        .filter_map(|target| match capture_window_image(target) {
+            Ok(image) => Some(fit_sheet_cell(
+                output.in_points(image, || window_scale_factor(target.window_id)),
+            )),
+            Err(err) => {
+                warn!(
+                    window_id = target.window_id,
+                    error = %err,
+                    "leaving window out of the contact sheet"
+                );
+                None
+            }
+        })
+        .collect();
+
+    if images.is_empty() {
+        return Err("None of the windows could be captured".into());
+    }
+
+    let count = images.len() as u32;
+    let columns = (count as f64).sqrt().ceil() as u32;
+    let rows = count.div_ceil(columns);
+    let cell_width = images.iter().map(|image| image.width()).max().unwrap_or(1);
+    let cell_height = images.iter().map(|image| image.height()).max().unwrap_or(1);
+
+    let mut sheet = RgbaImage::from_pixel(
+        columns * (cell_width + SHEET_PADDING) + SHEET_PADDING,
+        rows * (cell_height + SHEET_PADDING) + SHEET_PADDING,
+        SHEET_BACKGROUND,
+    );
+    for (index, image) in images.iter().enumerate() {
+        let (column, row) = (index as u32 % columns, index as u32 / columns);
+        // Centered in its cell
+        let x = SHEET_PADDING
+            + column * (cell_width + SHEET_PADDING)
+            + (cell_width - image.width()) / 2;
+        let y = SHEET_PADDING
+            + row * (cell_height + SHEET_PADDING)
+            + (cell_height - image.height()) / 2;
+        imageops::replace(&mut sheet, image, x as i64, y as i64);
+    }
+
+    output
+        .save(sheet, output_path)
+        .map_err(|err| format!("Failed to save contact sheet: {}", err))?;
+
+    Ok(images.len())
+}
+
+fn fit_sheet_cell(image: RgbaImage) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let scale =
+        (SHEET_CELL_WIDTH as f64 / width as f64).min(SHEET_CELL_HEIGHT as f64 / height as f64);
+    if scale >= 1.0 {
+        return image;
+    }
+
+    let scaled_width = ((width as f64 * scale).round() as u32).max(1);
+    let scaled_height = ((height as f64 * scale).round() as u32).max(1);
+    imageops::resize(&image, scaled_width, scaled_height, FilterType::Triangle)
+}
+
+/// Whether the window is on screen and how much of it the windows in front cover
+pub fn window_visibility(window_id: u32) -> WindowVisibility {
+    let Some(window) = window_dict(window_id) else {
+        return WindowVisibility::Closed;
+    };
+
+    let onscreen = dict_cf_type(&window, unsafe { kCGWindowIsOnscreen } as *const c_void)
+        .and_then(|value| value.downcast::<CFBoolean>())
+        .is_some_and(bool::from);
+    let Some(bounds) = onscreen.then(|| dict_bounds(&window)).flatten() else {
+        return if is_on_other_space(window_id) {
+            WindowVisibility::OtherSpace
+        } else {
+            WindowVisibility::Offscreen
+        };
+    };
+    if bounds.size.width <= 0.0 || bounds.size.height <= 0.0 {
+        return WindowVisibility::Offscreen;
+    }
+
+    let covering: Vec<CGRect> = window_info(
+        kCGWindowListOptionOnScreenAboveWindow | kCGWindowListExcludeDesktopElements,
+        window_id,
+    )
+    .iter()
+    .filter(|dict| {
+        let layer = dict_number_to_u32(dict, unsafe { kCGWindowLayer } as *const c_void);
+        let alpha = dict_cf_type(dict, unsafe { kCGWindowAlpha } as *const c_void)
+            .and_then(|value| value.downcast::<CFNumber>())
+            .and_then(|alpha| alpha.to_f64());
+        layer.is_some_and(|layer| layer < DOCK_WINDOW_LAYER) && alpha.is_some_and(|a| a > 0.0)
+    })
+    .filter_map(dict_bounds)
+    .collect();
+
+    let mut covered = 0;
+    for row in 0..COVERAGE_SAMPLES {
+        for column in 0..COVERAGE_SAMPLES {
+            let point = CGPoint::new(
+                bounds.origin.x
+                    + bounds.size.width * (column as f64 + 0.5) / COVERAGE_SAMPLES as f64,
+                bounds.origin.y + bounds.size.height * (row as f64 + 0.5) / COVERAGE_SAMPLES as f64,
+            );
+            if covering.iter().any(|rect| rect_contains(rect, &point)) {
+                covered += 1;
             }
         }
     }
 
-    Err(format!(
-        "No captureable window found for PID {}. Ensure the app has a visible window.",
-        pid
-    ))
+    let fraction = covered as f64 / (COVERAGE_SAMPLES * COVERAGE_SAMPLES) as f64;
+    if covered == 0 {
+        WindowVisibility::Visible
+    } else if fraction >= 1.0 {
+        WindowVisibility::FullyCovered
+    } else {
+        WindowVisibility::PartlyCovered(fraction)
+    }
 }
 
-pub fn capture_window(window_id: u32, output_path: &Path) -> Result<(), String> {
-    let image = capture_window_image(window_id)?;
+/// Whether the window belongs only to Spaces other than the active one; minimized windows
+/// and those of hidden apps stay on their Space
+fn is_on_other_space(window_id: u32) -> bool {
+    let windows = CFArray::from_CFTypes(&[CFNumber::from(window_id as i64)]);
+    let (active, spaces) = unsafe {
+        let connection = CGSMainConnectionID();
+        let spaces =
+            CGSCopySpacesForWindows(connection, ALL_SPACES_MASK, windows.as_concrete_TypeRef());
+        if spaces.is_null() {
+            return false;
+        }
+        let spaces: CFArray<CFNumber> = CFArray::wrap_under_create_rule(spaces);
+        (CGSGetActiveSpace(connection), spaces)
+    };
 
-    image
-        .save(output_path)
-        .map_err(|err| format!("Failed to save screenshot: {}", err))
+    let spaces: Vec<u64> = spaces
+        .iter()
+        .filter_map(|space| space.to_i64())
+        .map(|space| space as u64)
+        .collect();
+    !spaces.is_empty() && !spaces.contains(&active)
+}
+
+/// Pixels per point of the display showing most of the window, 2.0 on Retina displays; 1.0
+/// when it can't be told
+pub fn window_scale_factor(window_id: u32) -> f64 {
+    let Some(bounds) = window_dict(window_id).as_ref().and_then(dict_bounds) else {
+        return 1.0;
+    };
+    let Ok(displays) = CGDisplay::active_displays() else {
+        return 1.0;
+    };
+
+    // A window spanning two displays is drawn at the scale of the one it is mostly on
+    let overlap = |display: &CGDisplay| {
+        let screen = display.bounds();
+        let width = (bounds.origin.x + bounds.size.width).min(screen.origin.x + screen.size.width)
+            - bounds.origin.x.max(screen.origin.x);
+        let height = (bounds.origin.y + bounds.size.height)
+            .min(screen.origin.y + screen.size.height)
+            - bounds.origin.y.max(screen.origin.y);
+        width.max(0.0) * height.max(0.0)
+    };
+    let scale = displays
+        .into_iter()
+        .map(CGDisplay::new)
+        .max_by(|a, b| overlap(a).total_cmp(&overlap(b)))
+        .and_then(|display| display.display_mode())
+        .filter(|mode| mode.width() > 0)
+        .map(|mode| mode.pixel_width() as f64 / mode.width() as f64)
+        .unwrap_or(1.0);
+    debug!(window_id, scale, "window display scale factor");
+    scale
+}
+
+fn window_dict(window_id: u32) -> Option<CFDictionary> {
+    window_info(kCGWindowListOptionIncludingWindow, window_id)
+        .into_iter()
+        .find(|dict| {
+            dict_number_to_u32(dict, unsafe { kCGWindowNumber } as *const c_void) == Some(window_id)
+        })
+}
+
+fn window_info(options: CGWindowListOption, window_id: u32) -> Vec<CFDictionary> {
+    let array_ref = unsafe { CGWindowListCopyWindowInfo(options, window_id) };
+    if array_ref.is_null() {
+        return Vec::new();
+    }
+
+    let info: CFArray<CFDictionary> = unsafe { CFArray::wrap_under_create_rule(array_ref) };
+    info.iter().map(|dict| dict.clone()).collect()
+}
+
+fn dict_bounds(dict: &CFDictionary) -> Option<CGRect> {
+    let bounds = dict_cf_type(dict, unsafe { kCGWindowBounds } as *const c_void)?
+        .downcast::<CFDictionary>()?;
+    let value = |key: &str| {
+        let key = CFString::new(key);
+        dict_cf_type(&bounds, key.as_concrete_TypeRef() as *const c_void)?
+            .downcast::<CFNumber>()?
+            .to_f64()
+    };
+
+    Some(CGRect::new(
+        &CGPoint::new(value("X")?, value("Y")?),
+        &CGSize::new(value("Width")?, value("Height")?),
+    ))
+}
+
+fn rect_contains(rect: &CGRect, point: &CGPoint) -> bool {
+    point.x >= rect.origin.x
+        && point.x < rect.origin.x + rect.size.width
+        && point.y >= rect.origin.y
+        && point.y < rect.origin.y + rect.size.height
 }
 
 fn build_window_owner_map() -> Result<HashMap<u32, WindowMeta>, String> {
@@ -174,7 +685,7 @@ fn build_window_owner_map() -> Result<HashMap<u32, WindowMeta>, String> {
 
     let mut map = HashMap::with_capacity(info.len() as usize);
 
-    for dict_ref in info.iter() {
+    for (order, dict_ref) in info.iter().enumerate() {
         let dict = &*dict_ref;
 
         let window_id = dict_number_to_u32(dict, unsafe { kCGWindowNumber } as *const c_void);
@@ -182,6 +693,8 @@ fn build_window_owner_map() -> Result<HashMap<u32, WindowMeta>, String> {
         let owner_name = dict_string(dict, unsafe { kCGWindowOwnerName } as *const c_void)
             .map(|s| s.trim().to_string())
             .filter(|s| !s.is_empty());
+        let layer =
+            dict_number_to_u32(dict, unsafe { kCGWindowLayer } as *const c_void).unwrap_or(0);
 
         if let (Some(window_id), Some(owner_pid), Some(owner_name)) =
             (window_id, owner_pid, owner_name)
@@ -189,6 +702,8 @@ fn build_window_owner_map() -> Result<HashMap<u32, WindowMeta>, String> {
             map.entry(window_id).or_insert(WindowMeta {
                 pid: owner_pid,
                 app: owner_name,
+                order,
+                layer,
             });
         }
     }
@@ -215,48 +730,77 @@ fn dict_cf_type(dict: &CFDictionary, key: *const c_void) -> Option<CFType> {
     })
 }
 
-fn capture_window_image(window_id: u32) -> Result<RgbaImage, String> {
-    let rect = CGRect::new(&CGPoint::new(0.0, 0.0), &CGSize::new(0.0, 0.0));
-    let image = create_image(
-        rect,
-        kCGWindowListOptionIncludingWindow,
-        window_id,
-        kCGWindowImageDefault | kCGWindowImageBoundsIgnoreFraming | kCGWindowImageBestResolution,
-    )
-    .ok_or_else(|| format!("Unable to capture window image for id {}", window_id))?;
+/// Takes one frame of the window through a ScreenCaptureKit window filter, so the image
+/// holds only the window wherever it is and whatever overlaps it
+fn capture_window_image(target: &WindowCaptureTarget) -> Result<RgbaImage, String> {
+    let options = Options {
+        fps: 30,
+        show_cursor: false,
+        show_highlight: false,
+        target: Some(target.target.clone()),
+        output_type: FrameType::BGRAFrame,
+        output_resolution: Resolution::Captured,
+        ..Default::default()
+    };
 
-    cgimage_to_rgba(&image)
-}
+    // scap panics when the window is gone by the time the stream is built, and a stream
+    // that fails never delivers a frame, so the capture runs off to the side
+    let (tx, rx) = mpsc::channel();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let cancel = Arc::clone(&cancelled);
+    thread::spawn(move || {
+        let result = Capturer::build(options).map(|mut capturer| {
+            capturer.start_capture();
+            let frame = next_window_frame(&capturer, &cancel);
+            capturer.stop_capture();
+            frame
+        });
+        let _ = tx.send(result);
+    });
 
-fn cgimage_to_rgba(image: &CGImage) -> Result<RgbaImage, String> {
-    let width = image.width();
-    let height = image.height();
+    let frame = match rx.recv_timeout(WINDOW_FRAME_TIMEOUT) {
+        Ok(Ok(Some(frame))) => frame,
+        Ok(Ok(None)) => {
+            return Err(format!("Capture of window {} stopped", target.window_id));
+        }
+        Ok(Err(err)) => return Err(err.to_string()),
+        Err(_) => {
+            // The stream only hands control back with its next frame, so the thread stops
+            // it and exits then
+            cancelled.store(true, Ordering::Relaxed);
+            return Err(format!(
+                "No frame of window {} arrived in time",
+                target.window_id
+            ));
+        }
+    };
 
-    if width == 0 || height == 0 {
-        return Err("Captured window image had zero dimensions".into());
-    }
+    bgra_to_rgba(frame)
+}
 
-    let color_space = CGColorSpace::create_device_rgb();
-    let bytes_per_row = width * 4;
-
-    let mut context = CGContext::create_bitmap_context(
-        None,
-        width,
-        height,
-        8,
-        bytes_per_row,
-        &color_space,
-        kCGImageAlphaPremultipliedLast | kCGBitmapByteOrder32Big,
-    );
+/// The first frame with content, or `None` once `cancelled`; unchanged frames come
+/// through empty
+fn next_window_frame(capturer: &Capturer, cancelled: &AtomicBool) -> Option<BGRAFrame> {
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        match capturer.get_next_frame().ok()? {
+            Frame::Video(VideoFrame::BGRA(frame)) if frame.width > 0 && frame.height > 0 => {
+                return Some(frame);
+            }
+            _ => continue,
+        }
+    }
+}
 
-    let draw_rect = CGRect::new(
-        &CGPoint::new(0.0, 0.0),
-        &CGSize::new(width as f64, height as f64),
-    );
-    context.draw_image(draw_rect, image);
+fn bgra_to_rgba(frame: BGRAFrame) -> Result<RgbaImage, String> {
+    let mut data = frame.data;
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
 
-    let data = context.data().to_vec();
-    RgbaImage::from_vec(width as u32, height as u32, data)
+    RgbaImage::from_vec(frame.width as u32, frame.height as u32, data)
         .ok_or_else(|| "Failed to convert captured window image".to_string())
 }
 
@@ -0,0 +1,378 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::os::raw::c_void;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::runloop::{
+    CFRunLoop, CFRunLoopSource, CFRunLoopSourceRef, kCFRunLoopDefaultMode,
+};
+use core_foundation::string::{CFString, CFStringRef};
+
+type AXUIElementRef = *const c_void;
+type AXObserverRef = *const c_void;
+type AXObserverCallback = extern "C" fn(AXObserverRef, AXUIElementRef, CFStringRef, *mut c_void);
+type DisplayReconfigurationCallback = extern "C" fn(u32, u32, *mut c_void);
+
+const AX_ERROR_SUCCESS: i32 = 0;
+
+/// kCGDisplayBeginConfigurationFlag: sent before the change, followed by the actual one
+const DISPLAY_BEGIN_CONFIGURATION: u32 = 1;
+
+/// How long window events must settle before the capture, so a drag or a page load is
+/// captured once at its end instead of at every step
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Longest events may keep postponing a capture, so e.g. a scrolling animation or a ticking
+/// title still gets captured while it lasts
+pub const MAX_DEBOUNCE: Duration = Duration::from_secs(3);
+
+/// Longest heartbeat interval; longer ones, e.g. infinite, are cut down to it
+pub const MAX_HEARTBEAT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Accessibility notifications on the tracked app that warrant a new capture
+const NOTIFICATIONS: [(&str, Trigger); 6] = [
+    ("AXWindowMoved", Trigger::WindowMoved),
+    ("AXWindowResized", Trigger::WindowResized),
+    ("AXTitleChanged", Trigger::TitleChanged),
+    ("AXFocusedWindowChanged", Trigger::WindowChanged),
+    ("AXMainWindowChanged", Trigger::WindowChanged),
+    ("AXWindowDeminiaturized", Trigger::WindowChanged),
+];
+
+/// Window events not yet captured, by the PID of the app they are about
+static PENDING: Mutex<BTreeMap<u32, Pending>> = Mutex::new(BTreeMap::new());
+
+/// PIDs watched by a `ChangeTriggers`, which all hear about display changes
+static WATCHED: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// Number of `ChangeTriggers` alive, which share one display reconfiguration callback
+static DISPLAY_OBSERVERS: Mutex<usize> = Mutex::new(0);
+
+#[derive(Clone, Copy)]
+struct Pending {
+    /// Reason of the first event
+    trigger: Trigger,
+    first: Instant,
+    last: Instant,
+}
+
+impl Pending {
+    /// When the events have settled, or waited long enough regardless
+    fn due_at(&self) -> Instant {
+        (self.last + DEBOUNCE).min(self.first + MAX_DEBOUNCE)
+    }
+}
+
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn AXUIElementCreateApplication(pid: i32) -> AXUIElementRef;
+    fn AXObserverCreate(
+        pid: i32,
+        callback: AXObserverCallback,
+        observer: *mut AXObserverRef,
+    ) -> i32;
+    fn AXObserverAddNotification(
+        observer: AXObserverRef,
+        element: AXUIElementRef,
+        notification: CFStringRef,
+        refcon: *mut c_void,
+    ) -> i32;
+    fn AXObserverGetRunLoopSource(observer: AXObserverRef) -> CFRunLoopSourceRef;
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGDisplayRegisterReconfigurationCallback(
+        callback: DisplayReconfigurationCallback,
+        user_info: *mut c_void,
+    ) -> i32;
+    fn CGDisplayRemoveReconfigurationCallback(
+        callback: DisplayReconfigurationCallback,
+        user_info: *mut c_void,
+    ) -> i32;
+}
+
+/// Why a capture was taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Nothing captured yet for the current app
+    Started,
+    WindowMoved,
+    WindowResized,
+    /// The window or tab title changed, e.g. after navigating to another page
+    TitleChanged,
+    /// Another window of the app came to front or out of the Dock
+    WindowChanged,
+    DisplaysChanged,
+    /// No event for a whole heartbeat interval, in case the content changed on its own
+    Heartbeat,
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Trigger::Started => "start",
+            Trigger::WindowMoved => "window moved",
+            Trigger::WindowResized => "window resized",
+            Trigger::TitleChanged => "title changed",
+            Trigger::WindowChanged => "window changed",
+            Trigger::DisplaysChanged => "displays changed",
+            Trigger::Heartbeat => "heartbeat",
+        })
+    }
+}
+
+/// Decides when to capture, from window events of the tracked app, display reconfigurations
+/// and a heartbeat, instead of capturing at a fixed rate.
+///
+/// Several can run at once, one per watched app, each seeing only its own app's events.
+///
+/// Events are delivered while the main run loop runs, i.e. during `app::idle` or
+/// `FocusTracker::wait`, either of which returns early when one arrives.
+pub struct ChangeTriggers {
+    heartbeat: Duration,
+    observation: Option<Observation>,
+    pid: Option<u32>,
+    last_capture: Option<Instant>,
+}
+
+impl ChangeTriggers {
+    pub fn start(heartbeat: Duration) -> Self {
+        let heartbeat = heartbeat.min(MAX_HEARTBEAT);
+        let mut observers = lock(&DISPLAY_OBSERVERS);
+        if *observers == 0 {
+            unsafe {
+                CGDisplayRegisterReconfigurationCallback(displays_changed, std::ptr::null_mut());
+            }
+        }
+        *observers += 1;
+
+        Self {
+            heartbeat,
+            observation: None,
+            pid: None,
+            last_capture: None,
+        }
+    }
+
+    /// Follows the windows of `pid`; switching to another app captures it right away.
+    ///
+    /// Without Accessibility permission window events are missing and only the heartbeat
+    /// and display changes trigger captures.
+    pub fn watch(&mut self, pid: u32) {
+        if self.pid == Some(pid) {
+            return;
+        }
+
+        let mut watched = lock(&WATCHED);
+        if let Some(previous) = self.pid.replace(pid) {
+            unwatch(&mut watched, previous);
+        }
+        watched.push(pid);
+        self.last_capture = None;
+        drop(self.observation.take());
+        self.observation = Observation::new(pid);
+    }
+
+    /// Whether window events are observed for the current app
+    pub fn observes_windows(&self) -> bool {
+        self.observation.is_some()
+    }
+
+    /// Captures again on the next check, e.g. after the capture target was re-acquired
+    pub fn force(&mut self) {
+        self.last_capture = None;
+    }
+
+    /// Records a change noticed outside of the run loop, e.g. by `WindowTracker`, like a
+    /// window event
+    pub fn notify(&self, trigger: Trigger) {
+        if let Some(pid) = self.pid {
+            note(pid, trigger);
+        }
+    }
+
+    /// Why a capture is due now, if it is; the capture is then assumed taken
+    pub fn due(&mut self) -> Option<Trigger> {
+        let now = Instant::now();
+        let mut pending = lock(&PENDING);
+        let current = self.pid.and_then(|pid| pending.get(&pid).copied());
+        let trigger = match current {
+            None if self.last_capture.is_none() => Trigger::Started,
+            Some(pending) if now >= pending.due_at() => pending.trigger,
+            // Still settling; the heartbeat waits for it too
+            Some(_) => return None,
+            None if self.is_heartbeat_due(now) => Trigger::Heartbeat,
+            None => return None,
+        };
+
+        if let Some(pid) = self.pid {
+            pending.remove(&pid);
+        }
+        self.last_capture = Some(now);
+        Some(trigger)
+    }
+
+    /// How long to wait for events before checking `due` again
+    pub fn timeout(&self) -> Duration {
+        let now = Instant::now();
+        let pending = self.pid.and_then(|pid| lock(&PENDING).get(&pid).copied());
+        let remaining = match pending {
+            Some(pending) => pending.due_at().saturating_duration_since(now),
+            None => match self.last_capture {
+                Some(last_capture) => {
+                    (last_capture + self.heartbeat).saturating_duration_since(now)
+                }
+                None => Duration::ZERO,
+            },
+        };
+        // The run loop returns at once for a zero timeout, which would spin
+        remaining.max(Duration::from_millis(10))
+    }
+
+    fn is_heartbeat_due(&self, now: Instant) -> bool {
+        self.last_capture
+            .is_none_or(|last_capture| now.duration_since(last_capture) >= self.heartbeat)
+    }
+}
+
+impl Drop for ChangeTriggers {
+    fn drop(&mut self) {
+        if let Some(pid) = self.pid {
+            unwatch(&mut lock(&WATCHED), pid);
+        }
+        let mut observers = lock(&DISPLAY_OBSERVERS);
+        *observers -= 1;
+        if *observers == 0 {
+            unsafe {
+                CGDisplayRemoveReconfigurationCallback(displays_changed, std::ptr::null_mut());
+            }
+        }
+    }
+}
+
+/// Forgets one watcher of `pid`, and its pending events once nobody watches it
+fn unwatch(watched: &mut Vec<u32>, pid: u32) {
+    if let Some(index) = watched.iter().position(|watched| *watched == pid) {
+        watched.swap_remove(index);
+    }
+    if !watched.contains(&pid) {
+        lock(&PENDING).remove(&pid);
+    }
+}
+
+/// AX observer of one app registered on the main run loop; unregistered when dropped
+struct Observation {
+    run_loop: CFRunLoop,
+    source: CFRunLoopSource,
+    _observer: CFType,
+    _app: CFType,
+}
+
+impl Observation {
+    fn new(pid: u32) -> Option<Self> {
+        let mut observer: AXObserverRef = std::ptr::null();
+        let result = unsafe { AXObserverCreate(pid as i32, window_event, &mut observer) };
+        if result != AX_ERROR_SUCCESS || observer.is_null() {
+            return None;
+        }
+        let observer = unsafe { CFType::wrap_under_create_rule(observer as CFTypeRef) };
+
+        let app = unsafe { AXUIElementCreateApplication(pid as i32) };
+        if app.is_null() {
+            return None;
+        }
+        let app = unsafe { CFType::wrap_under_create_rule(app as CFTypeRef) };
+
+        let mut registered = false;
+        for (index, (name, _)) in NOTIFICATIONS.iter().enumerate() {
+            let name = CFString::new(name);
+            registered |= unsafe {
+                AXObserverAddNotification(
+                    observer.as_CFTypeRef(),
+                    app.as_CFTypeRef(),
+                    name.as_concrete_TypeRef(),
+                    // The app and the notification's index, so the callback doesn't compare
+                    // names
+                    refcon(pid, index),
+                )
+            } == AX_ERROR_SUCCESS;
+        }
+        if !registered {
+            return None;
+        }
+
+        let source = unsafe {
+            CFRunLoopSource::wrap_under_get_rule(AXObserverGetRunLoopSource(
+                observer.as_CFTypeRef(),
+            ))
+        };
+        let run_loop = CFRunLoop::get_current();
+        run_loop.add_source(&source, unsafe { kCFRunLoopDefaultMode });
+
+        Some(Self {
+            run_loop,
+            source,
+            _observer: observer,
+            _app: app,
+        })
+    }
+}
+
+impl Drop for Observation {
+    fn drop(&mut self) {
+        self.run_loop
+            .remove_source(&self.source, unsafe { kCFRunLoopDefaultMode });
+    }
+}
+
+extern "C" fn window_event(
+    _observer: AXObserverRef,
+    _element: AXUIElementRef,
+    _notification: CFStringRef,
+    refcon: *mut c_void,
+) {
+    let refcon = refcon as usize;
+    let pid = (refcon >> 8) as u32;
+    if let Some((_, trigger)) = NOTIFICATIONS.get(refcon & 0xff) {
+        note(pid, *trigger);
+        wake();
+    }
+}
+
+extern "C" fn displays_changed(_display: u32, flags: u32, _user_info: *mut c_void) {
+    if flags & DISPLAY_BEGIN_CONFIGURATION == 0 {
+        for pid in lock(&WATCHED).iter() {
+            note(*pid, Trigger::DisplaysChanged);
+        }
+        wake();
+    }
+}
+
+/// Packs the PID and the index of a notification into the observer's refcon
+fn refcon(pid: u32, index: usize) -> *mut c_void {
+    (((pid as usize) << 8) | index) as *mut c_void
+}
+
+fn lock<T>(mutex: &'static Mutex<T>) -> std::sync::MutexGuard<'static, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn note(pid: u32, trigger: Trigger) {
+    let now = Instant::now();
+    lock(&PENDING)
+        .entry(pid)
+        .and_modify(|pending| pending.last = now)
+        .or_insert(Pending {
+            trigger,
+            first: now,
+            last: now,
+        });
+}
+
+/// Ends the current wait so the debounce starts right away
+fn wake() {
+    CFRunLoop::get_current().stop();
+}
@@ -0,0 +1,244 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+
+use crate::app::{self, AppSelector};
+use crate::lifecycle::{LifecycleEvent, LifecycleWatcher, SleepState};
+use crate::proc::{self, WindowCaptureTarget};
+use crate::triggers::{ChangeTriggers, Trigger};
+use crate::{Cli, capture};
+
+/// How the apps of a watch list share the capture interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Schedule {
+    /// At most one app per interval, taking turns among those due
+    RoundRobin,
+    /// Every app due each interval, captured at the same time
+    Parallel,
+}
+
+/// One app of the watch list, with a capture target of its own
+struct WatchedApp {
+    selector: AppSelector,
+    /// `None` while the app isn't running
+    pid: Option<u32>,
+    name: String,
+    target: Option<WindowCaptureTarget>,
+    output_dir: PathBuf,
+    /// When the app's window is due for a capture, like the single app's
+    triggers: ChangeTriggers,
+}
+
+impl WatchedApp {
+    fn new(selector: AppSelector, output_root: &Path, heartbeat: Duration) -> Result<Self, String> {
+        let dir_name = match &selector {
+            AppSelector::Name(name) | AppSelector::BundleId(name) => sanitize(name),
+        };
+        let output_dir = output_root.join(dir_name);
+        fs::create_dir_all(&output_dir).map_err(|err| {
+            format!(
+                "Unable to create output directory {}: {}",
+                output_dir.display(),
+                err
+            )
+        })?;
+
+        Ok(Self {
+            selector,
+            pid: None,
+            name: String::new(),
+            target: None,
+            output_dir,
+            triggers: ChangeTriggers::start(heartbeat),
+        })
+    }
+
+    /// Looks up the app and its window if they went missing, returning whether there is
+    /// something to capture
+    fn refresh(&mut self) -> bool {
+        if self.pid.is_none() {
+            let Some(pid) = self.selector.find_running() else {
+                return false;
+            };
+            self.name = proc::resolve_app_name(pid).unwrap_or_else(|_| self.selector.to_string());
+            println!("{} is running as PID {}", self.selector, pid);
+            self.pid = Some(pid);
+            self.triggers.watch(pid);
+        }
+
+        if self.target.is_none()
+            && let Some(pid) = self.pid
+        {
+            self.target = proc::prepare_window_capture(pid).ok();
+            if let Some(target) = &self.target {
+                println!(
+                    "Tracking {} window '{}' (id={})",
+                    self.name, target.window_title, target.window_id
+                );
+            }
+        }
+
+        self.target.is_some()
+    }
+
+    /// Whether a capture is due, printing why unless it is just the heartbeat
+    fn due(&mut self) -> bool {
+        let Some(trigger) = self.triggers.due() else {
+            return false;
+        };
+        if trigger != Trigger::Heartbeat {
+            println!("Capturing {}: {}", self.name, trigger);
+        }
+        true
+    }
+
+    fn capture(&self, args: &Cli) -> Result<(), String> {
+        match (self.pid, &self.target) {
+            (Some(pid), Some(target)) => capture(args, pid, &self.name, target, &self.output_dir),
+            _ => Ok(()),
+        }
+    }
+
+    /// Handles a failed capture by re-acquiring the window on the next round
+    fn capture_failed(&mut self, err: String) {
+        if let Some(target) = self.target.take() {
+            eprintln!(
+                "Capture failed for {} window {} (id={}): {}",
+                self.name, target.window_title, target.window_id, err
+            );
+        }
+        self.triggers.force();
+    }
+}
+
+/// Captures every app of `selectors` into its own subdirectory of `output_root` until the
+/// process is stopped; apps that aren't running are waited for
+pub fn run(
+    args: &Cli,
+    selectors: Vec<AppSelector>,
+    output_root: &Path,
+    lifecycle: &LifecycleWatcher,
+) -> Result<(), String> {
+    let mut apps = selectors
+        .into_iter()
+        .map(|selector| WatchedApp::new(selector, output_root, args.heartbeat()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let interval = Duration::from_secs_f64(args.interval.max(0.1));
+
+    println!(
+        "Watching {} apps ({:?}). Press Ctrl+C to stop.",
+        apps.len(),
+        args.schedule
+    );
+
+    let mut next_turn = 0;
+    let mut sleep = SleepState::default();
+    loop {
+        let round_started = Instant::now();
+        for event in lifecycle.drain() {
+            // Windows and displays may have changed while asleep
+            if sleep.handle(event) {
+                for app in &mut apps {
+                    app.target = None;
+                    app.triggers.force();
+                }
+            }
+            match event {
+                // The windows may have come into view or gone out of it
+                LifecycleEvent::SpaceChanged => {
+                    for app in &mut apps {
+                        app.triggers.force();
+                    }
+                }
+                LifecycleEvent::Terminated(quit) => {
+                    for app in apps.iter_mut().filter(|app| app.pid == Some(quit)) {
+                        println!("{} quit; waiting for it to launch again", app.selector);
+                        app.pid = None;
+                        app.target = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !sleep.is_asleep() {
+            let ready: Vec<usize> = (0..apps.len()).filter(|&i| apps[i].refresh()).collect();
+            match args.schedule {
+                Schedule::RoundRobin => {
+                    // The first app due at or after the one whose turn it is
+                    let (later, earlier): (Vec<usize>, Vec<usize>) =
+                        ready.iter().partition(|&&i| i >= next_turn);
+                    let turn = later.into_iter().chain(earlier).find(|&i| apps[i].due());
+                    if let Some(turn) = turn {
+                        if let Err(err) = apps[turn].capture(args) {
+                            apps[turn].capture_failed(err);
+                        }
+                        next_turn = turn + 1;
+                    }
+                }
+                Schedule::Parallel => {
+                    let due: Vec<usize> = ready.into_iter().filter(|&i| apps[i].due()).collect();
+                    let results: Vec<(usize, Result<(), String>)> = thread::scope(|scope| {
+                        let handles: Vec<_> = due
+                            .iter()
+                            .map(|&i| {
+                                let app = &apps[i];
+                                (i, scope.spawn(move || app.capture(args)))
+                            })
+                            .collect();
+                        handles
+                            .into_iter()
+                            .map(|(i, handle)| {
+                                let result = handle
+                                    .join()
+                                    .unwrap_or_else(|_| Err("capture thread panicked".into()));
+                                (i, result)
+                            })
+                            .collect()
+                    });
+                    for (i, result) in results {
+                        if let Err(err) = result {
+                            apps[i].capture_failed(err);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Until the next capture is due, but a whole interval at least
+        let next_due = apps
+            .iter()
+            .map(|app| app.triggers.timeout())
+            .min()
+            .unwrap_or(interval);
+        app::idle(
+            interval
+                .saturating_sub(round_started.elapsed())
+                .max(next_due),
+        );
+    }
+}
+
+/// Directory name for an app, e.g. "Google Chrome" becomes Google-Chrome; leading and
+/// trailing dots are dropped, so the name can't be "." or ".."
+fn sanitize(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let name = name.trim_matches(['-', '.']);
+    if name.is_empty() {
+        "app".to_string()
+    } else {
+        name.to_string()
+    }
+}
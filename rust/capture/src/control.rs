@@ -0,0 +1,238 @@
+use crate::rpc::{parse_params, AskParams};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use watcher_core::{
+    CaptureOptions, CaptureSession, CaptureTarget, RpcRequest, RPC_INTERNAL_ERROR,
+    RPC_INVALID_PARAMS, RPC_INVALID_REQUEST, RPC_METHOD_NOT_FOUND, RPC_PARSE_ERROR,
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TargetParams {
+    window_id: Option<u32>,
+    pid: Option<u32>,
+    window_title: Option<String>,
+    display_id: Option<u32>,
+}
+
+impl TargetParams {
+    /// The requested target; the main display when none is given
+    fn target(self) -> CaptureTarget {
+        match (self.window_id, self.pid, self.window_title, self.display_id) {
+            (Some(id), _, _, _) => CaptureTarget::Window(id),
+            (_, Some(pid), _, _) => CaptureTarget::Process(pid),
+            (_, _, Some(title), _) => CaptureTarget::WindowTitle(title),
+            (_, _, _, Some(id)) => CaptureTarget::DisplayId(id),
+            _ => CaptureTarget::Display,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptParams {
+    prompt: Option<String>,
+}
+
+/// Serves JSON-RPC commands to a running watcher over a Unix socket, one JSON message per
+/// line, so another process, e.g. a menu bar app, can pause it, ask questions or point it at
+/// another window
+pub struct ControlServer {
+    listener: UnixListener,
+    path: PathBuf,
+    session: Arc<CaptureSession>,
+    /// Capture settings a new target is applied to
+    options: CaptureOptions,
+}
+
+impl ControlServer {
+    /// Listens on `path`, replacing a socket left behind by an earlier run
+    pub fn bind(
+        path: &Path,
+        session: Arc<CaptureSession>,
+        options: CaptureOptions,
+    ) -> std::io::Result<Self> {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                format!("{} is served by another watcher", path.display()),
+            ));
+        }
+        let _ = std::fs::remove_file(path);
+        Ok(Self {
+            listener: UnixListener::bind(path)?,
+            path: path.to_path_buf(),
+            session,
+            options,
+        })
+    }
+
+    /// Accepts clients until the returned handle is dropped, which also removes the socket
+    pub fn spawn(self) -> ControlSocket {
+        let path = self.path.clone();
+        let state = Arc::new(Mutex::new(self.options));
+        let session = self.session;
+        let listener = self.listener;
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        session
+                            .printer()
+                            .print_error(&format!("❌ Control socket error: {}", e));
+                        break;
+                    }
+                };
+                let client = ControlClient {
+                    session: Arc::clone(&session),
+                    options: Arc::clone(&state),
+                };
+                tokio::spawn(client.serve(stream));
+            }
+        });
+        ControlSocket { path, task }
+    }
+}
+
+/// A listening control socket; dropping it stops accepting clients and removes the file
+pub struct ControlSocket {
+    path: PathBuf,
+    task: JoinHandle<()>,
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// One connected client; commands are handled in order and answered on the same connection
+struct ControlClient {
+    session: Arc<CaptureSession>,
+    options: Arc<Mutex<CaptureOptions>>,
+}
+
+impl ControlClient {
+    async fn serve(self, stream: UnixStream) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle(message).await,
+                Err(e) => Some(error_response(Value::Null, RPC_PARSE_ERROR, e.to_string())),
+            };
+            let Some(response) = response else {
+                continue;
+            };
+            let mut payload = response.to_string();
+            payload.push('\n');
+            if writer.write_all(payload.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Response to a message, or `None` for a notification
+    async fn handle(&self, message: Value) -> Option<Value> {
+        let request: RpcRequest = match serde_json::from_value(message) {
+            Ok(request) => request,
+            Err(e) => {
+                return Some(error_response(
+                    Value::Null,
+                    RPC_INVALID_REQUEST,
+                    e.to_string(),
+                ));
+            }
+        };
+        let result = self.dispatch(&request.method, request.params).await;
+        let id = request.id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, message),
+        })
+    }
+
+    async fn dispatch(&self, method: &str, params: Value) -> Result<Value, (i64, String)> {
+        match method {
+            "pause" => {
+                self.session.pause();
+                Ok(json!({ "paused": true }))
+            }
+            "resume" => {
+                self.session.resume();
+                Ok(json!({ "paused": self.session.is_paused() }))
+            }
+            "status" => Ok(json!({
+                "paused": self.session.is_paused(),
+                "connected": !self.session.sender().is_closed(),
+                "target": target_json(&self.options.lock().await.target),
+            })),
+            "ask" => {
+                let params: AskParams = parse_params(params)?;
+                if params.question.trim().is_empty() {
+                    return Err((RPC_INVALID_PARAMS, "question must not be empty".to_string()));
+                }
+                self.session
+                    .send_question(params.question)
+                    .await
+                    .map_err(|err| (RPC_INTERNAL_ERROR, err.to_string()))?;
+                Ok(json!({ "sent": true }))
+            }
+            "setTarget" => {
+                if self.session.captures_all_displays() {
+                    return Err((
+                        RPC_INVALID_REQUEST,
+                        "The target can't change while capturing all displays".to_string(),
+                    ));
+                }
+                let params: TargetParams = parse_params(params)?;
+                let mut options = self.options.lock().await;
+                let mut retargeted = options.clone();
+                retargeted.target = params.target();
+                // The window highlight border would end up in every window frame
+                retargeted.show_highlight = !retargeted.target.is_window();
+                self.session
+                    .retarget(retargeted.clone())
+                    .map_err(|err| (RPC_INTERNAL_ERROR, err.to_string()))?;
+                *options = retargeted;
+                Ok(json!({ "target": target_json(&options.target) }))
+            }
+            "setPrompt" => {
+                let params: PromptParams = parse_params(params)?;
+                self.session.set_prompt(params.prompt);
+                Ok(json!({ "configured": true }))
+            }
+            other => Err((RPC_METHOD_NOT_FOUND, format!("Unknown method: {}", other))),
+        }
+    }
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message }
+    })
+}
+
+/// Target in the shape `setTarget` accepts it
+fn target_json(target: &CaptureTarget) -> Value {
+    match target {
+        CaptureTarget::Display => json!({}),
+        CaptureTarget::DisplayId(id) => json!({ "displayId": id }),
+        CaptureTarget::Window(id) => json!({ "windowId": id }),
+        CaptureTarget::Process(pid) => json!({ "pid": pid }),
+        CaptureTarget::WindowTitle(title) => json!({ "windowTitle": title }),
+    }
+}
@@ -1,85 +1,2381 @@
-use scap::capturer::{Capturer, Options};
+mod control;
+mod memory_refresh;
+mod reconnect;
+mod rpc;
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(feature = "voice")]
+mod voice;
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "sqlite")]
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
 use watcher_core::{
-    ensure_clean_directory, ensure_screen_recording_permission, CaptureSession,
-    CliResponsePrinter, ConnectionOptions, Content, FrameSource, GenerationConfig, GeminiSession,
-    OutputProcessor, Setup,
+    display_sources, ensure_screen_recording_permission, export_timelapse, find_legacy_frames,
+    migrate_legacy_output, tool_declarations, ActivityAggregator, ActivityMeter,
+    AggregatingPrinter, AggregationConfig, AlertRules, AnalysisBackend, AnalysisEvents,
+    AnnotationSaver, AnonymizeMode, Anonymizer, AnswerHistory, AppExclusion, Backend, BatchOptions,
+    CaptureEvent, CaptureOptions, CaptureSession, CaptureTarget, CategoryAnonymizer,
+    ChangeDetector, CliResponsePrinter, ClickWatcher, ClipboardContext, ConnectionOptions, Content,
+    DirectoryFrameSource, DisplayMode, DryRun, FocusSession, FrameDeduplicator, FrameFormat,
+    FrameSource, FrameStore, GenerationConfig, HashAnonymizer, IdleWatch, KeyLimits, KeyPool,
+    LiveFeed, LiveFeedServer, ManifestWriter, MenuWatcher, OfflineQueue, OutputProcessor,
+    Passthrough, PointerTracker, PreviewFeed, PreviewServer, PromptProfile, Rect, RedactionRule,
+    RedactionStyle, Redactor, ResizeFilter, ResizeOptions, ResizeTarget, ResourceLimits,
+    ResponsePrinter, RetentionManager, RetentionPolicy, RpcWriter, ScreenActivity, ScreenLockWatch,
+    ScreenshotTool, SessionOptions, Setup, SummaryCapture, SummaryLog, Telemetry, TimelapseFormat,
+    TimelapseOptions, ToolHandler, TurnTracker, WatcherConfig, ZoomFollow, ACTIVITY_INSTRUCTION,
+    AGGREGATE_INSTRUCTION, AUTO_ARCHIVE_MAX_FRAMES, DEFAULT_FILENAME_PATTERN, DEFAULT_FOCUS_STREAK,
+    MAX_CLIPBOARD_TEXT, ZOOM_NARRATION_INSTRUCTION,
 };
 
+#[derive(Parser, Debug)]
+#[command(about = "Capture the screen and describe it with Gemini", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Follow the mouse cursor with a zoomed crop and narrate it like a presenter demo
+    #[arg(long)]
+    zoom_follow: bool,
+
+    /// Capture a single window by its window id instead of the whole display
+    #[arg(long, group = "window_target", conflicts_with = "zoom_follow")]
+    window_id: Option<u32>,
+
+    /// Capture the frontmost window owned by this process
+    #[arg(long, group = "window_target", conflicts_with = "zoom_follow")]
+    pid: Option<u32>,
+
+    /// Capture the first window whose title contains this text
+    #[arg(long, group = "window_target", conflicts_with = "zoom_follow")]
+    window_title: Option<String>,
+
+    /// Analyze the images of a directory, e.g. an earlier session's output, instead of the
+    /// screen
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = [
+            "zoom_follow", "window_target", "region", "all_displays", "menu_events",
+            "redact_app", "redact_title", "redact_region",
+        ]
+    )]
+    replay: Option<PathBuf>,
+
+    /// Seconds between replayed images
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 1.0,
+        requires = "replay"
+    )]
+    replay_interval: f64,
+
+    /// Only capture this rectangle of the display or window, in points: x,y,width,height
+    #[arg(
+        long,
+        value_name = "X,Y,W,H",
+        conflicts_with_all = ["zoom_follow", "menu_events", "all_displays"]
+    )]
+    region: Option<Rect>,
+
+    /// Capture every connected display, alternating between them (round-robin) or side by
+    /// side in one frame (composite)
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        default_missing_value = "round-robin",
+        conflicts_with = "window_target"
+    )]
+    all_displays: Option<DisplayMode>,
+
+    /// Black out the windows of apps whose name contains this text, e.g. 1Password; repeatable
+    #[arg(long, value_name = "APP", conflicts_with = "window_target")]
+    redact_app: Vec<String>,
+
+    /// Black out windows whose title contains this text; repeatable
+    #[arg(long, value_name = "TEXT", conflicts_with = "window_target")]
+    redact_title: Vec<String>,
+
+    /// Black out this screen rectangle, in global points: x,y,width,height; repeatable
+    #[arg(long, value_name = "X,Y,W,H", conflicts_with = "window_target")]
+    redact_region: Vec<Rect>,
+
+    /// Capture nothing while an app with this bundle ID or name is in front or has a window
+    /// in the captured area, e.g. com.apple.MobileSMS, logging a redacted interval instead;
+    /// repeatable
+    #[arg(long, value_name = "APP", conflicts_with = "replay")]
+    exclude_app: Vec<String>,
+
+    /// How redacted areas are masked: black or pixelate
+    #[arg(long, default_value = "black")]
+    redact_style: RedactionStyle,
+
+    /// Tell the model where the mouse pointer is and where the user clicked (clicks need
+    /// Input Monitoring permission)
+    #[arg(long, conflicts_with_all = ["aggregate", "window_target"])]
+    pointer: bool,
+
+    /// Tell the model how many keys were pressed and how often the user clicked between
+    /// screenshots, and list the counts in the manifest; never which keys or where (needs
+    /// Input Monitoring permission)
+    #[arg(long, conflicts_with_all = ["aggregate", "replay"])]
+    activity_meter: bool,
+
+    /// Quote text copied to the clipboard in the next screenshot prompt, with API keys,
+    /// emails and long tokens redacted
+    #[arg(long, conflicts_with_all = ["aggregate", "replay"])]
+    clipboard: bool,
+
+    /// Also redact clipboard words matching this pattern, where * matches anything, e.g.
+    /// "acme-*"; repeatable
+    #[arg(long, value_name = "PATTERN", requires = "clipboard")]
+    clipboard_redact: Vec<String>,
+
+    /// Longest clipboard text quoted, in characters
+    #[arg(
+        long,
+        value_name = "CHARS",
+        default_value_t = MAX_CLIPBOARD_TEXT,
+        requires = "clipboard"
+    )]
+    clipboard_chars: usize,
+
+    /// Also capture a tight crop whenever a menu is opened and log the menu items chosen
+    /// (requires Accessibility permission)
+    #[arg(long, conflicts_with_all = ["aggregate", "window_target"])]
+    menu_events: bool,
+
+    /// Pause capture once there has been no keyboard or mouse input for this many seconds and
+    /// resume on the next input, noting the gap in the activity log
+    #[arg(long, value_name = "SECONDS", conflicts_with = "replay")]
+    pause_when_idle: Option<u64>,
+
+    /// How app names and window titles are rewritten before they reach prompts and logs:
+    /// passthrough, hash or category
+    #[arg(long, default_value = "passthrough")]
+    anonymize: AnonymizeMode,
+
+    /// Salt mixed into hashed names, so hashes can't be matched against other datasets
+    #[arg(long, default_value = "")]
+    anonymize_salt: String,
+
+    /// JSON file with category rules used by --anonymize category
+    #[arg(long, value_name = "FILE")]
+    anonymize_categories: Option<PathBuf>,
+
+    /// Magnification used by --zoom-follow
+    #[arg(long, default_value_t = 2.0)]
+    zoom: f64,
+
+    /// Downscale frames so their longer side is at most this many pixels before encoding
+    #[arg(long, value_name = "PIXELS", conflicts_with_all = ["resize_width", "resize_height"])]
+    max_edge: Option<u32>,
+
+    /// Downscale frames to fit this width before encoding (keeps the aspect ratio)
+    #[arg(long, value_name = "PIXELS")]
+    resize_width: Option<u32>,
+
+    /// Downscale frames to fit this height before encoding (keeps the aspect ratio)
+    #[arg(long, value_name = "PIXELS")]
+    resize_height: Option<u32>,
+
+    /// Resampling filter used for downscaling: nearest, bilinear or lanczos
+    #[arg(long, default_value = "lanczos")]
+    resize_filter: ResizeFilter,
+
+    /// Skip frames where less than this percentage of the screen changed since the last frame
+    /// that was sent
+    #[arg(long, value_name = "PERCENT", num_args = 0..=1, default_missing_value = "1.0")]
+    skip_unchanged: Option<f64>,
+
+    /// Don't resend a screen that matches one of the last N analyzed frames, e.g. when
+    /// switching back and forth between two windows
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "8")]
+    dedup: Option<usize>,
+
+    /// Send frames in batches of N as one turn asking what happened over the period
+    #[arg(long, value_name = "N", conflicts_with_all = ["aggregate", "annotate"])]
+    batch: Option<usize>,
+
+    /// Also send a batch once its first frame is this many seconds old
+    #[arg(long, value_name = "SECONDS", requires = "batch")]
+    batch_seconds: Option<u64>,
+
+    /// Keep capturing until Ctrl+C instead of stopping after --frames frames
+    #[arg(long)]
+    continuous: bool,
+
+    /// Frames to capture before stopping, also with `--ocr offline` [default: 10, or the
+    /// config's capture.frames]
+    #[arg(long, value_name = "N", conflicts_with = "continuous")]
+    frames: Option<usize>,
+
+    /// Seconds between frames in continuous mode
+    #[arg(long, default_value_t = 1.0, requires = "continuous")]
+    interval: f64,
+
+    /// Question sent with each frame; {index}, {time} and {app} are replaced with the frame
+    /// number, the time and the active app
+    #[arg(long, value_name = "TEMPLATE", conflicts_with = "aggregate")]
+    prompt: Option<String>,
+
+    /// Context put in front of every prompt, e.g. "I am a backend developer."
+    #[arg(long, value_name = "TEXT", conflicts_with = "aggregate")]
+    preamble: Option<String>,
+
+    /// Image format frames are saved and sent in: jpeg or png
+    #[arg(long, default_value = "jpeg")]
+    format: FrameFormat,
+
+    /// JPEG quality from 1 to 100
+    #[arg(long, default_value_t = 90, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: u8,
+
+    /// Directory frames, the manifest and session data are kept in; defaults to output.dir in
+    /// the config file, or ./output
+    #[arg(long, value_name = "PATH")]
+    output_dir: Option<PathBuf>,
+
+    /// Send frames without saving them to the output directory
+    #[arg(long)]
+    no_save: bool,
+
+    /// Save JPEGs without EXIF and XMP metadata about the capture and the model's answer
+    #[arg(long)]
+    no_metadata: bool,
+
+    /// Don't list saved frames and answers in the output directory's manifest.jsonl
+    #[arg(long)]
+    no_manifest: bool,
+
+    /// Also keep every frame and answer in this SQLite database, to look them up by time, app
+    /// or category later
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "FILE", conflicts_with = "aggregate")]
+    activity_db: Option<PathBuf>,
+
+    /// Answer /status, /activity, /latest-frame and /summary/today from the activity database
+    /// on this localhost port
+    #[cfg(feature = "http-api")]
+    #[arg(long, value_name = "PORT", requires = "activity_db")]
+    http_port: Option<u16>,
+
+    /// Name of saved frames; {index} is the frame number, {session} the session's start time
+    /// and {ext} the format's extension
+    #[arg(long, default_value = DEFAULT_FILENAME_PATTERN)]
+    filename_pattern: String,
+
+    /// Keep at most this many saved frames in the output directory, deleting the oldest
+    #[arg(long, value_name = "N")]
+    keep_frames: Option<usize>,
+
+    /// Keep the saved frames in the output directory within this many megabytes
+    #[arg(long, value_name = "MB")]
+    keep_megabytes: Option<u64>,
+
+    /// Delete saved frames older than this many hours
+    #[arg(long, value_name = "HOURS")]
+    keep_hours: Option<f64>,
+
+    /// Throttle capture while the watcher itself uses more than this much CPU (percent of one core)
+    #[arg(long, value_name = "PERCENT")]
+    max_cpu: Option<f64>,
+
+    /// Throttle capture while the watcher's resident memory exceeds this many megabytes
+    #[arg(long, value_name = "MB")]
+    max_memory: Option<u64>,
+
+    /// Serve a live MJPEG preview of the frames sent to the model on this localhost port
+    /// (open http://127.0.0.1:PORT/ in a browser; /snapshot.jpg has the latest frame)
+    #[arg(long, value_name = "PORT", conflicts_with = "aggregate")]
+    preview_port: Option<u16>,
+
+    /// Push every answer as JSON to WebSocket clients on this localhost port, e.g. for a live
+    /// "what am I doing" feed (connect to ws://127.0.0.1:PORT/)
+    #[arg(long, value_name = "PORT", conflicts_with = "aggregate")]
+    live_port: Option<u16>,
+
+    /// Also push a small JPEG thumbnail of every frame to the live feed
+    #[arg(long, requires = "live_port")]
+    live_thumbnails: bool,
+
+    /// Show a macOS notification when the Gemini session disconnects and when a [notify] rule
+    /// of the config fires
+    #[cfg(feature = "desktop-notifications")]
+    #[arg(long)]
+    notify: bool,
+
+    /// Capture the screen and ask about it whenever this key combination is pressed, e.g.
+    /// "cmd+shift+a", besides the regular frames; with --notify the answer also shows as a
+    /// notification. Needs the Input Monitoring permission
+    #[cfg(feature = "hotkey")]
+    #[arg(
+        long,
+        value_name = "KEYS",
+        num_args = 0..=1,
+        default_missing_value = watcher_core::DEFAULT_HOTKEY,
+        conflicts_with_all = ["replay", "aggregate"]
+    )]
+    hotkey: Option<watcher_core::Hotkey>,
+
+    /// Question sent with the screenshot taken when the hotkey is pressed
+    #[cfg(feature = "hotkey")]
+    #[arg(
+        long,
+        value_name = "QUESTION",
+        default_value = watcher_core::DEFAULT_HOTKEY_QUESTION,
+        requires = "hotkey"
+    )]
+    hotkey_question: String,
+
+    /// Ask questions out loud: press this key combination, e.g. "cmd+shift+v", to show the
+    /// model the screen and open the microphone, and again when done to hear the answer;
+    /// implies --speak. Needs the Input Monitoring and Microphone permissions
+    #[cfg(feature = "voice")]
+    #[arg(
+        long,
+        value_name = "KEYS",
+        num_args = 0..=1,
+        default_missing_value = voice::DEFAULT_VOICE_HOTKEY,
+        conflicts_with_all = ["replay", "aggregate", "microphone", "focus"]
+    )]
+    voice: Option<watcher_core::Hotkey>,
+
+    /// Longest a spoken question may run before the microphone closes by itself
+    #[cfg(feature = "voice")]
+    #[arg(long, value_name = "SECONDS", default_value_t = 20, requires = "voice")]
+    voice_limit: u64,
+
+    /// Show a full-screen dashboard of the frame rate, connection, token usage, last answer
+    /// and a scrolling activity timeline instead of printing every line (q quits)
+    #[cfg(feature = "tui")]
+    #[arg(long, conflicts_with_all = ["rpc", "aggregate"])]
+    tui: bool,
+
+    /// Accept JSON-RPC commands on stdin and emit events on stdout instead of capturing right away
+    #[arg(long)]
+    rpc: bool,
+
+    /// Accept JSON-RPC commands (pause, resume, status, ask, setTarget, setPrompt), one per
+    /// line, on this Unix socket while capturing
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    control_socket: Option<PathBuf>,
+
+    /// Only keep k-anonymized time-per-category statistics; frames and answers are never stored
+    #[arg(long, conflicts_with_all = ["rpc", "zoom_follow"])]
+    aggregate: bool,
+
+    /// Ask for a JSON activity record per frame (category, app, summary, confidence, flags)
+    /// instead of free text
+    #[arg(long, conflicts_with_all = ["aggregate", "zoom_follow", "annotate"])]
+    structured: bool,
+
+    /// What this session is for, e.g. "writing the Q3 report"; implies --structured, with
+    /// every answer saying whether the screen is on task and an alert after a streak of
+    /// off-task ones
+    #[arg(
+        long,
+        value_name = "INTENTION",
+        conflicts_with_all = ["aggregate", "zoom_follow", "annotate", "profile"]
+    )]
+    focus: Option<String>,
+
+    /// Off-task answers in a row before the focus alert
+    #[arg(
+        long,
+        value_name = "ANSWERS",
+        default_value_t = DEFAULT_FOCUS_STREAK,
+        requires = "focus"
+    )]
+    focus_streak: usize,
+
+    /// Prompt profile setting the system instruction, question and answer schema:
+    /// productivity-coach, security-audit, ux-research, a TOML or JSON profile file, or the
+    /// name of one in ~/.config/watcher/profiles
+    #[arg(
+        long,
+        value_name = "NAME|FILE",
+        conflicts_with_all = ["aggregate", "structured", "zoom_follow"]
+    )]
+    profile: Option<String>,
+
+    /// Model the frames are sent to: gemini, ollama for a vision model on a local Ollama
+    /// server so nothing leaves the machine, openai for the chat completions API, or anthropic
+    /// for Claude (see the [ollama], [openai] and [anthropic] sections of the config)
+    #[arg(long, default_value = "gemini")]
+    backend: Backend,
+
+    /// Run the whole capture, encoding and redaction pipeline but write every turn that
+    /// would be sent, images included, to a new folder in dry-run/ in the output directory
+    /// instead of connecting to a model
+    #[arg(long, conflicts_with_all = ["memory_refresh", "aggregate"])]
+    dry_run: bool,
+
+    /// Minimum samples a category needs before it appears in the aggregate report
+    #[arg(long, default_value_t = 5)]
+    aggregate_min_samples: u64,
+
+    /// Add Laplace noise with this privacy budget to the reported totals
+    #[arg(long)]
+    aggregate_epsilon: Option<f64>,
+
+    /// Where the aggregate report is written when capture stops
+    #[arg(long, default_value = "aggregate.json")]
+    aggregate_output: PathBuf,
+
+    /// Ask the model to return each screenshot with the UI elements it mentions outlined and
+    /// save it next to the original frame (requires a model with image output)
+    #[arg(long, conflicts_with = "aggregate")]
+    annotate: bool,
+
+    /// Number of recent answers the model can look up with the recall_recent_answers tool
+    #[arg(long, default_value_t = 20)]
+    recall_history: usize,
+
+    /// Let the model take a fresh screenshot, of the whole screen or one app's window, with
+    /// the take_screenshot tool whenever it needs another look
+    #[arg(long, conflicts_with_all = ["aggregate", "replay"])]
+    screenshot_tool: bool,
+
+    /// Ask the model for a compact session summary every N minutes
+    #[arg(long, value_name = "MINUTES", conflicts_with = "aggregate")]
+    memory_refresh: Option<u64>,
+
+    /// Summarize only the minutes since the previous summary and restart from the latest
+    /// period summaries and answers, pruning the detailed history of long sessions
+    #[arg(long, requires = "memory_refresh", conflicts_with = "context_compression")]
+    rolling_summary: bool,
+
+    /// Frames kept on disk while the Gemini connection is down, sent with the time they were
+    /// taken once it reconnects; the oldest are dropped beyond this, and 0 drops all of them,
+    /// as do --no-save and --aggregate. Queued frames don't count toward --keep-frames and
+    /// --keep-megabytes, and are deleted once sent
+    #[arg(long, value_name = "FRAMES", default_value_t = 500)]
+    offline_queue: usize,
+
+    /// Let the server compress long contexts instead of restarting from the summary
+    #[arg(long)]
+    context_compression: bool,
+
+    /// File the memory refresh summaries are appended to
+    #[arg(long, default_value = "summaries.jsonl")]
+    summary_log: PathBuf,
+
+    /// Time every pipeline stage per frame, log each frame's timings and print a summary
+    /// at exit
+    #[arg(long)]
+    telemetry: bool,
+
+    /// Stream the default microphone to Gemini alongside the screenshots
+    #[cfg(feature = "audio")]
+    #[arg(long)]
+    microphone: bool,
+
+    /// Stream the system audio, e.g. a meeting, to Gemini alongside the screenshots
+    #[arg(long, conflicts_with = "aggregate")]
+    system_audio: bool,
+
+    /// Recognize the text on screen on-device and add it to the prompt (augment), or only
+    /// print and save it without connecting to Gemini (offline)
+    #[cfg(feature = "ocr")]
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        default_missing_value = "augment",
+        conflicts_with = "aggregate"
+    )]
+    ocr: Option<watcher_core::OcrMode>,
+
+    /// Tell the model which calendar event is going on and note it in the activity log, so
+    /// meetings can be told from solo work (requires Calendars permission)
+    #[cfg(feature = "calendar")]
+    #[arg(long, conflicts_with_all = ["aggregate", "replay"])]
+    calendar: bool,
+
+    /// Launch an MCP server, e.g. "npx chrome-devtools-mcp@latest", and let the model call its
+    /// tools; repeat for more servers
+    #[cfg(feature = "mcp")]
+    #[arg(long, value_name = "COMMAND")]
+    mcp_server: Vec<String>,
+
+    /// JSON file of MCP servers to launch, in the usual {"mcpServers": {...}} layout; their
+    /// tools are offered to the model as <server>__<tool>
+    #[cfg(feature = "mcp")]
+    #[arg(long, value_name = "FILE")]
+    mcp_config: Option<PathBuf>,
+
+    /// Ask Gemini to answer with speech and play it on the default output device
+    #[cfg(feature = "playback")]
+    #[arg(long, conflicts_with = "focus")]
+    speak: bool,
+
+    /// Also record every captured frame into an H.264 MP4 file
+    #[cfg(feature = "recording")]
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["exclude_app", "aggregate"])]
+    record: Option<PathBuf>,
+
+    /// Analyze a screen recording, e.g. an MP4 or MOV from QuickTime, instead of the screen
+    #[cfg(feature = "video")]
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "replay", "zoom_follow", "window_target", "region", "all_displays", "menu_events",
+            "redact_app", "redact_title", "redact_region",
+        ]
+    )]
+    video: Option<PathBuf>,
+
+    /// Seconds of the recording between analyzed frames; the frames in between are not
+    /// analyzed, 0 analyzes every frame
+    #[cfg(feature = "video")]
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 5.0,
+        requires = "video"
+    )]
+    video_interval: f64,
+
+    /// Seconds to wait between analyzed frames, leaving time for each answer
+    #[cfg(feature = "video")]
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 1.0,
+        requires = "video"
+    )]
+    video_pace: f64,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Import frames from a legacy flat output directory into the session archive
+    Migrate {
+        /// Legacy directory containing frame_NNNN.jpg files
+        #[arg(long, default_value = "output")]
+        from: PathBuf,
+
+        /// Archive root to import into
+        #[arg(long, default_value = "archive")]
+        to: PathBuf,
+
+        /// Delete the legacy files once they have been imported
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Assemble the saved frames of a directory into an animated GIF or WebP
+    Timelapse {
+        /// Output directory or archive session holding the frames
+        #[arg(long, default_value = "output")]
+        from: PathBuf,
+
+        /// Animation file; the format follows the .gif or .webp extension
+        #[arg(long, default_value = "timelapse.gif")]
+        output: PathBuf,
+
+        /// How long each frame is shown, in milliseconds
+        #[arg(long, default_value_t = 200)]
+        interval_ms: u64,
+
+        /// Longer side of the animation in pixels; 0 keeps the captured size
+        #[arg(long, default_value_t = 640)]
+        max_edge: u32,
+
+        /// Use every n-th frame
+        #[arg(long, default_value_t = 1)]
+        step: usize,
+    },
+    /// Look up what the activity database recorded
+    #[cfg(feature = "sqlite")]
+    Report {
+        #[command(subcommand)]
+        command: ReportCommand,
+    },
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Subcommand, Debug)]
+enum ReportCommand {
+    /// Write the stored analyses, with their token usage and saved frames, as JSON lines or
+    /// CSV for spreadsheets and other tools
+    Export {
+        /// Activity database written with --activity-db
+        #[arg(long, value_name = "FILE")]
+        db: PathBuf,
+
+        /// jsonl or csv
+        #[arg(long, default_value = "jsonl")]
+        format: watcher_core::ExportFormat,
+
+        /// Only entries from this time on: YYYY-MM-DD, YYYY-MM-DD HH:MM[:SS] in local time or
+        /// milliseconds since the Unix epoch
+        #[arg(long, value_name = "TIME", value_parser = time_arg)]
+        from: Option<SystemTime>,
+
+        /// Only entries before this time, in the same forms as --from
+        #[arg(long, value_name = "TIME", value_parser = time_arg)]
+        to: Option<SystemTime>,
+
+        /// File to write instead of standard output
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Sum up a day or week of the stored analyses: time per app and category, sessions and
+    /// long stretches in one app
+    Summary {
+        /// Activity database written with --activity-db
+        #[arg(long, value_name = "FILE")]
+        db: PathBuf,
+
+        /// day or week (Monday to Sunday)
+        #[arg(long, default_value = "day")]
+        period: watcher_core::ReportPeriod,
+
+        /// A time in the day or week to sum up, in the same forms as export --from; today by
+        /// default
+        #[arg(long, value_name = "TIME", value_parser = time_arg)]
+        date: Option<SystemTime>,
+
+        /// markdown or html
+        #[arg(long, default_value = "markdown")]
+        format: watcher_core::ReportFormat,
+
+        /// Have Gemini write a few paragraphs about the totals; only app and category names
+        /// and times are sent
+        #[arg(long)]
+        narrative: bool,
+
+        /// File to write instead of standard output
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[cfg(feature = "sqlite")]
+fn time_arg(value: &str) -> Result<SystemTime, String> {
+    watcher_core::parse_local_time(value).ok_or_else(|| {
+        format!(
+            "invalid time '{}' (expected YYYY-MM-DD, YYYY-MM-DD HH:MM[:SS] or milliseconds)",
+            value
+        )
+    })
+}
+
+fn migrate(from: &Path, to: &Path, remove: bool) -> bool {
+    let result =
+        FrameStore::open(to).and_then(|store| migrate_legacy_output(from, &store, remove, None));
+    match result {
+        Ok(report) => match report.session_dir {
+            Some(dir) => {
+                println!(
+                    "📦 Imported {} legacy frames from {} into {}",
+                    report.imported,
+                    from.display(),
+                    dir.display()
+                );
+                true
+            }
+            None => {
+                println!("No legacy frames found in {}", from.display());
+                true
+            }
+        },
+        Err(e) => {
+            eprintln!("❌ Migration failed: {}", e);
+            false
+        }
+    }
+}
+
+fn timelapse(from: &Path, output: &Path, options: TimelapseOptions) -> bool {
+    match export_timelapse(from, output, options) {
+        Ok(report) => {
+            println!(
+                "🎞️  Wrote {} frames ({}x{}) to {}",
+                report.frames,
+                report.width,
+                report.height,
+                output.display()
+            );
+            true
+        }
+        Err(e) => {
+            eprintln!("❌ Timelapse failed: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn export_report(
+    db: &Path,
+    format: watcher_core::ExportFormat,
+    from: Option<SystemTime>,
+    to: Option<SystemTime>,
+    output: Option<&Path>,
+) -> bool {
+    let store = match watcher_core::ActivityStore::open(db) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("❌ Failed to open {}: {}", db.display(), e);
+            return false;
+        }
+    };
+    let entries = match store.between(
+        from.unwrap_or(UNIX_EPOCH),
+        to.unwrap_or_else(SystemTime::now),
+    ) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("❌ Failed to read {}: {}", db.display(), e);
+            return false;
+        }
+    };
+    let written = match output {
+        Some(path) => std::fs::File::create(path).and_then(|file| {
+            watcher_core::export_activity(&entries, format, std::io::BufWriter::new(file))
+        }),
+        None => watcher_core::export_activity(&entries, format, std::io::stdout().lock()),
+    };
+    match written {
+        Ok(()) => {
+            if let Some(path) = output {
+                println!(
+                    "📄 Exported {} entries to {}",
+                    entries.len(),
+                    path.display()
+                );
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!("❌ Export failed: {}", e);
+            false
+        }
+    }
+}
+
+/// How long Gemini gets to write the narrative of a summary report
+#[cfg(feature = "sqlite")]
+const NARRATIVE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[cfg(feature = "sqlite")]
+async fn summary_report(
+    db: &Path,
+    period: watcher_core::ReportPeriod,
+    date: SystemTime,
+    format: watcher_core::ReportFormat,
+    narrative: bool,
+    output: Option<&Path>,
+    config: &WatcherConfig,
+) -> bool {
+    let store = match watcher_core::ActivityStore::open(db) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("❌ Failed to open {}: {}", db.display(), e);
+            return false;
+        }
+    };
+    let (from, to) = period.range(date);
+    let entries = match store.between(from, to) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("❌ Failed to read {}: {}", db.display(), e);
+            return false;
+        }
+    };
+    let mut report = watcher_core::ActivityReport::build(&entries, period, from);
+
+    if narrative && !entries.is_empty() {
+        let options = match connection_options(config) {
+            Ok(options) => options,
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                return false;
+            }
+        };
+        let narrate = report.narrate(&config.gemini.model, options);
+        let written = tokio::time::timeout(NARRATIVE_TIMEOUT, narrate).await;
+        // The totals are still worth writing without the prose
+        match written {
+            Ok(Ok(narrative)) => report.narrative = Some(narrative),
+            Ok(Err(e)) => eprintln!("⚠️ Gemini didn't write a narrative: {}", e),
+            Err(_) => eprintln!("⚠️ Gemini took too long to write a narrative"),
+        }
+    }
+
+    let text = report.render(format);
+    let written = match output {
+        Some(path) => std::fs::write(path, text),
+        None => std::io::Write::write_all(&mut std::io::stdout().lock(), text.as_bytes()),
+    };
+    match written {
+        Ok(()) => {
+            if let Some(path) = output {
+                println!(
+                    "📊 Summed up {} entries in {}",
+                    entries.len(),
+                    path.display()
+                );
+            }
+            true
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to write the report: {}", e);
+            false
+        }
+    }
+}
+
+/// Connection to Gemini with the keys from GOOGLE_API_KEY, GOOGLE_API_KEYS or the config
+/// file; GOOGLE_API_KEYS holds a comma-separated pool to shard sessions across
+fn connection_options(config: &WatcherConfig) -> Result<ConnectionOptions, String> {
+    let api_key = std::env::var("GOOGLE_API_KEY")
+        .ok()
+        .or_else(|| config.gemini.api_key.clone());
+    let key_pool: Vec<String> = std::env::var("GOOGLE_API_KEYS")
+        .map(|keys| {
+            keys.split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    if api_key.is_none() && key_pool.is_empty() {
+        return Err(
+            "GOOGLE_API_KEY or GOOGLE_API_KEYS environment variable, or gemini.api_key \
+                    in the config file, must be set"
+                .to_string(),
+        );
+    }
+
+    let mut options = ConnectionOptions::builder().key_pool(key_pool);
+    if let Some(api_key) = api_key {
+        options = options.api_key(api_key);
+    }
+    options.build().map_err(|e| e.to_string())
+}
+
+/// Session with a chat model configured in the `[ollama]`, `[openai]` or `[anthropic]` section,
+/// exiting when it can't be reached
+#[cfg(any(feature = "anthropic", feature = "ollama", feature = "openai"))]
+async fn connect_chat(
+    backend: Backend,
+    config: &WatcherConfig,
+    setup: &Setup,
+) -> (Arc<dyn AnalysisBackend>, Box<dyn AnalysisEvents>) {
+    let connected = match backend {
+        #[cfg(feature = "ollama")]
+        Backend::Ollama => {
+            let ollama = &config.ollama;
+            println!(
+                "🦙 Analyzing frames locally with {} at {}",
+                ollama.model, ollama.url
+            );
+            watcher_core::ChatSession::ollama(&ollama.url, &ollama.model, setup).await
+        }
+        #[cfg(feature = "openai")]
+        Backend::OpenAi => {
+            let openai = &config.openai;
+            let api_key = std::env::var("OPENAI_API_KEY")
+                .ok()
+                .or_else(|| openai.api_key.clone());
+            if api_key.is_none() && openai.url == watcher_core::DEFAULT_OPENAI_URL {
+                eprintln!(
+                    "❌ OPENAI_API_KEY environment variable, or openai.api_key in the config \
+                     file, must be set"
+                );
+                std::process::exit(1);
+            }
+            println!(
+                "🤖 Analyzing frames with {} at {}",
+                openai.model, openai.url
+            );
+            watcher_core::ChatSession::openai(&openai.url, &openai.model, api_key.as_deref(), setup)
+                .await
+        }
+        #[cfg(feature = "anthropic")]
+        Backend::Anthropic => {
+            let anthropic = &config.anthropic;
+            let Some(api_key) = std::env::var("ANTHROPIC_API_KEY")
+                .ok()
+                .or_else(|| anthropic.api_key.clone())
+            else {
+                eprintln!(
+                    "❌ ANTHROPIC_API_KEY environment variable, or anthropic.api_key in the \
+                     config file, must be set"
+                );
+                std::process::exit(1);
+            };
+            println!("🤖 Analyzing frames with {}", anthropic.model);
+            watcher_core::ChatSession::anthropic(
+                &anthropic.url,
+                &anthropic.model,
+                &api_key,
+                anthropic.max_tokens,
+                setup,
+            )
+            .await
+        }
+        other => {
+            eprintln!(
+                "❌ The {} backend needs a build with the {} feature",
+                other, other
+            );
+            std::process::exit(1);
+        }
+    };
+    match connected {
+        Ok(session) => (Arc::new(session.sender_handle()), Box::new(session)),
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(any(feature = "anthropic", feature = "ollama", feature = "openai")))]
+async fn connect_chat(
+    backend: Backend,
+    _config: &WatcherConfig,
+    _setup: &Setup,
+) -> (Arc<dyn AnalysisBackend>, Box<dyn AnalysisEvents>) {
+    eprintln!(
+        "❌ The {} backend needs a build with the {} feature",
+        backend, backend
+    );
+    std::process::exit(1);
+}
+
+fn capture_options(args: &Cli) -> CaptureOptions {
+    let target = match (args.window_id, args.pid, &args.window_title) {
+        (Some(id), _, _) => CaptureTarget::Window(id),
+        (_, Some(pid), _) => CaptureTarget::Process(pid),
+        (_, _, Some(title)) => CaptureTarget::WindowTitle(title.clone()),
+        _ => CaptureTarget::Display,
+    };
+    let mut options = CaptureOptions::builder()
+        // The window highlight border would end up in every window frame
+        .show_highlight(!target.is_window())
+        .target(target)
+        .captures_audio(args.system_audio);
+    if let Some(region) = args.region {
+        options = options.region(region);
+    }
+    options.build().expect("Failed to build capture options")
+}
+
+/// Captures frames and prints the text recognized in them, without Gemini
+#[cfg(feature = "ocr")]
+async fn ocr_offline(options: CaptureOptions, count: usize) -> bool {
+    let frame_source = match FrameSource::from_options(options) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("❌ Failed to create capturer: {}", e);
+            return false;
+        }
+    };
+    if let Err(e) = std::fs::create_dir_all("ocr") {
+        eprintln!("❌ Failed to create the ocr directory: {}", e);
+        return false;
+    }
+    let session = watcher_core::session_stamp(std::time::SystemTime::now());
+    let recognizer = watcher_core::TextRecognizer::new();
+    for i in 1..=count {
+        let frame = match frame_source.get_next_frame().await {
+            Ok(frame) => frame,
+            Err(e) => {
+                eprintln!("❌ Error capturing frame {}: {}", i, e);
+                continue;
+            }
+        };
+        let recognized = {
+            let frame = Arc::clone(&frame);
+            tokio::task::spawn_blocking(move || recognizer.recognize(&frame)).await
+        };
+        let lines = match recognized {
+            Ok(Ok(lines)) => lines,
+            Ok(Err(e)) => {
+                eprintln!("❌ Error recognizing text: {}", e);
+                return false;
+            }
+            Err(e) => {
+                eprintln!("❌ Text recognition failed: {}", e);
+                return false;
+            }
+        };
+        let text = watcher_core::ocr_text(&lines);
+        let filename = format!("ocr/frame_{}_{:04}", session, i);
+        let jpeg = format!("{}.jpg", filename);
+        if let Err(e) =
+            watcher_core::encode_bgra_to_jpeg(&frame.data, frame.width, frame.height, &jpeg, 90)
+        {
+            eprintln!("❌ Error saving frame {}: {}", i, e);
+        }
+        if let Err(e) = std::fs::write(format!("{}.txt", filename), &text) {
+            eprintln!("❌ Error saving text of frame {}: {}", i, e);
+        }
+        println!("📝 Frame {}: {} lines -> {}.txt", i, lines.len(), filename);
+        println!("{}", text);
+        frame_source.buffer_pool().recycle_frame(frame);
+    }
+    frame_source.stop();
+    true
+}
+
+/// First flag the chat backends can't serve: they neither hear audio nor answer with speech or
+/// images, and memory refresh reconnects through the Gemini key pool
+fn chat_unsupported(args: &Cli) -> Option<&'static str> {
+    #[cfg(feature = "voice")]
+    if args.voice.is_some() {
+        return Some("--voice");
+    }
+    #[cfg(feature = "audio")]
+    if args.microphone {
+        return Some("--microphone");
+    }
+    #[cfg(feature = "playback")]
+    if args.speak {
+        return Some("--speak");
+    }
+    if args.system_audio {
+        Some("--system-audio")
+    } else if args.annotate {
+        Some("--annotate")
+    } else if args.memory_refresh.is_some() {
+        Some("--memory-refresh")
+    } else if args.screenshot_tool {
+        // Chat backends don't offer tools to the model
+        Some("--screenshot-tool")
+    } else {
+        None
+    }
+}
+
+/// Whether frames come from files rather than the screen
+fn replays_files(args: &Cli) -> bool {
+    #[cfg(feature = "video")]
+    if args.video.is_some() {
+        return true;
+    }
+    args.replay.is_some()
+}
+
+/// Fills in the flags not given on the command line from the config file and the `WATCHER_*`
+/// environment variables
+fn apply_config(
+    args: &mut Cli,
+    matches: &ArgMatches,
+    config: &WatcherConfig,
+) -> Result<(), String> {
+    let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+
+    if unset("interval")
+        && let Some(interval) = config.capture.interval
+    {
+        args.interval = interval;
+    }
+    if unset("max_edge")
+        && unset("resize_width")
+        && unset("resize_height")
+        && let Some(edge) = config.capture.max_edge
+    {
+        args.max_edge = Some(edge);
+    }
+    if unset("skip_unchanged") && config.capture.skip_unchanged.is_some() {
+        args.skip_unchanged = config.capture.skip_unchanged;
+    }
+    if unset("backend") {
+        args.backend = config.backend;
+    }
+    if unset("context_compression") && !args.rolling_summary {
+        args.context_compression = config.gemini.context_compression;
+    }
+    if unset("profile") && !args.aggregate && !args.structured && !args.zoom_follow {
+        args.profile = config.prompts.profile.clone();
+    }
+    // A profile brings its own question
+    if unset("prompt") && args.profile.is_none() && config.prompts.prompt.is_some() {
+        args.prompt = config.prompts.prompt.clone();
+    }
+    if unset("preamble") && config.prompts.preamble.is_some() {
+        args.preamble = config.prompts.preamble.clone();
+    }
+
+    if unset("format")
+        && let Some(format) = &config.output.format
+    {
+        args.format = format
+            .parse()
+            .map_err(|e| format!("output.format: {}", e))?;
+    }
+    if unset("quality")
+        && let Some(quality) = config.output.quality
+    {
+        if !(1..=100).contains(&quality) {
+            return Err(format!("output.quality must be 1 to 100, not {}", quality));
+        }
+        args.quality = quality;
+    }
+    if unset("keep_frames") && config.output.keep_frames.is_some() {
+        args.keep_frames = config.output.keep_frames;
+    }
+    if unset("keep_megabytes") && config.output.keep_megabytes.is_some() {
+        args.keep_megabytes = config.output.keep_megabytes;
+    }
+    if unset("keep_hours") && config.output.keep_hours.is_some() {
+        args.keep_hours = config.output.keep_hours;
+    }
+
+    // Window redaction only makes sense for live captures of the whole screen
+    let redacts = !matches.contains_id("window_target") && args.replay.is_none();
+    if redacts && unset("redact_app") && !config.privacy.redact_apps.is_empty() {
+        args.redact_app = config.privacy.redact_apps.clone();
+    }
+    if redacts && unset("redact_title") && !config.privacy.redact_titles.is_empty() {
+        args.redact_title = config.privacy.redact_titles.clone();
+    }
+    if args.replay.is_none() && unset("exclude_app") && !config.privacy.exclude_apps.is_empty() {
+        // The recording would still show the excluded apps
+        #[cfg(feature = "recording")]
+        if args.record.is_some() {
+            return Err("privacy.exclude_apps can't be used with --record".to_string());
+        }
+        args.exclude_app = config.privacy.exclude_apps.clone();
+    }
+    if unset("clipboard_redact") && !config.privacy.clipboard_redact.is_empty() {
+        args.clipboard_redact = config.privacy.clipboard_redact.clone();
+    }
+    if unset("anonymize")
+        && let Some(mode) = &config.privacy.anonymize
+    {
+        args.anonymize = mode
+            .parse()
+            .map_err(|e| format!("privacy.anonymize: {}", e))?;
+    }
+    if unset("anonymize_salt")
+        && let Some(salt) = &config.privacy.anonymize_salt
+    {
+        args.anonymize_salt = salt.clone();
+    }
+    Ok(())
+}
+
+#[cfg(feature = "recording")]
+fn finish_recording(recorder: Option<watcher_core::Recorder>) {
+    match recorder.map(watcher_core::Recorder::finish) {
+        Some(Ok(summary)) => println!(
+            "🎬 Saved {} frames ({:.0}s) to {}",
+            summary.frames,
+            summary.duration.as_secs_f64(),
+            summary.path.display()
+        ),
+        Some(Err(e)) => eprintln!("❌ Recording error: {}", e),
+        None => {}
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    // Check permissions
-    if let Err(e) = ensure_screen_recording_permission() {
+    let matches = Cli::command().get_matches();
+    let mut args = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    args.structured |= args.focus.is_some();
+    #[cfg(feature = "voice")]
+    {
+        args.speak |= args.voice.is_some();
+    }
+    // Both would fire on every press
+    #[cfg(feature = "voice")]
+    if args.hotkey.is_some() && args.hotkey == args.voice {
+        eprintln!("❌ --hotkey and --voice need different keys");
+        std::process::exit(1);
+    }
+    let mut config = match WatcherConfig::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(dir) = &args.output_dir {
+        config.output.dir = dir.clone();
+    }
+    if let Err(e) = apply_config(&mut args, &matches, &config) {
+        eprintln!("❌ Invalid config: {}", e);
+        std::process::exit(1);
+    }
+    // The preview and the live feed only stream JPEG
+    if args.format == FrameFormat::Png && (args.preview_port.is_some() || args.live_thumbnails) {
+        eprintln!("❌ PNG frames can't be used with --preview-port or --live-thumbnails");
+        std::process::exit(1);
+    }
+    let alert_rules = match AlertRules::new(config.notify.rules.clone()) {
+        Ok(alert_rules) => alert_rules,
+        Err(e) => {
+            eprintln!("❌ Invalid config: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(Command::Migrate { from, to, remove }) = &args.command {
+        if !migrate(from, to, *remove) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(Command::Timelapse {
+        from,
+        output,
+        interval_ms,
+        max_edge,
+        step,
+    }) = &args.command
+    {
+        let Some(format) = TimelapseFormat::from_path(output) else {
+            eprintln!("❌ Timelapse output must end in .gif or .webp");
+            std::process::exit(1);
+        };
+        let options = TimelapseOptions {
+            format,
+            frame_delay: Duration::from_millis(*interval_ms),
+            max_edge: (*max_edge > 0).then_some(*max_edge),
+            step: *step,
+        };
+        if !timelapse(from, output, options) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    #[cfg(feature = "sqlite")]
+    if let Some(Command::Report {
+        command:
+            ReportCommand::Export {
+                db,
+                format,
+                from,
+                to,
+                output,
+            },
+    }) = &args.command
+    {
+        if !export_report(db, *format, *from, *to, output.as_deref()) {
+            std::process::exit(1);
+        }
+        return;
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(Command::Report {
+        command:
+            ReportCommand::Summary {
+                db,
+                period,
+                date,
+                format,
+                narrative,
+                output,
+            },
+    }) = &args.command
+    {
+        let date = date.unwrap_or_else(SystemTime::now);
+        if !summary_report(
+            db,
+            *period,
+            date,
+            *format,
+            *narrative,
+            output.as_deref(),
+            &config,
+        )
+        .await
+        {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Check permissions; replays and recordings don't capture the screen
+    if !replays_files(&args)
+        && let Err(e) = ensure_screen_recording_permission()
+    {
         eprintln!("❌ Permission error: {}", e);
         return;
     }
 
-    // Get API key
-    let api_key =
-        std::env::var("GOOGLE_API_KEY").expect("GOOGLE_API_KEY environment variable must be set");
+    #[cfg(feature = "ocr")]
+    if args.ocr == Some(watcher_core::OcrMode::Offline) {
+        let count = args.frames.or(config.capture.frames).unwrap_or(10);
+        if !ocr_offline(capture_options(&args), count).await {
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    // Setup Gemini session
-    let connection_options = ConnectionOptions::builder()
-        .api_key(api_key)
+    // Setup Gemini session; the chat backends bring their own keys
+    let key_pool = match args.backend {
+        _ if args.dry_run => None,
+        Backend::Gemini => match connection_options(&config) {
+            Ok(options) => Some(KeyPool::new(options, KeyLimits::default())),
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                return;
+            }
+        },
+        Backend::Ollama | Backend::OpenAi | Backend::Anthropic => {
+            if let Some(flag) = chat_unsupported(&args) {
+                eprintln!(
+                    "❌ {} isn't supported with the {} backend",
+                    flag, args.backend
+                );
+                return;
+            }
+            None
+        }
+    };
+
+    let profile = match &args.profile {
+        Some(name) => match PromptProfile::resolve(name) {
+            Ok(profile) => Some(profile),
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let focus = args
+        .focus
+        .as_ref()
+        .map(|intention| FocusSession::new(intention.as_str()).with_streak(args.focus_streak));
+    let focus_instruction = focus
+        .as_ref()
+        .map(|focus| format!("{} {}", ACTIVITY_INSTRUCTION, focus.instruction()));
+    let system_instruction = if args.aggregate {
+        AGGREGATE_INSTRUCTION
+    } else if let Some(instruction) = &focus_instruction {
+        instruction.as_str()
+    } else if args.structured {
+        ACTIVITY_INSTRUCTION
+    } else if args.zoom_follow {
+        ZOOM_NARRATION_INSTRUCTION
+    } else if let Some(profile) = &profile {
+        profile.system_instruction.as_str()
+    } else {
+        config.prompts.system_instruction.as_str()
+    };
+
+    #[cfg(feature = "playback")]
+    let response_modality = if args.speak && !args.aggregate {
+        "AUDIO"
+    } else {
+        "TEXT"
+    };
+    #[cfg(not(feature = "playback"))]
+    let response_modality = "TEXT";
+
+    let mut response_modalities = vec![response_modality.to_string()];
+    if args.annotate {
+        response_modalities.push("IMAGE".to_string());
+    }
+    let mut generation_config = GenerationConfig {
+        response_modalities,
+        ..Default::default()
+    };
+    // Spoken answers can't follow a schema
+    if focus.is_some() && response_modality == "TEXT" {
+        generation_config = FocusSession::generation_config(generation_config);
+    } else if args.structured && response_modality == "TEXT" {
+        generation_config = ScreenActivity::generation_config(generation_config);
+    } else if let Some(profile) = profile.as_ref().filter(|_| response_modality == "TEXT") {
+        generation_config = profile.generation_config(generation_config);
+    }
+
+    let mut setup = match Setup::builder(config.gemini.model.as_str())
+        .system_instruction(Content::system(system_instruction))
+        .generation_config(generation_config)
         .build()
-        .expect("Failed to build connection options");
-
-    let setup = Setup::builder("models/gemini-live-2.5-flash-preview")
-        .system_instruction(Content::system(
-            "You are analyzing screenshots of a user's computer screen. \
-             For each screenshot, provide a brief description of what the user is doing. \
-             Focus on the main activity visible on the screen. Keep your response concise (1-2 sentences).",
-        ))
-        .generation_config(GenerationConfig {
-            response_modalities: vec!["TEXT".to_string()],
+    {
+        Ok(setup) => setup,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return;
+        }
+    };
+    if args.context_compression {
+        setup.context_window_compression = Some(serde_json::json!({ "slidingWindow": {} }));
+    }
+
+    let rpc_writer = args.rpc.then(RpcWriter::stdout);
+    let aggregator = args.aggregate.then(|| {
+        Arc::new(ActivityAggregator::new(AggregationConfig {
+            min_samples: args.aggregate_min_samples,
+            epsilon: args.aggregate_epsilon,
             ..Default::default()
-        })
-        .build()
-        .expect("Failed to build setup");
+        }))
+    });
+    let printer: Arc<dyn ResponsePrinter> = match (&rpc_writer, &aggregator) {
+        (Some(writer), _) => Arc::new(writer.clone()),
+        (None, Some(aggregator)) => Arc::new(AggregatingPrinter::new(Arc::clone(aggregator))),
+        (None, None) => Arc::new(CliResponsePrinter::new()),
+    };
+    #[cfg(feature = "tui")]
+    let dashboard = args.tui.then(tui::Dashboard::new);
+    #[cfg(feature = "tui")]
+    let printer: Arc<dyn ResponsePrinter> = match &dashboard {
+        Some(dashboard) => Arc::new(dashboard.clone()),
+        None => printer,
+    };
+    let printer: Arc<dyn ResponsePrinter> = if args.annotate {
+        Arc::new(AnnotationSaver::new(printer))
+    } else {
+        printer
+    };
+    let mut tool_handlers: Vec<Arc<dyn ToolHandler>> = Vec::new();
+    let mut answer_history = None;
+    let printer: Arc<dyn ResponsePrinter> = if args.aggregate {
+        printer
+    } else {
+        // Lets the model look up its own earlier descriptions for follow-up questions
+        let history = Arc::new(AnswerHistory::new(printer, args.recall_history));
+        tool_handlers.push(history.clone());
+        answer_history = Some(history.clone());
+        history
+    };
+    let screenshot_tool = args
+        .screenshot_tool
+        .then(|| Arc::new(ScreenshotTool::new()));
+    if let Some(tool) = &screenshot_tool {
+        tool_handlers.push(tool.clone());
+    }
+    #[cfg(feature = "mcp")]
+    {
+        let mut bridge = watcher_core::McpBridge::new();
+        for command in &args.mcp_server {
+            match bridge.spawn_server(command).await {
+                Ok(count) => println!("🧰 {} offers {} tools to the model", command, count),
+                Err(e) => eprintln!("❌ {}: {}", command, e),
+            }
+        }
+        if let Some(path) = &args.mcp_config {
+            let config = match watcher_core::McpConfig::load(path) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("❌ {}: {}", path.display(), e);
+                    return;
+                }
+            };
+            for (name, server) in config.mcp_servers {
+                match bridge.launch(name.as_str(), server).await {
+                    Ok(count) => println!("🧰 {} offers {} tools to the model", name, count),
+                    Err(e) => eprintln!("❌ MCP server {}: {}", name, e),
+                }
+            }
+        }
+        if !bridge.is_empty() {
+            tool_handlers.push(Arc::new(bridge));
+        }
+    }
+    let tools = tool_declarations(&tool_handlers);
+    if !tools.is_empty() {
+        setup.tools = Some(tools);
+    }
+    let summary_capture = args
+        .memory_refresh
+        .map(|_| Arc::new(SummaryCapture::new(Arc::clone(&printer))));
+    let printer: Arc<dyn ResponsePrinter> = match &summary_capture {
+        Some(capture) => capture.clone(),
+        None => printer,
+    };
 
-    let session = GeminiSession::connect(setup, connection_options)
-        .await
-        .expect("Failed to connect to Gemini");
+    #[cfg(feature = "playback")]
+    let audio_sink = if args.speak {
+        match watcher_core::AudioSink::to_default_output() {
+            Ok(sink) => Some(Arc::new(sink)),
+            Err(e) => {
+                eprintln!("❌ Audio output error: {}", e);
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut key_lease = None;
+    let (sender, events): (Arc<dyn AnalysisBackend>, Box<dyn AnalysisEvents>) = match &key_pool {
+        Some(key_pool) => {
+            let (session, lease) = key_pool
+                .connect(setup.clone())
+                .await
+                .expect("Failed to connect to Gemini");
+            if let Some(hint) = lease.key_hint().filter(|_| key_pool.len() > 1) {
+                println!(
+                    "🔑 Using API key {} of {} pooled keys",
+                    hint,
+                    key_pool.len()
+                );
+            }
+            key_lease = Some(lease);
+            (Arc::new(session.sender_handle()), Box::new(session))
+        }
+        None if args.dry_run => {
+            let dir = config.output.dir.join("dry-run");
+            match DryRun::open(&dir, &setup) {
+                Ok((dry_run, events)) => {
+                    println!(
+                        "🧪 Dry run: writing what would be sent to {} instead of uploading",
+                        dry_run.dir().display()
+                    );
+                    (dry_run, Box::new(events))
+                }
+                Err(e) => {
+                    eprintln!("❌ Failed to start the dry run: {}", e);
+                    return;
+                }
+            }
+        }
+        None => connect_chat(args.backend, &config, &setup).await,
+    };
 
-    let sender = session.sender_handle();
-    let printer: Arc<dyn watcher_core::ResponsePrinter> = Arc::new(CliResponsePrinter::new());
+    // Answers are paired with their frames for the manifest, saved JPEGs and RPC clients
+    let turns = Arc::new(TurnTracker::new());
+    let (results, mut answers) = tokio::sync::mpsc::unbounded_channel();
+    let telemetry = args
+        .telemetry
+        .then(|| Arc::new(Telemetry::default().with_logging(Arc::clone(&printer))));
 
     // Start output processor to handle Gemini responses
-    let output_processor = OutputProcessor::new(Arc::clone(&printer));
-    output_processor.spawn(session);
+    let spawn_output: Arc<dyn Fn(Box<dyn AnalysisEvents>) + Send + Sync> = Arc::new({
+        let printer = Arc::clone(&printer);
+        let turns = Arc::clone(&turns);
+        let telemetry = telemetry.clone();
+        move |events: Box<dyn AnalysisEvents>| {
+            let output_processor = OutputProcessor::new(Arc::clone(&printer))
+                .with_tool_handlers(tool_handlers.clone())
+                .with_results(Arc::clone(&turns), results.clone());
+            let output_processor = match &telemetry {
+                Some(telemetry) => output_processor.with_telemetry(Arc::clone(telemetry)),
+                None => output_processor,
+            };
+            #[cfg(feature = "playback")]
+            let output_processor = match &audio_sink {
+                Some(sink) => output_processor.with_audio_sink(Arc::clone(sink)),
+                None => output_processor,
+            };
+            output_processor.spawn(events);
+        }
+    });
+    spawn_output(events);
+    let key_lease = Arc::new(tokio::sync::Mutex::new(key_lease));
 
-    // Ensure output directory is clean
-    ensure_clean_directory("output").expect("Failed to create output directory");
+    #[cfg(feature = "audio")]
+    let _microphone = if args.microphone {
+        match watcher_core::AudioSource::from_default_input() {
+            Ok(source) => {
+                printer.print_status("🎙️ Streaming microphone audio to Gemini");
+                Some(source.spawn_forwarder(sender.clone()))
+            }
+            Err(e) => {
+                eprintln!("❌ Microphone error: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Preserve frames of a directory written before the archive existed, once; the marker it
+    // leaves keeps later launches from copying the directory again
+    let output_dir = config.output.dir.as_path();
+    if find_legacy_frames(output_dir).is_ok_and(|frames| !frames.is_empty()) {
+        let max_frames = args.keep_frames.unwrap_or(AUTO_ARCHIVE_MAX_FRAMES);
+        let migrated = FrameStore::open(&config.output.archive_dir)
+            .and_then(|store| migrate_legacy_output(output_dir, &store, false, Some(max_frames)));
+        match migrated {
+            Ok(report) if report.skipped > 0 => printer.print_status(&format!(
+                "📦 Archived the newest {} frames from the previous run, skipped {} older ones",
+                report.imported, report.skipped
+            )),
+            Ok(report) => printer.print_status(&format!(
+                "📦 Archived {} frames from the previous run",
+                report.imported
+            )),
+            Err(e) => {
+                eprintln!("❌ Failed to archive previous frames: {}", e);
+                return;
+            }
+        }
+    }
+
+    // Earlier sessions' frames stay in the output directory, within the retention limits
+    let retention = RetentionPolicy {
+        max_files: args.keep_frames,
+        max_bytes: args.keep_megabytes.map(|mb| mb * 1024 * 1024),
+        max_age: args
+            .keep_hours
+            .map(|hours| Duration::from_secs_f64(hours.max(0.0) * 3600.0)),
+    };
+    let mut retention_manager =
+        RetentionManager::open(output_dir, retention).expect("Failed to create output directory");
+    match retention_manager.prune() {
+        Ok(pruned) if pruned.files > 0 => printer.print_status(&format!(
+            "🧹 Deleted {} old frames ({} MB)",
+            pruned.files,
+            pruned.bytes / (1024 * 1024)
+        )),
+        Ok(_) => {}
+        Err(e) => eprintln!("❌ Failed to prune old frames: {}", e),
+    }
 
     // Configure screen capturer
-    let capture_options = Options {
-        fps: 1,
-        target: None,
-        show_cursor: true,
-        show_highlight: true,
-        excluded_targets: None,
-        output_type: scap::frame::FrameType::BGRAFrame,
-        output_resolution: scap::capturer::Resolution::_720p,
-        crop_area: None,
-        captures_audio: false,
-        exclude_current_process_audio: false,
-    };
-
-    let capturer = Capturer::build(capture_options).expect("Failed to create capturer");
-    let frame_source = FrameSource::new(capturer);
-
-    println!("📸 Capturing frames at 1 FPS and sending to Gemini...");
-    println!("💾 Saving frames to output/ directory as JPEG");
+    let capture_options = capture_options(&args);
+    let control_options = capture_options.clone();
+    if let Some(region) = args.region {
+        printer.print_status(&format!(
+            "✂️ Capturing region {}x{} at ({}, {})",
+            region.width, region.height, region.x, region.y
+        ));
+    }
+
+    let mut frame_count = args.frames.or(config.capture.frames).unwrap_or(10);
+    #[cfg(feature = "video")]
+    let video = match &args.video {
+        Some(path) => match watcher_core::VideoFrameSource::open(path) {
+            Ok(video) => {
+                let video = video
+                    .with_sample_interval(Duration::from_secs_f64(args.video_interval.max(0.0)))
+                    .with_pace(Duration::from_secs_f64(args.video_pace.max(0.0)));
+                printer.print_status(&format!(
+                    "🎞️ Analyzing {} ({:.0}s) every {}s of video",
+                    path.display(),
+                    video.duration().as_secs_f64(),
+                    args.video_interval
+                ));
+                // The source stops at the end of the recording either way
+                frame_count = video.sample_count();
+                Some(video.into_frame_source())
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to open the video: {}", e);
+                return;
+            }
+        },
+        None => None,
+    };
+    #[cfg(not(feature = "video"))]
+    let video: Option<FrameSource> = None;
+
+    let sources = match (video, &args.replay, args.all_displays) {
+        (Some(video), _, _) => Ok(vec![video]),
+        (None, Some(dir), _) => DirectoryFrameSource::open(dir).map(|replay| {
+            printer.print_status(&format!(
+                "⏪ Replaying {} images from {}",
+                replay.len(),
+                dir.display()
+            ));
+            frame_count = replay.len();
+            let interval = Duration::from_secs_f64(args.replay_interval.max(0.0));
+            vec![replay.with_interval(interval).into_frame_source()]
+        }),
+        (None, None, Some(_)) => display_sources(&capture_options),
+        (None, None, None) => FrameSource::from_options(capture_options).map(|source| vec![source]),
+    };
+    let mut sources = match sources {
+        Ok(sources) => sources.into_iter(),
+        Err(e) => {
+            eprintln!("❌ Failed to create capturer: {}", e);
+            return;
+        }
+    };
+    let Some(frame_source) = sources.next() else {
+        return;
+    };
+    let other_displays: Vec<FrameSource> = sources.collect();
+
+    let mut capture_events = frame_source.event_channel(8);
+    let event_printer = Arc::clone(&printer);
+    tokio::spawn(async move {
+        while let Some(event) = capture_events.recv().await {
+            match event {
+                CaptureEvent::Interrupted => {
+                    event_printer.print_status("⚠️ Screen capture interrupted, restarting...")
+                }
+                CaptureEvent::Restarted { attempts } => event_printer.print_status(&format!(
+                    "🔄 Screen capture restarted after {} attempt(s)",
+                    attempts
+                )),
+            }
+        }
+    });
+
+    #[cfg(feature = "recording")]
+    let recorder = match &args.record {
+        Some(path) => match watcher_core::Recorder::start(&frame_source, path) {
+            Ok(recorder) => {
+                printer.print_status(&format!("🎬 Recording video to {}", path.display()));
+                Some(recorder)
+            }
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let mut session_options = SessionOptions::builder()
+        .format(args.format)
+        .quality(args.quality)
+        .save_frames(!args.no_save)
+        .embed_metadata(!args.no_metadata)
+        .filename_pattern(args.filename_pattern.clone())
+        .output_dir(output_dir.to_string_lossy());
+    let prompt = args
+        .prompt
+        .as_ref()
+        .or_else(|| profile.as_ref().and_then(|profile| profile.prompt.as_ref()));
+    if let Some(prompt) = prompt {
+        session_options = session_options.prompt_template(prompt.clone());
+    }
+    if let Some(preamble) = &args.preamble {
+        session_options = session_options.preamble(preamble.clone());
+    }
+    let session_options = match session_options.build() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("❌ Invalid session options: {}", e);
+            return;
+        }
+    };
+    let mut session = CaptureSession::new(
+        frame_source,
+        sender.clone(),
+        Arc::clone(&printer),
+        session_options,
+    );
+    // No answers come back in a dry run, so its turns would stay pending forever
+    if !args.dry_run {
+        session = session.with_turn_tracker(turns);
+    }
+    // The queue writes frames to disk, which --no-save and --aggregate promise not to do
+    if key_pool.is_some() && args.offline_queue > 0 && !args.no_save && !args.aggregate {
+        match OfflineQueue::open(output_dir.join("offline"), args.offline_queue) {
+            Ok(queue) => session = session.with_offline_queue(queue),
+            Err(e) => eprintln!("❌ Failed to open the offline queue: {}", e),
+        }
+    }
+    if let Some(telemetry) = &telemetry {
+        session = session.with_telemetry(Arc::clone(telemetry));
+    }
+    // A timeline of answers is what aggregation mode keeps from being stored
+    if !args.no_save && !args.no_manifest && !args.aggregate {
+        match ManifestWriter::open(output_dir) {
+            Ok(manifest) => session = session.with_manifest(manifest),
+            Err(e) => eprintln!("❌ Failed to open the manifest: {}", e),
+        }
+    }
+    #[cfg(feature = "sqlite")]
+    #[cfg_attr(not(feature = "http-api"), allow(unused_variables))]
+    let activity_store = match &args.activity_db {
+        Some(path) => match watcher_core::ActivityStore::open(path) {
+            Ok(store) => {
+                let store = Arc::new(store);
+                session = session.with_activity_store(Arc::clone(&store));
+                Some(store)
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to open {}: {}", path.display(), e);
+                None
+            }
+        },
+        None => None,
+    };
+    if let Some(mode) = args.all_displays {
+        printer.print_status(&format!(
+            "🖥️ Capturing {} displays ({:?})",
+            other_displays.len() + 1,
+            mode
+        ));
+        session = session.with_displays(other_displays, mode);
+    }
+    let rules: Vec<RedactionRule> = args
+        .redact_app
+        .iter()
+        .cloned()
+        .map(RedactionRule::App)
+        .chain(args.redact_title.iter().cloned().map(RedactionRule::Title))
+        .chain(
+            args.redact_region
+                .iter()
+                .copied()
+                .map(RedactionRule::Region),
+        )
+        .collect();
+    if !rules.is_empty() {
+        printer.print_status(&format!(
+            "🙈 Redacting {} apps, titles or regions",
+            rules.len()
+        ));
+        let mut redactor = Redactor::new(rules, args.redact_style);
+        if let Some(region) = args.region {
+            redactor = redactor.with_capture_region(region);
+        }
+        session = session.with_redaction(redactor);
+    }
+    if !args.exclude_app.is_empty() {
+        printer.print_status(&format!(
+            "🙈 Pausing capture while any of {} excluded apps is on screen",
+            args.exclude_app.len()
+        ));
+        let mut exclusion = AppExclusion::new(args.exclude_app.clone());
+        if let Some(region) = args.region {
+            exclusion = exclusion.with_capture_region(region);
+        }
+        session = session.with_app_exclusion(exclusion);
+    }
+    if args.pointer {
+        let clicks = match ClickWatcher::start() {
+            Ok(clicks) => Some(clicks),
+            Err(e) => {
+                eprintln!("⚠️ Click tracking disabled: {}", e);
+                None
+            }
+        };
+        printer.print_status("🖱️ Reporting the pointer position to Gemini");
+        session = session.with_pointer(PointerTracker::new(clicks));
+    }
+    if args.activity_meter {
+        match ActivityMeter::start() {
+            Ok(meter) => {
+                printer.print_status("⌨️ Counting keypresses and clicks between screenshots");
+                session = session.with_activity_meter(meter);
+            }
+            Err(e) => eprintln!("⚠️ Activity meter disabled: {}", e),
+        }
+    }
+    if args.clipboard {
+        printer.print_status("📎 Adding copied text to the prompt");
+        session = session.with_clipboard(
+            ClipboardContext::new(args.clipboard_redact.clone())
+                .with_max_chars(args.clipboard_chars),
+        );
+    }
+    #[cfg(feature = "ocr")]
+    if args.ocr.is_some() {
+        printer.print_status("📝 Adding on-screen text to the prompt");
+        session = session.with_ocr(watcher_core::TextRecognizer::new());
+    }
+    #[cfg(feature = "calendar")]
+    if args.calendar {
+        // Waits for the user to answer the permission prompt
+        match tokio::task::spawn_blocking(watcher_core::Calendar::connect).await {
+            Ok(Ok(calendar)) => {
+                printer.print_status("📅 Adding the current calendar event to the prompt");
+                session = session.with_calendar(calendar);
+            }
+            Ok(Err(e)) => eprintln!("⚠️ Calendar context disabled: {}", e),
+            Err(e) => eprintln!("⚠️ Calendar context disabled: {}", e),
+        }
+    }
+    if let Some(profile) = &profile {
+        printer.print_status(&format!("🧭 Using the {} prompt profile", profile.name));
+    }
+    if args.zoom_follow {
+        printer.print_status(&format!("🔍 Zoom-follow enabled at {:.1}x", args.zoom));
+        session = session.with_zoom_follow(ZoomFollow::new(args.zoom));
+    }
+    let resize_target = match (args.max_edge, args.resize_width, args.resize_height) {
+        (Some(edge), _, _) => Some(ResizeTarget::MaxLongEdge(edge)),
+        (None, None, None) => None,
+        (None, width, height) => Some(ResizeTarget::Fit {
+            width: width.unwrap_or(u32::MAX),
+            height: height.unwrap_or(u32::MAX),
+        }),
+    };
+    if let Some(target) = resize_target {
+        session = session.with_resize(ResizeOptions::new(target).with_filter(args.resize_filter));
+    }
+    if args.annotate {
+        session = session.with_annotations();
+    }
+    if args.aggregate {
+        session = session.with_aggregation_mode();
+    }
+    let anonymizer: Arc<dyn Anonymizer> = match args.anonymize {
+        AnonymizeMode::Passthrough => Arc::new(Passthrough),
+        AnonymizeMode::Hash => Arc::new(HashAnonymizer::new(args.anonymize_salt.clone())),
+        AnonymizeMode::Category => match &args.anonymize_categories {
+            Some(path) => match CategoryAnonymizer::from_file(path) {
+                Ok(categories) => Arc::new(categories),
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    return;
+                }
+            },
+            None => Arc::new(CategoryAnonymizer::default()),
+        },
+    };
+    session = session.with_anonymizer(anonymizer);
+    if let Some(threshold) = args.skip_unchanged {
+        session = session.with_change_detection(ChangeDetector::new(threshold));
+    }
+    if let Some(capacity) = args.dedup {
+        session = session.with_deduplication(FrameDeduplicator::new(capacity));
+    }
+    if let Some(frames) = args.batch {
+        let mut options = BatchOptions::frames(frames);
+        if let Some(seconds) = args.batch_seconds {
+            options = options.with_max_age(Duration::from_secs(seconds));
+        }
+        printer.print_status(&format!(
+            "🧺 Sending frames in batches of {}",
+            options.max_frames
+        ));
+        session = session.with_batching(options);
+    }
+    // The preview server and the live feed's thumbnails both show the frames sent to the model
+    let preview_feed = (args.preview_port.is_some() || args.live_thumbnails).then(PreviewFeed::new);
+    if let (Some(port), Some(feed)) = (args.preview_port, &preview_feed) {
+        match PreviewServer::bind(([127, 0, 0, 1], port).into(), feed.clone()).await {
+            Ok(server) => {
+                if let Ok(addr) = server.local_addr() {
+                    printer.print_status(&format!("👀 Live preview at http://{}/", addr));
+                }
+                server.spawn();
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to start preview server on port {}: {}", port, e);
+                return;
+            }
+        }
+    }
+    if let Some(feed) = &preview_feed {
+        session = session.with_preview(feed.clone());
+    }
+    let live_feed = match args.live_port {
+        Some(port) => {
+            let feed = LiveFeed::new();
+            match LiveFeedServer::bind(([127, 0, 0, 1], port).into(), feed.clone()).await {
+                Ok(server) => {
+                    if let Ok(addr) = server.local_addr() {
+                        printer.print_status(&format!("📡 Live feed at ws://{}/", addr));
+                    }
+                    server.spawn();
+                }
+                Err(e) => {
+                    eprintln!("❌ Failed to start the live feed on port {}: {}", port, e);
+                    return;
+                }
+            }
+            if let Some(preview) = preview_feed.as_ref().filter(|_| args.live_thumbnails) {
+                feed.forward_thumbnails(preview);
+            }
+            Some(feed)
+        }
+        None => None,
+    };
+    if !retention.is_empty() {
+        session = session.with_retention(retention_manager);
+    }
+    session = session.with_resource_limits(ResourceLimits {
+        max_cpu_percent: args.max_cpu,
+        max_rss_bytes: args.max_memory.map(|mb| mb * 1024 * 1024),
+    });
+    let session = Arc::new(session);
+    if let Some(tool) = &screenshot_tool {
+        tool.attach(&session);
+        printer.print_status("📷 The model can take screenshots with the take_screenshot tool");
+    }
+    #[cfg(feature = "http-api")]
+    if let (Some(port), Some(store)) = (args.http_port, &activity_store) {
+        match watcher_core::ActivityApi::bind(([127, 0, 0, 1], port).into(), Arc::clone(store))
+            .await
+        {
+            Ok(api) => {
+                if let Ok(addr) = api.local_addr() {
+                    printer.print_status(&format!("🌐 Activity API at http://{}/status", addr));
+                }
+                api.with_session(Arc::clone(&session)).spawn();
+            }
+            Err(e) => {
+                eprintln!(
+                    "❌ Failed to start the activity API on port {}: {}",
+                    port, e
+                );
+                return;
+            }
+        }
+    }
+    if let Some(focus) = &focus {
+        printer.print_status(&format!(
+            "🎯 Focusing on {}, alerting after {} off-task answers in a row",
+            focus.intention(),
+            args.focus_streak.max(1)
+        ));
+    }
+    #[cfg(feature = "webhooks")]
+    let webhooks = match watcher_core::WebhookNotifier::spawn(config.notify.webhooks.clone()) {
+        // Off-task focus alerts go to the webhooks too
+        Ok(Some(webhooks)) if !alert_rules.is_empty() || focus.is_some() => {
+            printer.print_status(&format!(
+                "🔔 Notifying {} webhooks about {} rules{}",
+                config.notify.webhooks.len(),
+                alert_rules.len(),
+                if focus.is_some() { " and focus alerts" } else { "" }
+            ));
+            Some(webhooks)
+        }
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("❌ Failed to start notifications: {}", e);
+            return;
+        }
+    };
+    #[cfg(feature = "desktop-notifications")]
+    let desktop_notifier = if args.notify {
+        match watcher_core::DesktopNotifier::new() {
+            Ok(notifier) => Some(Arc::new(notifier)),
+            Err(e) => {
+                eprintln!("⚠️ Desktop notifications disabled: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    #[cfg(feature = "desktop-notifications")]
+    let connection_watch = desktop_notifier
+        .as_ref()
+        .map(|notifier| notifier.watch_connection(Arc::clone(&session)));
+    {
+        let session = Arc::clone(&session);
+        let writer = rpc_writer.clone();
+        let printer = Arc::clone(&printer);
+        #[cfg(feature = "desktop-notifications")]
+        let desktop_notifier = desktop_notifier.clone();
+        #[cfg(feature = "tui")]
+        let dashboard = dashboard.clone();
+        tokio::spawn(async move {
+            while let Some(result) = answers.recv().await {
+                session.record_answer(&result);
+                #[cfg(feature = "tui")]
+                if let Some(dashboard) = &dashboard {
+                    dashboard.record_answer(&result);
+                }
+                if let Some(feed) = &live_feed {
+                    feed.publish(&result);
+                }
+                let focus_alert = focus.as_ref().and_then(|focus| focus.observe(&result));
+                for notification in alert_rules.observe(&result).into_iter().chain(focus_alert) {
+                    printer.print_status(&notification.text());
+                    #[cfg(feature = "webhooks")]
+                    if let Some(webhooks) = &webhooks {
+                        webhooks.send(&notification);
+                    }
+                    #[cfg(feature = "desktop-notifications")]
+                    if let Some(notifier) = &desktop_notifier
+                        && let Err(e) =
+                            notifier.notify(watcher_core::NOTIFICATION_TITLE, &notification.text())
+                    {
+                        printer.print_error(&format!("❌ Failed to show a notification: {}", e));
+                    }
+                }
+                #[cfg(all(feature = "hotkey", feature = "desktop-notifications"))]
+                if result.on_demand
+                    && let Some(notifier) = &desktop_notifier
+                {
+                    let answer = result
+                        .activity
+                        .as_ref()
+                        .map_or(result.text.as_str(), |activity| activity.summary.as_str());
+                    if let Err(e) = notifier.notify(watcher_core::NOTIFICATION_TITLE, answer) {
+                        printer.print_error(&format!("❌ Failed to show a notification: {}", e));
+                    }
+                }
+                if let Some(writer) = &writer {
+                    rpc::notify_result(writer, &result);
+                }
+            }
+        });
+    }
+    if args.system_audio {
+        printer.print_status("🔊 Streaming system audio to Gemini");
+        session.spawn_system_audio();
+    }
+
+    let setup = Arc::new(tokio::sync::Mutex::new(setup));
+    // Chat backends answer over HTTP and have no connection to lose
+    let reconnector = key_pool.as_ref().map(|key_pool| {
+        reconnect::Reconnector {
+            session: Arc::clone(&session),
+            printer: Arc::clone(&printer),
+            setup: Arc::clone(&setup),
+            key_pool: key_pool.clone(),
+            key_lease: Arc::clone(&key_lease),
+            spawn_output: Arc::clone(&spawn_output),
+        }
+        .spawn()
+    });
+
+    if let (Some(minutes), Some(capture), Some(key_pool)) =
+        (args.memory_refresh, summary_capture, key_pool)
+    {
+        memory_refresh::MemoryRefresher {
+            session: Arc::clone(&session),
+            capture,
+            log: SummaryLog::new(&args.summary_log),
+            interval: Duration::from_secs(minutes.max(1) * 60),
+            restart: !args.context_compression,
+            rolling: args.rolling_summary,
+            history: answer_history,
+            setup,
+            base_instruction: system_instruction.to_string(),
+            key_pool,
+            key_lease,
+            spawn_output,
+        }
+        .spawn();
+    }
+
+    let _menu_watcher = if args.menu_events {
+        match MenuWatcher::start() {
+            Ok((watcher, mut events)) => {
+                printer.print_status("📋 Watching menu and Dock interactions");
+                let session = Arc::clone(&session);
+                tokio::spawn(async move {
+                    while let Some(event) = events.recv().await {
+                        if let Err(e) = session.capture_menu_event(&event).await {
+                            session
+                                .printer()
+                                .print_error(&format!("❌ Error sending menu event: {}", e));
+                        }
+                    }
+                });
+                Some(watcher)
+            }
+            Err(e) => {
+                eprintln!("⚠️ Menu events disabled: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    #[cfg(feature = "hotkey")]
+    let _hotkey = match args.hotkey {
+        Some(hotkey) => match watcher_core::HotkeyListener::start(hotkey) {
+            Ok((listener, mut presses)) => {
+                printer.print_status(&format!("⌨️ Press {} to ask about the screen", hotkey));
+                let session = Arc::clone(&session);
+                let printer = Arc::clone(&printer);
+                let question = args.hotkey_question.clone();
+                tokio::spawn(async move {
+                    while presses.recv().await.is_some() {
+                        printer.print_status("⌨️ Hotkey pressed, asking about the screen");
+                        if let Err(e) = session.capture_on_demand(&question).await {
+                            printer
+                                .print_error(&format!("❌ Error asking about the screen: {}", e));
+                        }
+                    }
+                });
+                Some(listener)
+            }
+            Err(e) => {
+                eprintln!("⚠️ Hotkey disabled: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    #[cfg(feature = "voice")]
+    let _voice = match args.voice {
+        Some(hotkey) => match watcher_core::HotkeyListener::start(hotkey) {
+            Ok((listener, presses)) => {
+                printer.print_status(&format!("🎙️ Press {} to ask a question out loud", hotkey));
+                voice::VoiceQuestions {
+                    session: Arc::clone(&session),
+                    printer: Arc::clone(&printer),
+                    hotkey,
+                    limit: Duration::from_secs(args.voice_limit.max(1)),
+                }
+                .spawn(presses);
+                Some(listener)
+            }
+            Err(e) => {
+                eprintln!("⚠️ Voice questions disabled: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    if let Some(seconds) = args.pause_when_idle
+        && !replays_files(&args)
+    {
+        if watcher_core::user_idle_time().is_some() {
+            printer.print_status(&format!(
+                "💤 Pausing capture after {}s without input",
+                seconds
+            ));
+            IdleWatch::new(Duration::from_secs(seconds.max(1)), Arc::clone(&printer))
+                .spawn(Arc::clone(&session));
+        } else {
+            eprintln!("⚠️ Idle detection is not available on this platform");
+        }
+    }
+
+    // Nothing on a locked screen is worth capturing or sending
+    if !replays_files(&args) && watcher_core::is_screen_locked().is_some() {
+        ScreenLockWatch::new(Arc::clone(&printer)).spawn(Arc::clone(&session));
+    }
+
+    let _control = match &args.control_socket {
+        Some(_) if replays_files(&args) => {
+            eprintln!("❌ --control-socket needs a live capture");
+            return;
+        }
+        Some(path) => {
+            match control::ControlServer::bind(path, Arc::clone(&session), control_options) {
+                Ok(server) => {
+                    printer.print_status(&format!("🎛️ Accepting commands on {}", path.display()));
+                    Some(server.spawn())
+                }
+                Err(e) => {
+                    eprintln!(
+                        "❌ Failed to open the control socket {}: {}",
+                        path.display(),
+                        e
+                    );
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+
+    if let Some(writer) = rpc_writer {
+        // Commands drive the capture loop until stdin is closed
+        rpc::RpcServer::new(Arc::clone(&session), writer, args.zoom)
+            .serve()
+            .await;
+        session.stop();
+        session.finish_exclusion().await;
+        #[cfg(feature = "desktop-notifications")]
+        if let Some(watch) = connection_watch {
+            watch.abort();
+        }
+        if let Some(reconnector) = reconnector {
+            reconnector.abort();
+        }
+        #[cfg(feature = "recording")]
+        finish_recording(recorder);
+        session.sender().close().await.ok();
+        return;
+    }
+
+    let interval = Duration::from_secs_f64(args.interval.max(0.1));
+    if args.continuous {
+        println!(
+            "📸 Capturing a frame every {}s and sending to Gemini...",
+            interval.as_secs_f64()
+        );
+    } else {
+        println!("📸 Capturing {} frames and sending to Gemini...", frame_count);
+    }
+    if args.aggregate {
+        println!("🔒 Aggregation mode: frames and answers are not stored");
+    } else if args.no_save {
+        println!("📤 Frames are sent without being saved");
+    } else {
+        println!(
+            "💾 Saving frames to {}/ directory as {:?}",
+            output_dir.display(),
+            args.format
+        );
+    }
     println!("Press Ctrl+C to stop\n");
 
-    // Run capture session
-    let session = CaptureSession::new(frame_source, sender.clone(), printer, "output".to_string());
+    // Stopped by Ctrl+C, or by quitting the dashboard
+    let cancel = CancellationToken::new();
+    #[cfg(feature = "tui")]
+    let dashboard = match &dashboard {
+        Some(dashboard) => match dashboard.spawn(Arc::clone(&session), cancel.clone()) {
+            Ok(ui) => Some(ui),
+            Err(e) => {
+                eprintln!("❌ Failed to start the dashboard: {}", e);
+                return;
+            }
+        },
+        None => None,
+    };
 
-    if let Err(e) = session.capture_frames(10).await {
-        eprintln!("❌ Capture error: {}", e);
+    // Run capture session
+    if args.continuous {
+        let on_ctrl_c = cancel.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                on_ctrl_c.cancel();
+            }
+        });
+        session.run(interval, cancel.clone()).await;
+    } else {
+        tokio::select! {
+            result = session.capture_frames(frame_count) => if let Err(e) = result {
+                printer.print_error(&format!("❌ Capture error: {}", e));
+            },
+            _ = cancel.cancelled() => {}
+        }
     }
 
+    session.stop();
+    session.finish_exclusion().await;
+    #[cfg(feature = "desktop-notifications")]
+    if let Some(watch) = connection_watch {
+        watch.abort();
+    }
+    if let Some(reconnector) = reconnector {
+        reconnector.abort();
+    }
+    #[cfg(feature = "tui")]
+    if let Some(ui) = dashboard {
+        // Gives the terminal back before the closing lines are printed
+        cancel.cancel();
+        ui.join().ok();
+    }
+    #[cfg(feature = "recording")]
+    finish_recording(recorder);
     println!("\n✅ Capture stopped. Closing Gemini session...");
-    sender.close().await.ok();
+    session.sender().close().await.ok();
     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+
+    if let Some(telemetry) = &telemetry {
+        print!("\n{}", telemetry.summary());
+    }
+
+    if let Some(aggregator) = aggregator {
+        let report = aggregator.report();
+        let written = serde_json::to_string_pretty(&report)
+            .map_err(std::io::Error::other)
+            .and_then(|json| std::fs::write(&args.aggregate_output, json));
+        match written {
+            Ok(()) => println!(
+                "📊 Aggregate report written to {}",
+                args.aggregate_output.display()
+            ),
+            Err(e) => eprintln!("❌ Failed to write aggregate report: {}", e),
+        }
+    }
 }
@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use watcher_core::{
+    rolling_instruction, seeded_instruction, unix_millis, AnalysisEvents, AnswerHistory,
+    CaptureSession, Content, KeyLease, KeyPool, ResponsePrinter, SessionSummary, Setup,
+    SummaryCapture, SummaryLog, MEMORY_REFRESH_PROMPT, ROLLING_SUMMARY_PROMPT,
+};
+
+/// How long the model gets to answer a summary turn
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Period summaries a rolling restart seeds the session with; older ones stay in the log
+const ROLLING_SUMMARIES: usize = 12;
+
+/// Latest answers a rolling restart carries over in full
+const ROLLING_RECENT_ANSWERS: usize = 3;
+
+/// Periodically asks the model for a session summary and, when the server cannot compress
+/// its context, restarts the session seeded with that summary
+pub struct MemoryRefresher {
+    pub session: Arc<CaptureSession>,
+    pub capture: Arc<SummaryCapture>,
+    pub log: SummaryLog,
+    pub interval: Duration,
+    /// Reconnect with a seeded instruction after each summary
+    pub restart: bool,
+    /// Summarize only the time since the previous summary and restart from the latest period
+    /// summaries and answers instead of a single summary of the whole session
+    pub rolling: bool,
+    /// Answers a rolling restart carries over
+    pub history: Option<Arc<AnswerHistory>>,
+    /// Setup of the current session, shared with the reconnector so it keeps the seeded
+    /// instruction
+    pub setup: Arc<tokio::sync::Mutex<Setup>>,
+    pub base_instruction: String,
+    pub key_pool: KeyPool,
+    /// Key slot held by the current session, swapped on every restart
+    pub key_lease: Arc<tokio::sync::Mutex<Option<KeyLease>>>,
+    /// Starts response handling for a freshly connected session
+    pub spawn_output: Arc<dyn Fn(Box<dyn AnalysisEvents>) + Send + Sync>,
+}
+
+impl MemoryRefresher {
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            // The first tick completes immediately
+            ticker.tick().await;
+
+            let started = SystemTime::now();
+            let mut summaries = VecDeque::new();
+            loop {
+                ticker.tick().await;
+                if !self.refresh(started, &mut summaries).await {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Runs a single refresh, returning false once the session is gone; `summaries` holds the
+    /// latest period summaries of a rolling refresher
+    async fn refresh(&self, started: SystemTime, summaries: &mut VecDeque<SessionSummary>) -> bool {
+        let sender = self.session.sender();
+        if sender.is_closed() {
+            return false;
+        }
+
+        let prompt = if self.rolling {
+            ROLLING_SUMMARY_PROMPT
+        } else {
+            MEMORY_REFRESH_PROMPT
+        };
+        let asked = SystemTime::now();
+        self.capture.begin();
+        if let Err(e) = self.session.send_question(prompt).await {
+            self.capture
+                .print_error(&format!("❌ Error requesting session summary: {}", e));
+            return !sender.is_closed();
+        }
+
+        let Some(text) = self.capture.wait(SUMMARY_TIMEOUT).await else {
+            self.capture
+                .print_error("⚠️ No session summary received, keeping the current session");
+            return true;
+        };
+
+        let summary = if self.rolling {
+            let since = summaries.back().map_or(started, |last| {
+                UNIX_EPOCH + Duration::from_millis(last.timestamp_ms)
+            });
+            SessionSummary::since(since, text)
+        } else {
+            SessionSummary::now(text)
+        };
+        if let Err(e) = self.log.append(&summary) {
+            self.capture
+                .print_error(&format!("❌ Failed to store session summary: {}", e));
+        }
+        if self.rolling {
+            if summaries.len() == ROLLING_SUMMARIES {
+                summaries.pop_front();
+            }
+            summaries.push_back(summary.clone());
+        }
+
+        if !self.restart {
+            self.capture.print_status("🧠 Session summary stored");
+            return true;
+        }
+
+        let instruction = if self.rolling {
+            // The summary turn itself is the newest answer
+            let asked = unix_millis(asked);
+            let mut recent = self.history.as_ref().map_or_else(Vec::new, |history| {
+                history.recent(ROLLING_RECENT_ANSWERS + 1)
+            });
+            recent.retain(|answer| answer.timestamp_ms < asked);
+            let skip = recent.len().saturating_sub(ROLLING_RECENT_ANSWERS);
+            rolling_instruction(
+                &self.base_instruction,
+                summaries.make_contiguous(),
+                &recent[skip..],
+            )
+        } else {
+            seeded_instruction(&self.base_instruction, &summary.text)
+        };
+        let mut setup = self.setup.lock().await.clone();
+        setup.system_instruction = Some(Content::system(instruction));
+        match self.key_pool.connect(setup.clone()).await {
+            Ok((fresh, lease)) => {
+                self.session.set_sender(fresh.sender_handle());
+                *self.key_lease.lock().await = Some(lease);
+                *self.setup.lock().await = setup;
+                (self.spawn_output)(Box::new(fresh));
+                sender.close().await.ok();
+                self.capture
+                    .print_status("🧠 Session restarted from summary");
+            }
+            Err(e) => {
+                self.capture
+                    .print_error(&format!("❌ Failed to restart session from summary: {}", e));
+            }
+        }
+        true
+    }
+}
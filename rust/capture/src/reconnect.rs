@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use watcher_core::{AnalysisEvents, CaptureSession, KeyLease, KeyPool, ResponsePrinter, Setup};
+
+/// How often the connection is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Longest wait between failed reconnects, which double from `CHECK_INTERVAL`
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Opens a new Gemini session whenever the current one closes, e.g. after a network error,
+/// and sends the turns the capture session queued meanwhile
+///
+/// Abort the task before closing the session on purpose.
+pub struct Reconnector {
+    pub session: Arc<CaptureSession>,
+    pub printer: Arc<dyn ResponsePrinter>,
+    /// Setup of the current session, whose instruction memory refresh may have replaced
+    pub setup: Arc<tokio::sync::Mutex<Setup>>,
+    pub key_pool: KeyPool,
+    /// Key slot held by the current session, shared with the memory refresher
+    pub key_lease: Arc<tokio::sync::Mutex<Option<KeyLease>>>,
+    /// Starts response handling for a freshly connected session
+    pub spawn_output: Arc<dyn Fn(Box<dyn AnalysisEvents>) + Send + Sync>,
+}
+
+impl Reconnector {
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut backoff = CHECK_INTERVAL;
+            loop {
+                tokio::time::sleep(backoff).await;
+                if !self.session.sender().is_closed() {
+                    backoff = CHECK_INTERVAL;
+                    continue;
+                }
+                let setup = self.setup.lock().await.clone();
+                match self.key_pool.connect(setup).await {
+                    Ok((fresh, lease)) => {
+                        self.session.set_sender(fresh.sender_handle());
+                        *self.key_lease.lock().await = Some(lease);
+                        (self.spawn_output)(Box::new(fresh));
+                        self.printer.print_status("🔌 Reconnected to Gemini");
+                        self.session.flush_offline_queue().await;
+                        backoff = CHECK_INTERVAL;
+                    }
+                    Err(e) => {
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                        self.printer.print_error(&format!(
+                            "❌ Failed to reconnect, retrying in {}s: {}",
+                            backoff.as_secs(),
+                            e
+                        ));
+                    }
+                }
+            }
+        })
+    }
+}
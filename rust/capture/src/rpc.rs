@@ -0,0 +1,225 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::io::BufReader;
+use tokio::task::JoinHandle;
+use watcher_core::{
+    read_rpc_message, AnalysisResult, CaptureSession, RpcError, RpcRequest, RpcWriter,
+    TelemetrySummary, ZoomFollow, RPC_INTERNAL_ERROR, RPC_INVALID_PARAMS, RPC_INVALID_REQUEST,
+    RPC_METHOD_NOT_FOUND, RPC_PARSE_ERROR,
+};
+
+#[derive(Debug, Default, Deserialize)]
+struct StartParams {
+    /// Number of frames to capture; runs until `stop` when omitted
+    count: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct AskParams {
+    pub question: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigureParams {
+    prompt: Option<String>,
+    #[serde(default)]
+    reset_prompt: bool,
+    zoom_follow: Option<bool>,
+    zoom: Option<f64>,
+}
+
+/// Serves JSON-RPC commands from stdin until the input is closed
+pub struct RpcServer {
+    session: Arc<CaptureSession>,
+    writer: RpcWriter,
+    capture_task: Option<JoinHandle<()>>,
+    zoom: f64,
+}
+
+impl RpcServer {
+    pub fn new(session: Arc<CaptureSession>, writer: RpcWriter, zoom: f64) -> Self {
+        Self {
+            session,
+            writer,
+            capture_task: None,
+            zoom,
+        }
+    }
+
+    pub async fn serve(mut self) {
+        let mut stdin = BufReader::new(tokio::io::stdin());
+        let _ = self.writer.notify("session/ready", json!({}));
+
+        loop {
+            let message = match read_rpc_message(&mut stdin).await {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(RpcError::Json(err)) => {
+                    let _ =
+                        self.writer
+                            .respond_error(Value::Null, RPC_PARSE_ERROR, err.to_string());
+                    continue;
+                }
+                Err(err) => {
+                    eprintln!("❌ RPC input error: {}", err);
+                    break;
+                }
+            };
+
+            let request: RpcRequest = match serde_json::from_value(message) {
+                Ok(request) => request,
+                Err(err) => {
+                    let _ = self.writer.respond_error(
+                        Value::Null,
+                        RPC_INVALID_REQUEST,
+                        err.to_string(),
+                    );
+                    continue;
+                }
+            };
+
+            let result = self.dispatch(&request.method, request.params).await;
+            if let Some(id) = request.id {
+                let _ = match result {
+                    Ok(value) => self.writer.respond(id, value),
+                    Err((code, message)) => self.writer.respond_error(id, code, message),
+                };
+            }
+        }
+
+        self.stop_capture();
+    }
+
+    async fn dispatch(&mut self, method: &str, params: Value) -> Result<Value, (i64, String)> {
+        match method {
+            "start" => {
+                let params: StartParams = parse_params(params)?;
+                self.start_capture(params.count)?;
+                Ok(json!({ "running": true }))
+            }
+            "stop" => {
+                let was_running = self.stop_capture();
+                Ok(json!({ "running": false, "wasRunning": was_running }))
+            }
+            "ask" => {
+                let params: AskParams = parse_params(params)?;
+                if params.question.trim().is_empty() {
+                    return Err((RPC_INVALID_PARAMS, "question must not be empty".to_string()));
+                }
+                self.session
+                    .send_question(params.question)
+                    .await
+                    .map_err(|err| (RPC_INTERNAL_ERROR, err.to_string()))?;
+                Ok(json!({ "sent": true }))
+            }
+            "pause" => {
+                self.session.pause();
+                Ok(json!({ "paused": true }))
+            }
+            "resume" => {
+                self.session.resume();
+                Ok(json!({ "paused": self.session.is_paused() }))
+            }
+            "status" => Ok(json!({
+                "running": self.is_running(),
+                "paused": self.session.is_paused(),
+                "connected": !self.session.sender().is_closed(),
+            })),
+            "telemetry" => {
+                let telemetry = self
+                    .session
+                    .telemetry()
+                    .ok_or_else(|| (RPC_INVALID_REQUEST, "Telemetry is not enabled".to_string()))?;
+                Ok(telemetry_json(&telemetry.summary()))
+            }
+            "configure" => {
+                let params: ConfigureParams = parse_params(params)?;
+                if let Some(zoom) = params.zoom {
+                    self.zoom = zoom;
+                }
+                if let Some(enabled) = params.zoom_follow {
+                    self.session
+                        .set_zoom_follow(enabled.then(|| ZoomFollow::new(self.zoom)));
+                }
+                if params.reset_prompt {
+                    self.session.set_prompt(None);
+                } else if let Some(prompt) = params.prompt {
+                    self.session.set_prompt(Some(prompt));
+                }
+                Ok(json!({ "configured": true }))
+            }
+            other => Err((RPC_METHOD_NOT_FOUND, format!("Unknown method: {}", other))),
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.capture_task
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
+    }
+
+    fn start_capture(&mut self, count: Option<usize>) -> Result<(), (i64, String)> {
+        if self.is_running() {
+            return Err((RPC_INTERNAL_ERROR, "Capture is already running".to_string()));
+        }
+
+        let session = Arc::clone(&self.session);
+        let writer = self.writer.clone();
+        self.capture_task = Some(tokio::spawn(async move {
+            let result = session.capture_frames(count.unwrap_or(usize::MAX)).await;
+            let params = match result {
+                Ok(()) => json!({}),
+                Err(err) => json!({ "error": err.to_string() }),
+            };
+            let _ = writer.notify("capture/stopped", params);
+        }));
+        Ok(())
+    }
+
+    fn stop_capture(&mut self) -> bool {
+        match self.capture_task.take() {
+            Some(task) if !task.is_finished() => {
+                task.abort();
+                let _ = self.writer.notify("capture/stopped", json!({}));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Per-stage timings in milliseconds
+fn telemetry_json(summary: &TelemetrySummary) -> Value {
+    let millis = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+    let stages: serde_json::Map<String, Value> = summary
+        .stages
+        .iter()
+        .map(|stage| {
+            let timings = json!({
+                "count": stage.count,
+                "meanMs": millis(stage.mean),
+                "p50Ms": millis(stage.p50),
+                "p95Ms": millis(stage.p95),
+                "maxMs": millis(stage.max),
+            });
+            (stage.stage.as_str().to_string(), timings)
+        })
+        .collect();
+    json!({ "frames": summary.frames, "stages": stages })
+}
+
+/// Emits an answer as an `analysis/result` notification, naming the frame it is about
+pub fn notify_result(writer: &RpcWriter, result: &AnalysisResult) {
+    let _ = writer.notify("analysis/result", result.to_json());
+}
+
+pub(crate) fn parse_params<T: serde::de::DeserializeOwned + Default>(
+    params: Value,
+) -> Result<T, (i64, String)> {
+    if params.is_null() {
+        return Ok(T::default());
+    }
+    serde_json::from_value(params).map_err(|err| (RPC_INVALID_PARAMS, err.to_string()))
+}
@@ -0,0 +1,298 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+use tokio_util::sync::CancellationToken;
+use watcher_core::{clock_time, AnalysisResult, CaptureSession, Content, Part, ResponsePrinter};
+
+/// Span the frame rate is averaged over
+const FRAME_RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Timeline entries kept for scrolling back
+const TIMELINE_CAPACITY: usize = 1000;
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Rows of the last response panel, borders included
+const RESPONSE_HEIGHT: u16 = 8;
+
+struct TimelineEntry {
+    at: SystemTime,
+    /// Answers stand out from pipeline status lines
+    answer: bool,
+    text: String,
+}
+
+struct DashboardState {
+    started: Instant,
+    frames: VecDeque<Instant>,
+    frames_sent: usize,
+    answers: usize,
+    /// Text of the turn the model is still generating
+    streaming: String,
+    last_response: String,
+    last_prompt_tokens: Option<i32>,
+    last_response_tokens: Option<i32>,
+    total_tokens: i64,
+    timeline: VecDeque<TimelineEntry>,
+}
+
+impl DashboardState {
+    fn push(&mut self, at: SystemTime, answer: bool, text: &str) {
+        if self.timeline.len() == TIMELINE_CAPACITY {
+            self.timeline.pop_front();
+        }
+        self.timeline.push_back(TimelineEntry {
+            at,
+            answer,
+            // One row per entry; the response panel shows answers in full
+            text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+        });
+    }
+
+    fn frame_rate(&mut self, now: Instant) -> f64 {
+        while self
+            .frames
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) > FRAME_RATE_WINDOW)
+        {
+            self.frames.pop_front();
+        }
+        let span = now.duration_since(self.started).min(FRAME_RATE_WINDOW);
+        self.frames.len() as f64 / span.as_secs_f64().max(1.0)
+    }
+}
+
+/// Full-screen terminal dashboard replacing the printed output of a capture: frame rate,
+/// connection state, token usage, the last answer and a scrolling timeline of answers and
+/// status lines
+#[derive(Clone)]
+pub struct Dashboard {
+    state: Arc<Mutex<DashboardState>>,
+}
+
+impl Default for Dashboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dashboard {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(DashboardState {
+                started: Instant::now(),
+                frames: VecDeque::new(),
+                frames_sent: 0,
+                answers: 0,
+                streaming: String::new(),
+                last_response: String::new(),
+                last_prompt_tokens: None,
+                last_response_tokens: None,
+                total_tokens: 0,
+                timeline: VecDeque::new(),
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, DashboardState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Adds a completed answer to the timeline and its tokens to the usage
+    pub fn record_answer(&self, result: &AnalysisResult) {
+        let text = match &result.activity {
+            Some(activity) => format!("[{}] {}", activity.category.as_str(), activity.summary),
+            None => result.text.clone(),
+        };
+        let mut state = self.state();
+        state.answers += 1;
+        if let Some(usage) = &result.usage {
+            state.last_prompt_tokens = usage.prompt_token_count;
+            state.last_response_tokens = usage.response_token_count;
+            state.total_tokens += i64::from(usage.total_token_count.unwrap_or(0));
+        }
+        state.push(result.timestamp, true, &text);
+    }
+
+    /// Takes over the terminal and redraws it until `cancel` is cancelled, which pressing q,
+    /// Esc or Ctrl+C does too; the terminal is restored when the returned thread ends
+    pub fn spawn(
+        &self,
+        session: Arc<CaptureSession>,
+        cancel: CancellationToken,
+    ) -> std::io::Result<JoinHandle<()>> {
+        let terminal = ratatui::try_init()?;
+        let dashboard = self.clone();
+        Ok(std::thread::spawn(move || {
+            let result = dashboard.run(terminal, &session, &cancel);
+            ratatui::restore();
+            if let Err(e) = result {
+                eprintln!("❌ Dashboard error: {}", e);
+            }
+        }))
+    }
+
+    fn run(
+        &self,
+        mut terminal: DefaultTerminal,
+        session: &CaptureSession,
+        cancel: &CancellationToken,
+    ) -> std::io::Result<()> {
+        // Timeline rows scrolled back from the newest entry
+        let mut scroll = 0usize;
+        while !cancel.is_cancelled() {
+            terminal.draw(|frame| self.draw(frame, session, scroll))?;
+            if !event::poll(REDRAW_INTERVAL)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                // Raw mode swallows the interrupt signal, so Ctrl+C arrives as a key
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    cancel.cancel()
+                }
+                KeyCode::Char('q') | KeyCode::Esc => cancel.cancel(),
+                KeyCode::Up => scroll += 1,
+                KeyCode::Down => scroll = scroll.saturating_sub(1),
+                KeyCode::PageUp => scroll += 10,
+                KeyCode::PageDown => scroll = scroll.saturating_sub(10),
+                KeyCode::End => scroll = 0,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&self, frame: &mut Frame, session: &CaptureSession, scroll: usize) {
+        let [header, response, timeline, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(RESPONSE_HEIGHT),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let mut state = self.state();
+        let frame_rate = state.frame_rate(Instant::now());
+
+        let (connection, color) = if session.sender().is_closed() {
+            ("● Disconnected", Color::Red)
+        } else if session.is_locked() {
+            ("● Screen locked", Color::Yellow)
+        } else if session.is_paused() {
+            ("● Paused", Color::Yellow)
+        } else {
+            ("● Connected", Color::Green)
+        };
+        let tokens = |count: Option<i32>| count.map_or_else(|| "-".to_string(), |n| n.to_string());
+        let status = Line::from(vec![
+            Span::styled(connection, Style::new().fg(color)),
+            Span::raw(format!(
+                "   {:.2} fps   {} frames   {} answers   tokens: last {} in / {} out, {} total",
+                frame_rate,
+                state.frames_sent,
+                state.answers,
+                tokens(state.last_prompt_tokens),
+                tokens(state.last_response_tokens),
+                state.total_tokens
+            )),
+        ]);
+        frame.render_widget(
+            Paragraph::new(status).block(Block::new().borders(Borders::ALL).title(" watcher ")),
+            header,
+        );
+
+        let (title, text) = if state.streaming.trim().is_empty() {
+            (" Last response ", state.last_response.as_str())
+        } else {
+            (" Responding… ", state.streaming.as_str())
+        };
+        frame.render_widget(
+            Paragraph::new(text.trim())
+                .wrap(Wrap { trim: true })
+                .block(Block::new().borders(Borders::ALL).title(title)),
+            response,
+        );
+
+        let rows = timeline.height.saturating_sub(2) as usize;
+        let end = state.timeline.len().saturating_sub(scroll);
+        let items: Vec<ListItem> = state
+            .timeline
+            .range(end.saturating_sub(rows)..end)
+            .map(|entry| {
+                let style = if entry.answer {
+                    Style::new().add_modifier(Modifier::BOLD)
+                } else {
+                    Style::new().fg(Color::DarkGray)
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{} ", clock_time(entry.at)),
+                        Style::new().fg(Color::Cyan),
+                    ),
+                    Span::styled(entry.text.as_str(), style),
+                ]))
+            })
+            .collect();
+        let title = if scroll > 0 {
+            format!(" Timeline (scrolled back {}) ", scroll)
+        } else {
+            " Timeline ".to_string()
+        };
+        frame.render_widget(
+            List::new(items).block(Block::new().borders(Borders::ALL).title(title)),
+            timeline,
+        );
+
+        frame.render_widget(
+            Paragraph::new("q quit   ↑/↓ PgUp/PgDn scroll   End follow")
+                .style(Style::new().fg(Color::DarkGray)),
+            footer,
+        );
+    }
+}
+
+impl ResponsePrinter for Dashboard {
+    fn print_response(&self, content: &Content) {
+        let mut state = self.state();
+        for part in &content.parts {
+            // Inline audio and images are left to the printers wrapping this one
+            if let Part::Text { text } = part {
+                state.streaming.push_str(text);
+            }
+        }
+    }
+
+    fn print_status(&self, message: &str) {
+        self.state().push(SystemTime::now(), false, message);
+    }
+
+    fn print_error(&self, message: &str) {
+        self.state().push(SystemTime::now(), false, message);
+    }
+
+    fn print_turn_complete(&self) {
+        let mut state = self.state();
+        if !state.streaming.trim().is_empty() {
+            state.last_response = std::mem::take(&mut state.streaming);
+        }
+    }
+
+    fn frame_sent(&self, _frame_seq: usize) {
+        let mut state = self.state();
+        state.frames.push_back(Instant::now());
+        state.frames_sent += 1;
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::task::JoinHandle;
+use watcher_core::{AudioSource, CaptureSession, Hotkey, ResponsePrinter};
+
+/// Hotkey used when `--voice` is given without keys
+pub const DEFAULT_VOICE_HOTKEY: &str = "ctrl+option+v";
+
+/// Sent with the screenshot taken when the user starts asking
+const VOICE_PROMPT: &str = "The user is about to ask a question about this screenshot out \
+                            loud. Answer it briefly once they have asked, in speech.";
+
+/// Asks questions out loud: the first press of the hotkey shows the model the screen and
+/// opens the microphone, the next press, or `limit`, closes it and the spoken answer plays
+///
+/// The regular capture loop is paused while the user speaks, so a scheduled screenshot
+/// doesn't cut the question short. The microphone is only open while listening.
+pub struct VoiceQuestions {
+    pub session: Arc<CaptureSession>,
+    pub printer: Arc<dyn ResponsePrinter>,
+    pub hotkey: Hotkey,
+    /// Longest a question may run before the microphone closes by itself
+    pub limit: Duration,
+}
+
+impl VoiceQuestions {
+    pub fn spawn(self, mut presses: UnboundedReceiver<()>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            while presses.recv().await.is_some() {
+                if self.session.is_paused() {
+                    self.printer
+                        .print_status("⏸️ Capture is paused, not listening for a question");
+                    continue;
+                }
+                match self.session.show_screen(VOICE_PROMPT).await {
+                    Ok(true) => {}
+                    // E.g. locked or an excluded app is on screen
+                    Ok(false) => {
+                        self.printer
+                            .print_status("🙈 The screen can't be shown, not listening");
+                        continue;
+                    }
+                    Err(e) => {
+                        self.printer
+                            .print_error(&format!("❌ Error sending the screen: {}", e));
+                        continue;
+                    }
+                }
+                let source = match AudioSource::from_default_input() {
+                    Ok(source) => source,
+                    Err(e) => {
+                        self.printer
+                            .print_error(&format!("❌ Microphone error: {}", e));
+                        continue;
+                    }
+                };
+                self.session.pause();
+                let paused = self.session.pause_requests();
+                self.listen(source, &mut presses).await;
+                // Stays paused if it was paused meanwhile, e.g. over the control socket
+                if self.session.pause_requests() == paused {
+                    self.session.resume();
+                }
+            }
+        })
+    }
+
+    /// Streams the microphone until the hotkey is pressed again or `limit` passes
+    async fn listen(&self, mut source: AudioSource, presses: &mut UnboundedReceiver<()>) {
+        self.printer.print_status(&format!(
+            "🎙️ Listening, press {} again when done",
+            self.hotkey
+        ));
+        let limit = tokio::time::sleep(self.limit);
+        tokio::pin!(limit);
+        loop {
+            tokio::select! {
+                chunk = source.next_chunk() => {
+                    let Some(chunk) = chunk else { break };
+                    if let Err(e) = self.session.sender().send_audio(&chunk).await {
+                        self.printer.print_error(&format!("❌ Error sending audio to Gemini: {}", e));
+                        return;
+                    }
+                }
+                _ = presses.recv() => break,
+                _ = &mut limit => break,
+            }
+        }
+        drop(source);
+        self.printer.print_status("💬 Asking...");
+        if let Err(e) = self.session.end_spoken_question().await {
+            self.printer
+                .print_error(&format!("❌ Error sending audio to Gemini: {}", e));
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Times the vectorized BGRA to RGB conversion against a plain per-pixel loop
+//!
+//! `cargo bench -p core --bench pixel`; under `cargo test` each side runs once.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use watcher_core::bgra_to_rgb;
+
+/// A 16:10 Retina display
+const WIDTH: usize = 2560;
+const HEIGHT: usize = 1600;
+
+const BENCH_ITERATIONS: u32 = 50;
+
+fn scalar_bgra_to_rgb(bgra: &[u8]) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(bgra.len() / 4 * 3);
+    for pixel in bgra.chunks_exact(4) {
+        rgb.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+    }
+    rgb
+}
+
+/// Average time of one conversion
+fn measure(iterations: u32, frame: &[u8], convert: impl Fn(&[u8]) -> Vec<u8>) -> Duration {
+    let started = Instant::now();
+    for _ in 0..iterations {
+        black_box(convert(black_box(frame)));
+    }
+    started.elapsed() / iterations
+}
+
+fn main() {
+    let iterations = if std::env::args().any(|arg| arg == "--bench") {
+        BENCH_ITERATIONS
+    } else {
+        1
+    };
+    let frame: Vec<u8> = (0..WIDTH * HEIGHT * 4).map(|i| (i % 251) as u8).collect();
+    assert_eq!(bgra_to_rgb(&frame), scalar_bgra_to_rgb(&frame));
+
+    let scalar = measure(iterations, &frame, scalar_bgra_to_rgb);
+    let vectorized = measure(iterations, &frame, bgra_to_rgb);
+    println!(
+        "bgra_to_rgb {}x{}: scalar {:.2} ms, vectorized {:.2} ms ({:.1}x)",
+        WIDTH,
+        HEIGHT,
+        scalar.as_secs_f64() * 1000.0,
+        vectorized.as_secs_f64() * 1000.0,
+        scalar.as_secs_f64() / vectorized.as_secs_f64().max(f64::EPSILON)
+    );
+}
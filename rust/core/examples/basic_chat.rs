@@ -0,0 +1,41 @@
+//! Text chat over a live session.
+//!
+//! `cargo run -p core --example basic_chat [-- --dry-run]`
+mod common;
+
+use common::ExampleResult;
+use std::time::Duration;
+use watcher_core::{ClientContent, Content};
+
+const PROMPTS: [&str; 3] = [
+    "Hello, Gemini!",
+    "Share three fun facts about the Rust programming language.",
+    "Thanks for the info!",
+];
+
+#[tokio::main]
+async fn main() -> ExampleResult {
+    let setup = common::text_setup("You are a Rust sample app demonstrating the Gemini Live API.")?;
+
+    if common::dry_run() {
+        common::preview_setup(&setup)?;
+        for prompt in PROMPTS {
+            common::preview_turn(&ClientContent {
+                turns: vec![Content::text("user", prompt)],
+                turn_complete: Some(true),
+            });
+        }
+        return Ok(());
+    }
+
+    let sender = common::connect(setup, Vec::new()).await?;
+    for prompt in PROMPTS {
+        println!("you > {}", prompt);
+        sender.send_text_turn("user", prompt, true).await?;
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    sender.close().await.ok();
+    Ok(())
+}
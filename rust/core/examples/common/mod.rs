@@ -0,0 +1,187 @@
+//! Shared setup for the examples: credentials, session setup and the synthetic inputs used
+//! by `--dry-run`, which never connects to Gemini or touches the screen or microphone
+#![allow(dead_code)]
+
+use base64::Engine;
+use serde_json::json;
+use std::error::Error;
+use std::sync::Arc;
+use watcher_core::{
+    encode_bgra_to_jpeg_bytes, ensure_clean_directory, ensure_screen_recording_permission,
+    f32_to_pcm16, CaptureOptions, CaptureSession, CaptureTarget, CliResponsePrinter, ClientContent,
+    ConnectionOptions, Content, FrameData, FrameSource, GeminiSender, GeminiSession,
+    GenerationConfig, OutputProcessor, Part, SessionOptions, Setup, ToolHandler,
+};
+
+pub type ExampleResult<T = ()> = std::result::Result<T, Box<dyn Error>>;
+
+pub const MODEL: &str = "models/gemini-live-2.5-flash-preview";
+
+/// Sample rate of the PCM audio the live API expects
+pub const PCM_SAMPLE_RATE: u32 = 16_000;
+
+/// Returns true when the example was started with `--dry-run`
+pub fn dry_run() -> bool {
+    std::env::args().any(|arg| arg == "--dry-run")
+}
+
+/// First positional argument, ignoring flags
+pub fn positional_arg() -> Option<String> {
+    std::env::args().skip(1).find(|arg| !arg.starts_with("--"))
+}
+
+/// Connection options authenticated with GOOGLE_API_KEY
+pub fn connection_options() -> ExampleResult<ConnectionOptions> {
+    let api_key = std::env::var("GOOGLE_API_KEY")
+        .map_err(|_| "GOOGLE_API_KEY must be set (or run the example with --dry-run)")?;
+    Ok(ConnectionOptions::builder().api_key(api_key).build()?)
+}
+
+/// Text-only session setup with the given system instruction
+pub fn text_setup(instruction: &str) -> ExampleResult<Setup> {
+    Ok(Setup::builder(MODEL)
+        .system_instruction(Content::system(instruction))
+        .generation_config(GenerationConfig {
+            response_modalities: vec!["TEXT".to_string()],
+            ..Default::default()
+        })
+        .build()?)
+}
+
+/// Connects and prints every model answer; tool calls are answered by `tools`
+pub async fn connect(
+    setup: Setup,
+    tools: Vec<Arc<dyn ToolHandler>>,
+) -> ExampleResult<GeminiSender> {
+    let session = GeminiSession::connect(setup, connection_options()?).await?;
+    let sender = session.sender_handle();
+    OutputProcessor::new(Arc::new(CliResponsePrinter::new()))
+        .with_tool_handlers(tools)
+        .spawn(session);
+    Ok(sender)
+}
+
+/// Prints the setup message a dry run would have opened the session with
+pub fn preview_setup(setup: &Setup) -> ExampleResult {
+    println!("🧪 Dry run: no connection is made");
+    println!("setup > {}", serde_json::to_string_pretty(setup)?);
+    Ok(())
+}
+
+/// Prints a turn instead of sending it, with inline images reduced to their size
+pub fn preview_turn(content: &ClientContent) {
+    for turn in &content.turns {
+        for part in &turn.parts {
+            match (part, part.inline_data()) {
+                (_, Some(blob)) => println!(
+                    "you > [{}, {} bytes]",
+                    blob.mime_type
+                        .as_deref()
+                        .unwrap_or("application/octet-stream"),
+                    blob.decode().map_or(0, |bytes| bytes.len())
+                ),
+                (Part::Text { text }, None) => println!("you > {}", text),
+                (Part::Json(value), None) => println!("you > {}", value),
+            }
+        }
+    }
+}
+
+/// User turn carrying one JPEG screenshot and its question
+pub fn image_turn(jpeg_bytes: &[u8], prompt: &str) -> ClientContent {
+    let data = base64::engine::general_purpose::STANDARD.encode(jpeg_bytes);
+    ClientContent {
+        turns: vec![Content {
+            role: Some("user".to_string()),
+            parts: vec![
+                Part::json(json!({
+                    "inline_data": { "mime_type": "image/jpeg", "data": data }
+                })),
+                Part::text(prompt),
+            ],
+        }],
+        turn_complete: Some(true),
+    }
+}
+
+/// Captures `count` frames of `target` at 1 FPS and asks Gemini to describe each one;
+/// a dry run describes synthetic frames locally instead
+pub async fn watch(target: CaptureTarget, count: u32, output_dir: &str) -> ExampleResult {
+    let setup = text_setup(
+        "You are analyzing screenshots of a user's computer screen. \
+         For each screenshot, describe what the user is doing in one sentence.",
+    )?;
+
+    if dry_run() {
+        preview_setup(&setup)?;
+        for index in 1..=count {
+            let frame = synthetic_frame(index, 640, 360);
+            let jpeg = encode_bgra_to_jpeg_bytes(&frame.data, frame.width, frame.height, 90)?;
+            preview_turn(&image_turn(
+                &jpeg,
+                "What is the user doing in this screenshot?",
+            ));
+        }
+        return Ok(());
+    }
+
+    ensure_screen_recording_permission()?;
+    ensure_clean_directory(output_dir)?;
+    let options = CaptureOptions::builder()
+        .show_highlight(!target.is_window())
+        .target(target)
+        .build()?;
+    let frame_source = FrameSource::from_options(options)?;
+    let sender = connect(setup, Vec::new()).await?;
+    let session = CaptureSession::new(
+        frame_source,
+        sender.clone(),
+        Arc::new(CliResponsePrinter::new()),
+        SessionOptions::builder().output_dir(output_dir).build()?,
+    );
+    session.capture_frames(count as usize).await?;
+    session.stop();
+
+    // Leave time for the last answers to arrive
+    tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+    sender.close().await.ok();
+    Ok(())
+}
+
+/// BGRA test pattern standing in for a screenshot: a gradient background with a square
+/// that moves a little on every frame
+pub fn synthetic_frame(index: u32, width: u32, height: u32) -> FrameData {
+    let square = height / 4;
+    let left = (index * square / 2) % width.saturating_sub(square).max(1);
+    let top = (height - square) / 2;
+    let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let inside = (left..left + square).contains(&x) && (top..top + square).contains(&y);
+            let pixel = if inside {
+                [40, 40, 220, 255]
+            } else {
+                [(x * 255 / width) as u8, (y * 255 / height) as u8, 128, 255]
+            };
+            data.extend_from_slice(&pixel);
+        }
+    }
+    FrameData {
+        width,
+        height,
+        data,
+        display_id: None,
+        cursor: None,
+        active_window: None,
+    }
+}
+
+/// Little-endian PCM of a sine tone, standing in for microphone input
+pub fn synthetic_tone(frequency: f32, seconds: f32) -> Vec<u8> {
+    let rate = PCM_SAMPLE_RATE as f32;
+    let samples = (rate * seconds) as usize;
+    f32_to_pcm16(
+        (0..samples)
+            .map(|n| (2.0 * std::f32::consts::PI * frequency * n as f32 / rate).sin() * 0.25),
+    )
+}
@@ -0,0 +1,75 @@
+//! Function calling with locally implemented tools: the model can ask for the current time
+//! and look up its own earlier answers.
+//!
+//! `cargo run -p core --example local_tools [-- --dry-run]`
+mod common;
+
+use common::ExampleResult;
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use watcher_core::{
+    dispatch_tool_calls, tool_declarations, AnswerHistory, CliResponsePrinter, FunctionCall,
+    ToolHandler, RECALL_TOOL_NAME,
+};
+
+const CLOCK_TOOL_NAME: &str = "current_unix_time";
+
+/// Reports the local clock
+struct Clock;
+
+impl ToolHandler for Clock {
+    fn declarations(&self) -> Vec<Value> {
+        vec![json!({
+            "name": CLOCK_TOOL_NAME,
+            "description": "Returns the current time as seconds since the Unix epoch.",
+        })]
+    }
+
+    fn handle<'a>(&'a self, call: &'a FunctionCall) -> BoxFuture<'a, Option<Value>> {
+        Box::pin(async move {
+            if call.name != CLOCK_TOOL_NAME {
+                return None;
+            }
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            Some(json!({ "seconds": now.as_secs() }))
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExampleResult {
+    let history = Arc::new(AnswerHistory::new(Arc::new(CliResponsePrinter::new()), 10));
+    let tools: Vec<Arc<dyn ToolHandler>> = vec![Arc::new(Clock), history];
+    let mut setup = common::text_setup("Use the available tools whenever they help.")?;
+    setup.tools = Some(tool_declarations(&tools));
+
+    if common::dry_run() {
+        common::preview_setup(&setup)?;
+        // Answer the calls a model would make, without a model
+        let calls: Vec<FunctionCall> = [CLOCK_TOOL_NAME, RECALL_TOOL_NAME, "unknown_tool"]
+            .iter()
+            .enumerate()
+            .map(|(index, name)| FunctionCall {
+                id: format!("call-{}", index + 1),
+                name: name.to_string(),
+                args: None,
+            })
+            .collect();
+        let response = dispatch_tool_calls(&tools, &calls).await;
+        println!("tools > {}", serde_json::to_string_pretty(&response)?);
+        return Ok(());
+    }
+
+    let sender = common::connect(setup, tools).await?;
+    let prompt = "What time is it? Then tell me what you answered before this.";
+    println!("you > {}", prompt);
+    sender.send_text_turn("user", prompt, true).await?;
+
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    sender.close().await.ok();
+    Ok(())
+}
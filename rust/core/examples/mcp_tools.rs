@@ -0,0 +1,74 @@
+//! The tools of MCP servers offered to the model: servers from an `mcpServers` config file,
+//! or the Chrome DevTools server when none is given, are launched and their tools listed.
+//!
+//! `cargo run -p core --features mcp --example mcp_tools [-- --dry-run] [CONFIG]`
+mod common;
+
+use common::ExampleResult;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use watcher_core::{
+    dispatch_tool_calls, tool_declarations, FunctionCall, McpBridge, McpConfig, ToolHandler,
+};
+
+/// Launched when no config file is given
+const DEFAULT_SERVER: &str = "npx chrome-devtools-mcp@latest";
+
+#[tokio::main]
+async fn main() -> ExampleResult {
+    let mut bridge = McpBridge::new();
+    match common::positional_arg() {
+        Some(path) => {
+            for (name, server) in McpConfig::load(Path::new(&path))?.mcp_servers {
+                let count = bridge.launch(name.as_str(), server).await?;
+                println!("🧰 {} offers {} tools", name, count);
+            }
+        }
+        None => {
+            let count = bridge.spawn_server(DEFAULT_SERVER).await?;
+            println!("🧰 {} offers {} tools", DEFAULT_SERVER, count);
+        }
+    }
+    let declarations = bridge.declarations();
+    for declaration in &declarations {
+        println!(
+            "- {} — {}",
+            declaration["name"].as_str().unwrap_or_default(),
+            declaration["description"].as_str().unwrap_or_default()
+        );
+    }
+    let tools: Vec<Arc<dyn ToolHandler>> = vec![Arc::new(bridge)];
+    let mut setup = common::text_setup("Use the available tools whenever they help.")?;
+    setup.tools = Some(tool_declarations(&tools));
+
+    if common::dry_run() {
+        common::preview_setup(&setup)?;
+        // Call the first tool that takes no arguments, as a model might
+        let Some(name) = declarations
+            .iter()
+            .find(|declaration| declaration.get("parameters").is_none())
+            .and_then(|declaration| declaration["name"].as_str())
+        else {
+            return Ok(());
+        };
+        let call = FunctionCall {
+            id: "call-1".to_string(),
+            name: name.to_string(),
+            args: Some(Value::Object(Default::default())),
+        };
+        let response = dispatch_tool_calls(&tools, &[call]).await;
+        println!("tools > {}", serde_json::to_string_pretty(&response)?);
+        return Ok(());
+    }
+
+    let sender = common::connect(setup, tools).await?;
+    let prompt = "Use your tools to tell me what is open right now.";
+    println!("you > {}", prompt);
+    sender.send_text_turn("user", prompt, true).await?;
+
+    tokio::time::sleep(Duration::from_secs(20)).await;
+    sender.close().await.ok();
+    Ok(())
+}
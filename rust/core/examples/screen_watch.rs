@@ -0,0 +1,12 @@
+//! Describes what happens on the main display, one screenshot per second.
+//!
+//! `cargo run -p core --example screen_watch [-- --dry-run]`
+mod common;
+
+use common::ExampleResult;
+use watcher_core::CaptureTarget;
+
+#[tokio::main]
+async fn main() -> ExampleResult {
+    common::watch(CaptureTarget::Display, 5, "output/screen_watch").await
+}
@@ -0,0 +1,54 @@
+//! Ask a question out loud and get a text answer: the default microphone is streamed to the
+//! session for a few seconds.
+//!
+//! `cargo run -p core --features audio --example voice_ask [-- --dry-run]`
+mod common;
+
+use common::ExampleResult;
+use std::time::Duration;
+use watcher_core::{AudioSource, Blob, REALTIME_AUDIO_MIME_TYPE};
+
+const LISTEN_SECONDS: u64 = 8;
+
+#[tokio::main]
+async fn main() -> ExampleResult {
+    let setup = common::text_setup(
+        "The user asks questions by voice. Answer each question briefly in text.",
+    )?;
+
+    if common::dry_run() {
+        common::preview_setup(&setup)?;
+        // The microphone forwarder sends 100 ms chunks
+        let chunks: Vec<Blob> = (0..10 * LISTEN_SECONDS)
+            .map(|_| {
+                Blob::from_bytes(&common::synthetic_tone(440.0, 0.1))
+                    .with_mime_type(REALTIME_AUDIO_MIME_TYPE)
+            })
+            .collect();
+        let bytes: usize = chunks
+            .iter()
+            .filter_map(Blob::decode)
+            .map(|pcm| pcm.len())
+            .sum();
+        println!(
+            "you > [{}, {} bytes in {} chunks]",
+            REALTIME_AUDIO_MIME_TYPE,
+            bytes,
+            chunks.len()
+        );
+        return Ok(());
+    }
+
+    let sender = common::connect(setup, Vec::new()).await?;
+    let forwarder = AudioSource::from_default_input()?.spawn_forwarder(sender.clone());
+    println!(
+        "🎙️ Listening for {} seconds, ask your question...",
+        LISTEN_SECONDS
+    );
+    tokio::time::sleep(Duration::from_secs(LISTEN_SECONDS)).await;
+    forwarder.abort();
+
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    sender.close().await.ok();
+    Ok(())
+}
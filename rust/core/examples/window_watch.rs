@@ -0,0 +1,28 @@
+//! Describes a single window, picked by a case-insensitive title match.
+//!
+//! `cargo run -p core --example window_watch -- <TITLE> [--dry-run]`
+mod common;
+
+use common::ExampleResult;
+use watcher_core::{list_windows, CaptureTarget};
+
+#[tokio::main]
+async fn main() -> ExampleResult {
+    let Some(title) = common::positional_arg() else {
+        println!("Usage: window_watch <TITLE> [--dry-run]\n\nOn-screen windows:");
+        for window in list_windows()
+            .into_iter()
+            .filter(|window| window.is_normal())
+        {
+            println!(
+                "  {:>6}  {}  {}",
+                window.id,
+                window.app_name,
+                window.title.as_deref().unwrap_or("")
+            );
+        }
+        return Ok(());
+    };
+
+    common::watch(CaptureTarget::WindowTitle(title), 5, "output/window_watch").await
+}
@@ -0,0 +1,156 @@
+use crate::{date_time, ActivityEntry};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Fields of every exported entry, in the order of the CSV columns; later versions only add
+/// columns at the end
+pub const EXPORT_COLUMNS: [&str; 13] = [
+    "session",
+    "index",
+    "timestamp_ms",
+    "time",
+    "app",
+    "window_title",
+    "category",
+    "summary",
+    "prompt_tokens",
+    "response_tokens",
+    "total_tokens",
+    "frame_path",
+    "calendar_event",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// One JSON object per line
+    #[default]
+    Jsonl,
+    /// RFC 4180 CSV with a header row
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "jsonl" | "ndjson" => Ok(ExportFormat::Jsonl),
+            "csv" => Ok(ExportFormat::Csv),
+            other => Err(format!(
+                "unknown export format '{}' (expected jsonl or csv)",
+                other
+            )),
+        }
+    }
+}
+
+/// An entry as exported, with `time` as local date and time for people and spreadsheets
+#[derive(Serialize)]
+struct ExportRecord<'a> {
+    session: &'a str,
+    index: Option<usize>,
+    timestamp_ms: u64,
+    time: String,
+    app: Option<&'a str>,
+    window_title: Option<&'a str>,
+    category: Option<&'a str>,
+    summary: &'a str,
+    prompt_tokens: Option<i32>,
+    response_tokens: Option<i32>,
+    total_tokens: Option<i32>,
+    frame_path: Option<&'a str>,
+    calendar_event: Option<&'a str>,
+}
+
+impl<'a> ExportRecord<'a> {
+    fn new(entry: &'a ActivityEntry) -> Self {
+        Self {
+            session: &entry.session,
+            index: entry.index,
+            timestamp_ms: entry.timestamp_ms,
+            time: date_time(UNIX_EPOCH + Duration::from_millis(entry.timestamp_ms)),
+            app: entry.app.as_deref(),
+            window_title: entry.window_title.as_deref(),
+            category: entry.category.as_deref(),
+            summary: &entry.summary,
+            prompt_tokens: entry.prompt_tokens,
+            response_tokens: entry.response_tokens,
+            total_tokens: entry.total_tokens,
+            frame_path: entry.frame_path.as_deref(),
+            calendar_event: entry.calendar_event.as_deref(),
+        }
+    }
+
+    /// Values in the order of `EXPORT_COLUMNS`; missing ones are empty
+    fn csv_row(&self) -> [String; EXPORT_COLUMNS.len()] {
+        let text = |value: Option<&str>| value.unwrap_or_default().to_string();
+        let number = |value: Option<i32>| value.map(|n| n.to_string()).unwrap_or_default();
+        [
+            self.session.to_string(),
+            self.index.map(|n| n.to_string()).unwrap_or_default(),
+            self.timestamp_ms.to_string(),
+            self.time.clone(),
+            text(self.app),
+            text(self.window_title),
+            text(self.category),
+            self.summary.to_string(),
+            number(self.prompt_tokens),
+            number(self.response_tokens),
+            number(self.total_tokens),
+            text(self.frame_path),
+            text(self.calendar_event),
+        ]
+    }
+}
+
+/// Writes `entries` to `out` in `format`
+pub fn export_activity<W: Write>(
+    entries: &[ActivityEntry],
+    format: ExportFormat,
+    mut out: W,
+) -> io::Result<()> {
+    match format {
+        ExportFormat::Jsonl => {
+            for entry in entries {
+                serde_json::to_writer(&mut out, &ExportRecord::new(entry))?;
+                out.write_all(b"\n")?;
+            }
+        }
+        ExportFormat::Csv => {
+            write_csv_row(&mut out, EXPORT_COLUMNS.iter().copied())?;
+            for entry in entries {
+                let row = ExportRecord::new(entry).csv_row();
+                write_csv_row(&mut out, row.iter().map(String::as_str))?;
+            }
+        }
+    }
+    out.flush()
+}
+
+fn write_csv_row<'a, W: Write>(
+    out: &mut W,
+    fields: impl Iterator<Item = &'a str>,
+) -> io::Result<()> {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.write_all(b",")?;
+        }
+        // Spreadsheets run fields starting like a formula, and screen text can start with
+        // anything
+        let field = if field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+            Cow::Owned(format!("'{}", field))
+        } else {
+            Cow::Borrowed(field)
+        };
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(out, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            out.write_all(field.as_bytes())?;
+        }
+    }
+    // RFC 4180 ends records with CRLF
+    out.write_all(b"\r\n")
+}
@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ActivityMeterError {
+    #[error("Platform not supported")]
+    PlatformNotSupported,
+    #[error("Input Monitoring permission not granted")]
+    PermissionDenied,
+}
+
+pub type ActivityMeterResult<T> = std::result::Result<T, ActivityMeterError>;
+
+/// How much the user typed and clicked over an interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityCounts {
+    pub keypresses: u64,
+    pub clicks: u64,
+    pub interval_ms: u64,
+}
+
+impl ActivityCounts {
+    /// Prompt sentence about the input since the previous screenshot
+    pub fn describe(&self) -> String {
+        match (self.keypresses, self.clicks) {
+            (0, 0) => "Since the previous screenshot the user hasn't typed or clicked.".to_string(),
+            (keypresses, clicks) => format!(
+                "Since the previous screenshot ({}s) the user pressed {} keys and clicked {} times.",
+                self.interval_ms / 1000,
+                keypresses,
+                clicks
+            ),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    keypresses: AtomicU64,
+    clicks: AtomicU64,
+}
+
+/// Counts keypresses and mouse clicks system-wide through a listen-only event tap on a
+/// background thread, so typing can be told from reading
+///
+/// Only the counts are kept, never which keys were pressed or where the user clicked.
+pub struct ActivityMeter {
+    counters: Arc<Counters>,
+    since: parking_lot::Mutex<Instant>,
+    stop: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl ActivityMeter {
+    pub fn start() -> ActivityMeterResult<Self> {
+        let counters = Arc::new(Counters::default());
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = platform::spawn(Arc::clone(&counters), Arc::clone(&stop))?;
+        Ok(Self {
+            counters,
+            since: parking_lot::Mutex::new(Instant::now()),
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Counts since the previous call, or since the meter started, and starts over
+    ///
+    /// Frames skipped before taking leave their counts to the next frame; hand counts of a
+    /// frame that then isn't sent back with `restore`.
+    pub fn take(&self) -> ActivityCounts {
+        let interval = std::mem::replace(&mut *self.since.lock(), Instant::now()).elapsed();
+        ActivityCounts {
+            keypresses: self.counters.keypresses.swap(0, Ordering::Relaxed),
+            clicks: self.counters.clicks.swap(0, Ordering::Relaxed),
+            interval_ms: u64::try_from(interval.as_millis()).unwrap_or(u64::MAX),
+        }
+    }
+
+    /// Adds `counts` taken for a frame that was never sent back onto the next frame
+    pub fn restore(&self, counts: ActivityCounts) {
+        self.counters
+            .keypresses
+            .fetch_add(counts.keypresses, Ordering::Relaxed);
+        self.counters
+            .clicks
+            .fetch_add(counts.clicks, Ordering::Relaxed);
+        let mut since = self.since.lock();
+        if let Some(earlier) = since.checked_sub(Duration::from_millis(counts.interval_ms)) {
+            *since = earlier;
+        }
+    }
+}
+
+impl Drop for ActivityMeter {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{ActivityMeterError, ActivityMeterResult, Counters};
+    use core_foundation::runloop::{kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop};
+    use core_graphics::event::{
+        CGEventTap, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement, CGEventType,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    pub(super) fn spawn(
+        counters: Arc<Counters>,
+        stop: Arc<AtomicBool>,
+    ) -> ActivityMeterResult<std::thread::JoinHandle<()>> {
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            // Set when macOS turns the tap off, e.g. after a slow callback
+            let disabled = Arc::new(AtomicBool::new(false));
+            let tap_disabled = Arc::clone(&disabled);
+            let tap = CGEventTap::new(
+                CGEventTapLocation::Session,
+                CGEventTapPlacement::TailAppendEventTap,
+                CGEventTapOptions::ListenOnly,
+                vec![
+                    CGEventType::KeyDown,
+                    CGEventType::LeftMouseDown,
+                    CGEventType::RightMouseDown,
+                    CGEventType::OtherMouseDown,
+                ],
+                move |_, event_type, _| {
+                    // The event itself is never looked at
+                    let counter = match event_type {
+                        CGEventType::KeyDown => &counters.keypresses,
+                        CGEventType::TapDisabledByTimeout | CGEventType::TapDisabledByUserInput => {
+                            tap_disabled.store(true, Ordering::Relaxed);
+                            return None;
+                        }
+                        _ => &counters.clicks,
+                    };
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    None
+                },
+            );
+            // Creating the tap fails without Input Monitoring permission
+            let Some((tap, source)) = tap.ok().and_then(|tap| {
+                let source = tap.mach_port.create_runloop_source(0).ok()?;
+                Some((tap, source))
+            }) else {
+                let _ = ready_tx.send(false);
+                return;
+            };
+            CFRunLoop::get_current().add_source(&source, unsafe { kCFRunLoopCommonModes });
+            tap.enable();
+            let _ = ready_tx.send(true);
+
+            while !stop.load(Ordering::Relaxed) {
+                CFRunLoop::run_in_mode(
+                    unsafe { kCFRunLoopDefaultMode },
+                    Duration::from_millis(500),
+                    false,
+                );
+                if disabled.swap(false, Ordering::Relaxed) {
+                    tap.enable();
+                }
+            }
+        });
+
+        if ready_rx.recv() == Ok(true) {
+            Ok(thread)
+        } else {
+            let _ = thread.join();
+            Err(ActivityMeterError::PermissionDenied)
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::{ActivityMeterError, ActivityMeterResult, Counters};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    pub(super) fn spawn(
+        _counters: Arc<Counters>,
+        _stop: Arc<AtomicBool>,
+    ) -> ActivityMeterResult<std::thread::JoinHandle<()>> {
+        Err(ActivityMeterError::PlatformNotSupported)
+    }
+}
@@ -0,0 +1,480 @@
+use crate::gemini::{ConnectionOptions, GeminiError, GeminiSession, ServerEvent, Setup};
+use crate::{
+    clock_time, date_time, local_midnight, local_time, unix_millis, ActivityEntry, Content,
+    GenerationConfig, Part,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Longest time an answer is taken to cover; a longer gap before the next one is a break
+const MAX_ENTRY_SPAN: Duration = Duration::from_secs(5 * 60);
+
+/// Time in one app without a break that counts as a notable stretch
+const LONG_STRETCH: Duration = Duration::from_secs(30 * 60);
+
+/// Rows of the app and category tables
+const TOP_ROWS: usize = 15;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// System instruction of the session writing the narrative of a report
+pub const NARRATIVE_INSTRUCTION: &str = "You write short, friendly summaries of how someone \
+     spent their time on the computer. You get the totals of an activity log as JSON: time \
+     per app and per category in minutes, and notable events. Write two or three paragraphs \
+     of plain prose about what they mostly worked on, how focused they were and anything that \
+     stands out. Only use the numbers given; don't invent activities. No headings or lists.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    /// The local calendar day
+    #[default]
+    Day,
+    /// Monday to Sunday in local time
+    Week,
+}
+
+impl FromStr for ReportPeriod {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "day" | "daily" => Ok(ReportPeriod::Day),
+            "week" | "weekly" => Ok(ReportPeriod::Week),
+            other => Err(format!(
+                "unknown report period '{}' (expected day or week)",
+                other
+            )),
+        }
+    }
+}
+
+impl ReportPeriod {
+    /// Start and end of the day or week `time` falls in
+    pub fn range(self, time: SystemTime) -> (SystemTime, SystemTime) {
+        let start = local_midnight(time);
+        let (start, days) = match self {
+            ReportPeriod::Day => (start, 1),
+            ReportPeriod::Week => {
+                let weekday = local_time(start).map_or(1, |local| local.tm_wday);
+                // tm_wday counts from Sunday
+                let since_monday = (weekday + 6) % 7;
+                (add_days(start, -since_monday), 7)
+            }
+        };
+        (start, add_days(start, days))
+    }
+}
+
+/// Local midnight `days` days after `midnight`, whatever daylight saving time does in between
+fn add_days(midnight: SystemTime, days: i32) -> SystemTime {
+    // Noon of the day never lands on the wrong date when a day is 23 or 25 hours long
+    let noon = midnight + DAY / 2;
+    let shifted = if days < 0 {
+        noon - DAY * days.unsigned_abs()
+    } else {
+        noon + DAY * days as u32
+    };
+    local_midnight(shifted)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    /// A standalone page
+    Html,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            other => Err(format!(
+                "unknown report format '{}' (expected markdown or html)",
+                other
+            )),
+        }
+    }
+}
+
+/// Minutes spent in one app or category
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimeShare {
+    pub name: String,
+    pub minutes: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotableEvent {
+    pub timestamp_ms: u64,
+    /// Local date and time
+    pub time: String,
+    pub text: String,
+}
+
+/// Daily or weekly roll-up of the activity log: time per app and category, sessions and long
+/// stretches in one app, and optionally a narrative Gemini writes from them
+///
+/// Every answer is taken to cover the time until the next answer of its session, up to five
+/// minutes; longer gaps count as breaks.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityReport {
+    pub period: ReportPeriod,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    pub entries: usize,
+    pub sessions: usize,
+    pub tracked_minutes: f64,
+    pub total_tokens: i64,
+    /// Most time first
+    pub apps: Vec<TimeShare>,
+    /// Most time first; only structured answers have a category
+    pub categories: Vec<TimeShare>,
+    /// Oldest first
+    pub events: Vec<NotableEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub narrative: Option<String>,
+}
+
+impl ActivityReport {
+    /// Rolls up `entries`, oldest first, of the period starting at `from`
+    pub fn build(entries: &[ActivityEntry], period: ReportPeriod, from: SystemTime) -> Self {
+        let (from, to) = period.range(from);
+        let mut by_session: BTreeMap<&str, Vec<&ActivityEntry>> = BTreeMap::new();
+        for entry in entries {
+            by_session.entry(&entry.session).or_default().push(entry);
+        }
+
+        let mut apps: BTreeMap<&str, Duration> = BTreeMap::new();
+        let mut categories: BTreeMap<&str, Duration> = BTreeMap::new();
+        let mut tracked = Duration::ZERO;
+        let mut events = Vec::new();
+        for (session, entries) in &by_session {
+            let spans = spans(entries);
+            let first = entries[0].timestamp_ms;
+            let last = entries[entries.len() - 1].timestamp_ms;
+            events.push(event(
+                first,
+                format!(
+                    "Session {} ran until {} with {} answer{}",
+                    session,
+                    clock(last),
+                    entries.len(),
+                    if entries.len() == 1 { "" } else { "s" }
+                ),
+            ));
+
+            // Stretch of consecutive answers in the same app: app, start and time
+            let mut stretch: Option<(&str, u64, Duration)> = None;
+            for (entry, span) in entries.iter().zip(spans) {
+                tracked += span;
+                if let Some(category) = entry.category.as_deref() {
+                    *categories.entry(category).or_default() += span;
+                }
+                let Some(app) = entry.app.as_deref() else {
+                    continue;
+                };
+                *apps.entry(app).or_default() += span;
+                match &mut stretch {
+                    Some((current, _, time)) if *current == app => *time += span,
+                    _ => {
+                        events.extend(stretch.and_then(long_stretch));
+                        stretch = Some((app, entry.timestamp_ms, span));
+                    }
+                }
+                // A break ends the stretch even in the same app
+                if span >= MAX_ENTRY_SPAN {
+                    events.extend(stretch.take().and_then(long_stretch));
+                }
+            }
+            events.extend(stretch.and_then(long_stretch));
+        }
+        events.sort_by_key(|event| event.timestamp_ms);
+
+        Self {
+            period,
+            from_ms: unix_millis(from),
+            to_ms: unix_millis(to),
+            entries: entries.len(),
+            sessions: by_session.len(),
+            tracked_minutes: minutes(tracked),
+            total_tokens: entries
+                .iter()
+                .map(|entry| i64::from(entry.total_tokens.unwrap_or(0)))
+                .sum(),
+            apps: shares(apps),
+            categories: shares(categories),
+            events,
+            narrative: None,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        let from = date_time(UNIX_EPOCH + Duration::from_millis(self.from_ms));
+        let date = from.split(' ').next().unwrap_or_default();
+        match self.period {
+            ReportPeriod::Day => format!("Activity report for {}", date),
+            ReportPeriod::Week => format!("Activity report for the week of {}", date),
+        }
+    }
+
+    /// Asks Gemini to write `narrative` from the totals; nothing about the answers themselves
+    /// is sent beyond app and category names
+    pub async fn narrate(
+        &self,
+        model: &str,
+        options: ConnectionOptions,
+    ) -> crate::gemini::Result<String> {
+        let setup = Setup::builder(model)
+            .system_instruction(Content::system(NARRATIVE_INSTRUCTION))
+            .generation_config(GenerationConfig {
+                response_modalities: vec!["TEXT".to_string()],
+                ..Default::default()
+            })
+            .build()?;
+        let totals = serde_json::json!({
+            "title": self.title(),
+            "sessions": self.sessions,
+            "trackedMinutes": self.tracked_minutes,
+            "apps": &self.apps[..self.apps.len().min(TOP_ROWS)],
+            "categories": self.categories,
+            "events": self.events,
+        });
+
+        let mut session = GeminiSession::connect(setup, options).await?;
+        session
+            .send_text_turn("user", serde_json::to_string(&totals)?, true)
+            .await?;
+        let mut narrative = String::new();
+        let result = loop {
+            match session.recv().await {
+                Ok(Some(ServerEvent::ServerContent { content, .. })) => {
+                    for part in content.model_turn.iter().flat_map(|turn| &turn.parts) {
+                        if let Part::Text { text } = part {
+                            narrative.push_str(text);
+                        }
+                    }
+                    if content.turn_complete.unwrap_or(false) {
+                        break Ok(narrative.trim().to_string());
+                    }
+                }
+                Ok(Some(ServerEvent::Error { error, .. })) => {
+                    break Err(GeminiError::ServerError(error));
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => break Err(GeminiError::ConnectionClosed),
+                Err(e) => break Err(e),
+            }
+        };
+        session.close().await.ok();
+        result
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.markdown(),
+            ReportFormat::Html => self.html(),
+        }
+    }
+
+    /// One line under the title with the period and totals
+    fn overview(&self) -> String {
+        format!(
+            "{} to {} · {} sessions · {} answers · {} tracked · {} tokens",
+            date_time(UNIX_EPOCH + Duration::from_millis(self.from_ms)),
+            date_time(UNIX_EPOCH + Duration::from_millis(self.to_ms)),
+            self.sessions,
+            self.entries,
+            format_minutes(self.tracked_minutes),
+            self.total_tokens
+        )
+    }
+
+    fn markdown(&self) -> String {
+        let mut out = format!("# {}\n\n{}\n", self.title(), self.overview());
+        if let Some(narrative) = &self.narrative {
+            let _ = write!(out, "\n## Summary\n\n{}\n", narrative);
+        }
+        for (heading, label, shares) in self.tables() {
+            let _ = write!(
+                out,
+                "\n## {}\n\n| {} | Time | Share |\n| --- | ---: | ---: |\n",
+                heading, label
+            );
+            for share in shares {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} |",
+                    share.name.replace('|', "\\|"),
+                    format_minutes(share.minutes),
+                    self.percent(share)
+                );
+            }
+        }
+        if !self.events.is_empty() {
+            out.push_str("\n## Notable events\n\n");
+            for event in &self.events {
+                let _ = writeln!(out, "- {} — {}", short_time(&event.time), event.text);
+            }
+        }
+        out
+    }
+
+    fn html(&self) -> String {
+        let title = escape_html(&self.title());
+        let mut out = format!(
+            "<!doctype html>\n<html><head><meta charset=\"utf-8\"><title>{}</title>\n\
+             <style>body{{font-family:-apple-system,sans-serif;max-width:48rem;margin:2rem auto;\
+             padding:0 1rem;color:#222}}table{{border-collapse:collapse}}\
+             td,th{{padding:.2rem .8rem;border-bottom:1px solid #ddd;text-align:left}}\
+             td.n{{text-align:right}}.overview{{color:#666}}</style></head><body>\n\
+             <h1>{}</h1>\n<p class=\"overview\">{}</p>\n",
+            title,
+            title,
+            escape_html(&self.overview())
+        );
+        if let Some(narrative) = &self.narrative {
+            out.push_str("<h2>Summary</h2>\n");
+            for paragraph in narrative.split("\n\n").filter(|p| !p.trim().is_empty()) {
+                let _ = writeln!(out, "<p>{}</p>", escape_html(paragraph.trim()));
+            }
+        }
+        for (heading, label, shares) in self.tables() {
+            let _ = writeln!(
+                out,
+                "<h2>{}</h2>\n<table><tr><th>{}</th><th>Time</th><th>Share</th></tr>",
+                heading, label
+            );
+            for share in shares {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td></tr>",
+                    escape_html(&share.name),
+                    escape_html(&format_minutes(share.minutes)),
+                    self.percent(share)
+                );
+            }
+            out.push_str("</table>\n");
+        }
+        if !self.events.is_empty() {
+            out.push_str("<h2>Notable events</h2>\n<ul>\n");
+            for event in &self.events {
+                let _ = writeln!(
+                    out,
+                    "<li>{} — {}</li>",
+                    short_time(&event.time),
+                    escape_html(&event.text)
+                );
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</body></html>\n");
+        out
+    }
+
+    /// Non-empty tables as heading, column label and rows
+    fn tables(&self) -> Vec<(&'static str, &'static str, &[TimeShare])> {
+        [
+            ("Time per app", "App", &self.apps[..]),
+            ("Time per category", "Category", &self.categories[..]),
+        ]
+        .into_iter()
+        .filter(|(_, _, shares)| !shares.is_empty())
+        .map(|(heading, label, shares)| (heading, label, &shares[..shares.len().min(TOP_ROWS)]))
+        .collect()
+    }
+
+    fn percent(&self, share: &TimeShare) -> String {
+        if self.tracked_minutes > 0.0 {
+            format!("{:.0}%", share.minutes / self.tracked_minutes * 100.0)
+        } else {
+            "-".to_string()
+        }
+    }
+}
+
+/// Time each entry of a session covers, in order
+fn spans(entries: &[&ActivityEntry]) -> Vec<Duration> {
+    let mut spans: Vec<Duration> = entries
+        .windows(2)
+        .map(|pair| {
+            let gap = pair[1].timestamp_ms.saturating_sub(pair[0].timestamp_ms);
+            Duration::from_millis(gap).min(MAX_ENTRY_SPAN)
+        })
+        .collect();
+    // The last answer is taken to last as long as the one before it
+    spans.push(spans.last().copied().unwrap_or_default());
+    spans
+}
+
+fn long_stretch((app, start_ms, time): (&str, u64, Duration)) -> Option<NotableEvent> {
+    (time >= LONG_STRETCH).then(|| {
+        event(
+            start_ms,
+            format!(
+                "{} without a break in {}",
+                format_minutes(minutes(time)),
+                app
+            ),
+        )
+    })
+}
+
+fn event(timestamp_ms: u64, text: String) -> NotableEvent {
+    NotableEvent {
+        timestamp_ms,
+        time: date_time(UNIX_EPOCH + Duration::from_millis(timestamp_ms)),
+        text,
+    }
+}
+
+fn shares(times: BTreeMap<&str, Duration>) -> Vec<TimeShare> {
+    let mut shares: Vec<TimeShare> = times
+        .into_iter()
+        .map(|(name, time)| TimeShare {
+            name: name.to_string(),
+            minutes: minutes(time),
+        })
+        .collect();
+    shares.sort_by(|a, b| b.minutes.total_cmp(&a.minutes));
+    shares
+}
+
+fn minutes(time: Duration) -> f64 {
+    (time.as_secs_f64() / 60.0 * 10.0).round() / 10.0
+}
+
+fn clock(timestamp_ms: u64) -> String {
+    clock_time(UNIX_EPOCH + Duration::from_millis(timestamp_ms))
+}
+
+/// YYYY-MM-DD HH:MM of a `date_time`
+fn short_time(time: &str) -> &str {
+    time.get(..16).unwrap_or(time)
+}
+
+/// e.g. 2h 05m, 12m or <1m
+fn format_minutes(minutes: f64) -> String {
+    let whole = minutes.round() as u64;
+    match whole {
+        0 => "<1m".to_string(),
+        1..60 => format!("{}m", whole),
+        _ => format!("{}h {:02}m", whole / 60, whole % 60),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
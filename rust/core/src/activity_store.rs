@@ -0,0 +1,285 @@
+use crate::{unix_millis, ActivityCategory, AnalysisResult, FrameRecord};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::SystemTime;
+use thiserror::Error;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS activity (
+        id INTEGER PRIMARY KEY,
+        session TEXT NOT NULL,
+        frame_index INTEGER,
+        timestamp_ms INTEGER NOT NULL,
+        app TEXT,
+        window_title TEXT,
+        category TEXT,
+        summary TEXT,
+        prompt_tokens INTEGER,
+        response_tokens INTEGER,
+        total_tokens INTEGER,
+        frame_path TEXT,
+        calendar_event TEXT
+    );
+    CREATE INDEX IF NOT EXISTS activity_timestamp ON activity (timestamp_ms);
+    CREATE INDEX IF NOT EXISTS activity_app ON activity (app);
+    CREATE INDEX IF NOT EXISTS activity_category ON activity (category);
+    CREATE INDEX IF NOT EXISTS activity_frame ON activity (session, frame_index);
+";
+
+const COLUMNS: &str = "session, frame_index, timestamp_ms, app, window_title, category, summary, \
+                       prompt_tokens, response_tokens, total_tokens, frame_path, calendar_event";
+
+/// Columns added since the table was first created, for databases that predate them
+const ADDED_COLUMNS: &[(&str, &str)] = &[("calendar_event", "TEXT")];
+
+#[derive(Debug, Error)]
+pub enum ActivityStoreError {
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+pub type ActivityStoreResult<T> = std::result::Result<T, ActivityStoreError>;
+
+/// An analyzed frame, or an answer about no frame, as stored in the activity database
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActivityEntry {
+    pub session: String,
+    pub index: Option<usize>,
+    pub timestamp_ms: u64,
+    pub app: Option<String>,
+    pub window_title: Option<String>,
+    /// Only known for structured answers
+    pub category: Option<String>,
+    pub summary: String,
+    pub prompt_tokens: Option<i32>,
+    pub response_tokens: Option<i32>,
+    pub total_tokens: Option<i32>,
+    /// Where the frame was saved, when it was
+    pub frame_path: Option<String>,
+    /// Calendar event going on at the time, when calendars were read
+    pub calendar_event: Option<String>,
+}
+
+impl ActivityEntry {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            session: row.get(0)?,
+            index: row.get::<_, Option<i64>>(1)?.map(|index| index as usize),
+            timestamp_ms: row.get::<_, i64>(2)? as u64,
+            app: row.get(3)?,
+            window_title: row.get(4)?,
+            category: row.get(5)?,
+            summary: row.get(6)?,
+            prompt_tokens: row.get(7)?,
+            response_tokens: row.get(8)?,
+            total_tokens: row.get(9)?,
+            frame_path: row.get(10)?,
+            calendar_event: row.get(11)?,
+        })
+    }
+}
+
+/// Totals over a stretch of the activity log
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivitySummary {
+    pub entries: usize,
+    pub total_tokens: i64,
+    /// Answered entries per app, for entries with a known app
+    pub apps: BTreeMap<String, usize>,
+    /// Answered entries per category, for structured answers
+    pub categories: BTreeMap<String, usize>,
+}
+
+impl ActivitySummary {
+    pub fn of(entries: &[ActivityEntry]) -> Self {
+        let mut summary = Self {
+            entries: entries.len(),
+            ..Default::default()
+        };
+        for entry in entries {
+            summary.total_tokens += i64::from(entry.total_tokens.unwrap_or(0));
+            if let Some(app) = &entry.app {
+                *summary.apps.entry(app.clone()).or_default() += 1;
+            }
+            if let Some(category) = &entry.category {
+                *summary.categories.entry(category.clone()).or_default() += 1;
+            }
+        }
+        summary
+    }
+}
+
+/// Keeps every analysis result in a SQLite database, so what the watcher saw can be looked
+/// up by time, app or category long after the terminal scrolled past it
+///
+/// Saved frames are added when they are written and completed once their answer arrives;
+/// the queries only return answered frames.
+pub struct ActivityStore {
+    conn: parking_lot::Mutex<Connection>,
+}
+
+impl ActivityStore {
+    /// Opens (creating if needed) the database at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> ActivityStoreResult<Self> {
+        let conn = Connection::open(path)?;
+        // Lets readers, e.g. the MCP server, query while a session writes
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        conn.execute_batch(SCHEMA)?;
+        add_missing_columns(&conn)?;
+        Ok(Self {
+            conn: parking_lot::Mutex::new(conn),
+        })
+    }
+
+    /// Adds a saved frame, waiting for its answer
+    pub fn record_frame(&self, frame: &FrameRecord, frame_path: &str) -> ActivityStoreResult<()> {
+        self.conn.lock().execute(
+            "INSERT INTO activity (session, frame_index, timestamp_ms, app, window_title, \
+             frame_path, calendar_event) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                frame.session,
+                frame.index as i64,
+                frame.timestamp_ms as i64,
+                frame.app,
+                frame.window_title,
+                frame_path,
+                frame.calendar_event
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Completes the frame `result` is about, or adds the answer on its own when that frame
+    /// wasn't saved
+    pub fn record_answer(&self, session: &str, result: &AnalysisResult) -> ActivityStoreResult<()> {
+        let summary = match &result.activity {
+            Some(activity) => activity.summary.clone(),
+            None => result.text.trim().to_string(),
+        };
+        let category = result
+            .activity
+            .as_ref()
+            .map(|activity| activity.category.as_str());
+        let model_app = result
+            .activity
+            .as_ref()
+            .and_then(|activity| activity.app.clone());
+        let usage = result.usage.as_ref();
+        let prompt_tokens = usage.and_then(|usage| usage.prompt_token_count);
+        let response_tokens = usage.and_then(|usage| usage.response_token_count);
+        let total_tokens = usage.and_then(|usage| usage.total_token_count);
+        let index = result.frame_seq.map(|index| index as i64);
+
+        let conn = self.conn.lock();
+        let updated = conn.execute(
+            "UPDATE activity SET summary = ?3, category = ?4, app = COALESCE(app, ?5), \
+             prompt_tokens = ?6, response_tokens = ?7, total_tokens = ?8 \
+             WHERE session = ?1 AND frame_index = ?2 AND summary IS NULL",
+            params![
+                session,
+                index,
+                summary,
+                category,
+                model_app,
+                prompt_tokens,
+                response_tokens,
+                total_tokens
+            ],
+        )?;
+        if updated == 0 {
+            conn.execute(
+                "INSERT INTO activity (session, frame_index, timestamp_ms, app, category, \
+                 summary, prompt_tokens, response_tokens, total_tokens) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    session,
+                    index,
+                    unix_millis(result.timestamp) as i64,
+                    model_app,
+                    category,
+                    summary,
+                    prompt_tokens,
+                    response_tokens,
+                    total_tokens
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Answered entries from `from` up to, not including, `to`, oldest first
+    pub fn between(
+        &self,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> ActivityStoreResult<Vec<ActivityEntry>> {
+        self.query(
+            "timestamp_ms >= ?1 AND timestamp_ms < ?2",
+            params![unix_millis(from) as i64, unix_millis(to) as i64],
+        )
+    }
+
+    /// Answered entries of the app named `app`, oldest first
+    pub fn by_app(&self, app: &str) -> ActivityStoreResult<Vec<ActivityEntry>> {
+        self.query("app = ?1", params![app])
+    }
+
+    /// Answered entries the model put in `category`, oldest first
+    pub fn by_category(
+        &self,
+        category: ActivityCategory,
+    ) -> ActivityStoreResult<Vec<ActivityEntry>> {
+        self.query("category = ?1", params![category.as_str()])
+    }
+
+    /// Where the most recently saved frame is, answered or not
+    pub fn latest_frame_path(&self) -> ActivityStoreResult<Option<String>> {
+        let path = self
+            .conn
+            .lock()
+            .query_row(
+                "SELECT frame_path FROM activity WHERE frame_path IS NOT NULL \
+                 ORDER BY timestamp_ms DESC, id DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(path)
+    }
+
+    fn query(
+        &self,
+        filter: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> ActivityStoreResult<Vec<ActivityEntry>> {
+        let conn = self.conn.lock();
+        let mut statement = conn.prepare(&format!(
+            "SELECT {} FROM activity WHERE summary IS NOT NULL AND {} ORDER BY timestamp_ms, id",
+            COLUMNS, filter
+        ))?;
+        let entries = statement
+            .query_map(params, ActivityEntry::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+}
+
+/// Adds `ADDED_COLUMNS` to a table created by an older version
+fn add_missing_columns(conn: &Connection) -> rusqlite::Result<()> {
+    let existing = conn
+        .prepare("SELECT name FROM pragma_table_info('activity')")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (name, kind) in ADDED_COLUMNS {
+        if !existing.iter().any(|column| column == name) {
+            conn.execute_batch(&format!(
+                "ALTER TABLE activity ADD COLUMN {} {}",
+                name, kind
+            ))?;
+        }
+    }
+    Ok(())
+}
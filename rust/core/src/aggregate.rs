@@ -0,0 +1,185 @@
+use crate::{Content, Part, ResponsePrinter};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::SystemTime;
+
+/// System instruction used in aggregation mode; the model may only answer with a category
+pub const AGGREGATE_INSTRUCTION: &str = "You classify screenshots of a computer screen into a \
+     single activity category. Reply with exactly one word from this list and nothing else: \
+     coding, communication, browsing, documents, meetings, media, design, other. \
+     Never mention names, titles, text or any other content visible on the screen.";
+
+/// Per-frame prompt used in aggregation mode
+pub const AGGREGATE_PROMPT: &str = "Which category best describes this screenshot?";
+
+/// Coarse activity categories that are safe to aggregate and share
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityCategory {
+    Coding,
+    Communication,
+    Browsing,
+    Documents,
+    Meetings,
+    Media,
+    Design,
+    Other,
+}
+
+impl ActivityCategory {
+    pub const ALL: [ActivityCategory; 8] = [
+        ActivityCategory::Coding,
+        ActivityCategory::Communication,
+        ActivityCategory::Browsing,
+        ActivityCategory::Documents,
+        ActivityCategory::Meetings,
+        ActivityCategory::Media,
+        ActivityCategory::Design,
+        ActivityCategory::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityCategory::Coding => "coding",
+            ActivityCategory::Communication => "communication",
+            ActivityCategory::Browsing => "browsing",
+            ActivityCategory::Documents => "documents",
+            ActivityCategory::Meetings => "meetings",
+            ActivityCategory::Media => "media",
+            ActivityCategory::Design => "design",
+            ActivityCategory::Other => "other",
+        }
+    }
+
+    /// Finds the first category name mentioned in a model answer
+    pub fn parse_answer(text: &str) -> Option<Self> {
+        let lowered = text.to_lowercase();
+        lowered
+            .split(|c: char| !c.is_alphabetic())
+            .find_map(|word| Self::ALL.into_iter().find(|c| c.as_str() == word))
+    }
+}
+
+/// Settings for the privacy-preserving aggregation
+#[derive(Debug, Clone)]
+pub struct AggregationConfig {
+    /// Seconds of activity represented by one classified frame
+    pub sample_seconds: f64,
+    /// Minimum number of samples a category needs before it is reported at all
+    pub min_samples: u64,
+    /// Differential-privacy budget for Laplace noise on reported totals; `None` disables noise
+    pub epsilon: Option<f64>,
+}
+
+impl Default for AggregationConfig {
+    fn default() -> Self {
+        Self {
+            sample_seconds: 1.0,
+            min_samples: 5,
+            epsilon: None,
+        }
+    }
+}
+
+/// Aggregated report that contains no per-frame data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateReport {
+    pub period_start_ms: u64,
+    pub period_end_ms: u64,
+    pub seconds_by_category: BTreeMap<ActivityCategory, f64>,
+    /// Number of categories withheld because they had fewer than `min_samples` samples
+    pub suppressed_categories: usize,
+}
+
+/// Counts classified samples per category; never retains answers, frames or titles
+pub struct ActivityAggregator {
+    config: AggregationConfig,
+    counts: parking_lot::Mutex<HashMap<ActivityCategory, u64>>,
+    started_at: SystemTime,
+}
+
+impl ActivityAggregator {
+    pub fn new(config: AggregationConfig) -> Self {
+        Self {
+            config,
+            counts: parking_lot::Mutex::new(HashMap::new()),
+            started_at: SystemTime::now(),
+        }
+    }
+
+    pub fn record(&self, category: ActivityCategory) {
+        *self.counts.lock().entry(category).or_insert(0) += 1;
+    }
+
+    /// Builds a report with small cells suppressed and optional noise applied
+    pub fn report(&self) -> AggregateReport {
+        let counts = self.counts.lock().clone();
+        let mut rng = rand::thread_rng();
+        let mut seconds_by_category = BTreeMap::new();
+        let mut suppressed_categories = 0;
+
+        for (category, count) in counts {
+            if count < self.config.min_samples {
+                suppressed_categories += 1;
+                continue;
+            }
+
+            let mut seconds = count as f64 * self.config.sample_seconds;
+            if let Some(epsilon) = self.config.epsilon.filter(|e| *e > 0.0) {
+                // Laplace mechanism with sensitivity of one sample
+                let scale = self.config.sample_seconds / epsilon;
+                let u: f64 = rng.gen_range(-0.5..0.5);
+                seconds -= scale * u.signum() * (1.0 - 2.0 * u.abs()).ln();
+            }
+            seconds_by_category.insert(category, seconds.max(0.0));
+        }
+
+        AggregateReport {
+            period_start_ms: crate::unix_millis(self.started_at),
+            period_end_ms: crate::unix_millis(SystemTime::now()),
+            seconds_by_category,
+            suppressed_categories,
+        }
+    }
+}
+
+/// Printer that feeds model answers into an aggregator instead of displaying them
+///
+/// Answers stream in as several chunks, so the text is collected until the turn completes
+/// and each answer is recorded once.
+pub struct AggregatingPrinter {
+    aggregator: std::sync::Arc<ActivityAggregator>,
+    answer: parking_lot::Mutex<String>,
+}
+
+impl AggregatingPrinter {
+    pub fn new(aggregator: std::sync::Arc<ActivityAggregator>) -> Self {
+        Self {
+            aggregator,
+            answer: parking_lot::Mutex::new(String::new()),
+        }
+    }
+}
+
+impl ResponsePrinter for AggregatingPrinter {
+    fn print_response(&self, content: &Content) {
+        let mut answer = self.answer.lock();
+        for part in &content.parts {
+            if let Part::Text { text } = part {
+                answer.push_str(text);
+            }
+        }
+    }
+
+    fn print_status(&self, _message: &str) {}
+
+    fn print_turn_complete(&self) {
+        let answer = std::mem::take(&mut *self.answer.lock());
+        if answer.trim().is_empty() {
+            return;
+        }
+        let category = ActivityCategory::parse_answer(&answer).unwrap_or(ActivityCategory::Other);
+        self.aggregator.record(category);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{unix_millis, ActivityCategory, AnalysisResult, NotifyRule};
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AlertRulesError {
+    #[error("Invalid minutes in notify rule '{0}'")]
+    InvalidMinutes(String),
+}
+
+pub type AlertRulesResult<T> = std::result::Result<T, AlertRulesError>;
+
+/// A rule that fired, as shown to the user and posted to webhooks
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub rule: String,
+    /// When answers started matching the rule, in milliseconds since the Unix epoch
+    pub since_ms: u64,
+    /// When the frame that made the rule fire was sent
+    pub timestamp_ms: u64,
+    pub minutes: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<ActivityCategory>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app: Option<String>,
+    pub summary: String,
+}
+
+impl Notification {
+    fn new(rule: &NotifyRule, since: SystemTime, result: &AnalysisResult) -> Self {
+        let activity = result.activity.as_ref();
+        Self {
+            rule: rule.name.clone(),
+            since_ms: unix_millis(since),
+            timestamp_ms: unix_millis(result.timestamp),
+            minutes: result
+                .timestamp
+                .duration_since(since)
+                .unwrap_or_default()
+                .as_secs_f64()
+                / 60.0,
+            category: activity
+                .map(|activity| activity.category)
+                .or_else(|| ActivityCategory::parse_answer(&result.text)),
+            app: activity.and_then(|activity| activity.app.clone()),
+            summary: match activity {
+                Some(activity) => activity.summary.clone(),
+                None => result.text.trim().to_string(),
+            },
+        }
+    }
+
+    /// One line for people, e.g. in chat messages and desktop notifications
+    pub fn text(&self) -> String {
+        format!(
+            "🔔 {} for {:.0} min: {}",
+            self.rule, self.minutes, self.summary
+        )
+    }
+}
+
+struct Rule {
+    config: NotifyRule,
+    /// `for_minutes` and `cooldown_minutes` of the config
+    after: Duration,
+    cooldown: Duration,
+}
+
+#[derive(Default)]
+struct RuleState {
+    /// When the current streak of matching answers began
+    matching_since: Option<SystemTime>,
+    /// Whether the current streak already fired
+    fired: bool,
+    last_fired: Option<SystemTime>,
+}
+
+/// Watches analysis results for the `[notify]` rules of the config, e.g. the user has been
+/// watching videos for ten minutes
+///
+/// Each rule fires once per streak of matching answers and at most once per cooldown.
+pub struct AlertRules {
+    rules: Vec<Rule>,
+    states: parking_lot::Mutex<Vec<RuleState>>,
+}
+
+impl AlertRules {
+    /// Fails for a rule whose minutes are not a finite duration
+    pub fn new(rules: Vec<NotifyRule>) -> AlertRulesResult<Self> {
+        let rules = rules
+            .into_iter()
+            .map(|config| {
+                let (Some(after), Some(cooldown)) = (
+                    minutes(config.for_minutes),
+                    minutes(config.cooldown_minutes),
+                ) else {
+                    return Err(AlertRulesError::InvalidMinutes(config.name));
+                };
+                Ok(Rule {
+                    config,
+                    after,
+                    cooldown,
+                })
+            })
+            .collect::<AlertRulesResult<Vec<_>>>()?;
+        Ok(Self {
+            states: parking_lot::Mutex::new(rules.iter().map(|_| RuleState::default()).collect()),
+            rules,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Checks `result` against every rule and returns the ones that fire; answers to
+    /// questions rather than frames are ignored
+    pub fn observe(&self, result: &AnalysisResult) -> Vec<Notification> {
+        if result.frame_seq.is_none() {
+            return Vec::new();
+        }
+        let mut fired = Vec::new();
+        let mut states = self.states.lock();
+        for (rule, state) in self.rules.iter().zip(states.iter_mut()) {
+            if !matches(&rule.config, result) {
+                state.matching_since = None;
+                state.fired = false;
+                continue;
+            }
+            let since = *state.matching_since.get_or_insert(result.timestamp);
+            let elapsed =
+                |from: SystemTime| result.timestamp.duration_since(from).unwrap_or_default();
+            if state.fired || elapsed(since) < rule.after {
+                continue;
+            }
+            if state
+                .last_fired
+                .is_some_and(|last| elapsed(last) < rule.cooldown)
+            {
+                continue;
+            }
+            state.fired = true;
+            state.last_fired = Some(result.timestamp);
+            fired.push(Notification::new(&rule.config, since, result));
+        }
+        fired
+    }
+}
+
+/// None for minutes that don't make a duration, e.g. inf or NaN from the config
+fn minutes(minutes: f64) -> Option<Duration> {
+    // NaN.max(0.0) would be 0.0
+    if !minutes.is_finite() {
+        return None;
+    }
+    Duration::try_from_secs_f64(minutes.max(0.0) * 60.0).ok()
+}
+
+fn matches(rule: &NotifyRule, result: &AnalysisResult) -> bool {
+    let activity = result.activity.as_ref();
+    let contains =
+        |haystack: &str, needle: &str| haystack.to_lowercase().contains(&needle.to_lowercase());
+    if let Some(category) = rule.category {
+        let answered = activity
+            .map(|activity| activity.category)
+            .or_else(|| ActivityCategory::parse_answer(&result.text));
+        if answered != Some(category) {
+            return false;
+        }
+    }
+    if let Some(app) = &rule.app
+        && !activity
+            .and_then(|activity| activity.app.as_deref())
+            .is_some_and(|name| contains(name, app))
+    {
+        return false;
+    }
+    if let Some(flag) = rule.flag
+        && !activity.is_some_and(|activity| activity.flags.contains(&flag))
+    {
+        return false;
+    }
+    if let Some(text) = &rule.text
+        && !contains(&result.text, text)
+    {
+        return false;
+    }
+    true
+}
@@ -0,0 +1,111 @@
+use crate::analysis_result::parse_json_answer;
+use crate::{ActivityCategory, GenerationConfig};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// System instruction for structured analysis; the schema itself is sent as the response
+/// schema, so this only explains the fields
+pub const ACTIVITY_INSTRUCTION: &str = "You describe screenshots of a computer screen. For \
+     every screenshot, answer with one JSON object: the activity category, the app in the \
+     foreground, a one-sentence summary of what the user is doing, your confidence from 0 \
+     to 1, and flags for anything notable, such as passwords, personal data or error \
+     messages being visible.";
+
+/// Something notable about a screenshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityFlag {
+    /// Passwords, keys, financial or health information are visible
+    SensitiveContent,
+    /// Names, addresses, messages or other personal data of someone are visible
+    PersonalData,
+    /// An error message, crash report or failing build is visible
+    ErrorVisible,
+    /// Nothing is happening, e.g. a lock screen or screensaver
+    Idle,
+}
+
+impl ActivityFlag {
+    pub const ALL: [ActivityFlag; 4] = [
+        ActivityFlag::SensitiveContent,
+        ActivityFlag::PersonalData,
+        ActivityFlag::ErrorVisible,
+        ActivityFlag::Idle,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityFlag::SensitiveContent => "sensitive_content",
+            ActivityFlag::PersonalData => "personal_data",
+            ActivityFlag::ErrorVisible => "error_visible",
+            ActivityFlag::Idle => "idle",
+        }
+    }
+}
+
+/// What one screenshot shows, as the model answers with structured output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScreenActivity {
+    pub category: ActivityCategory,
+    /// App in the foreground, as the model read it off the screen
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app: Option<String>,
+    pub summary: String,
+    /// From 0 to 1
+    #[serde(default)]
+    pub confidence: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<ActivityFlag>,
+    /// Whether the screen shows work toward the intention of a focus session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_task: Option<bool>,
+}
+
+impl ScreenActivity {
+    /// Response schema in the OpenAPI subset Gemini accepts
+    pub fn schema() -> Value {
+        let categories: Vec<&str> = ActivityCategory::ALL.iter().map(|c| c.as_str()).collect();
+        let flags: Vec<&str> = ActivityFlag::ALL.iter().map(|f| f.as_str()).collect();
+        json!({
+            "type": "OBJECT",
+            "properties": {
+                "category": { "type": "STRING", "enum": categories },
+                "app": { "type": "STRING", "nullable": true },
+                "summary": { "type": "STRING" },
+                "confidence": { "type": "NUMBER", "minimum": 0, "maximum": 1 },
+                "flags": { "type": "ARRAY", "items": { "type": "STRING", "enum": flags } },
+            },
+            "required": ["category", "summary", "confidence"],
+            "propertyOrdering": ["category", "app", "summary", "confidence", "flags"],
+        })
+    }
+
+    /// `config` with JSON output constrained to the schema
+    pub fn generation_config(config: GenerationConfig) -> GenerationConfig {
+        GenerationConfig {
+            response_mime_type: Some("application/json".to_string()),
+            response_schema: Some(Self::schema()),
+            ..config
+        }
+    }
+
+    /// Parses a structured answer, also inside a Markdown code fence
+    pub fn parse(text: &str) -> Option<Self> {
+        Self::from_json(&parse_json_answer(text)?)
+    }
+
+    /// Reads an answer already parsed as JSON, clamping the confidence into range
+    pub fn from_json(value: &Value) -> Option<Self> {
+        let mut activity: Self = serde_json::from_value(value.clone()).ok()?;
+        activity.confidence = if activity.confidence.is_finite() {
+            activity.confidence.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        Some(activity)
+    }
+
+    pub fn has_flag(&self, flag: ActivityFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+}
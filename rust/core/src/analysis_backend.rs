@@ -0,0 +1,114 @@
+use crate::gemini::Result;
+use crate::{ClientContent, GeminiSender, GeminiSession, ServerEvent, ToolResponse};
+use futures::future::BoxFuture;
+use std::sync::Arc;
+
+/// Sending half of the model connection a `CaptureSession` routes frames, prompts and audio to
+///
+/// Turns and events use the shapes of the Gemini Live API, which the pipeline was built
+/// around: a backend for another model translates a `ClientContent` of inline images and text
+/// into its own request, and its answers into `ServerEvent`s for an `AnalysisEvents`.
+pub trait AnalysisBackend: Send + Sync {
+    /// Sends a turn, e.g. a frame and its prompt; a complete turn asks for an answer
+    fn send_turn(&self, content: ClientContent) -> BoxFuture<'_, Result<()>>;
+
+    /// Streams a chunk of 16 kHz mono PCM, e.g. from the microphone
+    fn send_audio<'a>(&'a self, pcm: &'a [u8]) -> BoxFuture<'a, Result<()>>;
+
+    /// Tells the model the audio stopped, e.g. the microphone was turned off, so it answers
+    /// what it heard instead of waiting for more; a no-op for backends that don't hear audio
+    fn end_audio(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn send_tool_response(&self, response: ToolResponse) -> BoxFuture<'_, Result<()>>;
+
+    /// Whether the connection is gone, e.g. after a network error
+    fn is_closed(&self) -> bool;
+
+    fn close(&self) -> BoxFuture<'_, Result<()>>;
+}
+
+/// Receiving half of a model connection, read by an `OutputProcessor`
+///
+/// Answers arrive as `ServerEvent::ServerContent` with the text and a completed turn at the
+/// end; token usage is reported in the `usage_metadata` of the events.
+pub trait AnalysisEvents: Send {
+    /// Next event, or `None` once the connection closed
+    fn recv(&mut self) -> BoxFuture<'_, Result<Option<ServerEvent>>>;
+
+    /// Answers the function calls of a `ServerEvent::ToolCall`
+    fn send_tool_response(&self, response: ToolResponse) -> BoxFuture<'_, Result<()>>;
+}
+
+impl AnalysisBackend for GeminiSender {
+    fn send_turn(&self, content: ClientContent) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.send_client_content(content))
+    }
+
+    fn send_audio<'a>(&'a self, pcm: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.send_realtime_audio(pcm))
+    }
+
+    fn end_audio(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(self.send_audio_stream_end())
+    }
+
+    fn send_tool_response(&self, response: ToolResponse) -> BoxFuture<'_, Result<()>> {
+        Box::pin(GeminiSender::send_tool_response(self, response))
+    }
+
+    fn is_closed(&self) -> bool {
+        GeminiSender::is_closed(self)
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(GeminiSender::close(self))
+    }
+}
+
+impl AnalysisEvents for GeminiSession {
+    fn recv(&mut self) -> BoxFuture<'_, Result<Option<ServerEvent>>> {
+        Box::pin(GeminiSession::recv(self))
+    }
+
+    fn send_tool_response(&self, response: ToolResponse) -> BoxFuture<'_, Result<()>> {
+        Box::pin(GeminiSession::send_tool_response(self, response))
+    }
+}
+
+impl<T: AnalysisBackend + ?Sized> AnalysisBackend for Arc<T> {
+    fn send_turn(&self, content: ClientContent) -> BoxFuture<'_, Result<()>> {
+        (**self).send_turn(content)
+    }
+
+    fn send_audio<'a>(&'a self, pcm: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        (**self).send_audio(pcm)
+    }
+
+    fn end_audio(&self) -> BoxFuture<'_, Result<()>> {
+        (**self).end_audio()
+    }
+
+    fn send_tool_response(&self, response: ToolResponse) -> BoxFuture<'_, Result<()>> {
+        (**self).send_tool_response(response)
+    }
+
+    fn is_closed(&self) -> bool {
+        (**self).is_closed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<()>> {
+        (**self).close()
+    }
+}
+
+impl<T: AnalysisEvents + ?Sized> AnalysisEvents for Box<T> {
+    fn recv(&mut self) -> BoxFuture<'_, Result<Option<ServerEvent>>> {
+        (**self).recv()
+    }
+
+    fn send_tool_response(&self, response: ToolResponse) -> BoxFuture<'_, Result<()>> {
+        (**self).send_tool_response(response)
+    }
+}
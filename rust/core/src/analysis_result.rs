@@ -0,0 +1,178 @@
+use crate::{unix_millis, ScreenActivity, UsageMetadata};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+/// One complete model answer, tied to the turn it answers
+#[derive(Debug, Clone)]
+pub struct AnalysisResult {
+    /// Number of the frame the turn carried, or the last frame of a batch; `None` for
+    /// questions, menu events and answers nobody asked for, e.g. to speech
+    pub frame_seq: Option<usize>,
+    /// When the turn was sent, or the answer completed when no turn was waiting for it
+    pub timestamp: SystemTime,
+    pub text: String,
+    /// The answer parsed as JSON, when it is a JSON document
+    pub json: Option<Value>,
+    /// The answer read as a ScreenActivity, when it follows that schema
+    pub activity: Option<ScreenActivity>,
+    /// Token counts reported last during the answer
+    pub usage: Option<UsageMetadata>,
+    /// Whether the user asked for this answer on the spot, e.g. with the hotkey
+    pub on_demand: bool,
+}
+
+impl AnalysisResult {
+    pub fn new(turn: Option<SentTurn>, text: String, usage: Option<UsageMetadata>) -> Self {
+        let json = parse_json_answer(&text);
+        let activity = json.as_ref().and_then(ScreenActivity::from_json);
+        Self {
+            frame_seq: turn.and_then(|turn| turn.frame_seq),
+            on_demand: turn.is_some_and(|turn| turn.on_demand),
+            timestamp: turn.map_or_else(SystemTime::now, |turn| turn.sent_at),
+            text,
+            json,
+            activity,
+            usage,
+        }
+    }
+
+    /// The result as sent to RPC and live feed clients
+    pub fn to_json(&self) -> Value {
+        json!({
+            "frameSeq": self.frame_seq,
+            "timestampMs": unix_millis(self.timestamp),
+            "text": self.text,
+            "json": self.json,
+            "usage": self.usage,
+            "onDemand": self.on_demand,
+        })
+    }
+}
+
+/// A turn waiting for its answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SentTurn {
+    /// Tells turns apart, also ones sent within the same clock tick
+    pub id: u64,
+    pub frame_seq: Option<usize>,
+    pub sent_at: SystemTime,
+    /// Sent because the user asked, e.g. with the hotkey, rather than on schedule
+    pub on_demand: bool,
+}
+
+/// Pairs the turns sent to the model with the answers coming back, which arrive in the
+/// order the turns were sent
+///
+/// Shared between the CaptureSession, which records every turn it completes, and the
+/// OutputProcessor, which takes the oldest one when an answer is complete. The protocol has
+/// no turn ids, so an answer is only paired with a turn sent before the answer started, and
+/// a turn whose answer was interrupted is skipped; an answer nobody asked for, e.g. to
+/// speech streamed continuously, is left unpaired rather than taking a later turn's place.
+#[derive(Debug, Default)]
+pub struct TurnTracker {
+    pending: parking_lot::Mutex<VecDeque<SentTurn>>,
+    next_id: AtomicU64,
+}
+
+impl TurnTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a complete user turn, carrying frame `frame_seq` if any
+    pub fn turn_sent(&self, frame_seq: Option<usize>, on_demand: bool) -> SentTurn {
+        let turn = SentTurn {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            frame_seq,
+            sent_at: SystemTime::now(),
+            on_demand,
+        };
+        self.pending.lock().push_back(turn);
+        turn
+    }
+
+    /// Drops a turn that could not be sent after all, so no answer is expected for it
+    pub fn withdraw(&self, turn: SentTurn) {
+        let mut pending = self.pending.lock();
+        if let Some(position) = pending.iter().position(|pending| pending.id == turn.id) {
+            pending.remove(position);
+        }
+    }
+
+    /// The turn an answer that started at `started` is about, without taking it
+    pub fn oldest(&self, started: SystemTime) -> Option<SentTurn> {
+        self.pending
+            .lock()
+            .front()
+            .copied()
+            .filter(|turn| turn.sent_at <= started)
+    }
+
+    /// Takes the turn an answer that started at `started` is about: the oldest one waiting,
+    /// unless it was sent after the answer started and so can't be what it answers
+    pub fn answered(&self, started: SystemTime) -> Option<SentTurn> {
+        let mut pending = self.pending.lock();
+        if pending.front()?.sent_at > started {
+            return None;
+        }
+        pending.pop_front()
+    }
+
+    /// Forgets the waiting turns, e.g. when they were sent to a session that has been replaced
+    pub fn reset(&self) {
+        self.pending.lock().clear();
+    }
+}
+
+/// Parses an answer that is a JSON object or array, also inside a Markdown code fence
+pub(crate) fn parse_json_answer(text: &str) -> Option<Value> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("```json")
+        .or_else(|| text.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(text)
+        .trim();
+    if !text.starts_with(['{', '[']) {
+        return None;
+    }
+    serde_json::from_str(text).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn answers_pair_with_turns_in_order() {
+        let turns = TurnTracker::new();
+        let first = turns.turn_sent(Some(1), false);
+        let second = turns.turn_sent(Some(2), true);
+        let now = SystemTime::now();
+        assert_eq!(turns.answered(now), Some(first));
+        assert_eq!(turns.answered(now), Some(second));
+        assert_eq!(turns.answered(now), None);
+    }
+
+    #[test]
+    fn answer_started_before_a_turn_is_left_unpaired() {
+        let turns = TurnTracker::new();
+        let started = SystemTime::now() - Duration::from_secs(1);
+        let turn = turns.turn_sent(Some(1), false);
+        assert_eq!(turns.oldest(started), None);
+        assert_eq!(turns.answered(started), None);
+        assert_eq!(turns.answered(SystemTime::now()), Some(turn));
+    }
+
+    #[test]
+    fn withdraw_removes_only_that_turn() {
+        let turns = TurnTracker::new();
+        let first = turns.turn_sent(None, false);
+        let second = turns.turn_sent(None, false);
+        turns.withdraw(first);
+        assert_eq!(turns.answered(SystemTime::now()), Some(second));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{Content, ResponsePrinter};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Appended to the frame prompt when annotated screenshots are requested
+pub const ANNOTATION_REQUEST: &str = "Also return the screenshot as an image with a bounding \
+     box drawn around every UI element you refer to in your answer.";
+
+/// File extension for an image MIME type returned by the model
+pub fn image_extension(mime_type: &str) -> Option<&'static str> {
+    match mime_type.split(';').next()?.trim() {
+        "image/png" => Some("png"),
+        "image/jpeg" | "image/jpg" => Some("jpg"),
+        "image/webp" => Some("webp"),
+        "image/gif" => Some("gif"),
+        _ => None,
+    }
+}
+
+/// Frames awaiting an answer; older entries are dropped if turns go unanswered
+const MAX_PENDING_FRAMES: usize = 8;
+
+/// Printer wrapper that saves images returned by the model next to the frame they annotate
+///
+/// Turns are answered in order, so the oldest frame without a completed turn is the one the
+/// current response refers to.
+pub struct AnnotationSaver {
+    inner: Arc<dyn ResponsePrinter>,
+    pending_frames: parking_lot::Mutex<VecDeque<PathBuf>>,
+}
+
+impl AnnotationSaver {
+    pub fn new(inner: Arc<dyn ResponsePrinter>) -> Self {
+        Self {
+            inner,
+            pending_frames: parking_lot::Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Path for the annotated copy of `frame`, e.g. `frame_0001_annotated.png`
+    fn annotated_path(frame: &Path, extension: &str) -> PathBuf {
+        let stem = frame
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("frame");
+        let mut path = frame.with_file_name(format!("{}_annotated.{}", stem, extension));
+        let mut suffix = 2;
+        while path.exists() {
+            path = frame.with_file_name(format!("{}_annotated_{}.{}", stem, suffix, extension));
+            suffix += 1;
+        }
+        path
+    }
+}
+
+impl ResponsePrinter for AnnotationSaver {
+    fn print_response(&self, content: &Content) {
+        let frame = self.pending_frames.lock().front().cloned();
+        for part in &content.parts {
+            let Some(blob) = part.inline_data() else {
+                continue;
+            };
+            let Some(extension) = blob.mime_type.as_deref().and_then(image_extension) else {
+                continue;
+            };
+            let (Some(frame), Some(bytes)) = (frame.as_deref(), blob.decode()) else {
+                continue;
+            };
+
+            let path = Self::annotated_path(frame, extension);
+            match std::fs::write(&path, bytes) {
+                Ok(()) => self
+                    .inner
+                    .print_status(&format!("🖍️ Annotated screenshot -> {}", path.display())),
+                Err(e) => self
+                    .inner
+                    .print_error(&format!("❌ Error saving annotated screenshot: {}", e)),
+            }
+        }
+        self.inner.print_response(content);
+    }
+
+    fn print_status(&self, message: &str) {
+        self.inner.print_status(message);
+    }
+
+    fn print_error(&self, message: &str) {
+        self.inner.print_error(message);
+    }
+
+    fn print_turn_complete(&self) {
+        self.pending_frames.lock().pop_front();
+        self.inner.print_turn_complete();
+    }
+
+    fn frame_captured(&self, path: &Path) {
+        {
+            let mut pending = self.pending_frames.lock();
+            pending.push_back(path.to_path_buf());
+            while pending.len() > MAX_PENDING_FRAMES {
+                pending.pop_front();
+            }
+        }
+        self.inner.frame_captured(path);
+    }
+
+    fn frame_sent(&self, frame_seq: usize) {
+        self.inner.frame_sent(frame_seq);
+    }
+}
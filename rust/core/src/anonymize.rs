@@ -0,0 +1,267 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AnonymizeError {
+    #[error("Failed to read category rules: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid category rules: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type AnonymizeResult<T> = std::result::Result<T, AnonymizeError>;
+
+/// Rewrites app names and window titles before they reach prompts, logs or exports
+pub trait Anonymizer: Send + Sync {
+    fn app_name(&self, name: &str) -> String;
+
+    /// Window, document or menu titles
+    fn title(&self, title: &str) -> String;
+}
+
+/// Leaves names untouched
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Passthrough;
+
+impl Anonymizer for Passthrough {
+    fn app_name(&self, name: &str) -> String {
+        name.to_string()
+    }
+
+    fn title(&self, title: &str) -> String {
+        title.to_string()
+    }
+}
+
+/// Replaces names with salted hashes, so equal names stay comparable across a dataset
+#[derive(Debug, Clone)]
+pub struct HashAnonymizer {
+    salt: String,
+}
+
+impl HashAnonymizer {
+    pub fn new(salt: impl Into<String>) -> Self {
+        Self { salt: salt.into() }
+    }
+
+    fn digest(&self, kind: &str, value: &str) -> String {
+        let hash = Sha256::new()
+            .chain_update(self.salt.as_bytes())
+            .chain_update([0])
+            .chain_update(value.as_bytes())
+            .finalize();
+        let hex: String = hash[..6]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("{}-{}", kind, hex)
+    }
+}
+
+impl Anonymizer for HashAnonymizer {
+    fn app_name(&self, name: &str) -> String {
+        self.digest("app", name)
+    }
+
+    fn title(&self, title: &str) -> String {
+        self.digest("title", title)
+    }
+}
+
+/// Case-insensitive text that maps a name onto a category
+///
+/// Letters at either end of the pattern must start or end a word in the name, so "word"
+/// matches "Microsoft Word" but not "1Password"; a pattern like ".pdf" matches any suffix.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CategoryRule {
+    pub pattern: String,
+    pub category: String,
+}
+
+impl CategoryRule {
+    pub fn new(pattern: impl Into<String>, category: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into().to_lowercase(),
+            category: category.into(),
+        }
+    }
+
+    /// Whether the lowercased `value` contains the pattern on word boundaries
+    fn matches(&self, value: &str) -> bool {
+        let pattern = self.pattern.as_str();
+        let (Some(first), Some(last)) = (pattern.chars().next(), pattern.chars().last()) else {
+            return false;
+        };
+        value.match_indices(pattern).any(|(start, _)| {
+            let before = value[..start].chars().next_back();
+            let after = value[start + pattern.len()..].chars().next();
+            (!first.is_alphabetic() || !before.is_some_and(char::is_alphabetic))
+                && (!last.is_alphabetic() || !after.is_some_and(char::is_alphabetic))
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct CategoryFile {
+    #[serde(default)]
+    apps: Vec<CategoryRule>,
+    #[serde(default)]
+    titles: Vec<CategoryRule>,
+    fallback: Option<String>,
+}
+
+/// Replaces names with the category of the first matching rule
+#[derive(Debug, Clone)]
+pub struct CategoryAnonymizer {
+    app_rules: Vec<CategoryRule>,
+    title_rules: Vec<CategoryRule>,
+    fallback: String,
+}
+
+impl Default for CategoryAnonymizer {
+    /// Common macOS apps and document types
+    fn default() -> Self {
+        let rules = |rules: &[(&str, &str)]| {
+            rules
+                .iter()
+                .map(|(pattern, category)| CategoryRule::new(*pattern, *category))
+                .collect()
+        };
+        Self {
+            app_rules: rules(&[
+                ("xcode", "development"),
+                ("code", "development"),
+                ("terminal", "development"),
+                ("iterm", "development"),
+                ("safari", "browser"),
+                ("chrome", "browser"),
+                ("firefox", "browser"),
+                ("mail", "communication"),
+                ("slack", "communication"),
+                ("messages", "communication"),
+                ("zoom", "meetings"),
+                ("teams", "meetings"),
+                ("excel", "spreadsheets"),
+                ("numbers", "spreadsheets"),
+                ("word", "documents"),
+                ("pages", "documents"),
+                ("keynote", "presentations"),
+                ("powerpoint", "presentations"),
+                ("finder", "files"),
+                ("dock", "system"),
+            ]),
+            title_rules: rules(&[
+                (".xlsx", "spreadsheet"),
+                (".numbers", "spreadsheet"),
+                (".csv", "spreadsheet"),
+                (".docx", "document"),
+                (".pages", "document"),
+                (".pdf", "document"),
+                (".pptx", "presentation"),
+                (".key", "presentation"),
+                (".rs", "source file"),
+                (".swift", "source file"),
+            ]),
+            fallback: "other".to_string(),
+        }
+    }
+}
+
+impl CategoryAnonymizer {
+    pub fn new(app_rules: Vec<CategoryRule>, title_rules: Vec<CategoryRule>) -> Self {
+        Self {
+            app_rules,
+            title_rules,
+            fallback: "other".to_string(),
+        }
+    }
+
+    /// Loads rules from JSON: `{"apps": [{"pattern", "category"}], "titles": [...], "fallback"}`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> AnonymizeResult<Self> {
+        let file: CategoryFile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let lowercase = |rules: Vec<CategoryRule>| {
+            rules
+                .into_iter()
+                .map(|rule| CategoryRule::new(rule.pattern, rule.category))
+                .collect()
+        };
+        Ok(Self {
+            app_rules: lowercase(file.apps),
+            title_rules: lowercase(file.titles),
+            fallback: file.fallback.unwrap_or_else(|| "other".to_string()),
+        })
+    }
+
+    pub fn with_fallback(mut self, fallback: impl Into<String>) -> Self {
+        self.fallback = fallback.into();
+        self
+    }
+
+    fn categorize(&self, rules: &[CategoryRule], value: &str) -> String {
+        let value = value.to_lowercase();
+        rules
+            .iter()
+            .find(|rule| rule.matches(&value))
+            .map_or_else(|| self.fallback.clone(), |rule| rule.category.clone())
+    }
+}
+
+impl Anonymizer for CategoryAnonymizer {
+    fn app_name(&self, name: &str) -> String {
+        self.categorize(&self.app_rules, name)
+    }
+
+    fn title(&self, title: &str) -> String {
+        self.categorize(&self.title_rules, title)
+    }
+}
+
+/// Anonymizer selectable on the command line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnonymizeMode {
+    #[default]
+    Passthrough,
+    Hash,
+    Category,
+}
+
+impl FromStr for AnonymizeMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "passthrough" | "none" | "off" => Ok(AnonymizeMode::Passthrough),
+            "hash" => Ok(AnonymizeMode::Hash),
+            "category" | "categories" => Ok(AnonymizeMode::Category),
+            other => Err(format!(
+                "unknown anonymizer '{}' (expected passthrough, hash or category)",
+                other
+            )),
+        }
+    }
+}
+
+/// The anonymizer used when none is configured
+pub fn passthrough() -> Arc<dyn Anonymizer> {
+    Arc::new(Passthrough)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn category_rules_match_whole_words() {
+        let anonymizer = CategoryAnonymizer::default();
+        assert_eq!(anonymizer.app_name("Microsoft Word"), "documents");
+        assert_eq!(anonymizer.app_name("1Password 7"), "other");
+        assert_eq!(anonymizer.app_name("Visual Studio Code"), "development");
+        assert_eq!(anonymizer.app_name("iTerm2"), "development");
+        assert_eq!(anonymizer.title("Q3 report.pdf"), "document");
+        assert_eq!(anonymizer.title("notes.keynote-backup"), "other");
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{unix_millis, Content, FunctionCall, Part, ResponsePrinter, ToolHandler};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Name of the function that lets the model look up its own earlier answers
+pub const RECALL_TOOL_NAME: &str = "recall_recent_answers";
+
+/// Answers returned when the model does not say how many it wants
+const DEFAULT_RECALL_COUNT: usize = 5;
+
+/// One completed model answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedAnswer {
+    pub timestamp_ms: u64,
+    pub text: String,
+}
+
+struct HistoryState {
+    answers: VecDeque<RecordedAnswer>,
+    current: String,
+}
+
+/// Ring buffer of the last model answers, filled by wrapping the response printer
+pub struct AnswerHistory {
+    inner: Arc<dyn ResponsePrinter>,
+    capacity: usize,
+    state: parking_lot::Mutex<HistoryState>,
+}
+
+impl AnswerHistory {
+    pub fn new(inner: Arc<dyn ResponsePrinter>, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            inner,
+            capacity,
+            state: parking_lot::Mutex::new(HistoryState {
+                answers: VecDeque::with_capacity(capacity),
+                current: String::new(),
+            }),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns up to `count` of the most recent answers, oldest first
+    pub fn recent(&self, count: usize) -> Vec<RecordedAnswer> {
+        let state = self.state.lock();
+        let skip = state.answers.len().saturating_sub(count);
+        state.answers.iter().skip(skip).cloned().collect()
+    }
+}
+
+impl ResponsePrinter for AnswerHistory {
+    fn print_response(&self, content: &Content) {
+        {
+            let mut state = self.state.lock();
+            for part in &content.parts {
+                if let Part::Text { text } = part {
+                    state.current.push_str(text);
+                }
+            }
+        }
+        self.inner.print_response(content);
+    }
+
+    fn print_status(&self, message: &str) {
+        self.inner.print_status(message);
+    }
+
+    fn print_error(&self, message: &str) {
+        self.inner.print_error(message);
+    }
+
+    fn print_turn_complete(&self) {
+        {
+            let mut state = self.state.lock();
+            let text = std::mem::take(&mut state.current).trim().to_string();
+            if !text.is_empty() {
+                if state.answers.len() == self.capacity {
+                    state.answers.pop_front();
+                }
+                state.answers.push_back(RecordedAnswer {
+                    timestamp_ms: unix_millis(SystemTime::now()),
+                    text,
+                });
+            }
+        }
+        self.inner.print_turn_complete();
+    }
+
+    fn frame_captured(&self, path: &Path) {
+        self.inner.frame_captured(path);
+    }
+
+    fn frame_sent(&self, frame_seq: usize) {
+        self.inner.frame_sent(frame_seq);
+    }
+}
+
+impl ToolHandler for AnswerHistory {
+    fn declarations(&self) -> Vec<Value> {
+        vec![json!({
+            "name": RECALL_TOOL_NAME,
+            "description": "Returns your most recent answers in this session, oldest first, \
+                with capture timestamps. Use it for follow-up questions about what happened \
+                earlier, e.g. whether an error has appeared before.",
+            "parameters": {
+                "type": "OBJECT",
+                "properties": {
+                    "n": {
+                        "type": "INTEGER",
+                        "description": format!(
+                            "Number of answers to return (1-{}, default {})",
+                            self.capacity, DEFAULT_RECALL_COUNT
+                        ),
+                    }
+                }
+            }
+        })]
+    }
+
+    fn handle<'a>(&'a self, call: &'a FunctionCall) -> BoxFuture<'a, Option<Value>> {
+        Box::pin(async move {
+            if call.name != RECALL_TOOL_NAME {
+                return None;
+            }
+            let count = call
+                .args
+                .as_ref()
+                .and_then(|args| args.get("n"))
+                .and_then(Value::as_u64)
+                .map_or(DEFAULT_RECALL_COUNT, |n| n as usize)
+                .clamp(1, self.capacity);
+            Some(json!({ "answers": self.recent(count) }))
+        })
+    }
+}
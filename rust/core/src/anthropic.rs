@@ -0,0 +1,241 @@
+use crate::chat_session::{
+    backend_error, instruction_text, json_schema, trim_history, ChatAnswer, ChatModel, Deltas,
+};
+use crate::gemini::{GeminiError, Result};
+use crate::{Blob, ChatSession, Setup};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Version of the Messages API the requests are written against
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+impl ChatSession {
+    /// Screen watching with a Claude model through the Anthropic Messages API, e.g. at
+    /// `https://api.anthropic.com/v1`, streaming each answer as it is written; checks that
+    /// `api_key` is accepted first
+    ///
+    /// The Messages API has no response schema, so a schema in `setup` is added to the system
+    /// prompt instead.
+    pub async fn anthropic(
+        url: &str,
+        model: &str,
+        api_key: &str,
+        max_tokens: u32,
+        setup: &Setup,
+    ) -> Result<Self> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        let key = api_key
+            .parse()
+            .map_err(|_| GeminiError::Backend("Invalid Anthropic API key".to_string()))?;
+        headers.insert("x-api-key", key);
+        headers.insert(
+            "anthropic-version",
+            reqwest::header::HeaderValue::from_static(ANTHROPIC_VERSION),
+        );
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .default_headers(headers)
+            .build()
+            .map_err(backend_error)?;
+        let url = url.trim_end_matches('/');
+        let response = client
+            .get(format!("{}/models", url))
+            .send()
+            .await
+            .map_err(|e| GeminiError::Backend(format!("{} isn't reachable: {}", url, e)))?;
+        if matches!(
+            response.status(),
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN
+        ) {
+            return Err(GeminiError::Backend(format!(
+                "{} rejected the API key",
+                url
+            )));
+        }
+
+        let config = setup.generation_config.as_ref();
+        let mut system = setup
+            .system_instruction
+            .as_ref()
+            .map(instruction_text)
+            .unwrap_or_default();
+        if let Some(schema) = config.and_then(|config| config.response_schema.as_ref()) {
+            system.push_str(&format!(
+                "\n\nAnswer with a single JSON object, without Markdown, that follows this JSON \
+                 schema:\n{}",
+                json_schema(schema)
+            ));
+        }
+        Ok(Self::spawn(AnthropicChat {
+            client,
+            url: format!("{}/messages", url),
+            model: model.to_string(),
+            system: Some(system.trim().to_string()).filter(|system| !system.is_empty()),
+            max_tokens,
+            temperature: config.and_then(|config| config.temperature),
+            history: VecDeque::new(),
+        }))
+    }
+}
+
+/// Server-sent event of a streamed message, e.g. a `content_block_delta` with more text
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart {
+        message: StartedMessage,
+    },
+    ContentBlockDelta {
+        delta: Delta,
+    },
+    MessageDelta {
+        usage: Option<Usage>,
+    },
+    Error {
+        error: StreamError,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct StartedMessage {
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Delta {
+    TextDelta {
+        text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    input_tokens: Option<i32>,
+    output_tokens: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct StreamError {
+    message: String,
+}
+
+/// `/messages` of the Anthropic API
+struct AnthropicChat {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+    /// Includes the response schema, if there is one
+    system: Option<String>,
+    max_tokens: u32,
+    temperature: Option<f32>,
+    /// Earlier user and assistant messages, oldest first, without images
+    history: VecDeque<Value>,
+}
+
+impl ChatModel for AnthropicChat {
+    const NAME: &'static str = "Anthropic";
+
+    fn ask<'a>(
+        &'a mut self,
+        text: &'a str,
+        images: Vec<Blob>,
+        deltas: &'a mut Deltas,
+    ) -> BoxFuture<'a, Result<ChatAnswer>> {
+        Box::pin(async move {
+            // Images go first, as the Messages API recommends
+            let mut content: Vec<Value> = images
+                .into_iter()
+                .map(|blob| {
+                    let media_type = blob.mime_type.unwrap_or_else(|| "image/jpeg".to_string());
+                    json!({
+                        "type": "image",
+                        "source": { "type": "base64", "media_type": media_type, "data": blob.data },
+                    })
+                })
+                .collect();
+            if !text.is_empty() {
+                content.push(json!({ "type": "text", "text": text }));
+            }
+            let mut messages: Vec<Value> = self.history.iter().cloned().collect();
+            messages.push(json!({ "role": "user", "content": content }));
+
+            let mut body = json!({
+                "model": self.model,
+                "max_tokens": self.max_tokens,
+                "messages": messages,
+                "stream": true,
+            });
+            if let Some(system) = &self.system {
+                body["system"] = json!(system);
+            }
+            if let Some(temperature) = self.temperature {
+                body["temperature"] = json!(temperature);
+            }
+            let mut response = self
+                .client
+                .post(&self.url)
+                .json(&body)
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .map_err(backend_error)?;
+
+            let mut answer = ChatAnswer {
+                text: String::new(),
+                prompt_tokens: None,
+                response_tokens: None,
+            };
+            let mut pending = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(backend_error)? {
+                pending.extend_from_slice(&chunk);
+                // Events end with a blank line; the last one may still be incomplete
+                while let Some(end) = pending.windows(2).position(|pair| pair == b"\n\n") {
+                    let event: Vec<u8> = pending.drain(..end + 2).collect();
+                    let event = String::from_utf8_lossy(&event);
+                    for data in event.lines().filter_map(|line| line.strip_prefix("data:")) {
+                        match serde_json::from_str(data.trim()) {
+                            Ok(StreamEvent::MessageStart { message }) => {
+                                if let Some(usage) = message.usage {
+                                    answer.prompt_tokens = usage.input_tokens;
+                                }
+                            }
+                            Ok(StreamEvent::ContentBlockDelta {
+                                delta: Delta::TextDelta { text },
+                            }) => {
+                                deltas.send(&text).await;
+                                answer.text.push_str(&text);
+                            }
+                            Ok(StreamEvent::MessageDelta { usage: Some(usage) }) => {
+                                answer.response_tokens = usage.output_tokens;
+                            }
+                            Ok(StreamEvent::Error { error }) => {
+                                return Err(GeminiError::Backend(error.message));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+
+            // The API rejects empty messages, so a frame without a prompt or answer is left out
+            if !text.is_empty() && !answer.text.is_empty() {
+                self.history
+                    .push_back(json!({ "role": "user", "content": text }));
+                self.history
+                    .push_back(json!({ "role": "assistant", "content": answer.text }));
+                trim_history(&mut self.history);
+            }
+            Ok(answer)
+        })
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{list_windows, Rect, WindowInfo};
+use std::collections::HashMap;
+
+/// Apps during which nothing is captured at all: no frame is saved or sent while one is in
+/// front or has a window in the captured area
+///
+/// Unlike redaction, which masks the app's windows, this leaves out whole frames, so nothing
+/// around the app, e.g. a notification quoting it, is seen either.
+#[derive(Debug, Default)]
+pub struct AppExclusion {
+    /// Lowercase bundle IDs, e.g. "com.1password.1password", or parts of app names
+    apps: Vec<String>,
+    capture_region: Option<Rect>,
+    /// Bundle IDs looked up so far, by process
+    bundle_ids: parking_lot::Mutex<HashMap<u32, Option<String>>>,
+}
+
+impl AppExclusion {
+    /// Excludes apps whose bundle ID is one of `apps` or whose name contains one, ignoring
+    /// case
+    pub fn new(apps: Vec<String>) -> Self {
+        Self {
+            apps: apps.into_iter().map(|app| app.to_lowercase()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Frames only show this region, in points from the top-left of the display, as set
+    /// with `CaptureOptions::region`
+    pub fn with_capture_region(mut self, region: Rect) -> Self {
+        self.capture_region = Some(region);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.apps.is_empty()
+    }
+
+    /// Whether `window` belongs to an excluded app
+    pub fn excludes(&self, window: &WindowInfo) -> bool {
+        let name = window.app_name.to_lowercase();
+        let bundle_id = self
+            .bundle_ids
+            .lock()
+            .entry(window.pid)
+            .or_insert_with(|| bundle_identifier(window.pid))
+            .as_deref()
+            .map(str::to_lowercase);
+        self.apps
+            .iter()
+            .any(|app| name.contains(app.as_str()) || bundle_id.as_deref() == Some(app.as_str()))
+    }
+
+    /// Name of an excluded app that is in front or shows a window in the part of `displays`
+    /// that is captured, e.g. all of them for a composite frame; without displays, e.g. for
+    /// a window capture, only the app in front counts
+    pub fn blocking_app(
+        &self,
+        frontmost: Option<&WindowInfo>,
+        displays: &[Rect],
+    ) -> Option<String> {
+        if let Some(window) = frontmost.filter(|window| self.excludes(window)) {
+            return Some(window.app_name.clone());
+        }
+        if displays.is_empty() {
+            return None;
+        }
+        let areas: Vec<Rect> = displays
+            .iter()
+            .map(|display| match self.capture_region {
+                Some(region) => Rect {
+                    x: display.x + region.x,
+                    y: display.y + region.y,
+                    ..region
+                },
+                None => *display,
+            })
+            .collect();
+        list_windows()
+            .into_iter()
+            .filter(WindowInfo::is_normal)
+            .find(|window| {
+                areas
+                    .iter()
+                    .any(|area| window.bounds.intersection(area).is_some())
+                    && self.excludes(window)
+            })
+            .map(|window| window.app_name)
+    }
+}
+
+/// Bundle ID of the app running as process `pid`, read from the Info.plist of the `.app`
+/// bundle its executable is in
+#[cfg(target_os = "macos")]
+pub fn bundle_identifier(pid: u32) -> Option<String> {
+    use core_foundation::bundle::CFBundle;
+    use core_foundation::string::CFString;
+    use core_foundation::url::CFURL;
+    use std::path::Path;
+
+    let mut buffer = vec![0u8; libc::PROC_PIDPATHINFO_MAXSIZE as usize];
+    let length = unsafe {
+        libc::proc_pidpath(
+            pid as libc::c_int,
+            buffer.as_mut_ptr().cast(),
+            buffer.len() as u32,
+        )
+    };
+    if length <= 0 {
+        return None;
+    }
+    let executable = String::from_utf8_lossy(&buffer[..length as usize]).into_owned();
+    // The outermost bundle, as helpers live in bundles nested inside the app
+    let bundle = Path::new(&executable)
+        .ancestors()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "app"))
+        .last()?;
+    let bundle = CFBundle::new(CFURL::from_path(bundle, true)?)?;
+    let identifier = bundle
+        .info_dictionary()
+        .find(&CFString::from_static_string("CFBundleIdentifier"))?
+        .downcast::<CFString>()?
+        .to_string();
+    Some(identifier)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn bundle_identifier(_pid: u32) -> Option<String> {
+    None
+}
@@ -0,0 +1,207 @@
+use crate::{pcm16_to_f32, pcm_sample_rate, Part, Resampler};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Sample rate of the PCM audio produced by Gemini when no rate is given in the MIME type
+pub const AUDIO_OUTPUT_SAMPLE_RATE: u32 = 24_000;
+
+/// Audio buffered before playback starts or resumes after an underrun (150 ms)
+const PREBUFFER_SECONDS: f32 = 0.15;
+
+#[derive(Debug, Error)]
+pub enum PlaybackError {
+    #[error("No audio output device available")]
+    NoOutputDevice,
+    #[error("Failed to query output config: {0}")]
+    DefaultConfig(#[from] cpal::DefaultStreamConfigError),
+    #[error("Failed to build output stream: {0}")]
+    BuildStream(#[from] cpal::BuildStreamError),
+    #[error("Failed to start output stream: {0}")]
+    PlayStream(#[from] cpal::PlayStreamError),
+    #[error("Unsupported sample format: {0:?}")]
+    UnsupportedFormat(SampleFormat),
+    #[error("Audio thread exited before the stream started")]
+    ThreadExited,
+}
+
+pub type PlaybackResult<T> = std::result::Result<T, PlaybackError>;
+
+/// Mono samples at the device rate waiting to be played
+struct PlaybackBuffer {
+    samples: VecDeque<f32>,
+    prebuffering: bool,
+}
+
+/// Resampling state for the PCM stream currently being queued
+struct InputState {
+    sample_rate: u32,
+    resampler: Resampler,
+}
+
+/// Plays model speech on the default output device
+pub struct AudioSink {
+    buffer: Arc<parking_lot::Mutex<PlaybackBuffer>>,
+    input: parking_lot::Mutex<Option<InputState>>,
+    output_rate: u32,
+    _stop: std::sync::mpsc::Sender<()>,
+}
+
+impl AudioSink {
+    /// Opens the system default output device
+    pub fn to_default_output() -> PlaybackResult<Self> {
+        let buffer = Arc::new(parking_lot::Mutex::new(PlaybackBuffer {
+            samples: VecDeque::new(),
+            prebuffering: true,
+        }));
+        let stream_buffer = Arc::clone(&buffer);
+        let (stop_tx, stop_rx) = std::sync::mpsc::channel::<()>();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<PlaybackResult<u32>>();
+
+        // cpal streams are not Send on every platform, so the stream lives on its own thread
+        std::thread::spawn(move || {
+            let (stream, output_rate) = match build_output_stream(stream_buffer) {
+                Ok(built) => built,
+                Err(err) => {
+                    let _ = ready_tx.send(Err(err));
+                    return;
+                }
+            };
+            if let Err(err) = stream.play() {
+                let _ = ready_tx.send(Err(err.into()));
+                return;
+            }
+            let _ = ready_tx.send(Ok(output_rate));
+
+            // Keep the stream alive until the sink is dropped
+            let _ = stop_rx.recv();
+        });
+
+        let output_rate = ready_rx.recv().map_err(|_| PlaybackError::ThreadExited)??;
+
+        Ok(Self {
+            buffer,
+            input: parking_lot::Mutex::new(None),
+            output_rate,
+            _stop: stop_tx,
+        })
+    }
+
+    /// Queues 16-bit little-endian mono PCM recorded at `sample_rate`
+    pub fn push_pcm(&self, pcm: &[u8], sample_rate: u32) {
+        let mut input = self.input.lock();
+        let state = match input.as_mut() {
+            Some(state) if state.sample_rate == sample_rate => state,
+            _ => {
+                let Some(resampler) = Resampler::new(sample_rate, 1, self.output_rate) else {
+                    return;
+                };
+                input.insert(InputState {
+                    sample_rate,
+                    resampler,
+                })
+            }
+        };
+
+        let mut resampled = Vec::new();
+        state.resampler.process(&pcm16_to_f32(pcm), &mut resampled);
+        self.buffer.lock().samples.extend(resampled);
+    }
+
+    /// Queues the audio carried by a model part, returning false for non-audio parts
+    pub fn play_part(&self, part: &Part) -> bool {
+        let Some(blob) = part.inline_data() else {
+            return false;
+        };
+        let mime_type = blob.mime_type.as_deref().unwrap_or_default();
+        if !mime_type.starts_with("audio/pcm") {
+            return false;
+        }
+        let Some(pcm) = blob.decode() else {
+            return false;
+        };
+
+        let sample_rate = pcm_sample_rate(mime_type).unwrap_or(AUDIO_OUTPUT_SAMPLE_RATE);
+        self.push_pcm(&pcm, sample_rate);
+        true
+    }
+
+    /// Drops any queued speech, e.g. when the user interrupts the model
+    pub fn interrupt(&self) {
+        let mut buffer = self.buffer.lock();
+        buffer.samples.clear();
+        buffer.prebuffering = true;
+        *self.input.lock() = None;
+    }
+
+    /// Returns true while queued speech remains to be played
+    pub fn is_playing(&self) -> bool {
+        !self.buffer.lock().samples.is_empty()
+    }
+}
+
+fn build_output_stream(
+    buffer: Arc<parking_lot::Mutex<PlaybackBuffer>>,
+) -> PlaybackResult<(cpal::Stream, u32)> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or(PlaybackError::NoOutputDevice)?;
+    let supported = device.default_output_config()?;
+    let format = supported.sample_format();
+    let config = supported.config();
+
+    let stream = match format {
+        SampleFormat::F32 => build_typed_stream::<f32>(&device, &config, buffer),
+        SampleFormat::I16 => build_typed_stream::<i16>(&device, &config, buffer),
+        SampleFormat::U16 => build_typed_stream::<u16>(&device, &config, buffer),
+        SampleFormat::I32 => build_typed_stream::<i32>(&device, &config, buffer),
+        other => Err(PlaybackError::UnsupportedFormat(other)),
+    }?;
+    Ok((stream, config.sample_rate.0))
+}
+
+fn build_typed_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    buffer: Arc<parking_lot::Mutex<PlaybackBuffer>>,
+) -> PlaybackResult<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels.max(1) as usize;
+    let prebuffer = (config.sample_rate.0 as f32 * PREBUFFER_SECONDS) as usize;
+
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            let mut buffer = buffer.lock();
+            if buffer.prebuffering && buffer.samples.len() >= prebuffer {
+                buffer.prebuffering = false;
+            }
+
+            for frame in data.chunks_mut(channels) {
+                let sample = if buffer.prebuffering {
+                    0.0
+                } else {
+                    match buffer.samples.pop_front() {
+                        Some(sample) => sample,
+                        None => {
+                            // Underrun: wait for a fresh prebuffer instead of crackling
+                            buffer.prebuffering = true;
+                            0.0
+                        }
+                    }
+                };
+                for slot in frame.iter_mut() {
+                    *slot = T::from_sample(sample);
+                }
+            }
+        },
+        |err| eprintln!("❌ Audio output error: {}", err),
+        None,
+    )?;
+
+    Ok(stream)
+}
@@ -0,0 +1,145 @@
+use crate::{f32_to_pcm16, AnalysisBackend, Resampler};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Sample rate expected by Gemini for realtime audio input
+pub const AUDIO_INPUT_SAMPLE_RATE: u32 = 16_000;
+
+/// Number of 16 kHz samples buffered before a chunk is emitted (100 ms)
+const CHUNK_SAMPLES: usize = 1_600;
+
+#[derive(Debug, Error)]
+pub enum AudioError {
+    #[error("No audio input device available")]
+    NoInputDevice,
+    #[error("Failed to query input config: {0}")]
+    DefaultConfig(#[from] cpal::DefaultStreamConfigError),
+    #[error("Failed to build input stream: {0}")]
+    BuildStream(#[from] cpal::BuildStreamError),
+    #[error("Failed to start input stream: {0}")]
+    PlayStream(#[from] cpal::PlayStreamError),
+    #[error("Unsupported sample format: {0:?}")]
+    UnsupportedFormat(SampleFormat),
+    #[error("Unsupported sample rate: {0} Hz")]
+    UnsupportedSampleRate(u32),
+    #[error("Audio thread exited before the stream started")]
+    ThreadExited,
+}
+
+pub type AudioResult<T> = std::result::Result<T, AudioError>;
+
+/// Captures microphone audio as 16 kHz mono little-endian PCM chunks
+pub struct AudioSource {
+    chunks: mpsc::Receiver<Vec<u8>>,
+    _stop: std::sync::mpsc::Sender<()>,
+}
+
+impl AudioSource {
+    /// Starts capturing from the system default input device
+    pub fn from_default_input() -> AudioResult<Self> {
+        let (chunk_tx, chunks) = mpsc::channel(64);
+        let (stop_tx, stop_rx) = std::sync::mpsc::channel::<()>();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<AudioResult<()>>();
+
+        // cpal streams are not Send on every platform, so the stream lives on its own thread
+        std::thread::spawn(move || {
+            let stream = match build_input_stream(chunk_tx) {
+                Ok(stream) => stream,
+                Err(err) => {
+                    let _ = ready_tx.send(Err(err));
+                    return;
+                }
+            };
+            if let Err(err) = stream.play() {
+                let _ = ready_tx.send(Err(err.into()));
+                return;
+            }
+            let _ = ready_tx.send(Ok(()));
+
+            // Keep the stream alive until the source is dropped
+            let _ = stop_rx.recv();
+        });
+
+        ready_rx.recv().map_err(|_| AudioError::ThreadExited)??;
+
+        Ok(Self {
+            chunks,
+            _stop: stop_tx,
+        })
+    }
+
+    /// Waits for the next PCM chunk, returning `None` once the stream has ended
+    pub async fn next_chunk(&mut self) -> Option<Vec<u8>> {
+        self.chunks.recv().await
+    }
+
+    /// Spawns a task that streams every chunk to Gemini, or another backend, via `send_audio`
+    pub fn spawn_forwarder(mut self, sender: impl AnalysisBackend + 'static) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            while let Some(chunk) = self.next_chunk().await {
+                if let Err(err) = sender.send_audio(&chunk).await {
+                    eprintln!("❌ Error sending audio to Gemini: {}", err);
+                    break;
+                }
+            }
+        })
+    }
+}
+
+fn build_input_stream(chunk_tx: mpsc::Sender<Vec<u8>>) -> AudioResult<cpal::Stream> {
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or(AudioError::NoInputDevice)?;
+    let supported = device.default_input_config()?;
+    let format = supported.sample_format();
+    let config = supported.config();
+
+    match format {
+        SampleFormat::F32 => build_typed_stream::<f32>(&device, &config, chunk_tx),
+        SampleFormat::I16 => build_typed_stream::<i16>(&device, &config, chunk_tx),
+        SampleFormat::U16 => build_typed_stream::<u16>(&device, &config, chunk_tx),
+        SampleFormat::I32 => build_typed_stream::<i32>(&device, &config, chunk_tx),
+        other => Err(AudioError::UnsupportedFormat(other)),
+    }
+}
+
+fn build_typed_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    chunk_tx: mpsc::Sender<Vec<u8>>,
+) -> AudioResult<cpal::Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let mut resampler = Resampler::new(
+        config.sample_rate.0,
+        config.channels,
+        AUDIO_INPUT_SAMPLE_RATE,
+    )
+    .ok_or(AudioError::UnsupportedSampleRate(config.sample_rate.0))?;
+    let mut converted = Vec::new();
+    let mut pending = Vec::with_capacity(CHUNK_SAMPLES);
+
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            converted.clear();
+            converted.extend(data.iter().map(|sample| sample.to_sample::<f32>()));
+            resampler.process(&converted, &mut pending);
+
+            while pending.len() >= CHUNK_SAMPLES {
+                let chunk = f32_to_pcm16(pending.drain(..CHUNK_SAMPLES));
+                // Drop audio rather than block the realtime callback when the consumer lags
+                let _ = chunk_tx.try_send(chunk);
+            }
+        },
+        |err| eprintln!("❌ Audio input error: {}", err),
+        None,
+    )?;
+
+    Ok(stream)
+}
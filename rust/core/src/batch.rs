@@ -0,0 +1,100 @@
+use crate::{ClientContent, Content, Part};
+use std::time::{Duration, Instant};
+
+/// Question sent after a batch of screenshots
+pub const BATCH_PROMPT: &str = "These screenshots were taken in order over the period \
+     described above. Describe what the user did over this period, including how their \
+     activity changed between screenshots.";
+
+/// When a CaptureSession sends the frames it has accumulated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchOptions {
+    /// Frames per turn
+    pub max_frames: usize,
+    /// Longest time to hold back the first frame of a batch, if any
+    pub max_age: Option<Duration>,
+}
+
+impl BatchOptions {
+    pub fn frames(max_frames: usize) -> Self {
+        Self {
+            max_frames: max_frames.max(1),
+            max_age: None,
+        }
+    }
+
+    /// Also sends the batch once its first frame is `max_age` old
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+}
+
+/// Screenshots and their captions waiting to be sent as one user turn
+#[derive(Debug)]
+pub struct FrameBatch {
+    options: BatchOptions,
+    parts: Vec<Part>,
+    frames: usize,
+    started: Option<Instant>,
+}
+
+impl FrameBatch {
+    pub fn new(options: BatchOptions) -> Self {
+        Self {
+            options,
+            parts: Vec::new(),
+            frames: 0,
+            started: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// Adds a screenshot with a caption placing it in the batch; returns true once the batch
+    /// is full or old enough to send
+    pub fn push(&mut self, image: Part, caption: &str) -> bool {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        self.frames += 1;
+        let heading = format!(
+            "Screenshot {} ({:.0}s into the period).",
+            self.frames,
+            started.elapsed().as_secs_f64()
+        );
+        let caption = if caption.is_empty() {
+            heading
+        } else {
+            format!("{} {}", heading, caption)
+        };
+        self.parts.push(Part::text(caption));
+        self.parts.push(image);
+
+        self.frames >= self.options.max_frames
+            || self
+                .options
+                .max_age
+                .is_some_and(|max_age| started.elapsed() >= max_age)
+    }
+
+    /// The accumulated screenshots as one turn ending with `prompt`; `None` when empty
+    pub fn take(&mut self, prompt: &str) -> Option<ClientContent> {
+        let started = self.started.take()?;
+        let frames = std::mem::take(&mut self.frames);
+        let mut parts = vec![Part::text(format!(
+            "{} screenshots taken over the last {:.0} seconds follow.",
+            frames,
+            started.elapsed().as_secs_f64()
+        ))];
+        parts.append(&mut self.parts);
+        parts.push(Part::text(prompt));
+        Some(ClientContent {
+            turns: vec![Content {
+                role: Some("user".to_string()),
+                parts,
+            }],
+            turn_complete: Some(true),
+        })
+    }
+}
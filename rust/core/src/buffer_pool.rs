@@ -0,0 +1,84 @@
+use crate::FrameData;
+use std::sync::Arc;
+
+/// Buffers kept for reuse when the caller does not pick a limit
+const DEFAULT_MAX_BUFFERS: usize = 8;
+
+struct PoolInner {
+    buffers: parking_lot::Mutex<Vec<Vec<u8>>>,
+    max_buffers: usize,
+}
+
+/// Shared free list of byte buffers, so per-frame pixel, conversion and JPEG buffers are
+/// recycled instead of reallocated
+#[derive(Clone)]
+pub struct BufferPool {
+    inner: Arc<PoolInner>,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BUFFERS)
+    }
+}
+
+impl BufferPool {
+    /// Creates a pool that keeps at most `max_buffers` idle buffers
+    pub fn new(max_buffers: usize) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                buffers: parking_lot::Mutex::new(Vec::with_capacity(max_buffers)),
+                max_buffers,
+            }),
+        }
+    }
+
+    /// Returns an empty buffer that can hold at least `capacity` bytes without reallocating
+    pub fn take(&self, capacity: usize) -> Vec<u8> {
+        let mut buffers = self.inner.buffers.lock();
+        // Smallest idle buffer that is large enough, so big frame buffers stay available
+        let best = buffers
+            .iter()
+            .enumerate()
+            .filter(|(_, buffer)| buffer.capacity() >= capacity)
+            .min_by_key(|(_, buffer)| buffer.capacity())
+            .map(|(index, _)| index);
+        match best {
+            Some(index) => {
+                let mut buffer = buffers.swap_remove(index);
+                buffer.clear();
+                buffer
+            }
+            None => Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Hands a buffer back for reuse; dropped when the pool is already full
+    pub fn recycle(&self, buffer: Vec<u8>) {
+        if buffer.capacity() == 0 {
+            return;
+        }
+        let mut buffers = self.inner.buffers.lock();
+        if buffers.len() < self.inner.max_buffers {
+            buffers.push(buffer);
+        } else if let Some(smallest) = buffers
+            .iter_mut()
+            .min_by_key(|idle| idle.capacity())
+            .filter(|idle| idle.capacity() < buffer.capacity())
+        {
+            *smallest = buffer;
+        }
+    }
+
+    /// Recycles the pixel buffer of a frame once nothing else references it
+    pub fn recycle_frame(&self, frame: Arc<FrameData>) {
+        if let Ok(frame) = Arc::try_unwrap(frame) {
+            self.recycle(frame.data);
+        }
+    }
+
+    /// Number of idle buffers currently held
+    pub fn idle(&self) -> usize {
+        self.inner.buffers.lock().len()
+    }
+}
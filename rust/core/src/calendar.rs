@@ -0,0 +1,217 @@
+use crate::clock_time;
+use std::time::{Duration, Instant, SystemTime};
+use thiserror::Error;
+
+/// How long a looked-up event is reused before the calendar is asked again
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long to wait for the user to answer the calendar access prompt
+const ACCESS_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Error)]
+pub enum CalendarError {
+    #[error("Calendar access is only available on macOS")]
+    Unsupported,
+    #[error("Calendar access not granted")]
+    PermissionDenied,
+}
+
+pub type CalendarResult<T> = std::result::Result<T, CalendarError>;
+
+/// A timed calendar event the user is busy with
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarEvent {
+    pub title: String,
+    pub ends_at: SystemTime,
+}
+
+impl CalendarEvent {
+    /// Prompt sentence about the event, so a shared screen in a meeting isn't taken for solo
+    /// work
+    pub fn describe(&self) -> String {
+        format!(
+            "The user's calendar shows '{}' until {}, so they may be in a meeting, e.g. \
+             presenting or watching a shared screen.",
+            self.title,
+            clock_time(self.ends_at)
+        )
+    }
+}
+
+/// Reads the current event from the macOS calendars through EventKit
+///
+/// All-day events and events marked as free are skipped, as they don't say what the user
+/// is doing right now.
+pub struct Calendar {
+    store: platform::EventStore,
+    /// Last lookup and when it was made
+    cached: parking_lot::Mutex<Option<(Instant, Option<CalendarEvent>)>>,
+}
+
+impl Calendar {
+    /// Asks for calendar access the first time, waiting for the user to answer
+    pub fn connect() -> CalendarResult<Self> {
+        let store = platform::EventStore::new()?;
+        if !store.request_access(ACCESS_TIMEOUT) {
+            return Err(CalendarError::PermissionDenied);
+        }
+        Ok(Self {
+            store,
+            cached: parking_lot::Mutex::new(None),
+        })
+    }
+
+    /// Event happening now; the calendar is asked at most once per `REFRESH_INTERVAL`
+    pub fn current_event(&self) -> Option<CalendarEvent> {
+        let mut cached = self.cached.lock();
+        let event = match &*cached {
+            Some((at, event)) if at.elapsed() < REFRESH_INTERVAL => event.clone(),
+            _ => {
+                let event = self
+                    .store
+                    .current_event()
+                    .map(|(title, ends_at)| CalendarEvent { title, ends_at });
+                *cached = Some((Instant::now(), event.clone()));
+                event
+            }
+        };
+        event.filter(|event| event.ends_at > SystemTime::now())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{CalendarError, CalendarResult};
+    use block::ConcreteBlock;
+    use objc::runtime::{Object, BOOL, YES};
+    use objc::{class, msg_send, sel, sel_impl};
+    use std::ffi::{c_char, CStr};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[allow(non_camel_case_types)]
+    type id = *mut Object;
+
+    const NIL: id = std::ptr::null_mut();
+
+    /// EKEntityTypeEvent
+    const ENTITY_TYPE_EVENT: usize = 0;
+
+    /// EKEventAvailabilityFree
+    const AVAILABILITY_FREE: isize = 1;
+
+    #[link(name = "EventKit", kind = "framework")]
+    unsafe extern "C" {}
+
+    /// A retained EKEventStore
+    pub struct EventStore(id);
+
+    // EKEventStore may be used from any thread
+    unsafe impl Send for EventStore {}
+    unsafe impl Sync for EventStore {}
+
+    impl EventStore {
+        pub fn new() -> CalendarResult<Self> {
+            let store: id = unsafe { msg_send![class!(EKEventStore), new] };
+            if store.is_null() {
+                return Err(CalendarError::Unsupported);
+            }
+            Ok(Self(store))
+        }
+
+        /// Whether the user granted access, asking them if they haven't answered yet
+        pub fn request_access(&self, timeout: Duration) -> bool {
+            let (granted_tx, granted) = std::sync::mpsc::channel();
+            let handler = ConcreteBlock::new(move |granted: BOOL, _error: id| {
+                granted_tx.send(granted == YES).ok();
+            })
+            .copy();
+            objc::rc::autoreleasepool(|| unsafe {
+                // macOS 14 split full access from adding events
+                let full: BOOL = msg_send![self.0, respondsToSelector:
+                                           sel!(requestFullAccessToEventsWithCompletion:)];
+                if full == YES {
+                    let _: () = msg_send![self.0, requestFullAccessToEventsWithCompletion:
+                                                  &*handler];
+                } else {
+                    let _: () = msg_send![self.0, requestAccessToEntityType: ENTITY_TYPE_EVENT
+                                                                 completion: &*handler];
+                }
+            });
+            granted.recv_timeout(timeout).unwrap_or(false)
+        }
+
+        /// Title and end of the first busy, timed event going on now
+        pub fn current_event(&self) -> Option<(String, SystemTime)> {
+            // Tokio threads have no autorelease pool of their own
+            objc::rc::autoreleasepool(|| unsafe {
+                let now: id = msg_send![class!(NSDate), date];
+                let soon: id = msg_send![class!(NSDate), dateWithTimeIntervalSinceNow: 1.0f64];
+                let predicate: id = msg_send![self.0, predicateForEventsWithStartDate: now
+                                                                              endDate: soon
+                                                                            calendars: NIL];
+                let events: id = msg_send![self.0, eventsMatchingPredicate: predicate];
+                let count: usize = msg_send![events, count];
+                for i in 0..count {
+                    let event: id = msg_send![events, objectAtIndex: i];
+                    let all_day: BOOL = msg_send![event, isAllDay];
+                    let availability: isize = msg_send![event, availability];
+                    if all_day == YES || availability == AVAILABILITY_FREE {
+                        continue;
+                    }
+                    let title: id = msg_send![event, title];
+                    let end: id = msg_send![event, endDate];
+                    let end: f64 = msg_send![end, timeIntervalSince1970];
+                    let Some(title) = rust_string(title).filter(|title| !title.is_empty()) else {
+                        continue;
+                    };
+                    return Some((title, UNIX_EPOCH + Duration::from_secs_f64(end.max(0.0))));
+                }
+                None
+            })
+        }
+    }
+
+    impl Drop for EventStore {
+        fn drop(&mut self) {
+            let _: () = unsafe { msg_send![self.0, release] };
+        }
+    }
+
+    /// Copy of an NSString, `None` for nil
+    unsafe fn rust_string(string: id) -> Option<String> {
+        if string.is_null() {
+            return None;
+        }
+        let utf8: *const c_char = unsafe { msg_send![string, UTF8String] };
+        if utf8.is_null() {
+            return None;
+        }
+        Some(
+            unsafe { CStr::from_ptr(utf8) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::{CalendarError, CalendarResult};
+    use std::time::{Duration, SystemTime};
+
+    pub struct EventStore;
+
+    impl EventStore {
+        pub fn new() -> CalendarResult<Self> {
+            Err(CalendarError::Unsupported)
+        }
+
+        pub fn request_access(&self, _timeout: Duration) -> bool {
+            false
+        }
+
+        pub fn current_event(&self) -> Option<(String, SystemTime)> {
+            None
+        }
+    }
+}
@@ -1,91 +1,1783 @@
+#[cfg(feature = "sqlite")]
+use crate::ActivityStore;
+#[cfg(feature = "calendar")]
+use crate::Calendar;
 use crate::{
-    encode_bgra_to_jpeg_bytes, ClientContent, Content, FrameSource, GeminiSender, Part,
-    ResponsePrinter,
+    clock_time, composite_frames, crop_to_bounds, cursor_position, display_bounds,
+    encode_bgra_to_jpeg_bytes_pooled, list_displays, list_windows, main_display_bounds,
+    main_display_id, passthrough, session_stamp, unix_millis, ActivityCounts, ActivityMeter,
+    AnalysisBackend, AnalysisResult, Anonymizer, AppExclusion, BatchOptions, CaptureError,
+    CaptureOptions, CaptureResult, ChangeDetector, ClientContent, ClipboardContext, Content,
+    DisplayMode, FrameBatch, FrameData, FrameDeduplicator, FrameFormat, FrameRecord, FrameSource,
+    GeminiError, ImageMetadata, JpegError, ManifestEntry, ManifestWriter, MenuEvent, MenuEventKind,
+    OfflineQueue, Part, PointerTracker, PreviewFeed, QueuedTurn, Rect, Redactor, ResizeOptions,
+    ResizeTarget, ResourceGovernor, ResourceLimits, ResponsePrinter, ResponseRecord,
+    RetentionManager, Stage, Telemetry, Throttle, TurnTracker, ZoomFollow, AGGREGATE_PROMPT,
+    ANNOTATION_REQUEST, BATCH_PROMPT, MENU_PROMPT, ZOOM_NARRATION_PROMPT,
 };
+#[cfg(feature = "ocr")]
+use crate::{ocr_prompt, TextRecognizer};
 use base64::Engine;
-use serde_json::json;
+use derive_builder::Builder;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use thiserror::Error;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+/// A frame that couldn't be captured, encoded, saved or sent; the session goes on with the
+/// next one
+#[derive(Debug, Error)]
+pub enum FrameError {
+    #[error("Error getting frame {index}: {source}")]
+    Capture { index: usize, source: CaptureError },
+    #[error("Error encoding frame {index}: {source}")]
+    Encode { index: usize, source: JpegError },
+    #[error("Error saving frame {index}: {source}")]
+    Save {
+        index: usize,
+        source: std::io::Error,
+    },
+    #[error("Error sending frame {index} to Gemini: {source}")]
+    Send { index: usize, source: GeminiError },
+}
+
+/// File name of saved frames unless configured otherwise, e.g.
+/// frame_20261014-093000_0001.jpg; naming the session keeps earlier sessions' frames
+pub const DEFAULT_FILENAME_PATTERN: &str = "frame_{session}_{index}.{ext}";
+
+/// Saved frames remembered for adding the model's answer to their metadata
+const DESCRIBED_FRAMES: usize = 32;
+
+/// Question sent with each frame when neither a template nor a mode provides one
+pub const DEFAULT_FRAME_PROMPT: &str = "What is the user doing in this screenshot?";
+
+/// Sent with a screenshot the model asked for with the `take_screenshot` tool
+const REQUESTED_SCREENSHOT_PROMPT: &str = "Here is the screenshot you asked for.";
+
+/// How a CaptureSession words its prompts and encodes and stores frames
+#[derive(Debug, Clone, Builder)]
+#[builder(pattern = "owned")]
+pub struct SessionOptions {
+    /// Directory frames are saved in
+    #[builder(setter(into), default = "\"output\".to_string()")]
+    pub output_dir: String,
+    /// Question sent with each frame instead of the default of the mode; `{index}`, `{time}`
+    /// and `{app}` stand for the frame number, the time as HH:MM and the active app
+    #[builder(setter(into, strip_option), default)]
+    pub prompt_template: Option<String>,
+    /// Context put in front of every prompt, e.g. what the user is working on
+    #[builder(setter(into, strip_option), default)]
+    pub preamble: Option<String>,
+    #[builder(default)]
+    pub format: FrameFormat,
+    /// JPEG quality from 1 to 100; throttling may lower it further
+    #[builder(default = "90")]
+    pub quality: u8,
+    /// Whether frames are written to `output_dir`; they are sent either way
+    #[builder(default = "true")]
+    pub save_frames: bool,
+    /// Saved file name; `{index}` stands for the frame number padded to four digits,
+    /// `{session}` for the session name and `{ext}` for the extension of the format
+    #[builder(setter(into), default = "DEFAULT_FILENAME_PATTERN.to_string()")]
+    pub filename_pattern: String,
+    /// Whether saved JPEGs carry EXIF and XMP metadata about the capture
+    #[builder(default = "true")]
+    pub embed_metadata: bool,
+    /// Name of the session in file names, by default its start time as YYYYMMDD-HHMMSS
+    #[builder(setter(into), default = "session_stamp(SystemTime::now())")]
+    pub session: String,
+}
+
+impl SessionOptions {
+    pub fn builder() -> SessionOptionsBuilder {
+        SessionOptionsBuilder::default()
+    }
+
+    /// Path frame `index` is saved at
+    pub fn frame_path(&self, index: usize) -> String {
+        let name = self
+            .filename_pattern
+            .replace("{index}", &format!("{:04}", index))
+            .replace("{session}", &self.session)
+            .replace("{ext}", self.format.extension());
+        format!("{}/{}", self.output_dir, name)
+    }
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self::builder()
+            .build()
+            .expect("all session options have defaults")
+    }
+}
 
 pub struct CaptureSession {
     frame_source: FrameSource,
-    sender: GeminiSender,
-    _printer: Arc<dyn ResponsePrinter>,
-    output_dir: String,
+    sender: parking_lot::RwLock<Arc<dyn AnalysisBackend>>,
+    printer: Arc<dyn ResponsePrinter>,
+    options: SessionOptions,
+    zoom_follow: parking_lot::Mutex<Option<ZoomFollow>>,
+    prompt: parking_lot::RwLock<Option<String>>,
+    aggregate_only: bool,
+    annotate: bool,
+    resize: Option<ResizeOptions>,
+    menu_captures: AtomicUsize,
+    on_demand_captures: AtomicUsize,
+    /// Calls to `pause`, including ones while already paused
+    pause_requests: AtomicUsize,
+    /// Set while the screen is locked; nothing is sent to Gemini then
+    locked: AtomicBool,
+    anonymizer: Arc<dyn Anonymizer>,
+    governor: Option<parking_lot::Mutex<ResourceGovernor>>,
+    change_detector: Option<parking_lot::Mutex<ChangeDetector>>,
+    deduplicator: Option<parking_lot::Mutex<FrameDeduplicator>>,
+    batch: Option<parking_lot::Mutex<FrameBatch>>,
+    errors: Option<UnboundedSender<FrameError>>,
+    retention: Option<parking_lot::Mutex<RetentionManager>>,
+    manifest: Option<ManifestWriter>,
+    #[cfg(feature = "sqlite")]
+    activity_store: Option<Arc<ActivityStore>>,
+    /// Recently saved frames with the metadata embedded into them, oldest first
+    saved: parking_lot::Mutex<VecDeque<(usize, String, ImageMetadata)>>,
+    turns: Option<Arc<TurnTracker>>,
+    telemetry: Option<Arc<Telemetry>>,
+    preview: Option<PreviewFeed>,
+    displays: Vec<FrameSource>,
+    display_mode: DisplayMode,
+    pointer: Option<PointerTracker>,
+    activity: Option<ActivityMeter>,
+    redactor: Option<Redactor>,
+    exclusion: Option<AppExclusion>,
+    /// When frames started being left out for an excluded app, while they are
+    excluded_since: parking_lot::Mutex<Option<SystemTime>>,
+    clipboard: Option<Arc<ClipboardContext>>,
+    /// Turns kept while the connection is down, sent once it is back
+    offline_queue: Option<OfflineQueue>,
+    /// Held while queued turns are being sent
+    flushing: tokio::sync::Mutex<()>,
+    #[cfg(feature = "ocr")]
+    ocr: Option<TextRecognizer>,
+    #[cfg(feature = "calendar")]
+    calendar: Option<Calendar>,
+}
+
+/// Inline screenshot part
+fn image_part(image_bytes: &[u8], format: FrameFormat) -> Part {
+    // Encode to base64 for Gemini
+    let base64_image = base64::engine::general_purpose::STANDARD.encode(image_bytes);
+    Part::json(json!({
+        "inline_data": {
+            "mime_type": format.mime_type(),
+            "data": base64_image
+        }
+    }))
+}
+
+/// User turn carrying one screenshot and its question
+fn image_turn(image_bytes: &[u8], format: FrameFormat, prompt: String) -> ClientContent {
+    // Send to Gemini with inline image data
+    ClientContent {
+        turns: vec![Content {
+            role: Some("user".to_string()),
+            parts: vec![image_part(image_bytes, format), Part::text(prompt)],
+        }],
+        turn_complete: Some(true),
+    }
 }
 
 impl CaptureSession {
     pub fn new(
         frame_source: FrameSource,
-        sender: GeminiSender,
+        sender: impl AnalysisBackend + 'static,
         printer: Arc<dyn ResponsePrinter>,
-        output_dir: String,
+        options: SessionOptions,
     ) -> Self {
         Self {
             frame_source,
-            sender,
-            _printer: printer,
-            output_dir,
+            sender: parking_lot::RwLock::new(Arc::new(sender)),
+            printer,
+            options,
+            zoom_follow: parking_lot::Mutex::new(None),
+            prompt: parking_lot::RwLock::new(None),
+            aggregate_only: false,
+            annotate: false,
+            resize: None,
+            menu_captures: AtomicUsize::new(0),
+            on_demand_captures: AtomicUsize::new(0),
+            pause_requests: AtomicUsize::new(0),
+            locked: AtomicBool::new(false),
+            anonymizer: passthrough(),
+            governor: None,
+            change_detector: None,
+            deduplicator: None,
+            batch: None,
+            errors: None,
+            retention: None,
+            manifest: None,
+            #[cfg(feature = "sqlite")]
+            activity_store: None,
+            saved: parking_lot::Mutex::new(VecDeque::new()),
+            turns: None,
+            telemetry: None,
+            preview: None,
+            displays: Vec::new(),
+            display_mode: DisplayMode::default(),
+            pointer: None,
+            activity: None,
+            redactor: None,
+            exclusion: None,
+            excluded_since: parking_lot::Mutex::new(None),
+            clipboard: None,
+            offline_queue: None,
+            flushing: tokio::sync::Mutex::new(()),
+            #[cfg(feature = "ocr")]
+            ocr: None,
+            #[cfg(feature = "calendar")]
+            calendar: None,
+        }
+    }
+
+    /// Restricts the session to aggregate statistics: frames and answers are never written
+    /// to disk, zoom-follow is disabled and every frame is sent with the fixed classification
+    /// prompt alone
+    ///
+    /// Whatever would store frames or answers or add context to prompts is dropped, before
+    /// or after this call: the offline queue, manifest, activity store, clipboard, calendar
+    /// and text recognition.
+    pub fn with_aggregation_mode(mut self) -> Self {
+        self.aggregate_only = true;
+        *self.zoom_follow.lock() = None;
+        *self.prompt.write() = None;
+        self.offline_queue = None;
+        self.manifest = None;
+        #[cfg(feature = "sqlite")]
+        {
+            self.activity_store = None;
+        }
+        self.clipboard = None;
+        #[cfg(feature = "ocr")]
+        {
+            self.ocr = None;
+        }
+        #[cfg(feature = "calendar")]
+        {
+            self.calendar = None;
         }
+        self
+    }
+
+    /// Downscales every frame before it is encoded and sent
+    pub fn with_resize(mut self, resize: ResizeOptions) -> Self {
+        self.resize = Some(resize);
+        self
+    }
+
+    /// Asks the model to return each screenshot with the elements it mentions outlined
+    pub fn with_annotations(mut self) -> Self {
+        self.annotate = !self.aggregate_only;
+        self
+    }
+
+    /// Rewrites app names and titles before they are logged or sent to the model
+    pub fn with_anonymizer(mut self, anonymizer: Arc<dyn Anonymizer>) -> Self {
+        self.anonymizer = anonymizer;
+        self
+    }
+
+    /// Lowers frame rate, resolution and JPEG quality while the watcher exceeds `limits`
+    pub fn with_resource_limits(mut self, limits: ResourceLimits) -> Self {
+        self.governor =
+            (!limits.is_empty()).then(|| parking_lot::Mutex::new(ResourceGovernor::new(limits)));
+        self
+    }
+
+    /// Skips frames that barely differ from the last frame sent, e.g. on an idle desktop
+    pub fn with_change_detection(mut self, detector: ChangeDetector) -> Self {
+        self.change_detector = Some(parking_lot::Mutex::new(detector));
+        self
+    }
+
+    /// Skips frames showing a screen that was already analyzed recently and tells Gemini it
+    /// is back instead
+    pub fn with_deduplication(mut self, deduplicator: FrameDeduplicator) -> Self {
+        self.deduplicator = Some(parking_lot::Mutex::new(deduplicator));
+        self
+    }
+
+    /// Collects frames and sends them together as one turn asking what happened over the
+    /// period, instead of one turn per frame
+    pub fn with_batching(mut self, options: BatchOptions) -> Self {
+        self.batch = Some(parking_lot::Mutex::new(FrameBatch::new(options)));
+        self
+    }
+
+    /// Delivers frames that fail to the channel instead of printing them to stderr
+    pub fn with_error_channel(mut self, errors: UnboundedSender<FrameError>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// Prunes the oldest saved frames after each one written, as `retention` allows
+    pub fn with_retention(mut self, retention: RetentionManager) -> Self {
+        self.retention = Some(parking_lot::Mutex::new(retention));
+        self
+    }
+
+    /// Lists every saved frame, and the answer about it once `record_answer` is called, in
+    /// `manifest`
+    pub fn with_manifest(mut self, manifest: ManifestWriter) -> Self {
+        if self.aggregate_only {
+            return self;
+        }
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Keeps every saved frame and every answer in the SQLite activity log `store`
+    #[cfg(feature = "sqlite")]
+    pub fn with_activity_store(mut self, store: Arc<ActivityStore>) -> Self {
+        if self.aggregate_only {
+            return self;
+        }
+        self.activity_store = Some(store);
+        self
+    }
+
+    /// Keeps turns in `queue` while the backend is disconnected instead of dropping them, and
+    /// sends them with the time they happened once a reconnect swapped in a working sender;
+    /// ignored in aggregation mode, which keeps frames off the disk
+    pub fn with_offline_queue(mut self, queue: OfflineQueue) -> Self {
+        if self.aggregate_only {
+            return self;
+        }
+        self.offline_queue = Some(queue);
+        self
+    }
+
+    /// Records every complete turn in `turns`, so an OutputProcessor sharing it can tell
+    /// which frame an answer is about
+    pub fn with_turn_tracker(mut self, turns: Arc<TurnTracker>) -> Self {
+        self.turns = Some(turns);
+        self
+    }
+
+    /// Times capturing, encoding and sending every frame into `telemetry`; share it with the
+    /// OutputProcessor to also time the answers
+    pub fn with_telemetry(mut self, telemetry: Arc<Telemetry>) -> Self {
+        self.telemetry = Some(telemetry);
+        self
+    }
+
+    /// Stage timings recorded so far, when enabled with `with_telemetry`
+    pub fn telemetry(&self) -> Option<&Arc<Telemetry>> {
+        self.telemetry.as_ref()
+    }
+
+    /// Publishes every JPEG sent to the model, exactly as cropped and scaled, to `feed`
+    pub fn with_preview(mut self, feed: PreviewFeed) -> Self {
+        self.preview = Some(feed);
+        self
+    }
+
+    /// Also captures `displays`, e.g. the other monitors, alternating between the sources
+    /// or compositing them into one frame
+    pub fn with_displays(mut self, displays: Vec<FrameSource>, mode: DisplayMode) -> Self {
+        for display in &displays {
+            display.set_redactor(self.redactor.clone());
+        }
+        self.displays = displays;
+        self.display_mode = mode;
+        self
+    }
+
+    /// Tells the model where the pointer is and where the user clicked, for screenshots of
+    /// the whole main display
+    pub fn with_pointer(mut self, tracker: PointerTracker) -> Self {
+        self.pointer = Some(tracker);
+        self
+    }
+
+    /// Tells the model how many keys the user pressed and how often they clicked since the
+    /// previous screenshot, and lists the counts in the manifest
+    pub fn with_activity_meter(mut self, meter: ActivityMeter) -> Self {
+        self.activity = Some(meter);
+        self
+    }
+
+    /// Masks configured apps, titles and regions in every frame of the session's sources,
+    /// for every consumer; see `FrameSource::set_redactor`
+    ///
+    /// Applies to display captures; frames of a window capture have no known position on
+    /// screen and are dropped.
+    pub fn with_redaction(mut self, redactor: Redactor) -> Self {
+        self.redactor = (!redactor.is_empty()).then_some(redactor);
+        self.sources()
+            .for_each(|source| source.set_redactor(self.redactor.clone()));
+        self
+    }
+
+    /// Leaves out every frame taken while an app of `exclusion` is in front or on the
+    /// captured part of the screen, logging the interval instead
+    pub fn with_app_exclusion(mut self, exclusion: AppExclusion) -> Self {
+        self.exclusion = (!exclusion.is_empty()).then_some(exclusion);
+        self
+    }
+
+    /// Quotes text copied since the previous screenshot in its prompt, redacted by `clipboard`
+    pub fn with_clipboard(mut self, clipboard: ClipboardContext) -> Self {
+        if self.aggregate_only {
+            return self;
+        }
+        self.clipboard = Some(Arc::new(clipboard));
+        self
+    }
+
+    pub fn is_aggregate_only(&self) -> bool {
+        self.aggregate_only
+    }
+
+    /// Adds the text recognized on-device in each frame to its prompt
+    #[cfg(feature = "ocr")]
+    pub fn with_ocr(mut self, recognizer: TextRecognizer) -> Self {
+        if self.aggregate_only {
+            return self;
+        }
+        self.ocr = Some(recognizer);
+        self
+    }
+
+    /// Names the calendar event going on in prompts and the activity log, so screens shared
+    /// in a meeting can be told from solo work
+    #[cfg(feature = "calendar")]
+    pub fn with_calendar(mut self, calendar: Calendar) -> Self {
+        if self.aggregate_only {
+            return self;
+        }
+        self.calendar = Some(calendar);
+        self
+    }
+
+    /// Crops every frame to a zoomed region following the cursor and asks for narration
+    pub fn with_zoom_follow(self, zoom: ZoomFollow) -> Self {
+        self.set_zoom_follow(Some(zoom));
+        self
+    }
+
+    /// Enables or disables zoom-follow while the session is running
+    pub fn set_zoom_follow(&self, zoom: Option<ZoomFollow>) {
+        if self.aggregate_only {
+            return;
+        }
+        *self.zoom_follow.lock() = zoom;
+    }
+
+    /// Stops screen capture; the recording indicator goes off once the capturer has shut down
+    pub fn stop(&self) {
+        self.sources().for_each(FrameSource::stop);
+    }
+
+    /// Suspends screen capture, e.g. during a meeting; the Gemini session stays connected
+    pub fn pause(&self) {
+        self.pause_requests.fetch_add(1, Ordering::SeqCst);
+        if !self.frame_source.is_paused() {
+            self.sources().for_each(FrameSource::pause);
+            self.printer.print_status("⏸️ Capture paused");
+        }
+    }
+
+    /// Restarts screen capture after `pause`; capture stays off while the screen is locked
+    pub fn resume(&self) {
+        if self.is_locked() {
+            self.printer
+                .print_status("🔒 Screen is locked, capture resumes once it is unlocked");
+        } else if self.frame_source.is_paused() {
+            self.sources().for_each(FrameSource::resume);
+            self.printer.print_status("▶️ Capture resumed");
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.frame_source.is_paused()
+    }
+
+    /// Number of `pause` calls so far, to tell whether capture was paused again while
+    /// paused, e.g. over the control socket while a voice question was asked
+    pub fn pause_requests(&self) -> usize {
+        self.pause_requests.load(Ordering::SeqCst)
+    }
+
+    /// Where progress and errors of the session are printed
+    pub fn printer(&self) -> &dyn ResponsePrinter {
+        self.printer.as_ref()
+    }
+
+    /// Holds back turns, e.g. questions over RPC, and `resume` while the screen is locked;
+    /// capture is paused separately
+    pub fn set_locked(&self, locked: bool) {
+        self.locked.store(locked, Ordering::SeqCst);
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::SeqCst)
+    }
+
+    /// Points capture at the target of `options` without restarting the session, e.g. to
+    /// follow another window
+    pub fn retarget(&self, options: CaptureOptions) -> CaptureResult<()> {
+        self.frame_source.retarget(options)?;
+        self.printer.print_status("🎯 Capture target changed");
+        Ok(())
+    }
+
+    /// Whether frames alternate between or combine several displays, which can't be retargeted
+    pub fn captures_all_displays(&self) -> bool {
+        !self.displays.is_empty()
+    }
+
+    /// Streams the captured system audio to Gemini as realtime input alongside the
+    /// screenshots, e.g. to follow a meeting; the source needs `captures_audio`
+    ///
+    /// The task ends once capture stops.
+    pub fn spawn_system_audio(self: &Arc<Self>) -> JoinHandle<()> {
+        let mut chunks = self.frame_source.audio_channel(64);
+        let session = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut failing = false;
+            while let Some(chunk) = chunks.recv().await {
+                // Looked up per chunk, so audio follows the session across reconnects
+                match session.sender().send_audio(&chunk).await {
+                    Ok(()) => failing = false,
+                    Err(e) if !failing => {
+                        session.printer.print_error(&format!(
+                            "❌ Error sending system audio to Gemini: {}",
+                            e
+                        ));
+                        failing = true;
+                    }
+                    Err(_) => {}
+                }
+            }
+        })
+    }
+
+    /// Returns the backend frames are currently routed to
+    pub fn sender(&self) -> Arc<dyn AnalysisBackend> {
+        self.sender.read().clone()
+    }
+
+    /// Routes subsequent frames to a different backend, e.g. a new Gemini session after a
+    /// reconnect
+    pub fn set_sender(&self, sender: impl AnalysisBackend + 'static) {
+        *self.sender.write() = Arc::new(sender);
+        // Turns still waiting went to the old session and won't be answered
+        if let Some(turns) = &self.turns {
+            turns.reset();
+        }
+        // The new session hasn't seen any of the remembered frames
+        if let Some(deduplicator) = &self.deduplicator {
+            deduplicator.lock().reset();
+        }
+    }
+
+    /// Overrides the per-frame question sent with each screenshot, with the same placeholders
+    /// as `SessionOptions::prompt_template`; `None` restores the configured one
+    pub fn set_prompt(&self, prompt: Option<String>) {
+        if self.aggregate_only {
+            return;
+        }
+        *self.prompt.write() = prompt;
+    }
+
+    fn sources(&self) -> impl Iterator<Item = &FrameSource> {
+        std::iter::once(&self.frame_source).chain(&self.displays)
+    }
+
+    /// Next frame to analyze: with several displays, the `index`-th display in turn or all
+    /// of them side by side
+    async fn next_frame(&self, index: usize) -> CaptureResult<Arc<FrameData>> {
+        if self.displays.is_empty() {
+            return self.frame_source.get_next_frame().await;
+        }
+        match self.display_mode {
+            DisplayMode::RoundRobin => {
+                let source = match index % (self.displays.len() + 1) {
+                    0 => &self.frame_source,
+                    n => &self.displays[n - 1],
+                };
+                source.get_next_frame().await
+            }
+            DisplayMode::Composite => {
+                let frames: Vec<_> = futures::future::try_join_all(
+                    self.sources().map(|source| source.get_next_frame()),
+                )
+                .await?;
+                let pool = self.frame_source.buffer_pool();
+                let composite = composite_frames(&frames, pool);
+                for frame in frames {
+                    pool.recycle_frame(frame);
+                }
+                composite
+                    .map(Arc::new)
+                    .ok_or(CaptureError::NoFrameAvailable)
+            }
+        }
+    }
+
+    /// Whether `frame` shows an excluded app and must be dropped; notes when such an interval
+    /// begins and logs it once it ends
+    async fn excluded(&self, frame: &FrameData) -> bool {
+        let Some(exclusion) = &self.exclusion else {
+            return false;
+        };
+        let displays: Vec<Rect> = match frame.display_id {
+            Some(id) => display_bounds(id).into_iter().collect(),
+            // Composites show every display side by side
+            None if self.captures_all_displays() => list_displays()
+                .iter()
+                .filter_map(|display| display_bounds(display.id))
+                .collect(),
+            None => Vec::new(),
+        };
+        let blocking = exclusion.blocking_app(frame.active_window.as_ref(), &displays);
+        let ended = {
+            let mut since = self.excluded_since.lock();
+            match (blocking.is_some(), *since) {
+                (true, None) => {
+                    *since = Some(SystemTime::now());
+                    self.printer
+                        .print_status("🙈 An excluded app is on screen, pausing capture");
+                    None
+                }
+                (false, Some(started)) => {
+                    *since = None;
+                    Some(started)
+                }
+                _ => None,
+            }
+        };
+        if let Some(started) = ended {
+            self.record_exclusion_gap(started).await;
+        }
+        // Nor is text copied from the app quoted afterwards
+        if blocking.is_some()
+            && let Some(clipboard) = &self.clipboard
+        {
+            let clipboard = Arc::clone(clipboard);
+            tokio::task::spawn_blocking(move || clipboard.skip())
+                .await
+                .ok();
+        }
+        blocking.is_some()
+    }
+
+    /// Logs an excluded-app interval that is still going on, e.g. when capture stops
+    pub async fn finish_exclusion(&self) {
+        let started = self.excluded_since.lock().take();
+        if let Some(started) = started {
+            self.record_exclusion_gap(started).await;
+        }
+    }
+
+    async fn record_exclusion_gap(&self, started: SystemTime) {
+        if let Err(e) = self
+            .record_gap("🙈", "Redacted interval", started, SystemTime::now())
+            .await
+        {
+            self.printer
+                .print_error(&format!("❌ Error sending redacted interval: {}", e));
+        }
+    }
+
+    /// Pointer sentence for a frame; only a full, unzoomed capture of the main display maps
+    /// onto screen coordinates
+    fn describe_pointer(&self, frame: &FrameData) -> Option<String> {
+        let tracker = self.pointer.as_ref()?;
+        if self.zoom_follow.lock().is_some() || frame.display_id != main_display_id() {
+            return None;
+        }
+        let display = main_display_bounds()?;
+        // Region captures have a different aspect ratio than the display
+        let aspect = frame.width as f64 / frame.height.max(1) as f64;
+        if (aspect - display.width / display.height).abs() > 0.02 {
+            return None;
+        }
+        tracker.describe(frame.cursor, display)
+    }
+
+    /// Prompt section with the text in a frame, recognized on a blocking thread
+    #[cfg(feature = "ocr")]
+    async fn recognize_text(&self, frame: &Arc<FrameData>) -> Option<String> {
+        let recognizer = self.ocr?;
+        let frame = Arc::clone(frame);
+        match tokio::task::spawn_blocking(move || recognizer.recognize(&frame)).await {
+            Ok(Ok(lines)) => ocr_prompt(&lines),
+            Ok(Err(e)) => {
+                self.printer
+                    .print_error(&format!("❌ Error recognizing text: {}", e));
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Prompt section with newly copied text, read on a blocking thread; copies made while a
+    /// redacted window is in front, or marked concealed by a password manager, are never
+    /// quoted
+    async fn clipboard_note(&self, frame: &FrameData) -> Option<String> {
+        let clipboard = Arc::clone(self.clipboard.as_ref()?);
+        let hidden = frame.active_window.as_ref().is_some_and(|window| {
+            self.redactor
+                .as_ref()
+                .is_some_and(|redactor| redactor.hides(window))
+        });
+        tokio::task::spawn_blocking(move || {
+            if hidden {
+                clipboard.skip();
+                None
+            } else {
+                clipboard.take_prompt()
+            }
+        })
+        .await
+        .ok()
+        .flatten()
+    }
+
+    /// Prompt sentence about the calendar event going on now and its title, redacted and
+    /// anonymized like window titles
+    #[cfg(feature = "calendar")]
+    fn calendar_event(&self) -> Option<(String, String)> {
+        let mut event = self.calendar.as_ref()?.current_event()?;
+        let hidden = self
+            .redactor
+            .as_ref()
+            .is_some_and(|redactor| redactor.hides_title(&event.title));
+        event.title = if hidden {
+            "(redacted)".to_string()
+        } else {
+            self.anonymizer.title(&event.title)
+        };
+        Some((event.describe(), event.title))
+    }
+
+    #[cfg(not(feature = "calendar"))]
+    fn calendar_event(&self) -> Option<(String, String)> {
+        None
+    }
+
+    /// Sentence naming the app and window in front when the frame was captured
+    fn describe_active_window(&self, frame: &FrameData) -> Option<String> {
+        match self.active_window_names(frame)? {
+            (app, Some(title)) => Some(format!("The active app is {}, window '{}'.", app, title)),
+            (app, None) => Some(format!("The active app is {}.", app)),
+        }
+    }
+
+    /// Anonymized app name and window title of the frontmost window, unless it is redacted
+    fn active_window_names(&self, frame: &FrameData) -> Option<(String, Option<String>)> {
+        let window = frame.active_window.as_ref()?;
+        let hidden = self
+            .redactor
+            .as_ref()
+            .is_some_and(|redactor| redactor.hides(window));
+        if window.app_name.is_empty() || hidden {
+            return None;
+        }
+        Some((
+            self.anonymizer.app_name(&window.app_name),
+            window
+                .title
+                .as_deref()
+                .map(|title| self.anonymizer.title(title)),
+        ))
+    }
+
+    /// Metadata embedded into a saved JPEG of `frame`
+    fn image_metadata(&self, frame: &FrameData) -> ImageMetadata {
+        let mut metadata = ImageMetadata::new(SystemTime::now());
+        if let Some((app, title)) = self.active_window_names(frame) {
+            metadata.app_name = Some(app);
+            metadata.window_title = title;
+        }
+        metadata.display_id = frame.display_id;
+        metadata
+    }
+
+    /// Writes a JPEG, embedding `metadata` when enabled
+    fn save_jpeg(&self, path: &str, jpeg: &[u8], metadata: &ImageMetadata) -> std::io::Result<()> {
+        if self.options.embed_metadata {
+            std::fs::write(path, metadata.embed(jpeg))
+        } else {
+            std::fs::write(path, jpeg)
+        }
+    }
+
+    /// Adds an answer to the manifest, the activity store and the metadata of the frame it
+    /// is about; aggregation mode stores no answers
+    pub fn record_answer(&self, result: &AnalysisResult) {
+        if self.aggregate_only {
+            return;
+        }
+        if let Some(frame_seq) = result.frame_seq
+            && let Err(e) = self.describe_saved_frame(frame_seq, &result.text)
+        {
+            self.printer
+                .print_error(&format!("❌ Failed to describe frame {}: {}", frame_seq, e));
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = &self.activity_store
+            && let Err(e) = store.record_answer(&self.options.session, result)
+        {
+            self.printer
+                .print_error(&format!("❌ Failed to store answer: {}", e));
+        }
+        if let Some(manifest) = &self.manifest {
+            let entry = ManifestEntry::Response(ResponseRecord {
+                session: self.options.session.clone(),
+                index: result.frame_seq,
+                timestamp_ms: unix_millis(SystemTime::now()),
+                text: result.text.clone(),
+                usage: result.usage.clone(),
+            });
+            if let Err(e) = manifest.append(&entry) {
+                self.printer
+                    .print_error(&format!("❌ Failed to write manifest: {}", e));
+            }
+        }
+    }
+
+    /// Lists a saved frame in the manifest and the activity store
+    fn record_frame(
+        &self,
+        index: usize,
+        filename: &str,
+        frame: &FrameData,
+        prompt: &str,
+        activity: Option<ActivityCounts>,
+        calendar_event: Option<String>,
+    ) {
+        let (app, window_title) = self.active_window_names(frame).unzip();
+        let file = Path::new(filename).file_name().map_or_else(
+            || filename.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let record = FrameRecord {
+            session: self.options.session.clone(),
+            index,
+            file,
+            timestamp_ms: unix_millis(SystemTime::now()),
+            app,
+            window_title: window_title.flatten(),
+            prompt: prompt.to_string(),
+            activity,
+            calendar_event,
+        };
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = &self.activity_store
+            && let Err(e) = store.record_frame(&record, filename)
+        {
+            self.printer
+                .print_error(&format!("❌ Failed to store frame {}: {}", index, e));
+        }
+        if let Some(manifest) = &self.manifest
+            && let Err(e) = manifest.append(&ManifestEntry::Frame(record))
+        {
+            self.printer
+                .print_error(&format!("❌ Failed to write manifest: {}", e));
+        }
+    }
+
+    /// Adds the model's answer about frame `frame_seq` to the metadata of its saved JPEG;
+    /// returns whether the frame was still there to update
+    pub fn describe_saved_frame(&self, frame_seq: usize, summary: &str) -> std::io::Result<bool> {
+        if !self.options.embed_metadata {
+            return Ok(false);
+        }
+        let (path, metadata) = {
+            let mut saved = self.saved.lock();
+            let Some((_, path, metadata)) =
+                saved.iter_mut().find(|(index, ..)| *index == frame_seq)
+            else {
+                return Ok(false);
+            };
+            metadata.summary = Some(summary.trim().to_string());
+            (path.clone(), metadata.clone())
+        };
+        match metadata.write_to(&path) {
+            Ok(()) => Ok(true),
+            // Pruned by the retention policy in the meantime
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Current throttle settings, reporting level changes through the printer
+    fn throttle(&self) -> Throttle {
+        let Some(governor) = &self.governor else {
+            return Throttle::for_level(0);
+        };
+        let mut governor = governor.lock();
+        if let Some(status) = governor.update() {
+            self.printer.print_status(&status);
+        }
+        governor.throttle()
     }
 
     /// Captures frames and sends them to Gemini for analysis
     pub async fn capture_frames(&self, count: usize) -> crate::gemini::Result<()> {
+        let mut captured = 0;
         for i in 1..=count {
-            match self.frame_source.get_next_frame().await {
-                Ok(frame) => {
-                    let filename = format!("{}/frame_{:04}.jpg", self.output_dir, i);
+            if self.frame_source.is_finished() {
+                break;
+            }
+            self.capture_frame(i).await;
+            captured = i;
+        }
+        self.flush_batch(captured).await;
+        Ok(())
+    }
+
+    /// Captures a frame every `interval` until `cancel` is triggered, e.g. by Ctrl+C, or the
+    /// frame source is finished
+    ///
+    /// Frames that fail are reported through the channel set with `with_error_channel` and
+    /// the loop carries on with the next one.
+    pub async fn run(&self, interval: Duration, cancel: CancellationToken) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut index = 0;
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = ticker.tick() => {}
+            }
+            if self.frame_source.is_finished() {
+                break;
+            }
+            index += 1;
+            // Cancelling drops a frame in progress, e.g. one stuck waiting for the capturer
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = self.capture_frame(index) => {}
+            }
+        }
+        self.flush_batch(index).await;
+    }
+
+    /// Sends the frames of an unfinished batch rather than dropping them
+    async fn flush_batch(&self, index: usize) {
+        let rest = self
+            .batch
+            .as_ref()
+            .and_then(|batch| batch.lock().take(&self.batch_prompt(index, None)));
+        if let Some(content) = rest
+            && let Err(source) = self.send_turn(content, Some(index)).await
+        {
+            self.report(FrameError::Send { index, source });
+        }
+    }
 
-                    // Encode as JPEG bytes
-                    match encode_bgra_to_jpeg_bytes(&frame.data, frame.width, frame.height, 90) {
-                        Ok(jpeg_bytes) => {
+    /// Hands a saved file to the retention manager, which may delete older ones
+    fn retain(&self, path: &str) {
+        if let Some(retention) = &self.retention
+            && let Err(e) = retention.lock().record(path)
+        {
+            self.printer
+                .print_error(&format!("❌ Failed to prune old frames: {}", e));
+        }
+    }
+
+    /// Adds how long a stage took for frame `index` to the telemetry, if enabled
+    fn time(&self, index: usize, stage: Stage, started: Instant) {
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.record(index, stage, started.elapsed());
+        }
+    }
+
+    /// Hands a failed frame to the error channel, or prints it when there is none
+    fn report(&self, error: FrameError) {
+        match &self.errors {
+            Some(errors) => {
+                let _ = errors.send(error);
+            }
+            None => self.printer.print_error(&format!("❌ {}", error)),
+        }
+    }
+
+    /// Captures frame `i` and sends it to Gemini, unless it is skipped as unchanged
+    async fn capture_frame(&self, i: usize) {
+        let throttle = self.throttle();
+        // Throttled sessions drop frames in between to lower the effective frame rate
+        for _ in 1..throttle.frame_stride {
+            if let Ok(skipped) = self.next_frame(i).await {
+                self.frame_source.buffer_pool().recycle_frame(skipped);
+            }
+        }
+
+        let waiting = Instant::now();
+        match self.next_frame(i).await {
+            Ok(frame) => {
+                self.time(i, Stage::CaptureWait, waiting);
+                if self.excluded(&frame).await {
+                    self.frame_source.buffer_pool().recycle_frame(frame);
+                    return;
+                }
+                if let Some(detector) = &self.change_detector
+                    && !detector.lock().is_changed(&frame)
+                {
+                    self.printer
+                        .print_status(&format!("💤 Frame {}: unchanged, not sent", i));
+                    self.frame_source.buffer_pool().recycle_frame(frame);
+                    return;
+                }
+                let seen = self
+                    .deduplicator
+                    .as_ref()
+                    .and_then(|deduplicator| deduplicator.lock().check(&frame, i));
+                if let Some(seen) = seen {
+                    let note = format!(
+                        "The screen is unchanged since {} (frame {}).",
+                        clock_time(seen.at),
+                        seen.index
+                    );
+                    self.printer.print_status(&format!(
+                        "♻️ Frame {}: same as frame {}, not sent",
+                        i, seen.index
+                    ));
+                    self.frame_source.buffer_pool().recycle_frame(frame);
+                    // Left open as context for the next screenshot, like menu events
+                    let content = ClientContent {
+                        turns: vec![Content {
+                            role: Some("user".to_string()),
+                            parts: vec![Part::text(note)],
+                        }],
+                        turn_complete: Some(false),
+                    };
+                    if let Err(source) = self.send_turn(content, None).await {
+                        self.report(FrameError::Send { index: i, source });
+                    }
+                    return;
+                }
+
+                // Aggregate mode sends nothing but the image and its fixed prompt, e.g. no
+                // window titles
+                let (pointer, active_window, activity, clipboard, (calendar, calendar_event)) =
+                    if self.aggregate_only {
+                        Default::default()
+                    } else {
+                        (
+                            self.describe_pointer(&frame),
+                            self.describe_active_window(&frame),
+                            self.activity.as_ref().map(ActivityMeter::take),
+                            self.clipboard_note(&frame).await,
+                            self.calendar_event().unzip(),
+                        )
+                    };
+
+                // Zoom coordinates refer to the main display
+                let zoomable = self.displays.is_empty() || frame.display_id == main_display_id();
+                let (frame, default_prompt) = match self.zoom_follow.lock().as_mut() {
+                    Some(zoom) if zoomable => {
+                        let cropped = cursor_position()
+                            .zip(main_display_bounds())
+                            .and_then(|(cursor, display)| zoom.apply(&frame, cursor, display));
+                        (
+                            cropped.map(Arc::new).unwrap_or(frame),
+                            ZOOM_NARRATION_PROMPT,
+                        )
+                    }
+                    _ => (frame, DEFAULT_FRAME_PROMPT),
+                };
+                // Recognized before downscaling, which makes small text unreadable
+                #[cfg(feature = "ocr")]
+                let screen_text = if self.aggregate_only {
+                    None
+                } else {
+                    self.recognize_text(&frame).await
+                };
+                let frame = match &self.resize {
+                    Some(resize) => resize.apply(&frame).map(Arc::new).unwrap_or(frame),
+                    None => frame,
+                };
+                let frame = match throttle.max_edge {
+                    Some(edge) => ResizeOptions::new(ResizeTarget::MaxLongEdge(edge))
+                        .apply(&frame)
+                        .map(Arc::new)
+                        .unwrap_or(frame),
+                    None => frame,
+                };
+                let prompt = if self.aggregate_only {
+                    AGGREGATE_PROMPT.to_string()
+                } else {
+                    self.frame_prompt(default_prompt, i, &frame)
+                };
+                let prompt = if self.annotate {
+                    format!("{} {}", prompt, ANNOTATION_REQUEST)
+                } else {
+                    prompt
+                };
+                // What is known about this frame besides the image; batches caption each
+                // frame with it
+                let notes = [
+                    pointer,
+                    active_window,
+                    activity.as_ref().map(ActivityCounts::describe),
+                    calendar,
+                ]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+                #[cfg(feature = "ocr")]
+                let notes = match screen_text {
+                    Some(text) => format!("{}\n\n{}", notes, text).trim_start().to_string(),
+                    None => notes,
+                };
+                let notes = match clipboard {
+                    Some(text) => format!("{}\n\n{}", notes, text).trim_start().to_string(),
+                    None => notes,
+                };
+                let prompt = if notes.is_empty() {
+                    prompt
+                } else {
+                    format!("{} {}", prompt, notes)
+                };
+
+                let filename = self.options.frame_path(i);
+                let format = self.options.format;
+
+                let pool = self.frame_source.buffer_pool();
+                match format.encode_timed(
+                    &frame.data,
+                    frame.width,
+                    frame.height,
+                    self.options.quality.min(throttle.jpeg_quality),
+                    pool,
+                ) {
+                    Ok((image_bytes, timing)) => {
+                        if let Some(telemetry) = &self.telemetry {
+                            telemetry.record(i, Stage::Convert, timing.convert);
+                            telemetry.record(i, Stage::Encode, timing.encode);
+                        }
+                        if self.aggregate_only || !self.options.save_frames {
+                            self.printer.print_status(&format!(
+                                "📸 Frame {}: {}x{} pixels (not stored)",
+                                i, frame.width, frame.height
+                            ));
+                        } else {
                             // Save to file
-                            if let Err(e) = std::fs::write(&filename, &jpeg_bytes) {
-                                eprintln!("❌ Error saving frame {}: {}", i, e);
-                                continue;
+                            let saved = if format == FrameFormat::Jpeg {
+                                let metadata = self.image_metadata(&frame);
+                                let saved = self.save_jpeg(&filename, &image_bytes, &metadata);
+                                if saved.is_ok() && self.options.embed_metadata {
+                                    let mut described = self.saved.lock();
+                                    if described.len() == DESCRIBED_FRAMES {
+                                        described.pop_front();
+                                    }
+                                    described.push_back((i, filename.clone(), metadata));
+                                }
+                                saved
+                            } else {
+                                std::fs::write(&filename, &image_bytes)
+                            };
+                            if let Err(source) = saved {
+                                self.restore_activity(activity);
+                                self.forget_frame(i);
+                                self.report(FrameError::Save { index: i, source });
+                                return;
                             }
+                            self.retain(&filename);
+                            self.record_frame(
+                                i,
+                                &filename,
+                                &frame,
+                                &prompt,
+                                activity,
+                                calendar_event,
+                            );
 
-                            println!(
+                            self.printer.print_status(&format!(
                                 "📸 Frame {}: {}x{} pixels -> {}",
                                 i, frame.width, frame.height, filename
-                            );
-
-                            // Encode to base64 for Gemini
-                            let base64_image =
-                                base64::engine::general_purpose::STANDARD.encode(&jpeg_bytes);
-
-                            // Send to Gemini with inline image data
-                            let content = ClientContent {
-                                turns: vec![Content {
-                                    role: Some("user".to_string()),
-                                    parts: vec![
-                                        Part::json(json!({
-                                            "inline_data": {
-                                                "mime_type": "image/jpeg",
-                                                "data": base64_image
-                                            }
-                                        })),
-                                        Part::text("What is the user doing in this screenshot?"),
-                                    ],
-                                }],
-                                turn_complete: Some(true),
-                                ..Default::default()
-                            };
+                            ));
+                            self.printer.frame_captured(Path::new(&filename));
+                        }
 
-                            if let Err(e) = self.sender.send_client_content(content).await {
-                                eprintln!("❌ Error sending to Gemini: {}", e);
+                        // The preview only streams JPEG
+                        if let Some(preview) = &self.preview
+                            && format == FrameFormat::Jpeg
+                        {
+                            preview.publish(&image_bytes);
+                        }
+                        self.printer.frame_sent(i);
+                        let encoding = Instant::now();
+                        let content = match &self.batch {
+                            Some(batch) => {
+                                let mut batch = batch.lock();
+                                let due = batch.push(image_part(&image_bytes, format), &notes);
+                                let prompt = || self.batch_prompt(i, Some(&frame));
+                                due.then(|| batch.take(&prompt())).flatten()
+                            }
+                            None => Some(image_turn(&image_bytes, format, prompt)),
+                        };
+                        self.time(i, Stage::Base64, encoding);
+                        pool.recycle(image_bytes);
+                        if let Some(content) = content {
+                            let sending = Instant::now();
+                            match self.send_turn(content, Some(i)).await {
+                                Ok(()) => self.time(i, Stage::Send, sending),
+                                Err(source) => {
+                                    self.restore_activity(activity);
+                                    self.forget_frame(i);
+                                    self.report(FrameError::Send { index: i, source });
+                                }
                             }
                         }
-                        Err(e) => {
-                            eprintln!("❌ Error encoding frame {}: {}", i, e);
+                    }
+                    Err(source) => {
+                        self.restore_activity(activity);
+                        self.forget_frame(i);
+                        self.report(FrameError::Encode { index: i, source });
+                    }
+                }
+                pool.recycle_frame(frame);
+            }
+            Err(source) => {
+                self.report(FrameError::Capture { index: i, source });
+            }
+        }
+    }
+
+    /// Hands the input counts of a frame that wasn't sent on to the next one
+    fn restore_activity(&self, counts: Option<ActivityCounts>) {
+        if let Some((meter, counts)) = self.activity.as_ref().zip(counts) {
+            meter.restore(counts);
+        }
+    }
+
+    /// Keeps the deduplicator from taking a later frame for frame `index`, which the model
+    /// never saw
+    fn forget_frame(&self, index: usize) {
+        if let Some(deduplicator) = &self.deduplicator {
+            deduplicator.lock().forget(index);
+        }
+    }
+
+    /// Prompt set with `set_prompt`, or else the configured template
+    fn prompt_template(&self) -> Option<String> {
+        self.prompt
+            .read()
+            .clone()
+            .or_else(|| self.options.prompt_template.clone())
+    }
+
+    /// Fills in the placeholders of a prompt template and adds the preamble; a redacted app
+    /// is "unknown" like a missing one
+    fn render_prompt(&self, template: &str, index: usize, frame: Option<&FrameData>) -> String {
+        let app = frame
+            .and_then(|frame| self.active_window_names(frame))
+            .map_or_else(|| "unknown".to_string(), |(app, _)| app);
+        let prompt = template
+            .replace("{index}", &index.to_string())
+            .replace("{time}", &clock_time(SystemTime::now()))
+            .replace("{app}", &app);
+        match &self.options.preamble {
+            Some(preamble) => format!("{} {}", preamble, prompt),
+            None => prompt,
+        }
+    }
+
+    /// Question sent with frame `index`; `default_prompt` applies without a template
+    fn frame_prompt(&self, default_prompt: &str, index: usize, frame: &FrameData) -> String {
+        let template = self
+            .prompt_template()
+            .unwrap_or_else(|| default_prompt.to_string());
+        self.render_prompt(&template, index, Some(frame))
+    }
+
+    /// Question closing a batch that ends with frame `index`
+    fn batch_prompt(&self, index: usize, last_frame: Option<&FrameData>) -> String {
+        let template = self
+            .prompt_template()
+            .unwrap_or_else(|| BATCH_PROMPT.to_string());
+        self.render_prompt(&template, index, last_frame)
+    }
+
+    /// Logs a menu interaction and sends a tight crop of an opened menu to Gemini
+    pub async fn capture_menu_event(&self, event: &MenuEvent) -> crate::gemini::Result<()> {
+        if self.aggregate_only {
+            return Ok(());
+        }
+        let event = event.anonymized(self.anonymizer.as_ref());
+        let description = event.describe();
+        self.printer.print_status(&format!("📋 {}", description));
+
+        let content = match (event.kind, event.bounds) {
+            (MenuEventKind::Opened, Some(bounds)) => {
+                // A separate subscriber, so the regular capture loop keeps its frames
+                let frame = match self.frame_source.subscribe().next_frame().await {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        self.printer
+                            .print_error(&format!("❌ Error getting frame: {}", e));
+                        return Ok(());
+                    }
+                };
+                if self.excluded(&frame).await {
+                    self.frame_source.buffer_pool().recycle_frame(frame);
+                    return Ok(());
+                }
+                // Window captures have no display to place the menu on
+                let Some(crop) = frame
+                    .display_id
+                    .and_then(display_bounds)
+                    .and_then(|display| crop_to_bounds(&frame, bounds, display))
+                else {
+                    return Ok(());
+                };
+                let pool = self.frame_source.buffer_pool();
+                pool.recycle_frame(frame);
+                let jpeg_bytes = match encode_bgra_to_jpeg_bytes_pooled(
+                    &crop.data,
+                    crop.width,
+                    crop.height,
+                    90,
+                    pool,
+                ) {
+                    Ok(jpeg_bytes) => jpeg_bytes,
+                    Err(e) => {
+                        self.printer
+                            .print_error(&format!("❌ Error encoding menu capture: {}", e));
+                        return Ok(());
+                    }
+                };
+                let metadata = self.image_metadata(&crop);
+                pool.recycle(crop.data);
+
+                let index = self.menu_captures.fetch_add(1, Ordering::Relaxed) + 1;
+                let filename = format!(
+                    "{}/menu_{}_{:04}.jpg",
+                    self.options.output_dir, self.options.session, index
+                );
+                if self.options.save_frames {
+                    match self.save_jpeg(&filename, &jpeg_bytes, &metadata) {
+                        Ok(()) => {
+                            self.retain(&filename);
+                            self.printer.frame_captured(Path::new(&filename));
                         }
+                        Err(e) => self
+                            .printer
+                            .print_error(&format!("❌ Error saving menu capture {}: {}", index, e)),
                     }
                 }
-                Err(e) => {
-                    eprintln!("❌ Error getting frame: {}", e);
+                if let Some(preview) = &self.preview {
+                    preview.publish(&jpeg_bytes);
+                }
+                image_turn(
+                    &jpeg_bytes,
+                    FrameFormat::Jpeg,
+                    format!("{}. {}", description, MENU_PROMPT),
+                )
+            }
+            // The menu has usually closed again by the next frame, so only the text is sent
+            // and the turn is left open as context for the next screenshot
+            _ => ClientContent {
+                turns: vec![Content {
+                    role: Some("user".to_string()),
+                    parts: vec![Part::text(format!("{}.", description))],
+                }],
+                turn_complete: Some(false),
+            },
+        };
+
+        self.send_turn(content, None).await
+    }
+
+    /// Captures the screen right away and asks `question` about it, outside of the regular
+    /// capture loop, e.g. when the user presses the hotkey; the answer is marked on demand
+    ///
+    /// Skipped while paused, locked or an excluded app is on screen. Unlike scheduled
+    /// turns it is never queued while offline, as the user waits for the answer now.
+    pub async fn capture_on_demand(&self, question: &str) -> crate::gemini::Result<()> {
+        match self.on_demand_turn(question, None).await? {
+            Some(content) => self.send_now(content, None, true).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Like `capture_on_demand`, but leaves the turn open as context for what follows, e.g. a
+    /// question asked out loud; false when no screenshot was sent, e.g. while paused
+    pub async fn show_screen(&self, note: &str) -> crate::gemini::Result<bool> {
+        match self.on_demand_turn(note, None).await? {
+            Some(content) => {
+                let content = ClientContent {
+                    turn_complete: Some(false),
+                    ..content
+                };
+                self.send_now(content, None, false).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Ends a question streamed as audio, recording it as an on-demand turn waiting for the
+    /// spoken answer
+    pub async fn end_spoken_question(&self) -> crate::gemini::Result<()> {
+        let turn = self.turns.as_ref().map(|turns| turns.turn_sent(None, true));
+        let result = self.sender().end_audio().await;
+        if result.is_err()
+            && let (Some(turns), Some(turn)) = (&self.turns, turn)
+        {
+            turns.withdraw(turn);
+        }
+        result
+    }
+
+    /// Answers a `take_screenshot` call of the model: sends a screenshot taken now, cropped
+    /// to the frontmost window of `app` if given, as an open turn the model sees next to
+    /// the tool response, and returns the response
+    pub async fn send_requested_screenshot(&self, app: Option<&str>) -> Value {
+        let bounds = match app {
+            Some(app) => {
+                let wanted = app.to_lowercase();
+                let windows: Vec<_> = list_windows()
+                    .into_iter()
+                    .filter(|window| {
+                        window.is_normal() && window.app_name.to_lowercase().contains(&wanted)
+                    })
+                    .collect();
+                // So "Code" doesn't pick Xcode when both are open
+                let exact = windows
+                    .iter()
+                    .position(|window| window.app_name.to_lowercase() == wanted);
+                let window = windows.into_iter().nth(exact.unwrap_or(0));
+                // Only the captured display can be cropped to the window
+                let display = self
+                    .frame_source
+                    .subscribe()
+                    .latest()
+                    .and_then(|frame| frame.display_id)
+                    .and_then(display_bounds);
+                match window {
+                    Some(window)
+                        if self
+                            .exclusion
+                            .as_ref()
+                            .is_some_and(|exclusion| exclusion.excludes(&window)) =>
+                    {
+                        return json!({ "error": format!("{} is excluded from capture", app) });
+                    }
+                    Some(window)
+                        if display.is_some_and(|display| {
+                            window.bounds.intersection(&display).is_none()
+                        }) =>
+                    {
+                        return json!({
+                            "error": format!("{} is on another display than the captured one", app)
+                        });
+                    }
+                    Some(window) => Some(window.bounds),
+                    None => return json!({ "error": format!("No window of {} is on screen", app) }),
+                }
+            }
+            None => None,
+        };
+        self.printer.print_status(&match app {
+            Some(app) => format!("📷 Model asked for a screenshot of {}", app),
+            None => "📷 Model asked for a screenshot".to_string(),
+        });
+        let content = match self
+            .on_demand_turn(REQUESTED_SCREENSHOT_PROMPT, bounds)
+            .await
+        {
+            Ok(Some(content)) => ClientContent {
+                turn_complete: Some(false),
+                ..content
+            },
+            Ok(None) => return json!({ "error": "The screen can't be captured right now" }),
+            Err(e) => return json!({ "error": e.to_string() }),
+        };
+        match self.send_now(content, None, false).await {
+            Ok(()) => json!({ "sent": true, "note": "The screenshot is in the latest user turn" }),
+            Err(e) => json!({ "error": e.to_string() }),
+        }
+    }
+
+    /// Screenshot taken now with `prompt`, saved like a menu capture and cropped to `crop`
+    /// in global points if given; `None` when skipped
+    async fn on_demand_turn(
+        &self,
+        prompt: &str,
+        crop: Option<Rect>,
+    ) -> crate::gemini::Result<Option<ClientContent>> {
+        // Its prompt and saved copy don't fit the fixed classification of aggregation mode
+        if self.aggregate_only {
+            self.printer
+                .print_status("📊 Aggregation mode, skipping the on-demand capture");
+            return Ok(None);
+        }
+        if self.is_paused() || self.is_locked() {
+            self.printer
+                .print_status("⏸️ Capture is paused, skipping the on-demand capture");
+            return Ok(None);
+        }
+        if self.sender().is_closed() {
+            return Err(GeminiError::ConnectionClosed);
+        }
+        // A separate subscriber, so the regular capture loop keeps its frames
+        let frame = match self.frame_source.subscribe().next_frame().await {
+            Ok(frame) => frame,
+            Err(e) => {
+                self.printer
+                    .print_error(&format!("❌ Error getting frame: {}", e));
+                return Ok(None);
+            }
+        };
+        if self.excluded(&frame).await {
+            self.frame_source.buffer_pool().recycle_frame(frame);
+            return Ok(None);
+        }
+        let active_window = self.describe_active_window(&frame);
+        let frame = match crop {
+            Some(bounds) => {
+                // Window captures have no display to place the window on
+                let cropped = frame
+                    .display_id
+                    .and_then(display_bounds)
+                    .and_then(|display| crop_to_bounds(&frame, bounds, display));
+                self.frame_source.buffer_pool().recycle_frame(frame);
+                match cropped {
+                    Some(cropped) => Arc::new(cropped),
+                    None => return Ok(None),
                 }
             }
+            None => frame,
+        };
+        let frame = match &self.resize {
+            Some(resize) => resize.apply(&frame).map(Arc::new).unwrap_or(frame),
+            None => frame,
+        };
+        let pool = self.frame_source.buffer_pool();
+        let jpeg_bytes = match encode_bgra_to_jpeg_bytes_pooled(
+            &frame.data,
+            frame.width,
+            frame.height,
+            self.options.quality,
+            pool,
+        ) {
+            Ok(jpeg_bytes) => jpeg_bytes,
+            Err(e) => {
+                self.printer
+                    .print_error(&format!("❌ Error encoding on-demand capture: {}", e));
+                return Ok(None);
+            }
+        };
+        let metadata = self.image_metadata(&frame);
+        pool.recycle_frame(frame);
+
+        let index = self.on_demand_captures.fetch_add(1, Ordering::Relaxed) + 1;
+        let filename = format!(
+            "{}/ask_{}_{:04}.jpg",
+            self.options.output_dir, self.options.session, index
+        );
+        if self.options.save_frames {
+            match self.save_jpeg(&filename, &jpeg_bytes, &metadata) {
+                Ok(()) => {
+                    self.retain(&filename);
+                    self.printer.frame_captured(Path::new(&filename));
+                }
+                Err(e) => self.printer.print_error(&format!(
+                    "❌ Error saving on-demand capture {}: {}",
+                    index, e
+                )),
+            }
+        }
+        if let Some(preview) = &self.preview {
+            preview.publish(&jpeg_bytes);
         }
+        let prompt = match active_window {
+            Some(window) => format!("{} {}", prompt, window),
+            None => prompt.to_string(),
+        };
+        let content = image_turn(&jpeg_bytes, FrameFormat::Jpeg, prompt);
+        pool.recycle(jpeg_bytes);
+        Ok(Some(content))
+    }
+
+    /// Logs that the user was away from `since` until `until` and tells the model, so the
+    /// missing screenshots aren't mistaken for an unchanged screen
+    pub async fn record_idle_gap(
+        &self,
+        since: SystemTime,
+        until: SystemTime,
+    ) -> crate::gemini::Result<()> {
+        self.record_gap("💤", "User was idle", since, until).await
+    }
+
+    /// Like `record_idle_gap`, for the time the screen was locked
+    pub async fn record_lock_gap(
+        &self,
+        since: SystemTime,
+        until: SystemTime,
+    ) -> crate::gemini::Result<()> {
+        self.record_gap("🔒", "Screen was locked", since, until)
+            .await
+    }
 
+    async fn record_gap(
+        &self,
+        icon: &str,
+        what: &str,
+        since: SystemTime,
+        until: SystemTime,
+    ) -> crate::gemini::Result<()> {
+        let minutes = until.duration_since(since).unwrap_or_default().as_secs() / 60;
+        let description = format!(
+            "{} from {} to {} ({} min), no screenshots were taken",
+            what,
+            clock_time(since),
+            clock_time(until),
+            minutes
+        );
+        self.printer
+            .print_status(&format!("{} {}", icon, description));
+        if self.aggregate_only {
+            return Ok(());
+        }
+
+        // Left open as context for the next screenshot, like menu events
+        let content = ClientContent {
+            turns: vec![Content {
+                role: Some("user".to_string()),
+                parts: vec![Part::text(format!("{}.", description))],
+            }],
+            turn_complete: Some(false),
+        };
+        self.send_turn(content, None).await
+    }
+
+    /// Asks the model a question about the session so far, outside of any frame
+    pub async fn send_question(&self, question: impl Into<String>) -> crate::gemini::Result<()> {
+        let content = ClientContent {
+            turns: vec![Content::text("user", question)],
+            turn_complete: Some(true),
+        };
+        self.send_turn(content, None).await
+    }
+
+    /// Sends a turn and, when it is complete, records it as waiting for an answer; with an
+    /// offline queue, turns are queued while disconnected and until earlier ones are sent
+    async fn send_turn(
+        &self,
+        content: ClientContent,
+        frame_seq: Option<usize>,
+    ) -> crate::gemini::Result<()> {
+        if self.is_locked() {
+            return Err(GeminiError::ScreenLocked);
+        }
+        let Some(queue) = &self.offline_queue else {
+            return self.send_now(content, frame_seq, false).await;
+        };
+        if !self.sender().is_closed() && queue.is_empty() {
+            match self.send_now(content.clone(), frame_seq, false).await {
+                Err(_) if self.sender().is_closed() => {}
+                result => return result,
+            }
+        }
+
+        match queue.push(&QueuedTurn::new(content, frame_seq)) {
+            Ok(dropped) => {
+                if let Some(index) = frame_seq {
+                    self.printer.print_status(&format!(
+                        "📥 Frame {}: offline, queued to send later",
+                        index
+                    ));
+                }
+                if dropped > 0 {
+                    self.printer.print_status(&format!(
+                        "⚠️ Offline queue is full, dropped the {} oldest turns",
+                        dropped
+                    ));
+                }
+            }
+            Err(e) => self
+                .printer
+                .print_error(&format!("❌ Failed to queue a turn while offline: {}", e)),
+        }
+        self.flush_offline_queue().await;
         Ok(())
     }
+
+    /// Sends the turns queued while disconnected, oldest first, each opening with the time it
+    /// happened; stops early if the connection drops again
+    pub async fn flush_offline_queue(&self) {
+        let Some(queue) = &self.offline_queue else {
+            return;
+        };
+        // Whoever is sending already picks up turns queued meanwhile
+        let Ok(_flushing) = self.flushing.try_lock() else {
+            return;
+        };
+        let mut sent = 0;
+        while !self.sender().is_closed() {
+            let (path, turn) = match queue.front() {
+                Ok(Some(front)) => front,
+                Ok(None) => break,
+                Err(e) => {
+                    self.printer
+                        .print_error(&format!("❌ Failed to read the offline queue: {}", e));
+                    break;
+                }
+            };
+            let frame_seq = turn.frame_seq;
+            if self
+                .send_now(turn.into_content(), frame_seq, false)
+                .await
+                .is_err()
+            {
+                break;
+            }
+            if let Err(e) = queue.remove(&path) {
+                self.printer.print_error(&format!(
+                    "❌ Failed to remove a sent turn from the offline queue: {}",
+                    e
+                ));
+                break;
+            }
+            sent += 1;
+        }
+        if sent > 0 {
+            self.printer
+                .print_status(&format!("📤 Sent {} turns queued while offline", sent));
+        }
+    }
+
+    async fn send_now(
+        &self,
+        content: ClientContent,
+        frame_seq: Option<usize>,
+        on_demand: bool,
+    ) -> crate::gemini::Result<()> {
+        // Recorded first, since the answer can arrive before sending returns
+        let turn = match (&self.turns, content.turn_complete) {
+            (Some(turns), Some(true)) => Some(turns.turn_sent(frame_seq, on_demand)),
+            _ => None,
+        };
+        let result = self.sender().send_turn(content).await;
+        if result.is_err()
+            && let (Some(turns), Some(turn)) = (&self.turns, turn)
+        {
+            turns.withdraw(turn);
+        }
+        result
+    }
 }
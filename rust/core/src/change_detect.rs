@@ -0,0 +1,106 @@
+use crate::FrameData;
+
+/// Cells per side of the luma thumbnail frames are compared on
+const GRID_SIZE: u32 = 32;
+
+/// Luma difference a cell needs before it counts as changed, so dithering and subpixel
+/// rendering noise is ignored
+const CELL_TOLERANCE: u8 = 4;
+
+/// Default share of changed cells, in percent, below which a frame counts as unchanged
+pub const DEFAULT_CHANGE_THRESHOLD: f64 = 1.0;
+
+/// Compares frames on a downsampled luma grid, so a blinking caret doesn't count as a
+/// change but a new window, scrolled text or a typed line does
+#[derive(Debug, Clone)]
+pub struct ChangeDetector {
+    threshold: f64,
+    reference: Option<(u32, u32, Vec<u8>)>,
+}
+
+impl Default for ChangeDetector {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHANGE_THRESHOLD)
+    }
+}
+
+impl ChangeDetector {
+    /// `threshold` is the share of grid cells, in percent, that must change
+    pub fn new(threshold: f64) -> Self {
+        Self {
+            threshold: threshold.max(0.0),
+            reference: None,
+        }
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// Returns true when `frame` differs enough from the last changed frame, which then
+    /// becomes the new reference; slow drift therefore still adds up to a change
+    pub fn is_changed(&mut self, frame: &FrameData) -> bool {
+        let thumbnail = luma_grid(frame);
+        let changed = match &self.reference {
+            Some((width, height, reference))
+                if (*width, *height) == (frame.width, frame.height) =>
+            {
+                changed_percent(reference, &thumbnail) >= self.threshold
+            }
+            // The first frame and resolution changes are always sent
+            _ => true,
+        };
+        if changed {
+            self.reference = Some((frame.width, frame.height, thumbnail));
+        }
+        changed
+    }
+
+    /// Forgets the reference, so the next frame counts as changed
+    pub fn reset(&mut self) {
+        self.reference = None;
+    }
+}
+
+/// Mean Rec. 601 luma of each grid cell
+pub(crate) fn luma_grid(frame: &FrameData) -> Vec<u8> {
+    let columns = GRID_SIZE.min(frame.width.max(1));
+    let rows = GRID_SIZE.min(frame.height.max(1));
+    let mut sums = vec![0u64; (columns * rows) as usize];
+    let mut counts = vec![0u64; sums.len()];
+    let stride = frame.width as usize * 4;
+
+    for y in 0..frame.height {
+        let row = (y * rows / frame.height.max(1)) as usize;
+        let Some(line) = frame
+            .data
+            .get(y as usize * stride..(y as usize + 1) * stride)
+        else {
+            break;
+        };
+        for (x, pixel) in line.chunks_exact(4).enumerate() {
+            let column = x * columns as usize / frame.width as usize;
+            let index = row * columns as usize + column;
+            let (b, g, r) = (pixel[0] as u64, pixel[1] as u64, pixel[2] as u64);
+            sums[index] += (299 * r + 587 * g + 114 * b) / 1000;
+            counts[index] += 1;
+        }
+    }
+
+    sums.iter()
+        .zip(&counts)
+        .map(|(sum, count)| (sum / (*count).max(1)) as u8)
+        .collect()
+}
+
+fn changed_percent(a: &[u8], b: &[u8]) -> f64 {
+    if a.is_empty() || a.len() != b.len() {
+        return 100.0;
+    }
+    let changed = a
+        .iter()
+        .zip(b)
+        .filter(|(a, b)| a.abs_diff(**b) > CELL_TOLERANCE)
+        .count();
+    changed as f64 / a.len() as f64 * 100.0
+}
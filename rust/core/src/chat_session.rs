@@ -0,0 +1,289 @@
+use crate::gemini::{GeminiError, Result};
+use crate::{
+    AnalysisBackend, AnalysisEvents, Blob, ClientContent, Content, Part, ServerContent,
+    ServerEvent, ToolResponse, UsageMetadata,
+};
+use futures::future::BoxFuture;
+use serde_json::{json, Map, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Earlier exchanges sent along with every turn; only the newest turn keeps its images
+const HISTORY_EXCHANGES: usize = 6;
+
+/// Turns waiting while the model answers an earlier one
+const QUEUE_CAPACITY: usize = 16;
+
+/// A model behind a request-per-answer HTTP API
+pub(crate) trait ChatModel: Send + 'static {
+    /// Name used in error messages
+    const NAME: &'static str;
+
+    /// Answer to `text` about `images`, remembering the exchange for the turns after it;
+    /// models that stream pass the text to `deltas` as it arrives
+    fn ask<'a>(
+        &'a mut self,
+        text: &'a str,
+        images: Vec<Blob>,
+        deltas: &'a mut Deltas,
+    ) -> BoxFuture<'a, Result<ChatAnswer>>;
+}
+
+pub(crate) struct ChatAnswer {
+    pub text: String,
+    pub prompt_tokens: Option<i32>,
+    pub response_tokens: Option<i32>,
+}
+
+/// Forwards the text of an answer still being generated, as Gemini Live streams it
+pub(crate) struct Deltas {
+    events: mpsc::Sender<ServerEvent>,
+    sent: bool,
+}
+
+impl Deltas {
+    pub async fn send(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.sent = true;
+        let event = ServerEvent::ServerContent {
+            usage_metadata: None,
+            content: ServerContent {
+                model_turn: Some(Content::text("model", text)),
+                ..Default::default()
+            },
+        };
+        // A closed receiver ends the session once the answer is complete
+        self.events.send(event).await.ok();
+    }
+}
+
+enum Request {
+    Turn(ClientContent),
+    Close,
+}
+
+/// Screen watching with a model that answers turns over HTTP instead of a Live session, e.g. a
+/// vision model on a local Ollama
+///
+/// Turns are answered one at a time, keeping a short history for context. The system
+/// instruction and response schema of the Gemini setup carry over; tools and audio are not
+/// supported.
+pub struct ChatSession {
+    sender: ChatSender,
+    events: mpsc::Receiver<ServerEvent>,
+}
+
+impl ChatSession {
+    pub(crate) fn spawn(model: impl ChatModel) -> Self {
+        let (requests, pending) = mpsc::channel(QUEUE_CAPACITY);
+        let (events_tx, events) = mpsc::channel(QUEUE_CAPACITY);
+        let closed = Arc::new(AtomicBool::new(false));
+        tokio::spawn(run(model, pending, events_tx, Arc::clone(&closed)));
+        Self {
+            sender: ChatSender { requests, closed },
+            events,
+        }
+    }
+
+    /// Clonable handle for sending turns from other tasks
+    pub fn sender_handle(&self) -> ChatSender {
+        self.sender.clone()
+    }
+}
+
+impl AnalysisEvents for ChatSession {
+    fn recv(&mut self) -> BoxFuture<'_, Result<Option<ServerEvent>>> {
+        Box::pin(async move { Ok(self.events.recv().await) })
+    }
+
+    fn send_tool_response(&self, _response: ToolResponse) -> BoxFuture<'_, Result<()>> {
+        // No tools are offered to the model, so it never calls one
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[derive(Clone)]
+pub struct ChatSender {
+    requests: mpsc::Sender<Request>,
+    closed: Arc<AtomicBool>,
+}
+
+impl AnalysisBackend for ChatSender {
+    fn send_turn(&self, content: ClientContent) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            if self.is_closed() {
+                return Err(GeminiError::ConnectionClosed);
+            }
+            self.requests
+                .send(Request::Turn(content))
+                .await
+                .map_err(|_| GeminiError::ConnectionClosed)
+        })
+    }
+
+    fn send_audio<'a>(&'a self, _pcm: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async {
+            Err(GeminiError::Backend(
+                "Chat models don't take audio".to_string(),
+            ))
+        })
+    }
+
+    fn send_tool_response(&self, _response: ToolResponse) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst) || self.requests.is_closed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            if !self.closed.swap(true, Ordering::SeqCst) {
+                self.requests.send(Request::Close).await.ok();
+            }
+            Ok(())
+        })
+    }
+}
+
+async fn run<M: ChatModel>(
+    mut model: M,
+    mut requests: mpsc::Receiver<Request>,
+    events: mpsc::Sender<ServerEvent>,
+    closed: Arc<AtomicBool>,
+) {
+    // Parts of a turn sent in pieces, e.g. a note ahead of the frame it is about
+    let mut text = Vec::new();
+    let mut images = Vec::new();
+    while let Some(Request::Turn(content)) = requests.recv().await {
+        for part in content.turns.iter().flat_map(|turn| &turn.parts) {
+            match part {
+                Part::Text { text: part } => text.push(part.clone()),
+                other => images.extend(other.inline_data().filter(|blob| {
+                    blob.mime_type
+                        .as_deref()
+                        .is_none_or(|mime| mime.starts_with("image/"))
+                })),
+            }
+        }
+        if content.turn_complete != Some(true) {
+            continue;
+        }
+
+        let text = std::mem::take(&mut text).join("\n");
+        let images = std::mem::take(&mut images);
+        let mut deltas = Deltas {
+            events: events.clone(),
+            sent: false,
+        };
+        let event = match model.ask(&text, images, &mut deltas).await {
+            Ok(answer) => answer_event(answer, deltas.sent),
+            Err(e) => {
+                eprintln!("❌ {} request failed: {}", M::NAME, e);
+                // Completes the turn, so later answers stay paired with their frames
+                ServerEvent::ServerContent {
+                    usage_metadata: None,
+                    content: ServerContent {
+                        turn_complete: Some(true),
+                        ..Default::default()
+                    },
+                }
+            }
+        };
+        if events.send(event).await.is_err() {
+            break;
+        }
+    }
+    closed.store(true, Ordering::SeqCst);
+}
+
+/// Completes a turn, with the text of the answer unless it was streamed already
+fn answer_event(answer: ChatAnswer, streamed: bool) -> ServerEvent {
+    let usage = UsageMetadata {
+        prompt_token_count: answer.prompt_tokens,
+        response_token_count: answer.response_tokens,
+        total_token_count: match (answer.prompt_tokens, answer.response_tokens) {
+            (None, None) => None,
+            (prompt, response) => Some(prompt.unwrap_or(0) + response.unwrap_or(0)),
+        },
+        ..Default::default()
+    };
+    ServerEvent::ServerContent {
+        usage_metadata: Some(usage),
+        content: ServerContent {
+            model_turn: (!streamed).then(|| Content::text("model", answer.text)),
+            generation_complete: Some(true),
+            turn_complete: Some(true),
+            ..Default::default()
+        },
+    }
+}
+
+pub(crate) fn backend_error(e: reqwest::Error) -> GeminiError {
+    GeminiError::Backend(e.to_string())
+}
+
+/// Text of a system instruction
+pub(crate) fn instruction_text(content: &Content) -> String {
+    content
+        .parts
+        .iter()
+        .filter_map(|part| match part {
+            Part::Text { text } => Some(text.as_str()),
+            Part::Json(_) => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Drops the oldest messages beyond `HISTORY_EXCHANGES` user and assistant pairs
+pub(crate) fn trim_history(history: &mut std::collections::VecDeque<Value>) {
+    while history.len() > HISTORY_EXCHANGES * 2 {
+        history.pop_front();
+    }
+}
+
+/// Plain JSON schema from a Gemini response schema, which spells types in upper case and
+/// marks optional values with `nullable`; property names are kept as they are, even ones
+/// like `type`
+pub(crate) fn json_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(fields) => {
+            let nullable = fields.get("nullable") == Some(&Value::Bool(true));
+            let mut converted = Map::new();
+            for (key, value) in fields {
+                match (key.as_str(), value) {
+                    ("nullable" | "propertyOrdering", _) => {}
+                    ("type", _) => {
+                        let kind = Value::String(value.as_str().unwrap_or("string").to_lowercase());
+                        converted.insert(
+                            key.clone(),
+                            if nullable {
+                                json!([kind, "null"])
+                            } else {
+                                kind
+                            },
+                        );
+                    }
+                    ("properties", Value::Object(properties)) => {
+                        let properties = properties
+                            .iter()
+                            .map(|(name, schema)| (name.clone(), json_schema(schema)))
+                            .collect();
+                        converted.insert(key.clone(), Value::Object(properties));
+                    }
+                    _ => {
+                        converted.insert(key.clone(), json_schema(value));
+                    }
+                }
+            }
+            Value::Object(converted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(json_schema).collect()),
+        other => other.clone(),
+    }
+}
@@ -0,0 +1,246 @@
+/// Longest clipboard text quoted in a prompt, in characters
+pub const MAX_CLIPBOARD_TEXT: usize = 500;
+
+/// Words replaced before clipboard text reaches a prompt: common API key and token formats
+/// and email addresses; `*` matches any run of characters and `?` a single one
+pub const DEFAULT_CLIPBOARD_REDACTIONS: &[&str] = &[
+    "sk-*",
+    "ghp_*",
+    "github_pat_*",
+    "xox?-*",
+    "AIza*",
+    "AKIA*",
+    "*@*.*",
+];
+
+/// Shortest unbroken run of letters and digits taken for a secret, e.g. a generated password
+const SECRET_LENGTH: usize = 24;
+
+/// Shortest word mixing kinds of characters taken for a password
+const PASSWORD_LENGTH: usize = 8;
+
+const REDACTED: &str = "[redacted]";
+
+/// Text on the clipboard, or `None` when it holds something else, e.g. an image, or a
+/// password manager marked the copy as concealed or transient
+pub fn clipboard_text() -> Option<String> {
+    platform::clipboard_text().filter(|text| !text.trim().is_empty())
+}
+
+/// Quotes newly copied text next to the screenshot prompt, since copying e.g. an error
+/// message says a lot about what the user is doing
+///
+/// Text is cut to `MAX_CLIPBOARD_TEXT` characters unless set otherwise, and words matching
+/// a redaction pattern or looking like a secret are replaced. Each copy is quoted once.
+#[derive(Debug)]
+pub struct ClipboardContext {
+    /// Lowercase patterns, matched against whole words
+    patterns: Vec<String>,
+    max_chars: usize,
+    /// Clipboard text as last seen, unredacted
+    last: parking_lot::Mutex<Option<String>>,
+}
+
+impl ClipboardContext {
+    /// Redacts `DEFAULT_CLIPBOARD_REDACTIONS` and `patterns`, e.g. "acme-*" for internal
+    /// hostnames
+    pub fn new(patterns: Vec<String>) -> Self {
+        let patterns = DEFAULT_CLIPBOARD_REDACTIONS
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .chain(patterns)
+            .map(|pattern| pattern.to_lowercase())
+            .collect();
+        Self {
+            patterns,
+            max_chars: MAX_CLIPBOARD_TEXT,
+            last: parking_lot::Mutex::new(clipboard_text()),
+        }
+    }
+
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars;
+        self
+    }
+
+    /// Prompt section quoting the clipboard, or `None` when nothing was copied since the
+    /// previous call; text copied before the watcher started is left out
+    pub fn take_prompt(&self) -> Option<String> {
+        let text = clipboard_text()?;
+        let mut last = self.last.lock();
+        if last.as_deref() == Some(text.as_str()) {
+            return None;
+        }
+        let prompt = self.prompt(&text);
+        *last = Some(text);
+        prompt
+    }
+
+    /// Takes the clipboard as seen without quoting it, e.g. while it may hold a password
+    pub fn skip(&self) {
+        *self.last.lock() = clipboard_text();
+    }
+
+    /// Prompt section quoting `text`, redacted and cut short
+    pub fn prompt(&self, text: &str) -> Option<String> {
+        let text = self.redact(text.trim());
+        if text.is_empty() {
+            return None;
+        }
+        let text = match text.char_indices().nth(self.max_chars) {
+            Some((end, _)) => format!("{}…", &text[..end]),
+            None => text,
+        };
+        Some(format!(
+            "The user just copied this text to the clipboard:\n{}",
+            text
+        ))
+    }
+
+    /// `text` with every word that matches a pattern or looks like a secret replaced,
+    /// keeping the whitespace between words
+    pub fn redact(&self, text: &str) -> String {
+        text.split_inclusive(char::is_whitespace)
+            .map(|chunk| {
+                let word = chunk.trim_end();
+                let bare = word.trim_matches(|c: char| "\"'`()[]{}<>,;".contains(c));
+                if bare.is_empty() || !self.hides(bare) {
+                    return chunk.to_string();
+                }
+                chunk.replacen(bare, REDACTED, 1)
+            })
+            .collect()
+    }
+
+    fn hides(&self, word: &str) -> bool {
+        let lower = word.to_lowercase();
+        looks_secret(word)
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| wildcard_match(pattern, &lower))
+    }
+}
+
+/// Long runs mixing letters and digits, like generated passwords and tokens, and shorter
+/// words with digits mixing at least three of lower and upper case letters, digits and
+/// symbols, like typical passwords; file names like "Cargo.toml" are kept
+fn looks_secret(word: &str) -> bool {
+    let long_run = word.split(|c: char| !c.is_ascii_alphanumeric()).any(|run| {
+        run.len() >= SECRET_LENGTH
+            && run.chars().any(|c| c.is_ascii_digit())
+            && run.chars().any(|c| c.is_ascii_alphabetic())
+    });
+    let kinds = [
+        char::is_ascii_lowercase,
+        char::is_ascii_uppercase,
+        char::is_ascii_digit,
+        char::is_ascii_punctuation,
+    ]
+    .iter()
+    .filter(|kind| word.chars().any(|c| kind(&c)))
+    .count();
+    let password = word.chars().count() >= PASSWORD_LENGTH
+        && word.chars().any(|c| c.is_ascii_digit())
+        && kinds >= 3;
+    long_run || password
+}
+
+/// Whether `text` matches `pattern` as a whole, where `*` stands for any run of characters
+/// and `?` for one
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text it was matched against, to retry from
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((after, matched)) => {
+                    p = after;
+                    t = matched + 1;
+                    star = Some((after, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+    use std::ffi::{c_char, CStr};
+
+    #[allow(non_camel_case_types)]
+    type id = *mut Object;
+
+    /// Types password managers add to copies that shouldn't be recorded, per nspasteboard.org;
+    /// `pbpaste` doesn't tell them apart
+    const PRIVATE_TYPES: &[&str] = &[
+        "org.nspasteboard.ConcealedType",
+        "org.nspasteboard.TransientType",
+    ];
+
+    #[link(name = "AppKit", kind = "framework")]
+    unsafe extern "C" {
+        static NSPasteboardTypeString: id;
+    }
+
+    pub fn clipboard_text() -> Option<String> {
+        // Tokio threads have no autorelease pool of their own
+        objc::rc::autoreleasepool(|| unsafe {
+            let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+            if pasteboard.is_null() {
+                return None;
+            }
+            let types: id = msg_send![pasteboard, types];
+            if types.is_null() {
+                return None;
+            }
+            let count: usize = msg_send![types, count];
+            for i in 0..count {
+                let kind: id = msg_send![types, objectAtIndex: i];
+                if rust_string(kind).is_some_and(|kind| PRIVATE_TYPES.contains(&kind.as_str())) {
+                    return None;
+                }
+            }
+            let text: id = msg_send![pasteboard, stringForType: NSPasteboardTypeString];
+            rust_string(text)
+        })
+    }
+
+    /// Copy of an NSString, `None` for nil
+    unsafe fn rust_string(string: id) -> Option<String> {
+        if string.is_null() {
+            return None;
+        }
+        let utf8: *const c_char = unsafe { msg_send![string, UTF8String] };
+        if utf8.is_null() {
+            return None;
+        }
+        Some(
+            unsafe { CStr::from_ptr(utf8) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    pub fn clipboard_text() -> Option<String> {
+        None
+    }
+}
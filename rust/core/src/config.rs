@@ -0,0 +1,472 @@
+use crate::{ActivityCategory, ActivityFlag};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Gemini Live model sessions use unless configured otherwise
+pub const DEFAULT_MODEL: &str = "models/gemini-live-2.5-flash-preview";
+
+/// System instruction for plain screenshot descriptions
+pub const DEFAULT_SYSTEM_INSTRUCTION: &str =
+    "You are analyzing screenshots of a user's computer screen. \
+     For each screenshot, provide a brief description of what the user is doing. \
+     Focus on the main activity visible on the screen. Keep your response concise (1-2 sentences).";
+
+/// Where `ollama serve` listens by default
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+/// Vision model the Ollama backend uses unless configured otherwise
+pub const DEFAULT_OLLAMA_MODEL: &str = "qwen2.5vl";
+
+/// Chat completions API the OpenAI backend talks to by default
+pub const DEFAULT_OPENAI_URL: &str = "https://api.openai.com/v1";
+
+/// Vision model the OpenAI backend uses unless configured otherwise
+pub const DEFAULT_OPENAI_MODEL: &str = "gpt-4o-mini";
+
+/// Messages API the Anthropic backend talks to by default
+pub const DEFAULT_ANTHROPIC_URL: &str = "https://api.anthropic.com/v1";
+
+/// Claude model the Anthropic backend uses unless configured otherwise
+pub const DEFAULT_ANTHROPIC_MODEL: &str = "claude-sonnet-4-5";
+
+/// Environment variable naming a config file to read instead of the default one
+pub const CONFIG_PATH_VAR: &str = "WATCHER_CONFIG";
+
+#[derive(Debug, Error)]
+pub enum WatcherConfigError {
+    #[error("Failed to read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid config in {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("Invalid value {value:?} for {name}")]
+    Env { name: &'static str, value: String },
+}
+
+pub type WatcherConfigResult<T> = std::result::Result<T, WatcherConfigError>;
+
+/// Settings shared by the watcher binaries, layered from lowest to highest precedence:
+/// built-in defaults, the config file, `WATCHER_*` environment variables and finally
+/// command line flags, which each binary applies itself
+///
+/// ```toml
+/// [gemini]
+/// model = "models/gemini-live-2.5-flash-preview"
+///
+/// [capture]
+/// interval = 2.0
+///
+/// [privacy]
+/// redact_apps = ["1Password", "Messages"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatcherConfig {
+    /// Which model analyzes the frames
+    pub backend: Backend,
+    pub capture: CaptureConfig,
+    pub gemini: GeminiConfig,
+    pub ollama: OllamaConfig,
+    pub openai: OpenAiConfig,
+    pub anthropic: AnthropicConfig,
+    pub prompts: PromptConfig,
+    pub output: OutputConfig,
+    pub privacy: PrivacyConfig,
+    pub notify: NotifyConfig,
+}
+
+/// Model the frames are sent to:
+///
+/// ```toml
+/// backend = "ollama"
+///
+/// [ollama]
+/// model = "qwen2.5vl"
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Gemini Live over the network
+    #[default]
+    Gemini,
+    /// A vision model on a local Ollama server, so no frame leaves the machine
+    Ollama,
+    /// The OpenAI chat completions API or a compatible server
+    OpenAi,
+    /// Claude through the Anthropic Messages API
+    Anthropic,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "gemini" => Ok(Backend::Gemini),
+            "ollama" => Ok(Backend::Ollama),
+            "openai" => Ok(Backend::OpenAi),
+            "anthropic" | "claude" => Ok(Backend::Anthropic),
+            other => Err(format!(
+                "unknown backend '{}' (expected gemini, ollama, openai or anthropic)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Backend::Gemini => "gemini",
+            Backend::Ollama => "ollama",
+            Backend::OpenAi => "openai",
+            Backend::Anthropic => "anthropic",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    /// Seconds between frames
+    pub interval: Option<f64>,
+    /// Frames captured when not capturing continuously
+    pub frames: Option<usize>,
+    /// Longer side frames are scaled down to, in pixels
+    pub max_edge: Option<u32>,
+    /// Percentage of the screen that must change for a frame to be sent
+    pub skip_unchanged: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeminiConfig {
+    pub model: String,
+    /// Used when neither GOOGLE_API_KEY nor GOOGLE_API_KEYS is set
+    pub api_key: Option<String>,
+    /// Let the server compress long contexts
+    pub context_compression: bool,
+}
+
+impl Default for GeminiConfig {
+    fn default() -> Self {
+        Self {
+            model: DEFAULT_MODEL.to_string(),
+            api_key: None,
+            context_compression: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OllamaConfig {
+    /// Base URL of the server; also works for servers with Ollama's `/api/chat`
+    pub url: String,
+    /// Vision-capable model, pulled beforehand with `ollama pull`
+    pub model: String,
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_OLLAMA_URL.to_string(),
+            model: DEFAULT_OLLAMA_MODEL.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OpenAiConfig {
+    /// Base URL of the API, e.g. of a local server with an OpenAI-compatible API
+    pub url: String,
+    /// Vision-capable model
+    pub model: String,
+    /// Used when OPENAI_API_KEY is not set; servers other than OpenAI may not need one
+    pub api_key: Option<String>,
+}
+
+impl Default for OpenAiConfig {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_OPENAI_URL.to_string(),
+            model: DEFAULT_OPENAI_MODEL.to_string(),
+            api_key: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnthropicConfig {
+    pub url: String,
+    pub model: String,
+    /// Used when ANTHROPIC_API_KEY is not set
+    pub api_key: Option<String>,
+    /// Longest answer the model may write, in tokens
+    pub max_tokens: u32,
+}
+
+impl Default for AnthropicConfig {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_ANTHROPIC_URL.to_string(),
+            model: DEFAULT_ANTHROPIC_MODEL.to_string(),
+            api_key: None,
+            max_tokens: 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptConfig {
+    /// Prompt profile replacing the system instruction and prompt below: a built-in name such
+    /// as security-audit, a profile file, or a file name in the `profiles` directory
+    pub profile: Option<String>,
+    pub system_instruction: String,
+    /// Template of the text sent with each frame; see `SessionOptions::prompt_template`
+    pub prompt: Option<String>,
+    /// Context put in front of every prompt
+    pub preamble: Option<String>,
+}
+
+impl Default for PromptConfig {
+    fn default() -> Self {
+        Self {
+            profile: None,
+            system_instruction: DEFAULT_SYSTEM_INSTRUCTION.to_string(),
+            prompt: None,
+            preamble: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub dir: PathBuf,
+    /// Where frames of earlier runs are archived
+    pub archive_dir: PathBuf,
+    /// Image format name, e.g. jpeg; each binary accepts the formats it can write
+    pub format: Option<String>,
+    /// JPEG quality from 1 to 100
+    pub quality: Option<u8>,
+    pub keep_frames: Option<usize>,
+    pub keep_megabytes: Option<u64>,
+    pub keep_hours: Option<f64>,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("output"),
+            archive_dir: PathBuf::from("archive"),
+            format: None,
+            quality: None,
+            keep_frames: None,
+            keep_megabytes: None,
+            keep_hours: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrivacyConfig {
+    /// Apps whose windows are blacked out, matched by name
+    pub redact_apps: Vec<String>,
+    /// Windows blacked out by a word in their title
+    pub redact_titles: Vec<String>,
+    /// Apps, by bundle ID or name, during which nothing is captured at all
+    pub exclude_apps: Vec<String>,
+    /// Words replaced in quoted clipboard text besides API keys and emails, e.g. "acme-*"
+    pub clipboard_redact: Vec<String>,
+    /// How app names and window titles are rewritten: passthrough, hash or category
+    pub anonymize: Option<String>,
+    pub anonymize_salt: Option<String>,
+}
+
+/// Webhooks told when answers match a rule, e.g. media for more than ten minutes:
+///
+/// ```toml
+/// [[notify.webhooks]]
+/// url = "https://hooks.slack.com/services/..."
+/// slack = true
+///
+/// [[notify.rules]]
+/// name = "distracted"
+/// category = "media"
+/// for_minutes = 10
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// Every webhook is told about every rule that fires
+    pub webhooks: Vec<WebhookConfig>,
+    pub rules: Vec<NotifyRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Post `{"text": ...}` as Slack incoming webhooks expect instead of the whole event
+    #[serde(default)]
+    pub slack: bool,
+}
+
+/// Fires once answers about the screen have matched all of its conditions for `for_minutes`;
+/// a rule without conditions matches every answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyRule {
+    pub name: String,
+    /// Read from structured answers, or else the first category an answer mentions
+    pub category: Option<ActivityCategory>,
+    /// Part of the app name of structured answers, ignoring case
+    pub app: Option<String>,
+    pub flag: Option<ActivityFlag>,
+    /// Part of the answer text, ignoring case
+    pub text: Option<String>,
+    #[serde(default)]
+    pub for_minutes: f64,
+    /// Minutes before the rule can fire again, however often its streak restarts
+    #[serde(default = "default_cooldown_minutes")]
+    pub cooldown_minutes: f64,
+}
+
+fn default_cooldown_minutes() -> f64 {
+    30.0
+}
+
+impl WatcherConfig {
+    /// `$WATCHER_CONFIG`, or `config.toml` in `$XDG_CONFIG_HOME/watcher`, falling back to
+    /// `~/.config/watcher`
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(CONFIG_PATH_VAR) {
+            return Some(PathBuf::from(path));
+        }
+        Some(config_home()?.join("watcher").join("config.toml"))
+    }
+
+    /// Reads the default config file, if there is one, with the environment overrides on top
+    pub fn load() -> WatcherConfigResult<Self> {
+        let mut config = match Self::default_path().filter(|path| path.exists()) {
+            Some(path) => Self::from_file(&path)?,
+            None => Self::default(),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> WatcherConfigResult<Self> {
+        let text = std::fs::read_to_string(path).map_err(|source| WatcherConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&text).map_err(|source| WatcherConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Overrides settings from `WATCHER_*` variables, e.g. `WATCHER_INTERVAL=5`; lists are
+    /// comma-separated
+    pub fn apply_env(&mut self) -> WatcherConfigResult<()> {
+        env_parse("WATCHER_INTERVAL", &mut self.capture.interval)?;
+        env_parse("WATCHER_FRAMES", &mut self.capture.frames)?;
+        env_parse("WATCHER_MAX_EDGE", &mut self.capture.max_edge)?;
+        env_parse("WATCHER_SKIP_UNCHANGED", &mut self.capture.skip_unchanged)?;
+        if let Some(backend) = env_var("WATCHER_BACKEND") {
+            self.backend = backend.parse().map_err(|_| WatcherConfigError::Env {
+                name: "WATCHER_BACKEND",
+                value: backend,
+            })?;
+        }
+        if let Some(model) = env_var("WATCHER_MODEL") {
+            self.gemini.model = model;
+        }
+        if let Some(url) = env_var("WATCHER_OLLAMA_URL") {
+            self.ollama.url = url;
+        }
+        if let Some(model) = env_var("WATCHER_OLLAMA_MODEL") {
+            self.ollama.model = model;
+        }
+        if let Some(url) = env_var("WATCHER_OPENAI_URL") {
+            self.openai.url = url;
+        }
+        if let Some(model) = env_var("WATCHER_OPENAI_MODEL") {
+            self.openai.model = model;
+        }
+        if let Some(model) = env_var("WATCHER_ANTHROPIC_MODEL") {
+            self.anthropic.model = model;
+        }
+        if let Some(instruction) = env_var("WATCHER_SYSTEM_INSTRUCTION") {
+            self.prompts.system_instruction = instruction;
+        }
+        env_parse("WATCHER_PROFILE", &mut self.prompts.profile)?;
+        env_parse("WATCHER_PROMPT", &mut self.prompts.prompt)?;
+        env_parse("WATCHER_PREAMBLE", &mut self.prompts.preamble)?;
+        if let Some(dir) = env_var("WATCHER_OUTPUT_DIR") {
+            self.output.dir = PathBuf::from(dir);
+        }
+        env_parse("WATCHER_FORMAT", &mut self.output.format)?;
+        env_parse("WATCHER_QUALITY", &mut self.output.quality)?;
+        if let Some(apps) = env_var("WATCHER_REDACT_APPS") {
+            self.privacy.redact_apps = split_list(&apps);
+        }
+        if let Some(titles) = env_var("WATCHER_REDACT_TITLES") {
+            self.privacy.redact_titles = split_list(&titles);
+        }
+        if let Some(apps) = env_var("WATCHER_EXCLUDE_APPS") {
+            self.privacy.exclude_apps = split_list(&apps);
+        }
+        if let Some(patterns) = env_var("WATCHER_CLIPBOARD_REDACT") {
+            self.privacy.clipboard_redact = split_list(&patterns);
+        }
+        env_parse("WATCHER_ANONYMIZE", &mut self.privacy.anonymize)?;
+        Ok(())
+    }
+}
+
+/// `$XDG_CONFIG_HOME`, falling back to `~/.config`
+pub(crate) fn config_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+}
+
+/// A set, non-empty environment variable
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn env_parse<T: FromStr>(name: &'static str, setting: &mut Option<T>) -> WatcherConfigResult<()> {
+    if let Some(value) = env_var(name) {
+        *setting = Some(
+            value
+                .parse()
+                .map_err(|_| WatcherConfigError::Env { name, value })?,
+        );
+    }
+    Ok(())
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
@@ -0,0 +1,198 @@
+use std::str::FromStr;
+
+/// A point in global display coordinates (points, origin at the top-left of the main display)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// A rectangle in global display coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    /// The rectangle grown by `margin` on every side
+    pub fn expanded(&self, margin: f64) -> Rect {
+        Rect {
+            x: self.x - margin,
+            y: self.y - margin,
+            width: self.width + 2.0 * margin,
+            height: self.height + 2.0 * margin,
+        }
+    }
+
+    /// The area both rectangles cover, `None` when they don't overlap
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        (right > left && bottom > top).then_some(Rect {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        })
+    }
+}
+
+impl FromStr for Rect {
+    type Err = String;
+
+    /// Parses `x,y,width,height`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<f64> = value
+            .split(',')
+            .map(|part| part.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("invalid rectangle '{}': {}", value, e))?;
+        match parts[..] {
+            [x, y, width, height] if width > 0.0 && height > 0.0 => Ok(Rect {
+                x,
+                y,
+                width,
+                height,
+            }),
+            [_, _, _, _] => Err(format!("rectangle '{}' must have a positive size", value)),
+            _ => Err(format!(
+                "invalid rectangle '{}' (expected x,y,width,height)",
+                value
+            )),
+        }
+    }
+}
+
+/// Returns the current mouse cursor location, if it can be queried on this platform
+#[cfg(target_os = "macos")]
+pub fn cursor_position() -> Option<Point> {
+    use core_graphics::event::CGEvent;
+    use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+
+    let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState).ok()?;
+    let event = CGEvent::new(source).ok()?;
+    let location = event.location();
+    Some(Point {
+        x: location.x,
+        y: location.y,
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn cursor_position() -> Option<Point> {
+    None
+}
+
+/// Returns the bounds of the main display in points
+#[cfg(target_os = "macos")]
+pub fn main_display_bounds() -> Option<Rect> {
+    let bounds = core_graphics::display::CGDisplay::main().bounds();
+    if bounds.size.width <= 0.0 || bounds.size.height <= 0.0 {
+        return None;
+    }
+    Some(Rect {
+        x: bounds.origin.x,
+        y: bounds.origin.y,
+        width: bounds.size.width,
+        height: bounds.size.height,
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn main_display_bounds() -> Option<Rect> {
+    None
+}
+
+/// Returns the bounds of a display in global points
+#[cfg(target_os = "macos")]
+pub fn display_bounds(display_id: u32) -> Option<Rect> {
+    let bounds = core_graphics::display::CGDisplay::new(display_id).bounds();
+    if bounds.size.width <= 0.0 || bounds.size.height <= 0.0 {
+        return None;
+    }
+    Some(Rect {
+        x: bounds.origin.x,
+        y: bounds.origin.y,
+        width: bounds.size.width,
+        height: bounds.size.height,
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn display_bounds(_display_id: u32) -> Option<Rect> {
+    None
+}
+
+/// Returns the CoreGraphics id of the main display
+#[cfg(target_os = "macos")]
+pub fn main_display_id() -> Option<u32> {
+    Some(core_graphics::display::CGDisplay::main().id)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn main_display_id() -> Option<u32> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn intersection_of_overlapping_rects() {
+        let a = rect(0.0, 0.0, 100.0, 100.0);
+        let b = rect(50.0, -20.0, 100.0, 50.0);
+        assert_eq!(a.intersection(&b), Some(rect(50.0, 0.0, 50.0, 30.0)));
+        assert_eq!(b.intersection(&a), a.intersection(&b));
+    }
+
+    #[test]
+    fn intersection_with_negative_origins() {
+        let left = rect(-1920.0, -200.0, 1920.0, 1080.0);
+        let window = rect(-100.0, 0.0, 300.0, 200.0);
+        assert_eq!(
+            left.intersection(&window),
+            Some(rect(-100.0, 0.0, 100.0, 200.0))
+        );
+    }
+
+    #[test]
+    fn intersection_of_a_contained_rect_is_itself() {
+        let outer = rect(0.0, 0.0, 100.0, 100.0);
+        let inner = rect(10.0, 20.0, 30.0, 40.0);
+        assert_eq!(outer.intersection(&inner), Some(inner));
+    }
+
+    #[test]
+    fn no_intersection_when_apart_or_only_touching() {
+        let a = rect(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(a.intersection(&rect(200.0, 0.0, 10.0, 10.0)), None);
+        assert_eq!(a.intersection(&rect(100.0, 0.0, 10.0, 10.0)), None);
+        assert_eq!(a.intersection(&rect(0.0, 100.0, 10.0, 10.0)), None);
+    }
+
+    #[test]
+    fn parses_rects() {
+        assert_eq!(
+            "-10, 20.5,30,40".parse::<Rect>(),
+            Ok(rect(-10.0, 20.5, 30.0, 40.0))
+        );
+        assert!("0,0,0,10".parse::<Rect>().is_err());
+        assert!("0,0,10".parse::<Rect>().is_err());
+        assert!("a,0,10,10".parse::<Rect>().is_err());
+    }
+}
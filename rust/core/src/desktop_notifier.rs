@@ -0,0 +1,190 @@
+use crate::CaptureSession;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+
+/// Title of desktop notifications
+pub const NOTIFICATION_TITLE: &str = "Watcher";
+
+/// How often `watch_connection` checks the Gemini session
+const CONNECTION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Error)]
+pub enum DesktopNotifierError {
+    #[error("Desktop notifications are only available on macOS")]
+    Unsupported,
+    #[error("Failed to run osascript: {0}")]
+    Script(#[from] std::io::Error),
+}
+
+pub type DesktopNotifierResult<T> = std::result::Result<T, DesktopNotifierError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    /// UNUserNotificationCenter, which only works inside an app bundle
+    UserNotifications,
+    /// `display notification` through osascript, for a binary run from the terminal
+    AppleScript,
+}
+
+/// Alerts the user locally through Notification Center, e.g. when the Gemini session
+/// disconnects or a notify rule fires, without setting up a webhook
+///
+/// Inside an app bundle, e.g. when launched by the Swift app, the notifications are posted
+/// with UserNotifications under the app's name; a bare binary has no bundle to post them
+/// under and goes through osascript instead.
+#[derive(Debug)]
+pub struct DesktopNotifier {
+    backend: Backend,
+}
+
+impl DesktopNotifier {
+    /// Picks the backend, asking for permission to show alerts the first time in an app bundle
+    pub fn new() -> DesktopNotifierResult<Self> {
+        if !cfg!(target_os = "macos") {
+            return Err(DesktopNotifierError::Unsupported);
+        }
+        let backend = if platform::has_bundle_identifier() {
+            platform::request_authorization();
+            Backend::UserNotifications
+        } else {
+            Backend::AppleScript
+        };
+        Ok(Self { backend })
+    }
+
+    /// Posts a notification and returns without waiting for it to be shown
+    pub fn notify(&self, title: &str, body: &str) -> DesktopNotifierResult<()> {
+        match self.backend {
+            Backend::UserNotifications => {
+                platform::post(title, body);
+                Ok(())
+            }
+            Backend::AppleScript => {
+                let script = format!(
+                    "display notification {} with title {}",
+                    applescript_string(body),
+                    applescript_string(title)
+                );
+                std::process::Command::new("osascript")
+                    .arg("-e")
+                    .arg(script)
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::null())
+                    .spawn()?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Notifies the user whenever the Gemini session of `session` closes, e.g. after a
+    /// network error; abort the task before closing the session on purpose
+    pub fn watch_connection(self: &Arc<Self>, session: Arc<CaptureSession>) -> JoinHandle<()> {
+        let notifier = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(CONNECTION_POLL_INTERVAL);
+            let mut connected = true;
+            loop {
+                ticker.tick().await;
+                // A memory refresh swaps in a new sender before closing the old one
+                let closed = session.sender().is_closed();
+                if closed
+                    && connected
+                    && let Err(e) =
+                        notifier.notify(NOTIFICATION_TITLE, "Gemini session disconnected")
+                {
+                    eprintln!("❌ Failed to show a notification: {}", e);
+                }
+                connected = !closed;
+            }
+        })
+    }
+}
+
+/// `text` as a quoted AppleScript string literal
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use block::ConcreteBlock;
+    use objc::runtime::{Object, BOOL};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    #[allow(non_camel_case_types)]
+    type id = *mut Object;
+
+    const NIL: id = std::ptr::null_mut();
+
+    const UTF8_ENCODING: usize = 4;
+
+    /// UNAuthorizationOptionSound | UNAuthorizationOptionAlert
+    const SOUND_AND_ALERT: usize = (1 << 1) | (1 << 2);
+
+    #[link(name = "UserNotifications", kind = "framework")]
+    unsafe extern "C" {}
+
+    /// Autoreleased NSString copy of `text`
+    unsafe fn ns_string(text: &str) -> id {
+        unsafe {
+            let string: id = msg_send![class!(NSString), alloc];
+            let string: id = msg_send![string, initWithBytes: text.as_ptr()
+                                                     length: text.len()
+                                                   encoding: UTF8_ENCODING];
+            msg_send![string, autorelease]
+        }
+    }
+
+    pub fn has_bundle_identifier() -> bool {
+        objc::rc::autoreleasepool(|| unsafe {
+            let bundle: id = msg_send![class!(NSBundle), mainBundle];
+            let identifier: id = msg_send![bundle, bundleIdentifier];
+            !identifier.is_null()
+        })
+    }
+
+    pub fn request_authorization() {
+        objc::rc::autoreleasepool(|| unsafe {
+            let center: id = msg_send![class!(UNUserNotificationCenter), currentNotificationCenter];
+            // Denied notifications are dropped by the system; there is nothing else to do
+            let handler = ConcreteBlock::new(|_granted: BOOL, _error: id| {}).copy();
+            let _: () = msg_send![center, requestAuthorizationWithOptions: SOUND_AND_ALERT
+                                                        completionHandler: &*handler];
+        });
+    }
+
+    pub fn post(title: &str, body: &str) {
+        // Tokio threads have no autorelease pool of their own
+        objc::rc::autoreleasepool(|| unsafe {
+            let content: id = msg_send![class!(UNMutableNotificationContent), new];
+            let _: () = msg_send![content, setTitle: ns_string(title)];
+            let _: () = msg_send![content, setBody: ns_string(body)];
+            let sound: id = msg_send![class!(UNNotificationSound), defaultSound];
+            let _: () = msg_send![content, setSound: sound];
+
+            let uuid: id = msg_send![class!(NSUUID), UUID];
+            let identifier: id = msg_send![uuid, UUIDString];
+            let request: id = msg_send![class!(UNNotificationRequest),
+                                        requestWithIdentifier: identifier
+                                                      content: content
+                                                      trigger: NIL];
+            let center: id = msg_send![class!(UNUserNotificationCenter), currentNotificationCenter];
+            let _: () = msg_send![center, addNotificationRequest: request
+                                            withCompletionHandler: NIL];
+            let _: () = msg_send![content, release];
+        });
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    pub fn has_bundle_identifier() -> bool {
+        false
+    }
+
+    pub fn request_authorization() {}
+
+    pub fn post(_title: &str, _body: &str) {}
+}
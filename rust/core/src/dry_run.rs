@@ -0,0 +1,166 @@
+use crate::gemini::{GeminiError, Result};
+use crate::{
+    session_stamp, unix_millis, AnalysisBackend, AnalysisEvents, ClientContent, Part, ServerEvent,
+    Setup, ToolResponse,
+};
+use base64::Engine;
+use futures::future::BoxFuture;
+use serde_json::json;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::watch;
+
+/// Stands in for the model connection in a dry run: everything that would be uploaded is
+/// written to a directory instead, so users can audit it before trusting the tool
+///
+/// Each run gets its own directory, so earlier runs stay intact, with `setup.json` holding
+/// the system instruction and generation config, one line per turn in `turns.jsonl` with its
+/// text and the files of its images, the images exactly as encoded for upload, and
+/// `audio.pcm` with any 16 kHz mono audio. No answers arrive, so nothing is analyzed.
+pub struct DryRun {
+    dir: PathBuf,
+    log: parking_lot::Mutex<DryRunLog>,
+    /// Set on close, which ends the events
+    closed: watch::Sender<bool>,
+}
+
+struct DryRunLog {
+    turns: File,
+    audio: Option<File>,
+    /// Number of the next turn
+    next: usize,
+}
+
+impl DryRun {
+    /// Writes to a subdirectory of `dir` named after the current time, e.g.
+    /// `20261015-143000`, starting with `setup`
+    pub fn open(dir: impl Into<PathBuf>, setup: &Setup) -> io::Result<(Arc<Self>, DryRunEvents)> {
+        let dir = dir.into().join(session_stamp(SystemTime::now()));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("setup.json"), serde_json::to_vec_pretty(setup)?)?;
+        let turns = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join("turns.jsonl"))?;
+        let (closed, closed_rx) = watch::channel(false);
+        let dry_run = Self {
+            dir,
+            log: parking_lot::Mutex::new(DryRunLog {
+                turns,
+                audio: None,
+                next: 1,
+            }),
+            closed,
+        };
+        Ok((Arc::new(dry_run), DryRunEvents { closed: closed_rx }))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn write_turn(&self, content: &ClientContent) -> io::Result<()> {
+        let mut log = self.log.lock();
+        let turn = log.next;
+        log.next += 1;
+
+        let mut text = Vec::new();
+        let mut images = Vec::new();
+        for part in content.turns.iter().flat_map(|turn| &turn.parts) {
+            if let Part::Text { text: part } = part {
+                text.push(part.as_str());
+            } else if let Some(blob) = part.inline_data() {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(&blob.data)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                let extension = match blob.mime_type.as_deref() {
+                    Some("image/jpeg") | None => "jpg",
+                    Some("image/webp") => "webp",
+                    Some("image/png") => "png",
+                    Some(_) => "bin",
+                };
+                let file = format!("turn_{:05}_{}.{}", turn, images.len() + 1, extension);
+                std::fs::write(self.dir.join(&file), bytes)?;
+                images.push(file);
+            }
+        }
+        let record = json!({
+            "turn": turn,
+            "timestampMs": unix_millis(SystemTime::now()),
+            "turnComplete": content.turn_complete == Some(true),
+            "text": text.join("\n"),
+            "images": images,
+        });
+        writeln!(log.turns, "{}", record)
+    }
+
+    fn write_audio(&self, pcm: &[u8]) -> io::Result<()> {
+        let mut log = self.log.lock();
+        let audio = match &mut log.audio {
+            Some(audio) => audio,
+            audio => audio.insert(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(self.dir.join("audio.pcm"))?,
+            ),
+        };
+        audio.write_all(pcm)
+    }
+}
+
+fn dry_run_error(e: io::Error) -> GeminiError {
+    GeminiError::Backend(format!("Failed to write the dry run: {}", e))
+}
+
+impl AnalysisBackend for DryRun {
+    fn send_turn(&self, content: ClientContent) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            if self.is_closed() {
+                return Err(GeminiError::ConnectionClosed);
+            }
+            self.write_turn(&content).map_err(dry_run_error)
+        })
+    }
+
+    fn send_audio<'a>(&'a self, pcm: &'a [u8]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move { self.write_audio(pcm).map_err(dry_run_error) })
+    }
+
+    fn send_tool_response(&self, _response: ToolResponse) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn is_closed(&self) -> bool {
+        *self.closed.borrow()
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            self.closed.send_replace(true);
+            Ok(())
+        })
+    }
+}
+
+/// Events of a dry run, which end when it is closed and carry nothing before
+pub struct DryRunEvents {
+    closed: watch::Receiver<bool>,
+}
+
+impl AnalysisEvents for DryRunEvents {
+    fn recv(&mut self) -> BoxFuture<'_, Result<Option<ServerEvent>>> {
+        Box::pin(async move {
+            // A dropped dry run ends the events too
+            self.closed.wait_for(|closed| *closed).await.ok();
+            Ok(None)
+        })
+    }
+
+    fn send_tool_response(&self, _response: ToolResponse) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
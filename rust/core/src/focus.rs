@@ -0,0 +1,102 @@
+use crate::{unix_millis, AnalysisResult, GenerationConfig, Notification, ScreenActivity};
+use serde_json::{json, Value};
+use std::time::SystemTime;
+
+/// Off-task answers in a row before the user is alerted, by default
+pub const DEFAULT_FOCUS_STREAK: usize = 3;
+
+#[derive(Default)]
+struct FocusState {
+    /// Off-task answers since the last on-task one
+    off_task: usize,
+    /// When the current off-task streak began
+    since: Option<SystemTime>,
+    /// Whether the current streak already alerted
+    alerted: bool,
+}
+
+/// A focus session: the user declares what they mean to work on, every structured answer
+/// says whether the screen shows work toward it, and a streak of answers that don't raises
+/// a notification
+pub struct FocusSession {
+    intention: String,
+    streak: usize,
+    state: parking_lot::Mutex<FocusState>,
+}
+
+impl FocusSession {
+    pub fn new(intention: impl Into<String>) -> Self {
+        Self {
+            intention: intention.into(),
+            streak: DEFAULT_FOCUS_STREAK,
+            state: parking_lot::Mutex::new(FocusState::default()),
+        }
+    }
+
+    /// Alerts after `streak` off-task answers in a row instead of `DEFAULT_FOCUS_STREAK`
+    pub fn with_streak(mut self, streak: usize) -> Self {
+        self.streak = streak.max(1);
+        self
+    }
+
+    pub fn intention(&self) -> &str {
+        &self.intention
+    }
+
+    /// Addition to the structured analysis instruction explaining `on_task`
+    pub fn instruction(&self) -> String {
+        format!(
+            "The user set out to focus on: {}. Also answer on_task: whether the screenshot \
+             shows work toward that; reference material, related messages and a short pause \
+             between steps count as on task.",
+            self.intention
+        )
+    }
+
+    /// `config` constrained to the structured analysis schema with `on_task` added
+    pub fn generation_config(config: GenerationConfig) -> GenerationConfig {
+        let mut schema = ScreenActivity::schema();
+        schema["properties"]["on_task"] = json!({ "type": "BOOLEAN" });
+        for list in ["required", "propertyOrdering"] {
+            if let Some(fields) = schema[list].as_array_mut() {
+                fields.push(Value::from("on_task"));
+            }
+        }
+        GenerationConfig {
+            response_schema: Some(schema),
+            ..ScreenActivity::generation_config(config)
+        }
+    }
+
+    /// Counts `result` toward the off-task streak and returns the notification when the
+    /// streak reaches its length; answers without `on_task` are skipped
+    pub fn observe(&self, result: &AnalysisResult) -> Option<Notification> {
+        result.frame_seq?;
+        let activity = result.activity.as_ref()?;
+        let mut state = self.state.lock();
+        if activity.on_task? {
+            *state = FocusState::default();
+            return None;
+        }
+        state.off_task += 1;
+        let since = *state.since.get_or_insert(result.timestamp);
+        if state.alerted || state.off_task < self.streak {
+            return None;
+        }
+        state.alerted = true;
+        Some(Notification {
+            rule: format!("Off task from {}", self.intention),
+            since_ms: unix_millis(since),
+            timestamp_ms: unix_millis(result.timestamp),
+            minutes: result
+                .timestamp
+                .duration_since(since)
+                .unwrap_or_default()
+                .as_secs_f64()
+                / 60.0,
+            category: Some(activity.category),
+            app: activity.app.clone(),
+            summary: activity.summary.clone(),
+        })
+    }
+}
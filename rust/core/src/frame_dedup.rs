@@ -0,0 +1,87 @@
+use crate::{luma_grid, FrameData};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::time::SystemTime;
+
+/// Recent frames remembered by default, enough for a few screens switched back and forth
+pub const DEFAULT_DEDUP_CAPACITY: usize = 8;
+
+/// Low luma bits dropped before hashing, so rendering noise doesn't change the hash
+const LUMA_QUANTIZATION_BITS: u8 = 3;
+
+/// An earlier frame with the same content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeenFrame {
+    pub hash: u64,
+    /// Frame number it was captured as
+    pub index: usize,
+    pub at: SystemTime,
+}
+
+/// Remembers the hashes of recently analyzed frames, so a screen that comes back, e.g. when
+/// alternating between two windows, isn't sent again
+///
+/// Unlike `ChangeDetector`, which only compares against the last sent frame, this keeps a
+/// small least-recently-used list of screens.
+#[derive(Debug, Clone)]
+pub struct FrameDeduplicator {
+    capacity: usize,
+    recent: VecDeque<SeenFrame>,
+}
+
+impl Default for FrameDeduplicator {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_CAPACITY)
+    }
+}
+
+impl FrameDeduplicator {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Returns the earlier sighting when `frame` was already seen, which then becomes the
+    /// most recent entry; otherwise remembers the frame as number `index`
+    pub fn check(&mut self, frame: &FrameData, index: usize) -> Option<SeenFrame> {
+        let hash = frame_hash(frame);
+        if let Some(position) = self.recent.iter().position(|seen| seen.hash == hash) {
+            let seen = self.recent.remove(position)?;
+            self.recent.push_front(seen);
+            return Some(seen);
+        }
+        if self.recent.len() == self.capacity {
+            self.recent.pop_back();
+        }
+        self.recent.push_front(SeenFrame {
+            hash,
+            index,
+            at: SystemTime::now(),
+        });
+        None
+    }
+
+    /// Forgets frame `index`, e.g. when it couldn't be sent, so a later frame like it isn't
+    /// reported as already seen
+    pub fn forget(&mut self, index: usize) {
+        self.recent.retain(|seen| seen.index != index);
+    }
+
+    /// Forgets every frame, e.g. after switching to a new Gemini session that hasn't seen them
+    pub fn reset(&mut self) {
+        self.recent.clear();
+    }
+}
+
+/// Hash of the frame size and its quantized luma grid
+pub fn frame_hash(frame: &FrameData) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (frame.width, frame.height).hash(&mut hasher);
+    for luma in luma_grid(frame) {
+        (luma >> LUMA_QUANTIZATION_BITS).hash(&mut hasher);
+    }
+    hasher.finish()
+}
@@ -0,0 +1,80 @@
+use crate::{BufferPool, FrameData};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A frame and the moment it arrived from the capturer
+#[derive(Clone)]
+pub struct TimedFrame {
+    pub captured_at: Instant,
+    pub frame: Arc<FrameData>,
+}
+
+/// Ring buffer of the most recent frames, oldest first
+pub struct FrameHistory {
+    frames: VecDeque<TimedFrame>,
+    capacity: usize,
+}
+
+impl FrameHistory {
+    /// Keeps up to `capacity` frames; 0 disables the history
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Changes the capacity, evicting the oldest frames beyond it
+    pub fn set_capacity(&mut self, capacity: usize, pool: &BufferPool) {
+        self.capacity = capacity;
+        self.evict(pool);
+    }
+
+    /// Appends a frame, handing evicted frames back to `pool`
+    pub fn push(&mut self, frame: TimedFrame, pool: &BufferPool) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.frames.push_back(frame);
+        self.evict(pool);
+    }
+
+    /// The `count` most recent frames, oldest first
+    pub fn last_n(&self, count: usize) -> Vec<TimedFrame> {
+        let skip = self.frames.len().saturating_sub(count);
+        self.frames.iter().skip(skip).cloned().collect()
+    }
+
+    /// Frames captured at or after `since`, oldest first
+    pub fn since(&self, since: Instant) -> Vec<TimedFrame> {
+        let start = self
+            .frames
+            .partition_point(|frame| frame.captured_at < since);
+        self.frames.range(start..).cloned().collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    fn evict(&mut self, pool: &BufferPool) {
+        while self.frames.len() > self.capacity {
+            if let Some(evicted) = self.frames.pop_front() {
+                pool.recycle_frame(evicted.frame);
+            }
+        }
+    }
+}
@@ -1,10 +1,24 @@
+use crate::{
+    cursor_position, display_bounds, frontmost_window, list_windows, main_display_id, nv12_to_bgra,
+    packed_to_bgra, BufferPool, FrameHistory, PackedLayout, Point, Rect, Redactor,
+    SystemAudioChunker, TimedFrame, WindowInfo,
+};
+use derive_builder::Builder;
+use futures::Stream;
 use scap::{
-    capturer::Capturer as ScapCapturer,
-    frame::{Frame, VideoFrame},
+    capturer::{
+        Area, Capturer as ScapCapturer, Options as ScapOptions, Point as ScapPoint, Resolution,
+        Size,
+    },
+    frame::{AudioFrame, Frame, FrameType, VideoFrame},
+    Target as ScapTarget,
 };
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::sync::Notify;
+use tokio::sync::mpsc::{error::TrySendError, Receiver, Sender};
+use tokio::sync::{watch, Notify};
 
 #[derive(Debug, Error)]
 pub enum CaptureError {
@@ -12,73 +26,764 @@ pub enum CaptureError {
     FrameError(String),
     #[error("No frame available")]
     NoFrameAvailable,
+    #[error("Capture target not found: {0}")]
+    TargetNotFound(String),
+    #[error("Invalid capture region: {0:?}")]
+    InvalidRegion(Rect),
+    #[error("Crop of {width}x{height} at {x},{y} is outside the frame")]
+    CropOutOfBounds {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    #[error("Capture stopped")]
+    Stopped,
+    #[error("Failed to create capturer: {0}")]
+    Capturer(#[from] scap::capturer::CapturerBuildError),
 }
 
 pub type CaptureResult<T> = std::result::Result<T, CaptureError>;
 
+/// What a FrameSource records
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum CaptureTarget {
+    /// The main display
+    #[default]
+    Display,
+    /// Another connected display, by CoreGraphics display id
+    DisplayId(u32),
+    /// A single window, by window server id
+    Window(u32),
+    /// The frontmost regular window owned by a process
+    Process(u32),
+    /// The first window whose title contains the text (case-insensitive)
+    WindowTitle(String),
+}
+
+impl CaptureTarget {
+    pub fn is_window(&self) -> bool {
+        !matches!(self, CaptureTarget::Display | CaptureTarget::DisplayId(_))
+    }
+
+    /// Looks up the scap target, returning `None` for the main display
+    pub fn resolve(&self) -> CaptureResult<Option<ScapTarget>> {
+        let window_id = match self {
+            CaptureTarget::Display => return Ok(None),
+            CaptureTarget::DisplayId(id) => {
+                return scap::get_all_targets()
+                    .into_iter()
+                    .find(|target| {
+                        matches!(target, ScapTarget::Display(display) if display.id == *id)
+                    })
+                    .map(Some)
+                    .ok_or_else(|| {
+                        CaptureError::TargetNotFound(format!("no display with id {}", id))
+                    });
+            }
+            CaptureTarget::Window(id) => *id,
+            CaptureTarget::Process(pid) => list_windows()
+                .into_iter()
+                .find(|window| window.pid == *pid && window.is_normal())
+                .map(|window| window.id)
+                .ok_or_else(|| {
+                    CaptureError::TargetNotFound(format!("no on-screen window for PID {}", pid))
+                })?,
+            CaptureTarget::WindowTitle(title) => {
+                let needle = title.to_lowercase();
+                return scap::get_all_targets()
+                    .into_iter()
+                    .find(|target| match target {
+                        ScapTarget::Window(window) => window.title.to_lowercase().contains(&needle),
+                        ScapTarget::Display(_) => false,
+                    })
+                    .map(Some)
+                    .ok_or_else(|| {
+                        CaptureError::TargetNotFound(format!("no window titled '{}'", title))
+                    });
+            }
+        };
+
+        scap::get_all_targets()
+            .into_iter()
+            .find(|target| matches!(target, ScapTarget::Window(window) if window.id == window_id))
+            .map(Some)
+            .ok_or_else(|| {
+                CaptureError::TargetNotFound(format!("window {} is not capturable", window_id))
+            })
+    }
+}
+
+/// Capturer settings used by `FrameSource::from_options`
+#[derive(Debug, Clone, Builder)]
+#[builder(pattern = "owned")]
+pub struct CaptureOptions {
+    #[builder(default)]
+    pub target: CaptureTarget,
+    #[builder(default = "1")]
+    pub fps: u32,
+    #[builder(default = "true")]
+    pub show_cursor: bool,
+    #[builder(default = "true")]
+    pub show_highlight: bool,
+    #[builder(default = "Resolution::_720p")]
+    pub output_resolution: Resolution,
+    /// Only capture this rectangle, in points from the top-left of the display or window
+    #[builder(default, setter(strip_option))]
+    pub region: Option<Rect>,
+    /// Recent frames kept for `last_n_frames` and `frames_since`
+    #[builder(default)]
+    pub history: usize,
+    /// Also capture system audio, delivered through `audio_channel`
+    #[builder(default)]
+    pub captures_audio: bool,
+}
+
+impl CaptureOptions {
+    pub fn builder() -> CaptureOptionsBuilder {
+        CaptureOptionsBuilder::default()
+    }
+}
+
 /// Owned frame data
 #[derive(Clone)]
 pub struct FrameData {
     pub width: u32,
     pub height: u32,
     pub data: Vec<u8>,
+    /// Display the frame was captured from; `None` for window captures
+    pub display_id: Option<u32>,
+    /// Mouse location in global points when the frame arrived
+    pub cursor: Option<Point>,
+    /// Frontmost window when the frame arrived
+    pub active_window: Option<WindowInfo>,
+}
+
+impl FrameData {
+    /// Copies a BGRA sub-rectangle out of the frame, failing if it is empty or out of bounds
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> CaptureResult<FrameData> {
+        let out_of_bounds = || CaptureError::CropOutOfBounds {
+            x,
+            y,
+            width,
+            height,
+        };
+        let right = x.checked_add(width).filter(|&right| right <= self.width);
+        let bottom = y
+            .checked_add(height)
+            .filter(|&bottom| bottom <= self.height);
+        if width == 0 || height == 0 || right.is_none() || bottom.is_none() {
+            return Err(out_of_bounds());
+        }
+
+        let stride = self.width as usize * 4;
+        let row_bytes = width as usize * 4;
+        let mut data = Vec::with_capacity(row_bytes * height as usize);
+        for row in y..y + height {
+            let start = row as usize * stride + x as usize * 4;
+            let row = self.data.get(start..start + row_bytes);
+            data.extend_from_slice(row.ok_or_else(out_of_bounds)?);
+        }
+
+        Ok(FrameData {
+            width,
+            height,
+            data,
+            display_id: self.display_id,
+            cursor: self.cursor,
+            active_window: self.active_window.clone(),
+        })
+    }
+}
+
+/// Latest-frame view of a FrameSource; each subscriber waits for new frames on its own,
+/// so a slow consumer never holds back the others
+#[derive(Clone)]
+pub struct FrameSubscriber {
+    latest: watch::Receiver<Option<Arc<FrameData>>>,
+}
+
+impl FrameSubscriber {
+    /// Waits for a frame this subscriber has not returned yet
+    pub async fn next_frame(&mut self) -> CaptureResult<Arc<FrameData>> {
+        loop {
+            self.latest
+                .changed()
+                .await
+                .map_err(|_| CaptureError::Stopped)?;
+            if let Some(frame) = self.latest.borrow_and_update().clone() {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Most recent frame, without waiting
+    pub fn latest(&self) -> Option<Arc<FrameData>> {
+        self.latest.borrow().clone()
+    }
+
+    /// Frames as a stream, ending once the source is stopped
+    pub fn frames(self) -> impl Stream<Item = Arc<FrameData>> {
+        futures::stream::unfold(self, |mut subscriber| async move {
+            let frame = subscriber.next_frame().await.ok()?;
+            Some((frame, subscriber))
+        })
+    }
+}
+
+/// What a FrameSource's thread pulls frames from: the screen, or e.g. saved images
+pub trait FrameProducer: Send + 'static {
+    /// Blocks until the next frame arrives; `None` ends the source
+    fn next_frame(&mut self) -> Option<ProducedFrame>;
+
+    /// Called on the source's thread when it is paused
+    fn pause(&mut self) {}
+
+    /// Called on the source's thread when it is resumed after a pause
+    fn resume(&mut self) {}
+
+    /// Called once on the source's thread when it stops
+    fn finish(&mut self) {}
+
+    /// Whether every frame must reach `get_next_frame`, so the thread waits for the previous
+    /// one to be taken instead of replacing it, e.g. for saved images
+    fn lossless(&self) -> bool {
+        false
+    }
 }
 
-/// Manages a scap Capturer and maintains the last captured frame
+/// Something a FrameProducer delivered
+pub enum ProducedFrame {
+    Video(FrameData),
+    /// System audio captured along with the screen
+    Audio(AudioFrame),
+    /// Nothing to deliver this time, e.g. a frame that failed to decode
+    Skipped,
+    /// The producer can't go on; the source stops and reports this from `get_next_frame`
+    Failed(String),
+    /// Something subscribers of `event_channel` should hear about
+    Event(CaptureEvent),
+}
+
+/// Changes in the state of a FrameSource's capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureEvent {
+    /// The capturer stopped delivering frames, e.g. because its display was disconnected or
+    /// the screen recording permission was revoked; it is rebuilt after a backoff
+    Interrupted,
+    /// The capturer was rebuilt with the same options, after `attempts` tries
+    Restarted { attempts: u32 },
+}
+
+/// Wait before the first attempt to rebuild an interrupted capturer, doubled per attempt
+const RECOVERY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest wait between attempts to rebuild a capturer
+const MAX_RECOVERY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Attempts to rebuild a capturer before capture gives up
+const MAX_RECOVERY_ATTEMPTS: u32 = 8;
+
+/// Consecutive frames in a pixel format that can't be converted before capture gives up,
+/// rather than waiting forever for a usable one
+const UNSUPPORTED_FRAME_LIMIT: u32 = 30;
+
+/// Frames of a scap Capturer, which runs while the source isn't paused
+struct ScreenProducer {
+    capturer: ScapCapturer,
+    display_id: Option<u32>,
+    capturing: bool,
+    /// Frames in a row that couldn't be converted to BGRA
+    unsupported: u32,
+    /// What the capturer was built with, so it can be rebuilt; `None` for capturers
+    /// handed to `FrameSource::new`
+    options: Option<ScapOptions>,
+    /// Failed attempts to rebuild the capturer since it was interrupted, if it was
+    recovering: Option<u32>,
+}
+
+impl ScreenProducer {
+    /// Rebuilds the capturer after a backoff, giving up after MAX_RECOVERY_ATTEMPTS
+    fn recover(&mut self, failed: u32) -> ProducedFrame {
+        let Some(options) = self.options.clone() else {
+            return ProducedFrame::Failed("the capturer stopped".to_string());
+        };
+        if failed >= MAX_RECOVERY_ATTEMPTS {
+            return ProducedFrame::Failed(format!(
+                "the capturer could not be restarted after {} attempts",
+                failed
+            ));
+        }
+        let backoff = RECOVERY_BACKOFF
+            .saturating_mul(1 << failed.min(16))
+            .min(MAX_RECOVERY_BACKOFF);
+        // Stopping unparks the thread, so it doesn't wait out the backoff
+        std::thread::park_timeout(backoff);
+
+        match ScapCapturer::build(options) {
+            Ok(mut capturer) => {
+                if self.capturing {
+                    capturer.start_capture();
+                }
+                self.capturer = capturer;
+                self.recovering = None;
+                ProducedFrame::Event(CaptureEvent::Restarted {
+                    attempts: failed + 1,
+                })
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to restart the capturer: {}", e);
+                self.recovering = Some(failed + 1);
+                ProducedFrame::Skipped
+            }
+        }
+    }
+}
+
+impl FrameProducer for ScreenProducer {
+    fn next_frame(&mut self) -> Option<ProducedFrame> {
+        if let Some(failed) = self.recovering {
+            return Some(self.recover(failed));
+        }
+        // An error means the capturer's channel closed
+        let Ok(frame) = self.capturer.get_next_frame() else {
+            // Without options there is nothing to rebuild from, so the source just stops
+            self.options.as_ref()?;
+            self.recovering = Some(0);
+            return Some(ProducedFrame::Event(CaptureEvent::Interrupted));
+        };
+        let video = match frame {
+            Frame::Video(video) => video,
+            Frame::Audio(audio_frame) => return Some(ProducedFrame::Audio(audio_frame)),
+        };
+        Some(match to_bgra(video) {
+            Ok((width, height, data)) => {
+                self.unsupported = 0;
+                ProducedFrame::Video(FrameData {
+                    width,
+                    height,
+                    data,
+                    display_id: self.display_id,
+                    cursor: cursor_position(),
+                    active_window: frontmost_window(),
+                })
+            }
+            Err(format) => {
+                self.unsupported += 1;
+                if self.unsupported < UNSUPPORTED_FRAME_LIMIT {
+                    ProducedFrame::Skipped
+                } else {
+                    ProducedFrame::Failed(format!("unsupported pixel format: {}", format))
+                }
+            }
+        })
+    }
+
+    fn pause(&mut self) {
+        if self.capturing {
+            // An interrupted capturer has nothing left to stop
+            if self.recovering.is_none() {
+                self.capturer.stop_capture();
+            }
+            self.capturing = false;
+        }
+    }
+
+    fn resume(&mut self) {
+        if !self.capturing {
+            if self.recovering.is_none() {
+                self.capturer.start_capture();
+            }
+            self.capturing = true;
+        }
+    }
+
+    fn finish(&mut self) {
+        self.pause();
+    }
+}
+
+/// Masks a display frame, returning false when the display's bounds are unknown, e.g. for
+/// replayed images, so the frame can't be redacted and must not be used
+fn redact(redactor: &Redactor, frame: &mut FrameData) -> bool {
+    let Some(display) = frame.display_id.and_then(display_bounds) else {
+        return false;
+    };
+    redactor.apply(frame, redactor.capture_area(display));
+    true
+}
+
+/// Width, height and BGRA pixels of a scap frame, or a description of a format that can't
+/// be converted
+fn to_bgra(frame: VideoFrame) -> Result<(u32, u32, Vec<u8>), String> {
+    let size = |width: i32, height: i32| (width.max(0) as usize, height.max(0) as usize);
+    let packed = |data: &[u8], layouts: &[PackedLayout], (width, height): (usize, usize)| {
+        // Capturers disagree on whether BGR0 has three or four bytes per pixel
+        layouts
+            .iter()
+            .find(|layout| data.len() == width * height * layout.bytes_per_pixel())
+            .and_then(|&layout| packed_to_bgra(data, layout, width, height))
+    };
+    let (format, (width, height), converted) = match frame {
+        VideoFrame::BGRA(bgra) => {
+            let (width, height) = size(bgra.width, bgra.height);
+            return Ok((width as u32, height as u32, bgra.data));
+        }
+        VideoFrame::YUVFrame(yuv) => {
+            let (width, height) = size(yuv.width, yuv.height);
+            let bgra = nv12_to_bgra(
+                &yuv.luminance_bytes,
+                yuv.luminance_stride.max(0) as usize,
+                &yuv.chrominance_bytes,
+                yuv.chrominance_stride.max(0) as usize,
+                width,
+                height,
+            );
+            ("NV12", (width, height), bgra)
+        }
+        VideoFrame::RGB(rgb) => {
+            let size = size(rgb.width, rgb.height);
+            ("RGB", size, packed(&rgb.data, &[PackedLayout::Rgb], size))
+        }
+        VideoFrame::RGBx(rgbx) => {
+            let size = size(rgbx.width, rgbx.height);
+            (
+                "RGBx",
+                size,
+                packed(&rgbx.data, &[PackedLayout::Rgbx], size),
+            )
+        }
+        VideoFrame::XBGR(xbgr) => {
+            let size = size(xbgr.width, xbgr.height);
+            (
+                "XBGR",
+                size,
+                packed(&xbgr.data, &[PackedLayout::Xbgr], size),
+            )
+        }
+        VideoFrame::BGRx(bgrx) => {
+            let size = size(bgrx.width, bgrx.height);
+            (
+                "BGRx",
+                size,
+                packed(&bgrx.data, &[PackedLayout::Bgrx], size),
+            )
+        }
+        VideoFrame::BGR0(bgr) => {
+            let size = size(bgr.width, bgr.height);
+            let layouts = [PackedLayout::Bgrx, PackedLayout::Bgr];
+            ("BGR0", size, packed(&bgr.data, &layouts, size))
+        }
+    };
+    converted
+        .map(|bgra| (width as u32, height as u32, bgra))
+        .ok_or_else(|| format!("{} frames of {}x{}", format, width, height))
+}
+
+/// Scap settings for `options`, and the display its frames come from
+fn scap_options(options: &CaptureOptions) -> CaptureResult<(ScapOptions, Option<u32>)> {
+    let crop_area = options
+        .region
+        .map(|region| {
+            if region.width <= 0.0 || region.height <= 0.0 || region.x < 0.0 || region.y < 0.0 {
+                return Err(CaptureError::InvalidRegion(region));
+            }
+            Ok(Area {
+                origin: ScapPoint {
+                    x: region.x,
+                    y: region.y,
+                },
+                size: Size {
+                    width: region.width,
+                    height: region.height,
+                },
+            })
+        })
+        .transpose()?;
+    let target = options.target.resolve()?;
+    let display_id = match &target {
+        Some(ScapTarget::Display(display)) => Some(display.id),
+        Some(ScapTarget::Window(_)) => None,
+        None => main_display_id(),
+    };
+    let scap_options = ScapOptions {
+        fps: options.fps,
+        target,
+        show_cursor: options.show_cursor,
+        show_highlight: options.show_highlight,
+        excluded_targets: None,
+        output_type: FrameType::BGRAFrame,
+        output_resolution: options.output_resolution,
+        crop_area,
+        captures_audio: options.captures_audio,
+        // Keeps spoken answers played back by the watcher out of its own input
+        exclude_current_process_audio: true,
+    };
+    Ok((scap_options, display_id))
+}
+
+/// How long `FrameSource::stop` waits for the capture thread to wind down
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often a paused capture thread re-checks for resume or stop without being woken
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often a lossless producer's thread checks whether its previous frame was taken
+const HANDOFF_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Runs a scap Capturer, or another FrameProducer, and maintains the last captured frame
+///
+/// A source built with `from_options` rebuilds its capturer when it stops delivering frames,
+/// reporting this on `event_channel`.
 pub struct FrameSource {
     last_frame: Arc<parking_lot::RwLock<Option<Arc<FrameData>>>>,
     frame_ready: Arc<Notify>,
-    _thread_handle: Option<std::thread::JoinHandle<()>>,
+    pool: BufferPool,
+    channels: Arc<parking_lot::Mutex<Vec<Sender<Arc<FrameData>>>>>,
+    audio_channels: Arc<parking_lot::Mutex<Vec<Sender<Vec<u8>>>>>,
+    event_channels: Arc<parking_lot::Mutex<Vec<Sender<CaptureEvent>>>>,
+    history: Arc<parking_lot::Mutex<FrameHistory>>,
+    latest: watch::Receiver<Option<Arc<FrameData>>>,
+    stopped: Arc<AtomicBool>,
+    /// Why the producer gave up, if it did
+    failure: Arc<parking_lot::Mutex<Option<String>>>,
+    paused: Arc<AtomicBool>,
+    /// Producer the capture thread switches to before its next frame, set by `retarget`
+    replacement: Arc<parking_lot::Mutex<Option<Box<dyn FrameProducer>>>>,
+    redactor: Arc<parking_lot::RwLock<Option<Redactor>>>,
+    thread: parking_lot::Mutex<Option<(std::thread::JoinHandle<()>, mpsc::Receiver<()>)>>,
 }
 
 impl FrameSource {
     /// Create a new FrameSource from a preconfigured scap Capturer
-    pub fn new(mut capturer: ScapCapturer) -> Self {
+    pub fn new(capturer: ScapCapturer) -> Self {
+        Self::with_display_id(capturer, None)
+    }
+
+    /// Like `new`, tagging every frame with the display the capturer records
+    pub fn with_display_id(capturer: ScapCapturer, display_id: Option<u32>) -> Self {
+        Self::from_capturer(capturer, display_id, None)
+    }
+
+    /// Starts `capturer`, which is rebuilt from `options` whenever it stops delivering frames
+    fn from_capturer(
+        mut capturer: ScapCapturer,
+        display_id: Option<u32>,
+        options: Option<ScapOptions>,
+    ) -> Self {
+        // Start capture
+        capturer.start_capture();
+        Self::from_producer(ScreenProducer {
+            capturer,
+            display_id,
+            capturing: true,
+            unsupported: 0,
+            options,
+            recovering: None,
+        })
+    }
+
+    /// Runs `producer` on a thread of its own, serving its frames like captured ones
+    pub fn from_producer(producer: impl FrameProducer) -> Self {
+        let mut producer: Box<dyn FrameProducer> = Box::new(producer);
         let last_frame = Arc::new(parking_lot::RwLock::new(None));
         let last_frame_clone = Arc::clone(&last_frame);
         let frame_ready = Arc::new(Notify::new());
         let frame_ready_clone = Arc::clone(&frame_ready);
-
-        // Start capture
-        capturer.start_capture();
+        let pool = BufferPool::default();
+        let pool_clone = pool.clone();
+        let channels: Arc<parking_lot::Mutex<Vec<Sender<Arc<FrameData>>>>> = Default::default();
+        let channels_clone = Arc::clone(&channels);
+        let audio_channels: Arc<parking_lot::Mutex<Vec<Sender<Vec<u8>>>>> = Default::default();
+        let audio_channels_clone = Arc::clone(&audio_channels);
+        let event_channels: Arc<parking_lot::Mutex<Vec<Sender<CaptureEvent>>>> = Default::default();
+        let event_channels_clone = Arc::clone(&event_channels);
+        let history = Arc::new(parking_lot::Mutex::new(FrameHistory::new(0)));
+        let history_clone = Arc::clone(&history);
+        // The thread owns the only sender, so subscribers see the end of the stream
+        let (latest_tx, latest) = watch::channel(None);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let stopped_clone = Arc::clone(&stopped);
+        let failure = Arc::new(parking_lot::Mutex::new(None));
+        let failure_clone = Arc::clone(&failure);
+        let paused = Arc::new(AtomicBool::new(false));
+        let paused_clone = Arc::clone(&paused);
+        let replacement: Arc<parking_lot::Mutex<Option<Box<dyn FrameProducer>>>> =
+            Default::default();
+        let replacement_clone = Arc::clone(&replacement);
+        let redactor: Arc<parking_lot::RwLock<Option<Redactor>>> = Default::default();
+        let redactor_clone = Arc::clone(&redactor);
+        let (exited_tx, exited_rx) = mpsc::channel();
 
         // Spawn thread to continuously receive frames
         let handle = std::thread::spawn(move || {
-            loop {
-                match capturer.get_next_frame() {
-                    Ok(frame) => {
-                        let frame_data = match frame {
-                            Frame::Video(video_frame) => match video_frame {
-                                VideoFrame::BGRA(bgra_frame) => Some(Arc::new(FrameData {
-                                    width: bgra_frame.width as u32,
-                                    height: bgra_frame.height as u32,
-                                    data: bgra_frame.data,
-                                })),
-                                _ => None,
-                            },
-                            Frame::Audio(_) => None,
-                        };
+            // The producer is owned by this thread, so it is also paused and stopped here
+            let mut producing = true;
+            let mut audio = SystemAudioChunker::new();
+            while !stopped_clone.load(Ordering::Acquire) {
+                if let Some(next) = replacement_clone.lock().take() {
+                    producer.finish();
+                    producer = next;
+                    // The new producer starts out stopped and is resumed below unless paused
+                    producing = false;
+                }
+                if paused_clone.load(Ordering::Acquire) {
+                    if producing {
+                        producer.pause();
+                        producing = false;
+                    }
+                    std::thread::park_timeout(PAUSE_POLL_INTERVAL);
+                    continue;
+                }
+                if !producing {
+                    producer.resume();
+                    producing = true;
+                }
 
-                        if let Some(frame_data) = frame_data {
-                            *last_frame_clone.write() = Some(frame_data);
-                            frame_ready_clone.notify_one();
+                match producer.next_frame() {
+                    // Frames still queued when the pause was requested are dropped
+                    Some(ProducedFrame::Video(_) | ProducedFrame::Audio(_))
+                        if paused_clone.load(Ordering::Acquire) => {}
+                    Some(ProducedFrame::Video(mut frame)) => {
+                        // Every consumer gets the masked frame, or none at all
+                        if let Some(redactor) = redactor_clone.read().as_ref()
+                            && !redact(redactor, &mut frame)
+                        {
+                            pool_clone.recycle(frame.data);
+                            continue;
+                        }
+                        let frame_data = Arc::new(frame);
+                        // Full channels miss this frame; closed ones are dropped
+                        channels_clone.lock().retain(|channel| {
+                            !matches!(
+                                channel.try_send(Arc::clone(&frame_data)),
+                                Err(TrySendError::Closed(_))
+                            )
+                        });
+                        history_clone.lock().push(
+                            TimedFrame {
+                                captured_at: Instant::now(),
+                                frame: Arc::clone(&frame_data),
+                            },
+                            &pool_clone,
+                        );
+                        latest_tx.send_replace(Some(Arc::clone(&frame_data)));
+                        if producer.lossless() {
+                            while last_frame_clone.read().is_some()
+                                && !stopped_clone.load(Ordering::Acquire)
+                            {
+                                std::thread::sleep(HANDOFF_POLL_INTERVAL);
+                            }
+                        }
+                        let skipped = last_frame_clone.write().replace(frame_data);
+                        frame_ready_clone.notify_one();
+                        // Frames nobody picked up feed the pool instead of being freed
+                        if let Some(skipped) = skipped {
+                            pool_clone.recycle_frame(skipped);
+                        }
+                    }
+                    Some(ProducedFrame::Audio(audio_frame)) => {
+                        let mut audio_channels = audio_channels_clone.lock();
+                        if !audio_channels.is_empty() {
+                            for chunk in audio.push(&audio_frame) {
+                                audio_channels.retain(|channel| {
+                                    !matches!(
+                                        channel.try_send(chunk.clone()),
+                                        Err(TrySendError::Closed(_))
+                                    )
+                                });
+                            }
                         }
                     }
-                    Err(_) => {
-                        // Channel closed, exit thread
+                    Some(ProducedFrame::Skipped) => {}
+                    Some(ProducedFrame::Event(event)) => {
+                        event_channels_clone.lock().retain(|channel| {
+                            !matches!(channel.try_send(event), Err(TrySendError::Closed(_)))
+                        });
+                    }
+                    Some(ProducedFrame::Failed(reason)) => {
+                        eprintln!("❌ Capture failed: {}", reason);
+                        *failure_clone.lock() = Some(reason);
                         break;
                     }
+                    None => break,
                 }
             }
+
+            producer.finish();
+            stopped_clone.store(true, Ordering::Release);
+            channels_clone.lock().clear();
+            audio_channels_clone.lock().clear();
+            event_channels_clone.lock().clear();
+            // Wake a pending get_next_frame so it can report the stop
+            frame_ready_clone.notify_one();
+            let _ = exited_tx.send(());
         });
 
         Self {
             last_frame,
             frame_ready,
-            _thread_handle: Some(handle),
+            pool,
+            channels,
+            audio_channels,
+            event_channels,
+            history,
+            latest,
+            stopped,
+            failure,
+            paused,
+            replacement,
+            redactor,
+            thread: parking_lot::Mutex::new(Some((handle, exited_rx))),
         }
     }
 
+    /// Builds a scap Capturer for the given target and starts it
+    pub fn from_options(options: CaptureOptions) -> CaptureResult<Self> {
+        let (scap_options, display_id) = scap_options(&options)?;
+        let capturer = ScapCapturer::build(scap_options.clone())?;
+        let source = Self::from_capturer(capturer, display_id, Some(scap_options));
+        source.set_history_capacity(options.history);
+        Ok(source)
+    }
+
+    /// Switches capture to a capturer built from `options`, e.g. to follow another window,
+    /// keeping subscribers, channels and the pause state
+    ///
+    /// Like `pause`, this takes effect once the capture thread has received its next frame.
+    pub fn retarget(&self, options: CaptureOptions) -> CaptureResult<()> {
+        if self.is_stopped() {
+            return Err(CaptureError::Stopped);
+        }
+        let (scap_options, display_id) = scap_options(&options)?;
+        let capturer = ScapCapturer::build(scap_options.clone())?;
+        *self.replacement.lock() = Some(Box::new(ScreenProducer {
+            capturer,
+            display_id,
+            capturing: false,
+            unsupported: 0,
+            options: Some(scap_options),
+            recovering: None,
+        }));
+        // Frames of the previous target are stale by the time the new capturer delivers
+        if let Some(frame) = self.last_frame.write().take() {
+            self.pool.recycle_frame(frame);
+        }
+        self.wake_thread();
+        Ok(())
+    }
+
+    /// Masks what `redactor` hides in every frame before any consumer, the history or a
+    /// recording sees it; frames that can't be placed on a display are dropped instead
+    pub fn set_redactor(&self, redactor: Option<Redactor>) {
+        *self.redactor.write() = redactor.filter(|redactor| !redactor.is_empty());
+    }
+
+    /// Pool that consumed frames and encoder buffers should be returned to
+    pub fn buffer_pool(&self) -> &BufferPool {
+        &self.pool
+    }
+
     /// Get the next captured frame, blocking until one is available.
     /// Resets the internal frame to None after retrieval.
     pub async fn get_next_frame(&self) -> CaptureResult<Arc<FrameData>> {
@@ -90,9 +795,154 @@ impl FrameSource {
                     return Ok(frame);
                 }
             }
+            if self.is_stopped() {
+                return Err(match self.failure.lock().clone() {
+                    Some(reason) => CaptureError::FrameError(reason),
+                    None => CaptureError::Stopped,
+                });
+            }
 
             // No frame available, wait for notification
             self.frame_ready.notified().await;
         }
     }
+
+    /// Consumes frames as a stream, ending once the source is stopped
+    ///
+    /// Shares the single latest-frame slot with `get_next_frame`, so use one or the other.
+    pub fn frames(&self) -> impl Stream<Item = Arc<FrameData>> + '_ {
+        futures::stream::unfold(self, |source| async move {
+            let frame = source.get_next_frame().await.ok()?;
+            Some((frame, source))
+        })
+    }
+
+    /// Keeps the `capacity` most recent frames for later retrieval; 0 disables the history
+    pub fn set_history_capacity(&self, capacity: usize) {
+        self.history.lock().set_capacity(capacity, &self.pool);
+    }
+
+    /// The `count` most recent frames from the history, oldest first
+    pub fn last_n_frames(&self, count: usize) -> Vec<TimedFrame> {
+        self.history.lock().last_n(count)
+    }
+
+    /// Frames from the history captured at or after `since`, oldest first, e.g. the few
+    /// seconds leading up to something the model flagged
+    pub fn frames_since(&self, since: Instant) -> Vec<TimedFrame> {
+        self.history.lock().since(since)
+    }
+
+    /// Adds an independent consumer that waits for frames captured from now on
+    pub fn subscribe(&self) -> FrameSubscriber {
+        let mut latest = self.latest.clone();
+        latest.mark_unchanged();
+        FrameSubscriber { latest }
+    }
+
+    /// Delivers every captured frame on its own bounded channel
+    ///
+    /// Frames are skipped for this receiver while its buffer is full, rather than stalling
+    /// capture or other consumers. The channel closes when the source stops.
+    pub fn frame_channel(&self, capacity: usize) -> Receiver<Arc<FrameData>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(capacity.max(1));
+        let mut channels = self.channels.lock();
+        // Checked under the lock so a stopping capture thread can't miss this sender
+        if !self.is_stopped() {
+            channels.push(tx);
+        }
+        rx
+    }
+
+    /// Delivers system audio as 16 kHz mono PCM chunks of 100 ms, ready for
+    /// `send_realtime_audio`
+    ///
+    /// Requires `captures_audio` in the capture options; otherwise the channel stays silent.
+    /// Chunks are dropped while the buffer is full and the channel closes when the source stops.
+    pub fn audio_channel(&self, capacity: usize) -> Receiver<Vec<u8>> {
+        let (tx, rx) = tokio::sync::mpsc::channel(capacity.max(1));
+        let mut channels = self.audio_channels.lock();
+        if !self.is_stopped() {
+            channels.push(tx);
+        }
+        rx
+    }
+
+    /// Delivers CaptureEvents, e.g. when the capturer was restarted after an interruption
+    ///
+    /// Events are dropped while the buffer is full and the channel closes when the source stops.
+    pub fn event_channel(&self, capacity: usize) -> Receiver<CaptureEvent> {
+        let (tx, rx) = tokio::sync::mpsc::channel(capacity.max(1));
+        let mut channels = self.event_channels.lock();
+        if !self.is_stopped() {
+            channels.push(tx);
+        }
+        rx
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    /// Whether the source has stopped and its last frame was taken, e.g. after a replay
+    pub fn is_finished(&self) -> bool {
+        self.is_stopped() && self.last_frame.read().is_none()
+    }
+
+    /// Stops the underlying capture until `resume`; consumers simply wait for the next frame
+    ///
+    /// Like `stop`, this takes effect once the capture thread has received its next frame.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Release);
+        // Anything captured before the pause is stale by the time capture resumes
+        if let Some(frame) = self.last_frame.write().take() {
+            self.pool.recycle_frame(frame);
+        }
+    }
+
+    /// Restarts capture after `pause`
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Release);
+        self.wake_thread();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    fn wake_thread(&self) {
+        if let Some((handle, _)) = self.thread.lock().as_ref() {
+            handle.thread().unpark();
+        }
+    }
+
+    /// Stops the capture thread, which stops its capturer on the way out, and joins it
+    ///
+    /// The thread notices the request after its next frame. Inside a tokio runtime the join
+    /// runs on a blocking thread so the caller doesn't stall; if the screen is static and no
+    /// frame arrives within a short timeout, the thread is left to finish on its own.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.frame_ready.notify_one();
+        self.wake_thread();
+
+        let Some((handle, exited)) = self.thread.lock().take() else {
+            return;
+        };
+        let join = move || {
+            if exited.recv_timeout(STOP_TIMEOUT).is_ok() {
+                let _ = handle.join();
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn_blocking(join)),
+            Err(_) => join(),
+        }
+    }
+}
+
+impl Drop for FrameSource {
+    fn drop(&mut self) {
+        self.stop();
+    }
 }
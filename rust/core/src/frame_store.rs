@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Per-session index of stored frames, one JSON object per line
+pub const FRAME_INDEX_FILE: &str = "index.jsonl";
+
+/// Marker written into a legacy output directory once it has been imported
+pub const LEGACY_MIGRATED_MARKER: &str = ".migrated";
+
+/// Most frames archived automatically from the output directory at startup
+pub const AUTO_ARCHIVE_MAX_FRAMES: usize = 1000;
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Failed to encode index entry: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+pub type StoreResult<T> = std::result::Result<T, StoreError>;
+
+/// Index entry describing one stored frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredFrame {
+    pub file: String,
+    pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imported_from: Option<String>,
+}
+
+/// Archive of capture sessions laid out as `<root>/sessions/<start-ms>/`
+pub struct FrameStore {
+    root: PathBuf,
+}
+
+impl FrameStore {
+    /// Opens (creating if needed) a store rooted at `root`
+    pub fn open<P: AsRef<Path>>(root: P) -> StoreResult<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(root.join("sessions"))?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Creates a new session directory named after its start time
+    pub fn create_session(&self, started_at_ms: u64) -> StoreResult<StoreSession> {
+        let mut dir = self.root.join("sessions").join(started_at_ms.to_string());
+        let mut suffix = 1;
+        while dir.exists() {
+            dir = self
+                .root
+                .join("sessions")
+                .join(format!("{}-{}", started_at_ms, suffix));
+            suffix += 1;
+        }
+        fs::create_dir_all(&dir)?;
+
+        let index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(FRAME_INDEX_FILE))?;
+        Ok(StoreSession { dir, index })
+    }
+
+    /// Lists session directories, oldest first
+    pub fn sessions(&self) -> StoreResult<Vec<PathBuf>> {
+        let mut sessions: Vec<PathBuf> = fs::read_dir(self.root.join("sessions"))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        sessions.sort();
+        Ok(sessions)
+    }
+}
+
+/// A single session's frame directory and index
+pub struct StoreSession {
+    dir: PathBuf,
+    index: File,
+}
+
+impl StoreSession {
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes encoded frame bytes and records them in the index
+    pub fn write_frame(
+        &mut self,
+        timestamp_ms: u64,
+        bytes: &[u8],
+        extension: &str,
+    ) -> StoreResult<StoredFrame> {
+        let file = self.unique_file_name(timestamp_ms, extension);
+        fs::write(self.dir.join(&file), bytes)?;
+        self.append(StoredFrame {
+            file,
+            timestamp_ms,
+            imported_from: None,
+        })
+    }
+
+    /// Copies an existing image into the session, keeping a reference to its origin
+    pub fn import_frame(&mut self, source: &Path, timestamp_ms: u64) -> StoreResult<StoredFrame> {
+        let extension = source
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("jpg");
+        let file = self.unique_file_name(timestamp_ms, extension);
+        fs::copy(source, self.dir.join(&file))?;
+        self.append(StoredFrame {
+            file,
+            timestamp_ms,
+            imported_from: Some(source.display().to_string()),
+        })
+    }
+
+    fn unique_file_name(&self, timestamp_ms: u64, extension: &str) -> String {
+        let mut file = format!("{}.{}", timestamp_ms, extension);
+        let mut suffix = 1;
+        while self.dir.join(&file).exists() {
+            file = format!("{}-{}.{}", timestamp_ms, suffix, extension);
+            suffix += 1;
+        }
+        file
+    }
+
+    fn append(&mut self, entry: StoredFrame) -> StoreResult<StoredFrame> {
+        writeln!(self.index, "{}", serde_json::to_string(&entry)?)?;
+        Ok(entry)
+    }
+}
+
+/// Summary of a legacy output import
+#[derive(Debug, Clone)]
+pub struct MigrationReport {
+    pub imported: usize,
+    /// Older frames left out because of the frame limit
+    pub skipped: usize,
+    pub session_dir: Option<PathBuf>,
+}
+
+/// Milliseconds since the Unix epoch for a point in time
+pub fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Finds `frame_NNNN.jpg` files written by the flat legacy layout, ordered by sequence number
+pub fn find_legacy_frames<P: AsRef<Path>>(legacy_dir: P) -> io::Result<Vec<PathBuf>> {
+    let legacy_dir = legacy_dir.as_ref();
+    if !legacy_dir.is_dir() || legacy_dir.join(LEGACY_MIGRATED_MARKER).exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut frames: Vec<(u64, PathBuf)> = fs::read_dir(legacy_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            let sequence = name.strip_prefix("frame_")?.strip_suffix(".jpg")?;
+            Some((sequence.parse().ok()?, path))
+        })
+        .collect();
+    frames.sort_by_key(|(sequence, _)| *sequence);
+    Ok(frames.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Imports a legacy flat output directory into a new store session, using file mtimes as
+/// capture timestamps, and marks the directory as migrated
+///
+/// Only the newest `max_frames` frames are imported when set; older ones are left in place.
+pub fn migrate_legacy_output<P: AsRef<Path>>(
+    legacy_dir: P,
+    store: &FrameStore,
+    remove_originals: bool,
+    max_frames: Option<usize>,
+) -> StoreResult<MigrationReport> {
+    let legacy_dir = legacy_dir.as_ref();
+    let mut frames = find_legacy_frames(legacy_dir)?;
+    if frames.is_empty() {
+        return Ok(MigrationReport {
+            imported: 0,
+            skipped: 0,
+            session_dir: None,
+        });
+    }
+    let skipped = max_frames.map_or(0, |max| frames.len().saturating_sub(max));
+    frames.drain(..skipped);
+
+    let mut timestamped = Vec::with_capacity(frames.len());
+    for path in frames {
+        let modified = fs::metadata(&path)?
+            .modified()
+            .unwrap_or_else(|_| SystemTime::now());
+        timestamped.push((unix_millis(modified), path));
+    }
+
+    let started_at = timestamped.iter().map(|(ts, _)| *ts).min().unwrap_or(0);
+    let mut session = store.create_session(started_at)?;
+    for (timestamp_ms, path) in &timestamped {
+        session.import_frame(path, *timestamp_ms)?;
+    }
+
+    if remove_originals {
+        for (_, path) in &timestamped {
+            fs::remove_file(path)?;
+        }
+    }
+    fs::write(
+        legacy_dir.join(LEGACY_MIGRATED_MARKER),
+        session.dir().display().to_string(),
+    )?;
+
+    Ok(MigrationReport {
+        imported: timestamped.len(),
+        skipped,
+        session_dir: Some(session.dir().to_path_buf()),
+    })
+}
@@ -29,6 +29,12 @@ use url::Url;
 /// The public preview endpoint for Gemini Live API sessions.
 pub const DEFAULT_LIVE_ENDPOINT: &str = "wss://generativelanguage.googleapis.com/ws/google.ai.generativelanguage.v1beta.GenerativeService.BidiGenerateContent";
 
+/// MIME type for the 16 kHz little-endian PCM audio accepted as realtime input.
+pub const REALTIME_AUDIO_MIME_TYPE: &str = "audio/pcm;rate=16000";
+
+/// Number of consecutive undecodable frames tolerated before `recv` returns an error.
+pub const MAX_CONSECUTIVE_PARSE_FAILURES: u32 = 5;
+
 /// Convenience result alias for Gemini live operations.
 pub type Result<T> = std::result::Result<T, GeminiError>;
 
@@ -75,11 +81,109 @@ pub enum GeminiError {
 
     #[error("server closed the connection: code {code}, reason {reason}")]
     ServerClosed { code: String, reason: String },
+
+    #[error("no API key available: every pooled key is rate-limited or at its session limit")]
+    NoAvailableApiKey,
+
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(#[from] ConfigError),
+
+    #[error("screen is locked, nothing is sent until it is unlocked")]
+    ScreenLocked,
+
+    /// Failure of an `AnalysisBackend` other than Gemini Live
+    #[error("{0}")]
+    Backend(String),
+}
+
+/// A rejected builder field, with the accepted values and a hint on how to fix it.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("`{field}` {message}{}", format_suggestion(.suggestion))]
+pub struct ConfigError {
+    pub field: &'static str,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+fn format_suggestion(suggestion: &Option<String>) -> String {
+    suggestion
+        .as_ref()
+        .map(|suggestion| format!(" ({})", suggestion))
+        .unwrap_or_default()
+}
+
+impl ConfigError {
+    /// Creates an error for `field` without a suggestion.
+    pub fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    /// Adds a hint on how to fix the value.
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+impl From<derive_builder::UninitializedFieldError> for GeminiError {
+    fn from(error: derive_builder::UninitializedFieldError) -> Self {
+        GeminiError::InvalidConfig(
+            ConfigError::new(error.field_name(), "must be set").with_suggestion(format!(
+                "call `.{}(...)` on the builder",
+                error.field_name()
+            )),
+        )
+    }
+}
+
+/// Checks that `value` lies in `min..=max`.
+fn check_range<T: PartialOrd + fmt::Display>(
+    field: &'static str,
+    value: Option<T>,
+    min: T,
+    max: T,
+) -> std::result::Result<(), ConfigError> {
+    match value {
+        Some(value) if value < min || value > max => Err(ConfigError::new(
+            field,
+            format!("must be between {} and {}, got {}", min, max, value),
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl GeminiError {
+    /// Returns true when the server rejected the session because of quota or rate limits.
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            GeminiError::HandshakeStatus(status) => *status == StatusCode::TOO_MANY_REQUESTS,
+            GeminiError::WebSocket(tungstenite::Error::Http(response)) => {
+                response.status() == StatusCode::TOO_MANY_REQUESTS
+            }
+            GeminiError::ServerError(error) => {
+                error.code == Some(429) || error.status.as_deref() == Some("RESOURCE_EXHAUSTED")
+            }
+            GeminiError::ServerClosed { reason, .. } => {
+                let reason = reason.to_lowercase();
+                reason.contains("quota")
+                    || reason.contains("rate limit")
+                    || reason.contains("resource_exhausted")
+            }
+            _ => false,
+        }
+    }
 }
 
 /// Connection parameters for creating a Gemini live session.
 #[derive(Debug, Clone, Builder)]
-#[builder(pattern = "owned")]
+#[builder(
+    pattern = "owned",
+    build_fn(validate = "Self::validate", error = "GeminiError")
+)]
 pub struct ConnectionOptions {
     #[builder(default = "Url::parse(DEFAULT_LIVE_ENDPOINT).expect(\"valid default endpoint\")")]
     endpoint: Url,
@@ -87,6 +191,9 @@ pub struct ConnectionOptions {
     api_key: Option<String>,
     #[builder(setter(strip_option, into), default)]
     access_token: Option<String>,
+    /// Additional API keys that sessions may be sharded across.
+    #[builder(setter(into), default)]
+    key_pool: Vec<String>,
 }
 
 impl ConnectionOptions {
@@ -102,6 +209,24 @@ impl ConnectionOptions {
         &self.endpoint
     }
 
+    /// Returns every configured API key, primary key first and without duplicates.
+    pub fn api_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.api_key.iter().cloned().collect();
+        for key in &self.key_pool {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        keys
+    }
+
+    /// Returns a copy of these options that authenticates with `key`.
+    pub fn with_api_key(&self, key: impl Into<String>) -> Self {
+        let mut options = self.clone();
+        options.api_key = Some(key.into());
+        options
+    }
+
     /// Returns a builder for customizing the connection options.
     pub fn builder() -> ConnectionOptionsBuilder {
         ConnectionOptionsBuilder::default()
@@ -135,12 +260,42 @@ impl ConnectionOptions {
     }
 }
 
+impl ConnectionOptionsBuilder {
+    fn validate(&self) -> std::result::Result<(), ConfigError> {
+        if let Some(endpoint) = &self.endpoint
+            && !matches!(endpoint.scheme(), "ws" | "wss")
+        {
+            return Err(ConfigError::new(
+                "endpoint",
+                format!(
+                    "must be a ws:// or wss:// URL, got {}://",
+                    endpoint.scheme()
+                ),
+            )
+            .with_suggestion("omit it to use DEFAULT_LIVE_ENDPOINT"));
+        }
+        if let Some(Some(key)) = &self.api_key
+            && key.trim().is_empty()
+        {
+            return Err(ConfigError::new("api_key", "must not be blank")
+                .with_suggestion("leave it unset to connect with an access token instead"));
+        }
+        if let Some(pool) = &self.key_pool
+            && pool.iter().any(|key| key.trim().is_empty())
+        {
+            return Err(ConfigError::new("key_pool", "must not contain blank keys"));
+        }
+        Ok(())
+    }
+}
+
 /// Wrapper around an active Gemini live session.
 pub struct GeminiSession {
     sender: SharedSender,
     receiver: Receiver,
     pending: VecDeque<ServerEvent>,
     closed: Arc<AtomicBool>,
+    parse_failures: u32,
 }
 
 async fn send_message_internal(
@@ -174,6 +329,7 @@ impl GeminiSession {
             receiver,
             pending: VecDeque::new(),
             closed,
+            parse_failures: 0,
         };
 
         session.send_setup(setup).await?;
@@ -226,6 +382,24 @@ impl GeminiSession {
         .await
     }
 
+    /// Sends a chunk of 16 kHz mono PCM audio as realtime input.
+    pub async fn send_realtime_audio(&self, pcm: &[u8]) -> Result<()> {
+        self.send_message(ClientMessage::RealtimeInput(RealtimeInput {
+            audio: Some(Blob::from_bytes(pcm).with_mime_type(REALTIME_AUDIO_MIME_TYPE)),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    /// Marks the end of the realtime audio, so buffered speech is answered right away.
+    pub async fn send_audio_stream_end(&self) -> Result<()> {
+        self.send_message(ClientMessage::RealtimeInput(RealtimeInput {
+            audio_stream_end: Some(true),
+            ..Default::default()
+        }))
+        .await
+    }
+
     /// Sends a tool response payload back to the model.
     pub async fn send_tool_response(&self, response: ToolResponse) -> Result<()> {
         self.send_message(ClientMessage::ToolResponse(response))
@@ -283,22 +457,15 @@ impl GeminiSession {
 
         while let Some(frame) = self.receiver.next().await {
             let message = frame?;
-            match message {
-                Message::Text(text) => {
-                    let value: Value = serde_json::from_str(&text)?;
-                    let event = parse_server_event(value)?;
-                    return Ok(Some(event));
-                }
-                Message::Binary(bytes) => {
-                    let value: Value = serde_json::from_slice(&bytes)?;
-                    let event = parse_server_event(value)?;
-                    return Ok(Some(event));
-                }
+            let payload = match message {
+                Message::Text(text) => text.into_bytes(),
+                Message::Binary(bytes) => bytes,
                 Message::Ping(payload) => {
                     let mut sender = self.sender.lock().await;
                     sender.send(Message::Pong(payload)).await?;
+                    continue;
                 }
-                Message::Pong(_) => {}
+                Message::Pong(_) => continue,
                 Message::Close(frame) => {
                     self.closed.store(true, Ordering::SeqCst);
                     if let Some(frame) = frame {
@@ -308,13 +475,42 @@ impl GeminiSession {
                     }
                     return Ok(None);
                 }
-                Message::Frame(_) => {}
-            }
+                Message::Frame(_) => continue,
+            };
+
+            let decoded = serde_json::from_slice::<Value>(&payload)
+                .map_err(GeminiError::from)
+                .and_then(parse_server_event);
+            return match decoded {
+                Ok(event) => {
+                    self.parse_failures = 0;
+                    Ok(Some(event))
+                }
+                Err(err) if self.record_parse_failure(&payload) => Err(err),
+                Err(_) => Ok(Some(ServerEvent::Unknown {
+                    usage_metadata: None,
+                    raw: Value::String(String::from_utf8_lossy(&payload).into_owned()),
+                })),
+            };
         }
 
         self.closed.store(true, Ordering::SeqCst);
         Ok(None)
     }
+
+    /// Counts an undecodable frame, returning true once too many have failed in a row.
+    /// Blank frames are treated as keep-alives and never count.
+    fn record_parse_failure(&mut self, payload: &[u8]) -> bool {
+        if payload.iter().all(u8::is_ascii_whitespace) {
+            return false;
+        }
+        self.parse_failures += 1;
+        if self.parse_failures >= MAX_CONSECUTIVE_PARSE_FAILURES {
+            self.parse_failures = 0;
+            return true;
+        }
+        false
+    }
 }
 
 #[derive(Clone)]
@@ -324,6 +520,11 @@ pub struct GeminiSender {
 }
 
 impl GeminiSender {
+    /// Returns whether the underlying connection has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
     async fn send_message(&self, message: ClientMessage) -> Result<()> {
         send_message_internal(&self.sender, &self.closed, message).await
     }
@@ -357,6 +558,22 @@ impl GeminiSender {
         .await
     }
 
+    pub async fn send_realtime_audio(&self, pcm: &[u8]) -> Result<()> {
+        self.send_message(ClientMessage::RealtimeInput(RealtimeInput {
+            audio: Some(Blob::from_bytes(pcm).with_mime_type(REALTIME_AUDIO_MIME_TYPE)),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    pub async fn send_audio_stream_end(&self) -> Result<()> {
+        self.send_message(ClientMessage::RealtimeInput(RealtimeInput {
+            audio_stream_end: Some(true),
+            ..Default::default()
+        }))
+        .await
+    }
+
     pub async fn send_tool_response(&self, response: ToolResponse) -> Result<()> {
         self.send_message(ClientMessage::ToolResponse(response))
             .await
@@ -488,7 +705,10 @@ pub enum ClientMessage {
 /// Session setup payload as required by the first message on a live session.
 #[derive(Debug, Serialize, Deserialize, Clone, Default, Builder)]
 #[serde(rename_all = "camelCase")]
-#[builder(pattern = "owned")]
+#[builder(
+    pattern = "owned",
+    build_fn(validate = "Self::validate", error = "GeminiError")
+)]
 pub struct Setup {
     pub model: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -518,6 +738,9 @@ pub struct Setup {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[builder(setter(strip_option), default)]
     pub proactivity: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(setter(strip_option), default)]
+    pub safety_settings: Option<Vec<SafetySetting>>,
 }
 
 impl Setup {
@@ -535,6 +758,25 @@ impl Setup {
     }
 }
 
+impl SetupBuilder {
+    fn validate(&self) -> std::result::Result<(), ConfigError> {
+        if let Some(model) = &self.model {
+            if model.trim().is_empty() {
+                return Err(ConfigError::new("model", "must not be empty")
+                    .with_suggestion("e.g. \"models/gemini-live-2.5-flash-preview\""));
+            }
+            if !model.starts_with("models/") {
+                return Err(ConfigError::new("model", "must start with 'models/'")
+                    .with_suggestion(format!("use \"models/{}\"", model)));
+            }
+        }
+        if let Some(Some(config)) = &self.generation_config {
+            config.validate()?;
+        }
+        Ok(())
+    }
+}
+
 /// Model generation configuration mirrors the REST API structure.
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
@@ -559,6 +801,57 @@ pub struct GenerationConfig {
     pub speech_config: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub media_resolution: Option<Value>,
+    /// `application/json` together with `response_schema` for structured output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<Value>,
+}
+
+impl GenerationConfig {
+    /// Checks every sampling parameter against the ranges the API accepts.
+    pub fn validate(&self) -> std::result::Result<(), ConfigError> {
+        check_range("generation_config.temperature", self.temperature, 0.0, 2.0)?;
+        check_range("generation_config.top_p", self.top_p, 0.0, 1.0)?;
+        check_range("generation_config.top_k", self.top_k, 1, i32::MAX)?;
+        check_range(
+            "generation_config.candidate_count",
+            self.candidate_count,
+            1,
+            i32::MAX,
+        )?;
+        check_range(
+            "generation_config.max_output_tokens",
+            self.max_output_tokens,
+            1,
+            i32::MAX,
+        )?;
+        check_range(
+            "generation_config.presence_penalty",
+            self.presence_penalty,
+            -2.0,
+            2.0,
+        )?;
+        check_range(
+            "generation_config.frequency_penalty",
+            self.frequency_penalty,
+            -2.0,
+            2.0,
+        )?;
+        const MODALITIES: [&str; 3] = ["TEXT", "AUDIO", "IMAGE"];
+        if let Some(modality) = self
+            .response_modalities
+            .iter()
+            .find(|modality| !MODALITIES.contains(&modality.as_str()))
+        {
+            return Err(ConfigError::new(
+                "generation_config.response_modalities",
+                format!("contains unknown modality \"{}\"", modality),
+            )
+            .with_suggestion(format!("use one of {}", MODALITIES.join(", "))));
+        }
+        Ok(())
+    }
 }
 
 /// Content turn payload appended to the conversation history.
@@ -664,7 +957,7 @@ impl FunctionResponse {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Blob {
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "mime_type", skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
     pub data: String,
 }
@@ -683,6 +976,13 @@ impl Blob {
         self.mime_type = Some(mime_type.into());
         self
     }
+
+    /// Decodes the base64 payload, returning `None` if it is malformed.
+    pub fn decode(&self) -> Option<Vec<u8>> {
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.data)
+            .ok()
+    }
 }
 
 /// Conversation content shared between client and server messages.
@@ -729,6 +1029,19 @@ impl Part {
     pub fn json(value: Value) -> Self {
         Part::Json(value)
     }
+
+    /// Returns the `inlineData` blob carried by this part, if any.
+    pub fn inline_data(&self) -> Option<Blob> {
+        match self {
+            Part::Json(value) => {
+                let raw = value
+                    .get("inlineData")
+                    .or_else(|| value.get("inline_data"))?;
+                serde_json::from_value(raw.clone()).ok()
+            }
+            Part::Text { .. } => None,
+        }
+    }
 }
 
 /// Messages broadcast by the server during a live session.
@@ -792,6 +1105,125 @@ pub struct ServerContent {
     pub url_context_metadata: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model_turn: Option<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<FinishReason>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub safety_ratings: Vec<SafetyRating>,
+}
+
+impl ServerContent {
+    /// Returns true when the model output was withheld by safety or policy filters.
+    pub fn is_blocked(&self) -> bool {
+        self.finish_reason
+            .as_ref()
+            .is_some_and(FinishReason::is_blocked)
+            || self
+                .safety_ratings
+                .iter()
+                .any(|rating| rating.blocked.unwrap_or(false))
+    }
+}
+
+/// Harm categories that safety settings and ratings refer to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HarmCategory {
+    #[serde(rename = "HARM_CATEGORY_HARASSMENT")]
+    Harassment,
+    #[serde(rename = "HARM_CATEGORY_HATE_SPEECH")]
+    HateSpeech,
+    #[serde(rename = "HARM_CATEGORY_SEXUALLY_EXPLICIT")]
+    SexuallyExplicit,
+    #[serde(rename = "HARM_CATEGORY_DANGEROUS_CONTENT")]
+    DangerousContent,
+    #[serde(rename = "HARM_CATEGORY_CIVIC_INTEGRITY")]
+    CivicIntegrity,
+    #[serde(rename = "HARM_CATEGORY_UNSPECIFIED", other)]
+    Unspecified,
+}
+
+/// Probability threshold at which content in a category is blocked.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HarmBlockThreshold {
+    BlockLowAndAbove,
+    BlockMediumAndAbove,
+    BlockOnlyHigh,
+    BlockNone,
+    Off,
+    #[serde(rename = "HARM_BLOCK_THRESHOLD_UNSPECIFIED", other)]
+    Unspecified,
+}
+
+/// A per-category blocking rule sent with the session setup.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SafetySetting {
+    pub category: HarmCategory,
+    pub threshold: HarmBlockThreshold,
+}
+
+impl SafetySetting {
+    /// Creates a safety setting for a category.
+    pub fn new(category: HarmCategory, threshold: HarmBlockThreshold) -> Self {
+        Self {
+            category,
+            threshold,
+        }
+    }
+}
+
+/// Likelihood that content belongs to a harm category.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HarmProbability {
+    Negligible,
+    Low,
+    Medium,
+    High,
+    #[serde(rename = "HARM_PROBABILITY_UNSPECIFIED", other)]
+    Unspecified,
+}
+
+/// Safety assessment attached to model output.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SafetyRating {
+    pub category: HarmCategory,
+    pub probability: HarmProbability,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked: Option<bool>,
+}
+
+/// Reason the model stopped generating.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FinishReason {
+    Stop,
+    MaxTokens,
+    Safety,
+    Recitation,
+    Language,
+    Blocklist,
+    ProhibitedContent,
+    Spii,
+    MalformedFunctionCall,
+    Other,
+    #[serde(rename = "FINISH_REASON_UNSPECIFIED", other)]
+    Unspecified,
+}
+
+impl FinishReason {
+    /// Returns true for finish reasons that mean the output was filtered.
+    pub fn is_blocked(&self) -> bool {
+        matches!(
+            self,
+            FinishReason::Safety
+                | FinishReason::Recitation
+                | FinishReason::Blocklist
+                | FinishReason::ProhibitedContent
+                | FinishReason::Spii
+        )
+    }
 }
 
 /// Transcription payload for audio streams.
@@ -0,0 +1,358 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+/// Hotkey used when `--hotkey` is given without keys
+pub const DEFAULT_HOTKEY: &str = "ctrl+option+space";
+
+/// Question sent with the screenshot taken when the hotkey is pressed, by default
+pub const DEFAULT_HOTKEY_QUESTION: &str = "The user pressed a hotkey to ask about this \
+                                           screenshot. Briefly say what they are looking at \
+                                           and anything on screen they should know or act on.";
+
+#[derive(Debug, Error)]
+pub enum HotkeyError {
+    #[error("Platform not supported")]
+    PlatformNotSupported,
+    #[error("Input Monitoring permission not granted")]
+    PermissionDenied,
+    #[error("Invalid hotkey '{0}': {1}")]
+    Invalid(String, String),
+}
+
+pub type HotkeyResult<T> = std::result::Result<T, HotkeyError>;
+
+/// CGEventFlags of the modifier keys
+const SHIFT: u64 = 0x0002_0000;
+const CONTROL: u64 = 0x0004_0000;
+const OPTION: u64 = 0x0008_0000;
+const COMMAND: u64 = 0x0010_0000;
+const MODIFIERS: u64 = SHIFT | CONTROL | OPTION | COMMAND;
+
+/// Virtual key codes of the keys a hotkey can end with, on an ANSI layout; letters are
+/// looked up in the current keyboard layout first
+const KEYS: &[(&str, u16)] = &[
+    ("a", 0x00),
+    ("s", 0x01),
+    ("d", 0x02),
+    ("f", 0x03),
+    ("h", 0x04),
+    ("g", 0x05),
+    ("z", 0x06),
+    ("x", 0x07),
+    ("c", 0x08),
+    ("v", 0x09),
+    ("b", 0x0B),
+    ("q", 0x0C),
+    ("w", 0x0D),
+    ("e", 0x0E),
+    ("r", 0x0F),
+    ("y", 0x10),
+    ("t", 0x11),
+    ("1", 0x12),
+    ("2", 0x13),
+    ("3", 0x14),
+    ("4", 0x15),
+    ("6", 0x16),
+    ("5", 0x17),
+    ("9", 0x19),
+    ("7", 0x1A),
+    ("8", 0x1C),
+    ("0", 0x1D),
+    ("o", 0x1F),
+    ("u", 0x20),
+    ("i", 0x22),
+    ("p", 0x23),
+    ("return", 0x24),
+    ("l", 0x25),
+    ("j", 0x26),
+    ("k", 0x28),
+    ("n", 0x2D),
+    ("m", 0x2E),
+    ("tab", 0x30),
+    ("space", 0x31),
+    ("escape", 0x35),
+    ("f5", 0x60),
+    ("f6", 0x61),
+    ("f7", 0x62),
+    ("f3", 0x63),
+    ("f8", 0x64),
+    ("f9", 0x65),
+    ("f11", 0x67),
+    ("f10", 0x6D),
+    ("f12", 0x6F),
+    ("f4", 0x76),
+    ("f2", 0x78),
+    ("f1", 0x7A),
+];
+
+/// A key combination such as "cmd+shift+a": modifiers joined by `+`, ending with a letter,
+/// digit, function key, space, return, tab or escape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hotkey {
+    /// Name of the key in `KEYS`
+    key: &'static str,
+    key_code: u16,
+    /// CGEventFlags of the modifiers that must be held, and no others
+    modifiers: u64,
+}
+
+impl Hotkey {
+    /// Whether a key press with `key_code` and event `flags` is this hotkey
+    pub fn matches(&self, key_code: u16, flags: u64) -> bool {
+        key_code == self.key_code && flags & MODIFIERS == self.modifiers
+    }
+}
+
+impl FromStr for Hotkey {
+    type Err = HotkeyError;
+
+    fn from_str(keys: &str) -> HotkeyResult<Self> {
+        let invalid = |reason: &str| HotkeyError::Invalid(keys.to_string(), reason.to_string());
+        let lower = keys.to_lowercase();
+        let mut parts: Vec<&str> = lower.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|key| !key.is_empty());
+        let (key, ansi_code) = key
+            .and_then(|key| KEYS.iter().find(|(name, _)| *name == key))
+            .copied()
+            .ok_or_else(|| invalid("unknown key"))?;
+        // Letters move around between layouts, e.g. "z" on a German keyboard
+        let key_code = match key.chars().next() {
+            Some(letter) if key.len() == 1 && letter.is_ascii_alphabetic() => {
+                platform::layout_key_code(letter).unwrap_or(ansi_code)
+            }
+            _ => ansi_code,
+        };
+        let mut modifiers = 0;
+        for part in parts {
+            modifiers |= match part {
+                "cmd" | "command" => COMMAND,
+                "ctrl" | "control" => CONTROL,
+                "opt" | "option" | "alt" => OPTION,
+                "shift" => SHIFT,
+                _ => return Err(invalid("unknown modifier")),
+            };
+        }
+        // Plain keys would fire while typing
+        if modifiers & (COMMAND | CONTROL | OPTION) == 0 {
+            return Err(invalid("needs cmd, ctrl or option"));
+        }
+        Ok(Self {
+            key,
+            key_code,
+            modifiers,
+        })
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (flag, name) in [
+            (CONTROL, "ctrl"),
+            (OPTION, "option"),
+            (SHIFT, "shift"),
+            (COMMAND, "cmd"),
+        ] {
+            if self.modifiers & flag != 0 {
+                write!(f, "{}+", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// Listens for a hotkey system-wide through a listen-only event tap on a background thread
+///
+/// The key press still reaches the app in front, so pick a combination it doesn't use.
+pub struct HotkeyListener {
+    stop: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl HotkeyListener {
+    /// Starts listening; every press of `hotkey` arrives on the returned channel
+    pub fn start(hotkey: Hotkey) -> HotkeyResult<(Self, UnboundedReceiver<()>)> {
+        let (presses_tx, presses) = mpsc::unbounded_channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = platform::spawn(hotkey, presses_tx, Arc::clone(&stop))?;
+        let listener = Self {
+            stop,
+            thread: Some(thread),
+        };
+        Ok((listener, presses))
+    }
+}
+
+impl Drop for HotkeyListener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{Hotkey, HotkeyError, HotkeyResult};
+    use core_foundation::base::{CFType, CFTypeRef, TCFType};
+    use core_foundation::data::{CFData, CFDataRef};
+    use core_foundation::runloop::{kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop};
+    use core_foundation::string::CFStringRef;
+    use core_graphics::event::{
+        CGEventTap, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement, CGEventType,
+        EventField,
+    };
+    use std::ffi::c_void;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc::UnboundedSender;
+
+    /// kUCKeyActionDisplay
+    const KEY_ACTION_DISPLAY: u16 = 3;
+
+    /// 1 << kUCKeyTranslateNoDeadKeysBit
+    const NO_DEAD_KEYS: u32 = 1;
+
+    /// Key codes of the main keyboard block; higher ones are the keypad and function keys
+    const MAIN_KEYS: u16 = 0x33;
+
+    #[link(name = "Carbon", kind = "framework")]
+    unsafe extern "C" {
+        static kTISPropertyUnicodeKeyLayoutData: CFStringRef;
+        fn TISCopyCurrentKeyboardLayoutInputSource() -> *mut c_void;
+        fn TISGetInputSourceProperty(source: *mut c_void, key: CFStringRef) -> *mut c_void;
+        fn LMGetKbdType() -> u8;
+        fn UCKeyTranslate(
+            layout: *const u8,
+            key_code: u16,
+            key_action: u16,
+            modifier_state: u32,
+            keyboard_type: u32,
+            options: u32,
+            dead_key_state: *mut u32,
+            max_length: usize,
+            length: *mut usize,
+            chars: *mut u16,
+        ) -> i32;
+    }
+
+    /// Key code typing `letter` without modifiers in the current keyboard layout, `None`
+    /// when the layout can't be read, e.g. for some input methods
+    pub(super) fn layout_key_code(letter: char) -> Option<u16> {
+        unsafe {
+            let source = TISCopyCurrentKeyboardLayoutInputSource();
+            if source.is_null() {
+                return None;
+            }
+            let source = CFType::wrap_under_create_rule(source as CFTypeRef);
+            let data = TISGetInputSourceProperty(
+                source.as_CFTypeRef() as *mut c_void,
+                kTISPropertyUnicodeKeyLayoutData,
+            );
+            if data.is_null() {
+                return None;
+            }
+            let layout = CFData::wrap_under_get_rule(data as CFDataRef);
+            let keyboard_type = u32::from(LMGetKbdType());
+            (0..MAIN_KEYS).find(|&key_code| {
+                let mut dead_keys = 0;
+                let mut chars = [0u16; 4];
+                let mut length = 0;
+                let status = UCKeyTranslate(
+                    layout.bytes().as_ptr(),
+                    key_code,
+                    KEY_ACTION_DISPLAY,
+                    0,
+                    keyboard_type,
+                    NO_DEAD_KEYS,
+                    &mut dead_keys,
+                    chars.len(),
+                    &mut length,
+                    chars.as_mut_ptr(),
+                );
+                status == 0
+                    && length == 1
+                    && char::from_u32(u32::from(chars[0]))
+                        .is_some_and(|typed| typed.eq_ignore_ascii_case(&letter))
+            })
+        }
+    }
+
+    pub(super) fn spawn(
+        hotkey: Hotkey,
+        presses: UnboundedSender<()>,
+        stop: Arc<AtomicBool>,
+    ) -> HotkeyResult<std::thread::JoinHandle<()>> {
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            let tap = CGEventTap::new(
+                CGEventTapLocation::Session,
+                CGEventTapPlacement::TailAppendEventTap,
+                CGEventTapOptions::ListenOnly,
+                vec![CGEventType::KeyDown],
+                move |_, _, event| {
+                    // Holding the keys down fires once
+                    let repeat =
+                        event.get_integer_value_field(EventField::KEYBOARD_EVENT_AUTOREPEAT);
+                    let key_code =
+                        event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE) as u16;
+                    if repeat == 0 && hotkey.matches(key_code, event.get_flags().bits()) {
+                        let _ = presses.send(());
+                    }
+                    None
+                },
+            );
+            // Creating the tap fails without Input Monitoring permission
+            let Some((tap, source)) = tap.ok().and_then(|tap| {
+                let source = tap.mach_port.create_runloop_source(0).ok()?;
+                Some((tap, source))
+            }) else {
+                let _ = ready_tx.send(false);
+                return;
+            };
+            CFRunLoop::get_current().add_source(&source, unsafe { kCFRunLoopCommonModes });
+            tap.enable();
+            let _ = ready_tx.send(true);
+
+            while !stop.load(Ordering::Relaxed) {
+                CFRunLoop::run_in_mode(
+                    unsafe { kCFRunLoopDefaultMode },
+                    Duration::from_millis(500),
+                    false,
+                );
+            }
+        });
+
+        if ready_rx.recv() == Ok(true) {
+            Ok(thread)
+        } else {
+            let _ = thread.join();
+            Err(HotkeyError::PermissionDenied)
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::{Hotkey, HotkeyError, HotkeyResult};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use tokio::sync::mpsc::UnboundedSender;
+
+    pub(super) fn layout_key_code(_letter: char) -> Option<u16> {
+        None
+    }
+
+    pub(super) fn spawn(
+        _hotkey: Hotkey,
+        _presses: UnboundedSender<()>,
+        _stop: Arc<AtomicBool>,
+    ) -> HotkeyResult<std::thread::JoinHandle<()>> {
+        Err(HotkeyError::PlatformNotSupported)
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{local_midnight, unix_millis, ActivityStore, ActivitySummary, CaptureSession};
+use axum::extract::{Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// How far back `/activity` looks when no `from` is given
+const DEFAULT_ACTIVITY_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone)]
+struct ApiState {
+    store: Arc<ActivityStore>,
+    session: Option<Arc<CaptureSession>>,
+    started_at: SystemTime,
+}
+
+/// Range of `/activity`, in milliseconds since the Unix epoch
+#[derive(Debug, Deserialize)]
+struct ActivityRange {
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+/// Serves the activity store over HTTP for dashboards and the Swift app:
+/// `/status`, `/activity?from=MS&to=MS`, `/latest-frame` and `/summary/today`
+///
+/// Only requests addressed to the loopback address are answered, so a web page whose domain
+/// is rebound to 127.0.0.1 can't read the API.
+pub struct ActivityApi {
+    listener: TcpListener,
+    state: ApiState,
+}
+
+impl ActivityApi {
+    pub async fn bind(addr: SocketAddr, store: Arc<ActivityStore>) -> std::io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            state: ApiState {
+                store,
+                session: None,
+                started_at: SystemTime::now(),
+            },
+        })
+    }
+
+    /// Reports whether `session` is paused or locked in `/status`
+    pub fn with_session(mut self, session: Arc<CaptureSession>) -> Self {
+        self.state.session = Some(session);
+        self
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Answers requests until the task is aborted
+    pub fn spawn(self) -> JoinHandle<()> {
+        let port = self.listener.local_addr().map_or(0, |addr| addr.port());
+        let router = Router::new()
+            .route("/status", get(status))
+            .route("/activity", get(activity))
+            .route("/latest-frame", get(latest_frame))
+            .route("/summary/today", get(summary_today))
+            .with_state(self.state)
+            .layer(middleware::from_fn_with_state(port, check_host));
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(self.listener, router).await {
+                eprintln!("❌ Activity API stopped: {}", e);
+            }
+        })
+    }
+}
+
+async fn check_host(State(port): State<u16>, request: Request, next: Next) -> Response {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+    if host.is_some_and(|host| is_loopback_host(host, port)) {
+        next.run(request).await
+    } else {
+        (StatusCode::FORBIDDEN, "Unexpected Host header").into_response()
+    }
+}
+
+/// Whether a Host header names this machine's loopback address and `port`
+fn is_loopback_host(host: &str, port: u16) -> bool {
+    ["127.0.0.1", "localhost", "[::1]"]
+        .iter()
+        .any(|name| host.eq_ignore_ascii_case(&format!("{}:{}", name, port)))
+}
+
+async fn status(State(state): State<ApiState>) -> Response {
+    let mut status = json!({
+        "startedAtMs": unix_millis(state.started_at),
+    });
+    if let Some(session) = &state.session {
+        status["paused"] = json!(session.is_paused());
+        status["locked"] = json!(session.is_locked());
+        status["connected"] = json!(!session.sender().is_closed());
+    }
+    Json(status).into_response()
+}
+
+async fn activity(State(state): State<ApiState>, Query(range): Query<ActivityRange>) -> Response {
+    let now = SystemTime::now();
+    let to = range.to.map_or(now, from_millis);
+    let from = range.from.map_or_else(
+        || {
+            to.checked_sub(DEFAULT_ACTIVITY_WINDOW)
+                .unwrap_or(UNIX_EPOCH)
+        },
+        from_millis,
+    );
+    match state.store.between(from, to) {
+        Ok(entries) => Json(entries).into_response(),
+        Err(e) => internal_error(e),
+    }
+}
+
+async fn latest_frame(State(state): State<ApiState>) -> Response {
+    let path = match state.store.latest_frame_path() {
+        Ok(Some(path)) => path,
+        Ok(None) => return (StatusCode::NOT_FOUND, "No frame saved yet").into_response(),
+        Err(e) => return internal_error(e),
+    };
+    let content_type = match Path::new(&path).extension().and_then(|ext| ext.to_str()) {
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        _ => "image/jpeg",
+    };
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => ([(header::CONTENT_TYPE, content_type)], bytes).into_response(),
+        // e.g. pruned by the retention limits
+        Err(e) => (StatusCode::NOT_FOUND, format!("{}: {}", path, e)).into_response(),
+    }
+}
+
+async fn summary_today(State(state): State<ApiState>) -> Response {
+    let now = SystemTime::now();
+    let midnight = local_midnight(now);
+    match state.store.between(midnight, now) {
+        Ok(entries) => {
+            let mut summary = json!(ActivitySummary::of(&entries));
+            summary["fromMs"] = json!(unix_millis(midnight));
+            summary["toMs"] = json!(unix_millis(now));
+            Json(summary).into_response()
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+fn from_millis(ms: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(ms)
+}
+
+fn internal_error(e: impl std::fmt::Display) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_loopback_hosts_on_the_port_pass() {
+        assert!(is_loopback_host("127.0.0.1:8787", 8787));
+        assert!(is_loopback_host("LocalHost:8787", 8787));
+        assert!(is_loopback_host("[::1]:8787", 8787));
+        assert!(!is_loopback_host("localhost:8788", 8787));
+        assert!(!is_loopback_host("localhost", 8787));
+        assert!(!is_loopback_host("attacker.example:8787", 8787));
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{CaptureSession, ResponsePrinter};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// How often the idle watch checks for input
+pub const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time since the last keyboard, mouse or trackpad input in the login session; `None` where
+/// it can't be read
+#[cfg(target_os = "macos")]
+pub fn user_idle_time() -> Option<Duration> {
+    /// kCGEventSourceStateCombinedSessionState: input from every source in the session
+    const COMBINED_SESSION_STATE: i32 = 0;
+    /// kCGAnyInputEventType
+    const ANY_INPUT_EVENT: u32 = !0;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    unsafe extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state: i32, event_type: u32) -> f64;
+    }
+
+    let seconds =
+        unsafe { CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT) };
+    (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn user_idle_time() -> Option<Duration> {
+    None
+}
+
+/// Pauses a capture session once the user has been idle for a while and resumes it on the
+/// next input, noting the gap in the activity log
+pub struct IdleWatch {
+    threshold: Duration,
+    printer: Arc<dyn ResponsePrinter>,
+}
+
+impl IdleWatch {
+    pub fn new(threshold: Duration, printer: Arc<dyn ResponsePrinter>) -> Self {
+        Self { threshold, printer }
+    }
+
+    /// Polls the idle time in the background
+    ///
+    /// A session paused by other means, e.g. an RPC command, is left alone.
+    pub fn spawn(self, session: Arc<CaptureSession>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(IDLE_POLL_INTERVAL);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // When the user's last input was, while the session is paused for their absence
+            let mut away_since: Option<SystemTime> = None;
+            loop {
+                ticker.tick().await;
+                let Some(idle) = user_idle_time() else {
+                    continue;
+                };
+                match away_since {
+                    None if idle >= self.threshold && !session.is_paused() => {
+                        let since = SystemTime::now() - idle;
+                        self.printer.print_status(&format!(
+                            "💤 No input for {}s, pausing until the user is back",
+                            idle.as_secs()
+                        ));
+                        session.pause();
+                        away_since = Some(since);
+                    }
+                    Some(since) if idle < self.threshold => {
+                        away_since = None;
+                        // Does nothing if the session was resumed over RPC in the meantime
+                        session.resume();
+                        let back = SystemTime::now() - idle;
+                        if let Err(e) = session.record_idle_gap(since, back).await {
+                            self.printer
+                                .print_error(&format!("❌ Error sending idle gap: {}", e));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,265 @@
+use crate::utils::local_time;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Written as the creating software in EXIF and XMP
+const CREATOR_TOOL: &str = "mac-watcher";
+
+/// Namespace of the XMP properties without a standard equivalent
+const WATCHER_NAMESPACE: &str = "https://github.com/andrewvorobyev/mac-watcher-swift/ns/1.0/";
+
+const XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const EXIF_SIGNATURE: &[u8] = b"Exif\0\0";
+
+/// Characters of a summary written into XMP, which has to fit one JPEG segment
+const MAX_SUMMARY_CHARS: usize = 8_000;
+
+/// Capture details embedded into a saved JPEG
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageMetadata {
+    pub captured_at: SystemTime,
+    /// Frontmost app, as named in prompts
+    pub app_name: Option<String>,
+    pub window_title: Option<String>,
+    pub display_id: Option<u32>,
+    /// The model's answer about the frame, once received
+    pub summary: Option<String>,
+}
+
+impl ImageMetadata {
+    pub fn new(captured_at: SystemTime) -> Self {
+        Self {
+            captured_at,
+            app_name: None,
+            window_title: None,
+            display_id: None,
+            summary: None,
+        }
+    }
+
+    /// JPEG bytes with EXIF and XMP segments describing the capture, replacing any the
+    /// image already had; bytes that aren't a JPEG are returned unchanged
+    pub fn embed(&self, jpeg: &[u8]) -> Vec<u8> {
+        let Some(body) = jpeg.strip_prefix(&[0xFF, 0xD8]) else {
+            return jpeg.to_vec();
+        };
+        let (leading, rest) = split_leading_segments(body);
+
+        let mut out = Vec::with_capacity(jpeg.len() + 4096);
+        out.extend_from_slice(&[0xFF, 0xD8]);
+        // JFIF requires its APP0 segment to come first
+        let mut kept = Vec::new();
+        for segment in leading {
+            if segment.get(1) == Some(&0xE0) {
+                out.extend_from_slice(segment);
+            } else if !is_metadata_segment(segment) {
+                kept.push(segment);
+            }
+        }
+        push_segment(&mut out, 0xE1, &[EXIF_SIGNATURE, &self.exif()].concat());
+        push_segment(
+            &mut out,
+            0xE1,
+            &[XMP_SIGNATURE, self.xmp().as_bytes()].concat(),
+        );
+        for segment in kept {
+            out.extend_from_slice(segment);
+        }
+        out.extend_from_slice(rest);
+        out
+    }
+
+    /// Rewrites the JPEG at `path` with this metadata
+    pub fn write_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let jpeg = fs::read(path)?;
+        fs::write(path, self.embed(&jpeg))
+    }
+
+    /// Little-endian TIFF structure with the capture time and creating software
+    fn exif(&self) -> Vec<u8> {
+        let time = exif_time(self.captured_at);
+        let ifd0 = |exif_offset: u32| {
+            vec![
+                (0x0131, Field::Ascii(CREATOR_TOOL)),
+                (0x0132, Field::Ascii(&time)),
+                (0x8769, Field::Long(exif_offset)),
+            ]
+        };
+        // The EXIF IFD follows IFD0, whose size doesn't depend on the pointer value
+        let ifd0_len = ifd(&ifd0(0), 8).len() as u32;
+        let mut tiff = b"II\x2A\x00\x08\x00\x00\x00".to_vec();
+        tiff.extend(ifd(&ifd0(8 + ifd0_len), 8));
+        tiff.extend(ifd(
+            &[(0x9003, Field::Ascii(&time)), (0x9004, Field::Ascii(&time))],
+            8 + ifd0_len,
+        ));
+        tiff
+    }
+
+    fn xmp(&self) -> String {
+        let mut properties = vec![
+            format!("<xmp:CreatorTool>{}</xmp:CreatorTool>", CREATOR_TOOL),
+            format!(
+                "<xmp:CreateDate>{}</xmp:CreateDate>",
+                iso_time(self.captured_at)
+            ),
+        ];
+        if let Some(app) = &self.app_name {
+            properties.push(format!("<watcher:App>{}</watcher:App>", escape_xml(app)));
+        }
+        if let Some(title) = &self.window_title {
+            properties.push(format!(
+                "<watcher:WindowTitle>{}</watcher:WindowTitle>",
+                escape_xml(title)
+            ));
+        }
+        if let Some(display_id) = self.display_id {
+            properties.push(format!(
+                "<watcher:DisplayId>{}</watcher:DisplayId>",
+                display_id
+            ));
+        }
+        if let Some(summary) = &self.summary {
+            let summary: String = summary.chars().take(MAX_SUMMARY_CHARS).collect();
+            properties.push(format!(
+                "<dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt>\
+                 </dc:description>",
+                escape_xml(&summary)
+            ));
+        }
+        format!(
+            "<?xpacket begin=\"\u{FEFF}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+             <rdf:Description rdf:about=\"\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:watcher=\"{}\">{}\
+             </rdf:Description></rdf:RDF></x:xmpmeta><?xpacket end=\"w\"?>",
+            WATCHER_NAMESPACE,
+            properties.concat()
+        )
+    }
+}
+
+enum Field<'a> {
+    Ascii(&'a str),
+    Long(u32),
+}
+
+/// One image file directory at `start` bytes into the TIFF structure, followed by the
+/// values too long to fit into its entries
+fn ifd(entries: &[(u16, Field)], start: u32) -> Vec<u8> {
+    let table_len = 2 + entries.len() * 12 + 4;
+    let mut table = Vec::with_capacity(table_len);
+    let mut values = Vec::new();
+    table.extend((entries.len() as u16).to_le_bytes());
+    for (tag, field) in entries {
+        table.extend(tag.to_le_bytes());
+        match field {
+            Field::Long(value) => {
+                table.extend(4u16.to_le_bytes());
+                table.extend(1u32.to_le_bytes());
+                table.extend(value.to_le_bytes());
+            }
+            Field::Ascii(text) => {
+                let mut bytes: Vec<u8> = text.bytes().filter(u8::is_ascii).collect();
+                bytes.push(0);
+                table.extend(2u16.to_le_bytes());
+                table.extend((bytes.len() as u32).to_le_bytes());
+                if bytes.len() <= 4 {
+                    bytes.resize(4, 0);
+                    table.extend(bytes);
+                } else {
+                    let offset = start + (table_len + values.len()) as u32;
+                    table.extend(offset.to_le_bytes());
+                    values.extend(&bytes);
+                    // Values start on word boundaries
+                    if values.len() % 2 == 1 {
+                        values.push(0);
+                    }
+                }
+            }
+        }
+    }
+    table.extend(0u32.to_le_bytes());
+    table.extend(values);
+    table
+}
+
+/// Splits the marker segments before the image data from the rest of a JPEG after SOI
+fn split_leading_segments(body: &[u8]) -> (Vec<&[u8]>, &[u8]) {
+    let mut segments = Vec::new();
+    let mut position = 0;
+    while let &[0xFF, marker, high, low, ..] = &body[position..] {
+        // Application and comment segments; anything else starts the image itself
+        if !(0xE0..=0xEF).contains(&marker) && marker != 0xFE {
+            break;
+        }
+        let end = position + 2 + u16::from_be_bytes([high, low]) as usize;
+        if end > body.len() {
+            break;
+        }
+        segments.push(&body[position..end]);
+        position = end;
+    }
+    (segments, &body[position..])
+}
+
+fn is_metadata_segment(segment: &[u8]) -> bool {
+    let payload = segment.get(4..).unwrap_or_default();
+    segment.get(1) == Some(&0xE1)
+        && (payload.starts_with(EXIF_SIGNATURE) || payload.starts_with(XMP_SIGNATURE))
+}
+
+fn push_segment(out: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    out.extend_from_slice(&[0xFF, marker]);
+    out.extend(((payload.len() + 2) as u16).to_be_bytes());
+    out.extend_from_slice(payload);
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Local time as EXIF writes it, YYYY:MM:DD HH:MM:SS
+fn exif_time(time: SystemTime) -> String {
+    match local_time(time) {
+        Some(local) => format!(
+            "{:04}:{:02}:{:02} {:02}:{:02}:{:02}",
+            local.tm_year + 1900,
+            local.tm_mon + 1,
+            local.tm_mday,
+            local.tm_hour,
+            local.tm_min,
+            local.tm_sec
+        ),
+        None => "0000:00:00 00:00:00".to_string(),
+    }
+}
+
+/// Local time in ISO 8601 with its UTC offset, as XMP dates are written
+fn iso_time(time: SystemTime) -> String {
+    match local_time(time) {
+        Some(local) => {
+            let offset = local.tm_gmtoff / 60;
+            format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}{:02}:{:02}",
+                local.tm_year + 1900,
+                local.tm_mon + 1,
+                local.tm_mday,
+                local.tm_hour,
+                local.tm_min,
+                local.tm_sec,
+                if offset < 0 { '-' } else { '+' },
+                offset.abs() / 60,
+                offset.abs() % 60
+            )
+        }
+        None => "1970-01-01T00:00:00Z".to_string(),
+    }
+}
@@ -1,6 +1,9 @@
-use image::{ImageBuffer, ImageError, RgbaImage};
+use crate::{bgra_to_rgb, bgra_to_rgb_into, BufferPool};
+use image::{ImageBuffer, ImageError, RgbImage};
 use std::io::Cursor;
 use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -9,10 +12,96 @@ pub enum JpegError {
     ImageError(#[from] ImageError),
     #[error("Invalid buffer dimensions")]
     InvalidDimensions,
+    #[cfg(feature = "turbojpeg")]
+    #[error("libjpeg-turbo error: {0}")]
+    TurboJpeg(#[from] turbojpeg::Error),
 }
 
 pub type JpegResult<T> = std::result::Result<T, JpegError>;
 
+/// Where the time encoding a frame went
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncodeTiming {
+    /// Reordering BGRA to RGB; zero when the encoder takes BGRA directly
+    pub convert: Duration,
+    pub encode: Duration,
+}
+
+/// Image format frames are encoded in before they are saved and sent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FrameFormat {
+    #[default]
+    Jpeg,
+    /// Lossless; several times larger, but small text stays crisp
+    Png,
+}
+
+impl FrameFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            FrameFormat::Jpeg => "jpg",
+            FrameFormat::Png => "png",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            FrameFormat::Jpeg => "image/jpeg",
+            FrameFormat::Png => "image/png",
+        }
+    }
+
+    /// Encodes BGRA pixels, drawing buffers from `pool`; `quality` only applies to JPEG
+    pub fn encode_pooled(
+        self,
+        bgra_data: &[u8],
+        width: u32,
+        height: u32,
+        quality: u8,
+        pool: &BufferPool,
+    ) -> JpegResult<Vec<u8>> {
+        self.encode_timed(bgra_data, width, height, quality, pool)
+            .map(|(bytes, _)| bytes)
+    }
+
+    /// Same as `encode_pooled`, also telling how long conversion and encoding took
+    pub fn encode_timed(
+        self,
+        bgra_data: &[u8],
+        width: u32,
+        height: u32,
+        quality: u8,
+        pool: &BufferPool,
+    ) -> JpegResult<(Vec<u8>, EncodeTiming)> {
+        if bgra_data.len() != (width * height * 4) as usize {
+            return Err(JpegError::InvalidDimensions);
+        }
+        let mut timing = EncodeTiming::default();
+        let bytes = match self {
+            FrameFormat::Jpeg => {
+                encode_verified_bgra(bgra_data, width, height, quality, pool, &mut timing)?
+            }
+            FrameFormat::Png => encode_verified_png(bgra_data, width, height, pool, &mut timing)?,
+        };
+        Ok((bytes, timing))
+    }
+}
+
+impl FromStr for FrameFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "jpeg" | "jpg" => Ok(FrameFormat::Jpeg),
+            "png" => Ok(FrameFormat::Png),
+            other => Err(format!(
+                "unknown image format '{}' (expected jpeg or png)",
+                other
+            )),
+        }
+    }
+}
+
 /// Encodes BGRA raw image data to JPEG format and saves to a file
 ///
 /// # Arguments
@@ -34,22 +123,10 @@ pub fn encode_bgra_to_jpeg<P: AsRef<Path>>(
         return Err(JpegError::InvalidDimensions);
     }
 
-    // Convert BGRA to RGBA
-    let mut rgba_data = Vec::with_capacity(bgra_data.len());
-    for chunk in bgra_data.chunks_exact(4) {
-        rgba_data.push(chunk[2]); // R (was B)
-        rgba_data.push(chunk[1]); // G
-        rgba_data.push(chunk[0]); // B (was R)
-        rgba_data.push(chunk[3]); // A
-    }
-
-    // Create image buffer
-    let img: RgbaImage = ImageBuffer::from_raw(width, height, rgba_data)
+    // Convert BGRA to RGB (JPEG doesn't support alpha)
+    let rgb_img: RgbImage = ImageBuffer::from_raw(width, height, bgra_to_rgb(bgra_data))
         .ok_or(JpegError::InvalidDimensions)?;
 
-    // Convert to RGB (JPEG doesn't support alpha)
-    let rgb_img = image::DynamicImage::ImageRgba8(img).to_rgb8();
-
     // Save as JPEG
     rgb_img.save_with_format(path, image::ImageFormat::Jpeg)?;
 
@@ -58,6 +135,8 @@ pub fn encode_bgra_to_jpeg<P: AsRef<Path>>(
 
 /// Encodes BGRA raw image data to JPEG format and returns as bytes
 ///
+/// Uses libjpeg-turbo with direct BGRA input when the `turbojpeg` feature is enabled.
+///
 /// # Arguments
 /// * `bgra_data` - Raw BGRA pixel data (4 bytes per pixel)
 /// * `width` - Image width in pixels
@@ -67,33 +146,119 @@ pub fn encode_bgra_to_jpeg_bytes(
     bgra_data: &[u8],
     width: u32,
     height: u32,
-    _quality: u8,
+    quality: u8,
+) -> JpegResult<Vec<u8>> {
+    encode_bgra_to_jpeg_bytes_pooled(bgra_data, width, height, quality, &BufferPool::new(0))
+}
+
+/// Same as [`encode_bgra_to_jpeg_bytes`], drawing the conversion and output buffers from
+/// `pool`; hand the returned bytes back with [`BufferPool::recycle`] once they are sent
+pub fn encode_bgra_to_jpeg_bytes_pooled(
+    bgra_data: &[u8],
+    width: u32,
+    height: u32,
+    quality: u8,
+    pool: &BufferPool,
 ) -> JpegResult<Vec<u8>> {
     // Verify buffer size
-    let expected_size = (width * height * 4) as usize;
-    if bgra_data.len() != expected_size {
-        return Err(JpegError::InvalidDimensions);
-    }
+    FrameFormat::Jpeg
+        .encode_timed(bgra_data, width, height, quality, pool)
+        .map(|(bytes, _)| bytes)
+}
 
-    // Convert BGRA to RGBA
-    let mut rgba_data = Vec::with_capacity(bgra_data.len());
-    for chunk in bgra_data.chunks_exact(4) {
-        rgba_data.push(chunk[2]); // R (was B)
-        rgba_data.push(chunk[1]); // G
-        rgba_data.push(chunk[0]); // B (was R)
-        rgba_data.push(chunk[3]); // A
-    }
+/// Encodes BGRA raw image data to PNG bytes, dropping the alpha channel
+pub fn encode_bgra_to_png_bytes_pooled(
+    bgra_data: &[u8],
+    width: u32,
+    height: u32,
+    pool: &BufferPool,
+) -> JpegResult<Vec<u8>> {
+    FrameFormat::Png
+        .encode_timed(bgra_data, width, height, 100, pool)
+        .map(|(bytes, _)| bytes)
+}
 
-    // Create image buffer
-    let img: RgbaImage = ImageBuffer::from_raw(width, height, rgba_data)
-        .ok_or(JpegError::InvalidDimensions)?;
+fn encode_verified_png(
+    bgra_data: &[u8],
+    width: u32,
+    height: u32,
+    pool: &BufferPool,
+    timing: &mut EncodeTiming,
+) -> JpegResult<Vec<u8>> {
+    let started = Instant::now();
+    let mut rgb_data = pool.take(bgra_data.len() / 4 * 3);
+    rgb_data.resize(bgra_data.len() / 4 * 3, 0);
+    bgra_to_rgb_into(bgra_data, &mut rgb_data);
+    let rgb_img: RgbImage =
+        ImageBuffer::from_raw(width, height, rgb_data).ok_or(JpegError::InvalidDimensions)?;
+    timing.convert = started.elapsed();
+
+    let started = Instant::now();
+    let mut buffer = Cursor::new(pool.take(0));
+    rgb_img.write_to(&mut buffer, image::ImageFormat::Png)?;
+    pool.recycle(rgb_img.into_raw());
+    timing.encode = started.elapsed();
 
-    // Convert to RGB (JPEG doesn't support alpha)
-    let rgb_img = image::DynamicImage::ImageRgba8(img).to_rgb8();
+    Ok(buffer.into_inner())
+}
+
+#[cfg(feature = "turbojpeg")]
+fn encode_verified_bgra(
+    bgra_data: &[u8],
+    width: u32,
+    height: u32,
+    quality: u8,
+    pool: &BufferPool,
+    timing: &mut EncodeTiming,
+) -> JpegResult<Vec<u8>> {
+    let started = Instant::now();
+    let image = turbojpeg::Image {
+        pixels: bgra_data,
+        width: width as usize,
+        pitch: width as usize * 4,
+        height: height as usize,
+        format: turbojpeg::PixelFormat::BGRA,
+    };
+    let subsamp = turbojpeg::Subsamp::Sub2x2;
+    let max_len = turbojpeg::compressed_buf_len(width as usize, height as usize, subsamp)?;
+    let mut jpeg = pool.take(max_len);
+    jpeg.resize(max_len, 0);
+
+    let mut compressor = turbojpeg::Compressor::new()?;
+    compressor.set_quality(quality.clamp(1, 100) as i32)?;
+    compressor.set_subsamp(subsamp)?;
+    let len = compressor.compress_to_slice(image, &mut jpeg)?;
+    jpeg.truncate(len);
+    timing.encode = started.elapsed();
+    Ok(jpeg)
+}
+
+#[cfg(not(feature = "turbojpeg"))]
+fn encode_verified_bgra(
+    bgra_data: &[u8],
+    width: u32,
+    height: u32,
+    quality: u8,
+    pool: &BufferPool,
+    timing: &mut EncodeTiming,
+) -> JpegResult<Vec<u8>> {
+    // Convert BGRA to RGB (JPEG doesn't support alpha)
+    let started = Instant::now();
+    let mut rgb_data = pool.take(bgra_data.len() / 4 * 3);
+    rgb_data.resize(bgra_data.len() / 4 * 3, 0);
+    bgra_to_rgb_into(bgra_data, &mut rgb_data);
+    let rgb_img: RgbImage =
+        ImageBuffer::from_raw(width, height, rgb_data).ok_or(JpegError::InvalidDimensions)?;
+    timing.convert = started.elapsed();
 
     // Encode to JPEG bytes
-    let mut buffer = Cursor::new(Vec::new());
-    rgb_img.write_to(&mut buffer, image::ImageFormat::Jpeg)?;
+    let started = Instant::now();
+    let mut buffer = Cursor::new(pool.take(0));
+    let encoder =
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, quality.clamp(1, 100));
+    rgb_img.write_with_encoder(encoder)?;
+    pool.recycle(rgb_img.into_raw());
+    timing.encode = started.elapsed();
 
     Ok(buffer.into_inner())
 }
@@ -0,0 +1,211 @@
+use crate::{ConnectionOptions, GeminiError, GeminiSession, Setup};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Limits enforced per API key
+#[derive(Debug, Clone)]
+pub struct KeyLimits {
+    /// Concurrent sessions allowed on one key; `None` means unlimited
+    pub max_sessions: Option<usize>,
+    /// How long a key is skipped after the server reports it as rate-limited
+    pub cooldown: Duration,
+}
+
+impl Default for KeyLimits {
+    fn default() -> Self {
+        Self {
+            max_sessions: None,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct KeyState {
+    active: usize,
+    connected: u64,
+    rate_limited: u64,
+    failures: u64,
+    cooldown_until: Option<Instant>,
+}
+
+/// Point-in-time usage of one pooled key
+#[derive(Debug, Clone)]
+pub struct KeyUsage {
+    /// Last characters of the key, safe to log
+    pub key_hint: String,
+    pub active_sessions: usize,
+    pub sessions_opened: u64,
+    pub rate_limited: u64,
+    pub failures: u64,
+    pub cooling_down: bool,
+}
+
+struct PoolInner {
+    keys: Vec<String>,
+    state: parking_lot::Mutex<Vec<KeyState>>,
+}
+
+/// Distributes sessions across several API keys and fails over when one is rate-limited
+#[derive(Clone)]
+pub struct KeyPool {
+    options: ConnectionOptions,
+    limits: KeyLimits,
+    inner: Arc<PoolInner>,
+}
+
+/// Keeps a session slot reserved on a key; released when dropped
+pub struct KeyLease {
+    /// `None` when the session was opened without a pooled key
+    slot: Option<(Arc<PoolInner>, usize)>,
+}
+
+impl KeyLease {
+    pub fn key_hint(&self) -> Option<String> {
+        self.slot
+            .as_ref()
+            .map(|(inner, index)| key_hint(&inner.keys[*index]))
+    }
+}
+
+impl Drop for KeyLease {
+    fn drop(&mut self) {
+        if let Some((inner, index)) = &self.slot {
+            let mut state = inner.state.lock();
+            state[*index].active = state[*index].active.saturating_sub(1);
+        }
+    }
+}
+
+fn key_hint(key: &str) -> String {
+    let start = key
+        .char_indices()
+        .rev()
+        .nth(3)
+        .map_or(0, |(index, _)| index);
+    format!("…{}", &key[start..])
+}
+
+impl KeyPool {
+    /// Builds a pool from every key configured on `options`
+    pub fn new(options: ConnectionOptions, limits: KeyLimits) -> Self {
+        let keys = options.api_keys();
+        let state = keys.iter().map(|_| KeyState::default()).collect();
+        Self {
+            options,
+            limits,
+            inner: Arc::new(PoolInner {
+                keys,
+                state: parking_lot::Mutex::new(state),
+            }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.keys.is_empty()
+    }
+
+    /// Keys that can take another session, least loaded first
+    fn candidates(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let state = self.inner.state.lock();
+        let mut candidates: Vec<usize> = (0..state.len())
+            .filter(|&index| {
+                let key = &state[index];
+                let cooling = key.cooldown_until.is_some_and(|until| until > now);
+                let full = self
+                    .limits
+                    .max_sessions
+                    .is_some_and(|max| key.active >= max);
+                !cooling && !full
+            })
+            .collect();
+        candidates.sort_by_key(|&index| (state[index].active, state[index].connected));
+        candidates
+    }
+
+    /// Connects using the least loaded key, trying the next one whenever a key is rate-limited
+    pub async fn connect(&self, setup: Setup) -> crate::gemini::Result<(GeminiSession, KeyLease)> {
+        let candidates = self.candidates();
+        if candidates.is_empty() {
+            // No pooled keys (e.g. token auth) or all of them are cooling down
+            if self.is_empty() {
+                let session = GeminiSession::connect(setup, self.options.clone()).await?;
+                return Ok((session, KeyLease { slot: None }));
+            }
+            return Err(GeminiError::NoAvailableApiKey);
+        }
+
+        let mut last_error = None;
+        for index in candidates {
+            // Reserve the slot before connecting so parallel connects spread out
+            self.inner.state.lock()[index].active += 1;
+            let lease = KeyLease {
+                slot: Some((Arc::clone(&self.inner), index)),
+            };
+
+            let options = self.options.with_api_key(self.inner.keys[index].clone());
+            match GeminiSession::connect(setup.clone(), options).await {
+                Ok(session) => {
+                    self.inner.state.lock()[index].connected += 1;
+                    return Ok((session, lease));
+                }
+                Err(err) => {
+                    drop(lease);
+                    let rate_limited = err.is_rate_limited();
+                    {
+                        let mut state = self.inner.state.lock();
+                        if rate_limited {
+                            state[index].rate_limited += 1;
+                            state[index].cooldown_until =
+                                Some(Instant::now() + self.limits.cooldown);
+                        } else {
+                            state[index].failures += 1;
+                        }
+                    }
+                    if !rate_limited {
+                        return Err(err);
+                    }
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or(GeminiError::NoAvailableApiKey))
+    }
+
+    /// Marks the key behind `lease` as rate-limited, e.g. after a mid-session quota error
+    pub fn report_rate_limited(&self, lease: &KeyLease) {
+        let Some((inner, index)) = &lease.slot else {
+            return;
+        };
+        if !Arc::ptr_eq(inner, &self.inner) {
+            return;
+        }
+        let mut state = self.inner.state.lock();
+        state[*index].rate_limited += 1;
+        state[*index].cooldown_until = Some(Instant::now() + self.limits.cooldown);
+    }
+
+    pub fn usage(&self) -> Vec<KeyUsage> {
+        let now = Instant::now();
+        let state = self.inner.state.lock();
+        self.inner
+            .keys
+            .iter()
+            .zip(state.iter())
+            .map(|(key, state)| KeyUsage {
+                key_hint: key_hint(key),
+                active_sessions: state.active,
+                sessions_opened: state.connected,
+                rate_limited: state.rate_limited,
+                failures: state.failures,
+                cooling_down: state.cooldown_until.is_some_and(|until| until > now),
+            })
+            .collect()
+    }
+}
@@ -1,15 +1,179 @@
+#[cfg(feature = "sqlite")]
+pub mod activity_export;
+pub mod activity_meter;
+#[cfg(feature = "sqlite")]
+pub mod activity_report;
+#[cfg(feature = "sqlite")]
+pub mod activity_store;
+pub mod aggregate;
+pub mod alert_rules;
+pub mod analysis;
+pub mod analysis_backend;
+pub mod analysis_result;
+pub mod annotation;
+pub mod anonymize;
+pub mod answer_history;
+#[cfg(feature = "anthropic")]
+pub mod anthropic;
+pub mod app_exclusion;
+#[cfg(feature = "playback")]
+pub mod audio_sink;
+#[cfg(feature = "audio")]
+pub mod audio_source;
+pub mod batch;
+pub mod buffer_pool;
+#[cfg(feature = "calendar")]
+pub mod calendar;
 pub mod capture_session;
+#[cfg(any(feature = "anthropic", feature = "ollama", feature = "openai"))]
+pub mod chat_session;
+pub mod change_detect;
+pub mod clipboard;
+pub mod config;
+pub mod cursor;
+#[cfg(feature = "desktop-notifications")]
+pub mod desktop_notifier;
+pub mod dry_run;
+pub mod focus;
+pub mod frame_dedup;
+pub mod frame_history;
 pub mod frame_source;
+pub mod frame_store;
 pub mod gemini;
+#[cfg(feature = "hotkey")]
+pub mod hotkey;
+#[cfg(feature = "http-api")]
+pub mod http_api;
+pub mod idle;
+pub mod image_metadata;
 pub mod jpeg;
+pub mod key_pool;
+pub mod live_feed;
+pub mod manifest;
+pub mod memory;
+#[cfg(feature = "mcp")]
+pub mod mcp_bridge;
+pub mod menu_watch;
+pub mod multi_display;
+#[cfg(feature = "webhooks")]
+pub mod notifier;
+#[cfg(feature = "ocr")]
+pub mod ocr;
+pub mod offline_queue;
+#[cfg(feature = "ollama")]
+pub mod ollama;
+#[cfg(feature = "openai")]
+pub mod openai;
+pub mod pcm;
 pub mod permissions;
+pub mod pixel;
+pub mod pointer;
+pub mod preview;
+pub mod prompt_profile;
+#[cfg(feature = "recording")]
+pub mod recording;
+pub mod redact;
+pub mod replay;
+pub mod resize;
+pub mod resource_limits;
 pub mod response_printer;
+pub mod retention;
+pub mod rpc;
+pub mod screen_lock;
+pub mod screenshot_tool;
+pub mod system_audio;
+pub mod telemetry;
+pub mod timelapse;
+pub mod tools;
 pub mod utils;
+#[cfg(feature = "video")]
+pub mod video_source;
+pub mod window_list;
+pub mod zoom;
 
+#[cfg(feature = "sqlite")]
+pub use activity_export::*;
+pub use activity_meter::*;
+#[cfg(feature = "sqlite")]
+pub use activity_report::*;
+#[cfg(feature = "sqlite")]
+pub use activity_store::*;
+pub use aggregate::*;
+pub use alert_rules::*;
+pub use analysis::*;
+pub use analysis_backend::*;
+pub use analysis_result::*;
+pub use annotation::*;
+pub use anonymize::*;
+pub use answer_history::*;
+pub use app_exclusion::*;
+#[cfg(feature = "playback")]
+pub use audio_sink::*;
+#[cfg(feature = "audio")]
+pub use audio_source::*;
+pub use batch::*;
+pub use buffer_pool::*;
+#[cfg(feature = "calendar")]
+pub use calendar::*;
 pub use capture_session::*;
+#[cfg(any(feature = "anthropic", feature = "ollama", feature = "openai"))]
+pub use chat_session::*;
+pub use change_detect::*;
+pub use clipboard::*;
+pub use config::*;
+pub use cursor::*;
+#[cfg(feature = "desktop-notifications")]
+pub use desktop_notifier::*;
+pub use dry_run::*;
+pub use focus::*;
+pub use frame_dedup::*;
+pub use frame_history::*;
 pub use frame_source::*;
+pub use frame_store::*;
 pub use gemini::*;
+#[cfg(feature = "hotkey")]
+pub use hotkey::*;
+#[cfg(feature = "http-api")]
+pub use http_api::*;
+pub use idle::*;
+pub use image_metadata::*;
 pub use jpeg::*;
+pub use key_pool::*;
+pub use live_feed::*;
+pub use manifest::*;
+pub use memory::*;
+#[cfg(feature = "mcp")]
+pub use mcp_bridge::*;
+pub use menu_watch::*;
+pub use multi_display::*;
+#[cfg(feature = "webhooks")]
+pub use notifier::*;
+#[cfg(feature = "ocr")]
+pub use ocr::*;
+pub use offline_queue::*;
+pub use pcm::*;
 pub use permissions::*;
+pub use pixel::*;
+pub use pointer::*;
+pub use preview::*;
+pub use prompt_profile::*;
+#[cfg(feature = "recording")]
+pub use recording::*;
+pub use redact::*;
+pub use replay::*;
+pub use resize::*;
+pub use resource_limits::*;
 pub use response_printer::*;
+pub use retention::*;
+pub use rpc::*;
+pub use screen_lock::*;
+pub use screenshot_tool::*;
+pub use system_audio::*;
+pub use telemetry::*;
+pub use timelapse::*;
+pub use tools::*;
 pub use utils::*;
+#[cfg(feature = "video")]
+pub use video_source::*;
+pub use window_list::*;
+pub use zoom::*;
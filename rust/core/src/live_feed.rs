@@ -0,0 +1,192 @@
+use crate::{AnalysisResult, PreviewFeed};
+use base64::Engine;
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{header, StatusCode};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+/// Longer side of the thumbnails pushed to live feed clients, in pixels
+pub const THUMBNAIL_EDGE: u32 = 320;
+
+const THUMBNAIL_QUALITY: u8 = 70;
+
+/// Messages a slow client can fall behind by before it skips ahead
+const FEED_CAPACITY: usize = 64;
+
+/// Analyses, and optionally frame thumbnails, pushed to the clients of a LiveFeedServer as
+/// JSON text messages
+#[derive(Clone)]
+pub struct LiveFeed {
+    messages: broadcast::Sender<Arc<str>>,
+}
+
+impl Default for LiveFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveFeed {
+    pub fn new() -> Self {
+        Self {
+            messages: broadcast::Sender::new(FEED_CAPACITY),
+        }
+    }
+
+    /// Pushes `result` as `{"type": "analysis", ...}` with the fields of the RPC
+    /// `analysis/result` notification
+    pub fn publish(&self, result: &AnalysisResult) {
+        let mut message = result.to_json();
+        message["type"] = json!("analysis");
+        self.send(message);
+    }
+
+    /// Pushes a thumbnail of every frame shown in `preview` as `{"type": "thumbnail",
+    /// "jpeg": BASE64, ...}`, while any client is connected
+    pub fn forward_thumbnails(&self, preview: &PreviewFeed) -> JoinHandle<()> {
+        let feed = self.clone();
+        let mut frames = preview.subscribe();
+        tokio::spawn(async move {
+            while frames.changed().await.is_ok() {
+                let Some(jpeg) = frames.borrow_and_update().clone() else {
+                    continue;
+                };
+                if feed.messages.receiver_count() == 0 {
+                    continue;
+                }
+                match tokio::task::spawn_blocking(move || thumbnail(&jpeg)).await {
+                    Ok(Ok(message)) => feed.send(message),
+                    Ok(Err(e)) => eprintln!("❌ Failed to make a thumbnail: {}", e),
+                    Err(_) => {}
+                }
+            }
+        })
+    }
+
+    fn send(&self, message: Value) {
+        // Failing only means no client is connected
+        let _ = self.messages.send(Arc::from(message.to_string()));
+    }
+}
+
+fn thumbnail(jpeg: &[u8]) -> image::ImageResult<Value> {
+    let image = image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg)?
+        .thumbnail(THUMBNAIL_EDGE, THUMBNAIL_EDGE)
+        .to_rgb8();
+    let mut bytes = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, THUMBNAIL_QUALITY)
+        .encode_image(&image)?;
+    Ok(json!({
+        "type": "thumbnail",
+        "width": image.width(),
+        "height": image.height(),
+        "jpeg": base64::engine::general_purpose::STANDARD.encode(bytes),
+    }))
+}
+
+/// Accepts WebSocket connections on any path and pushes the live feed to them, so a
+/// front-end can show what the user is doing without polling
+///
+/// Browsers may only connect from pages served by this machine, so other web pages the user
+/// opens can't watch along; clients that send no Origin, i.e. not browsers, are accepted.
+pub struct LiveFeedServer {
+    listener: TcpListener,
+    feed: LiveFeed,
+}
+
+impl LiveFeedServer {
+    pub async fn bind(addr: SocketAddr, feed: LiveFeed) -> std::io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            feed,
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections until the task is aborted
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = self.listener.accept().await else {
+                    // e.g. out of file descriptors; avoid spinning until some are freed
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    continue;
+                };
+                let messages = self.feed.messages.subscribe();
+                tokio::spawn(async move {
+                    // Clients going away mid-message are expected
+                    let _ = serve_connection(stream, messages).await;
+                });
+            }
+        })
+    }
+}
+
+async fn serve_connection(
+    stream: TcpStream,
+    mut messages: broadcast::Receiver<Arc<str>>,
+) -> tungstenite::Result<()> {
+    let (mut sink, mut incoming) = tokio_tungstenite::accept_hdr_async(stream, check_origin)
+        .await?
+        .split();
+    loop {
+        tokio::select! {
+            message = messages.recv() => match message {
+                Ok(text) => sink.send(Message::Text(text.to_string())).await?,
+                // A slow client skips what it missed
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            // Clients only listen; reading notices them leaving and answers their pings
+            incoming = incoming.next() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Handshake callback turning away browser pages not served by this machine
+// The error type is tungstenite's
+#[allow(clippy::result_large_err)]
+fn check_origin(request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+    match request.headers().get(header::ORIGIN) {
+        Some(origin) if !origin.to_str().is_ok_and(is_local_origin) => {
+            let mut rejection = ErrorResponse::new(Some("Origin not allowed".to_string()));
+            *rejection.status_mut() = StatusCode::FORBIDDEN;
+            Err(rejection)
+        }
+        _ => Ok(response),
+    }
+}
+
+/// Whether a page at `origin` is served by this machine, e.g. `http://localhost:3000`
+fn is_local_origin(origin: &str) -> bool {
+    url::Url::parse(origin)
+        .is_ok_and(|url| matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_local_origins_are_allowed() {
+        assert!(is_local_origin("http://localhost:3000"));
+        assert!(is_local_origin("http://127.0.0.1:8080"));
+        assert!(is_local_origin("https://[::1]"));
+        assert!(!is_local_origin("https://example.com"));
+        assert!(!is_local_origin("http://localhost.example.com"));
+        assert!(!is_local_origin("null"));
+    }
+}
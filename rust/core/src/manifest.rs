@@ -0,0 +1,118 @@
+use crate::{ActivityCounts, StoreResult, UsageMetadata};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Index of an output directory's frames and answers, one JSON object per line
+pub const MANIFEST_FILE: &str = "manifest.jsonl";
+
+/// A frame saved to the output directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameRecord {
+    /// Name the session was saved under; frame numbers restart with every session
+    pub session: String,
+    pub index: usize,
+    /// File name in the output directory
+    pub file: String,
+    pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_title: Option<String>,
+    pub prompt: String,
+    /// Keypresses and clicks since the previous frame, when metered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activity: Option<ActivityCounts>,
+    /// Calendar event going on when the frame was captured, when calendars are read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar_event: Option<String>,
+}
+
+/// The model's answer to a turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseRecord {
+    pub session: String,
+    /// Frame the answer is about, or the last frame of a batch; `None` for questions and
+    /// menu events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    pub timestamp_ms: u64,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageMetadata>,
+}
+
+/// One manifest line; a frame is written when it is saved and its answer once it arrives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ManifestEntry {
+    Frame(FrameRecord),
+    Response(ResponseRecord),
+}
+
+/// Appends entries to the manifest of an output directory, which earlier sessions share
+pub struct ManifestWriter {
+    file: parking_lot::Mutex<File>,
+}
+
+impl ManifestWriter {
+    /// Opens (creating if needed) the manifest in `dir`
+    pub fn open<P: AsRef<Path>>(dir: P) -> StoreResult<Self> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(MANIFEST_FILE))?;
+        Ok(Self {
+            file: parking_lot::Mutex::new(file),
+        })
+    }
+
+    pub fn append(&self, entry: &ManifestEntry) -> StoreResult<()> {
+        // One write per line, so concurrent sessions don't interleave within an entry
+        let line = format!("{}\n", serde_json::to_string(entry)?);
+        self.file.lock().write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// A saved frame with the answer about it, if one arrived
+#[derive(Debug, Clone)]
+pub struct ArchivedFrame {
+    pub frame: FrameRecord,
+    pub response: Option<ResponseRecord>,
+}
+
+/// Frames listed in the manifest of `dir` in the order they were saved, each with its answer
+pub fn read_manifest<P: AsRef<Path>>(dir: P) -> StoreResult<Vec<ArchivedFrame>> {
+    let file = File::open(dir.as_ref().join(MANIFEST_FILE))?;
+    let mut frames = Vec::new();
+    let mut positions: HashMap<(String, usize), usize> = HashMap::new();
+    for line in BufReader::new(file).lines() {
+        // Skip entries torn by a crash mid-write rather than failing the whole read
+        let Ok(entry) = serde_json::from_str::<ManifestEntry>(&line?) else {
+            continue;
+        };
+        match entry {
+            ManifestEntry::Frame(frame) => {
+                positions.insert((frame.session.clone(), frame.index), frames.len());
+                frames.push(ArchivedFrame {
+                    frame,
+                    response: None,
+                });
+            }
+            ManifestEntry::Response(response) => {
+                let position = response
+                    .index
+                    .and_then(|index| positions.get(&(response.session.clone(), index)));
+                if let Some(&position) = position {
+                    frames[position].response = Some(response);
+                }
+            }
+        }
+    }
+    Ok(frames)
+}
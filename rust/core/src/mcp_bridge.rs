@@ -0,0 +1,521 @@
+use crate::{FunctionCall, ToolHandler};
+use futures::future::BoxFuture;
+use rmcp::model::{CallToolRequestParam, CallToolResult, Tool};
+use rmcp::service::{Peer, RoleClient, RunningService, ServiceError};
+use rmcp::transport::TokioChildProcess;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use thiserror::Error;
+use tokio::process::Command;
+
+/// Between a server's name and its tool's name in the names Gemini sees, e.g.
+/// `chrome__list_pages`
+pub const MCP_TOOL_SEPARATOR: &str = "__";
+
+/// Times a server is relaunched after its process died before its tools give up
+pub const MAX_MCP_RESTARTS: u32 = 3;
+
+#[derive(Debug, Error)]
+pub enum McpBridgeError {
+    #[error("Empty MCP server command")]
+    EmptyCommand,
+    #[error("Failed to launch MCP server: {0}")]
+    Launch(#[from] std::io::Error),
+    #[error("MCP server didn't initialize: {0}")]
+    Initialize(String),
+    #[error("Failed to list MCP tools: {0}")]
+    ListTools(#[from] ServiceError),
+    #[error("Failed to read MCP config: {0}")]
+    Read(std::io::Error),
+    #[error("Invalid MCP config: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("Tool call failed: {0}")]
+    Call(ServiceError),
+    #[error("{0} died and was restarted too often")]
+    TooManyRestarts(String),
+    #[error("{0} died and can't be restarted")]
+    Closed(String),
+}
+
+pub type McpBridgeResult<T> = std::result::Result<T, McpBridgeError>;
+
+/// MCP servers to launch, in the `mcpServers` layout other MCP clients read too:
+///
+/// ```json
+/// { "mcpServers": { "chrome": { "command": "npx", "args": ["chrome-devtools-mcp@latest"] } } }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpConfig {
+    #[serde(default)]
+    pub mcp_servers: BTreeMap<String, McpServerConfig>,
+}
+
+impl McpConfig {
+    pub fn load(path: &Path) -> McpBridgeResult<Self> {
+        let text = std::fs::read_to_string(path).map_err(McpBridgeError::Read)?;
+        Ok(serde_json::from_str(&text)?)
+    }
+}
+
+/// How to launch one MCP server talking over stdio
+#[derive(Debug, Clone, Deserialize)]
+pub struct McpServerConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Added to the environment the watcher runs in
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+impl McpServerConfig {
+    /// A server run by a command line such as `npx chrome-devtools-mcp@latest`
+    pub fn from_command_line(command: &str) -> McpBridgeResult<Self> {
+        let mut parts = command.split_whitespace().map(str::to_string);
+        Ok(Self {
+            command: parts.next().ok_or(McpBridgeError::EmptyCommand)?,
+            args: parts.collect(),
+            env: BTreeMap::new(),
+        })
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.command);
+        command.args(&self.args).envs(&self.env);
+        command
+    }
+}
+
+/// A server of the bridge; `config` is `None` for attached servers, which aren't restarted
+struct BridgedServer {
+    name: String,
+    /// Whether its tools are offered under its name, e.g. `chrome__list_pages`
+    namespaced: bool,
+    config: Option<McpServerConfig>,
+    client: tokio::sync::Mutex<RunningService<RoleClient, ()>>,
+    /// Listed on launch; Gemini only learns the tools at setup, so restarts keep this list
+    tools: Vec<Tool>,
+    restarts: AtomicU32,
+}
+
+impl BridgedServer {
+    /// Name Gemini calls `tool` by
+    fn function_name(&self, tool: &Tool) -> String {
+        if self.namespaced {
+            format!("{}{}{}", self.name, MCP_TOOL_SEPARATOR, tool.name)
+        } else {
+            tool.name.to_string()
+        }
+    }
+
+    /// A handle to the running server, relaunching it first if its process died
+    async fn peer(&self) -> McpBridgeResult<Peer<RoleClient>> {
+        let mut client = self.client.lock().await;
+        if client.is_transport_closed() {
+            let config = self
+                .config
+                .as_ref()
+                .ok_or_else(|| McpBridgeError::Closed(self.name.clone()))?;
+            if self.restarts.fetch_add(1, Ordering::Relaxed) >= MAX_MCP_RESTARTS {
+                return Err(McpBridgeError::TooManyRestarts(self.name.clone()));
+            }
+            eprintln!("🔁 MCP server {} died, restarting it", self.name);
+            *client = launch(config.command()).await?;
+        }
+        Ok(client.peer().clone())
+    }
+}
+
+/// Offers the tools of MCP servers to Gemini and forwards the model's calls to them, e.g. so
+/// it can look at the browser through a DevTools MCP server
+///
+/// Servers launched by name, e.g. from a config file, offer their tools under that name,
+/// e.g. `chrome__list_pages`, so servers with the same tool names can sit side by side;
+/// their names should stick to letters, digits, `_`, `.` and `-`. Other servers offer their
+/// tools by the tools' own names. Gemini needs unique function names, so a tool named like
+/// one already offered is left out.
+///
+/// A launched server whose process dies is relaunched on the next call of one of its tools.
+#[derive(Default)]
+pub struct McpBridge {
+    servers: Vec<BridgedServer>,
+}
+
+impl McpBridge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Launches `command`, e.g. `npx chrome-devtools-mcp@latest`, as an MCP server talking
+    /// over stdio, returning how many tools it offers
+    ///
+    /// The server is killed when the bridge is dropped.
+    pub async fn spawn_server(&mut self, command: &str) -> McpBridgeResult<usize> {
+        let config = McpServerConfig::from_command_line(command)?;
+        let client = launch(config.command()).await?;
+        self.add(command.to_string(), false, Some(config), client)
+            .await
+    }
+
+    /// Launches the server `name`, offering its tools under its name, and returns how many
+    /// tools it offers
+    pub async fn launch(
+        &mut self,
+        name: impl Into<String>,
+        config: McpServerConfig,
+    ) -> McpBridgeResult<usize> {
+        let client = launch(config.command()).await?;
+        self.add(name.into(), true, Some(config), client).await
+    }
+
+    /// Adds an already connected server under `name`, returning how many tools it offers
+    pub async fn attach(
+        &mut self,
+        name: impl Into<String>,
+        client: RunningService<RoleClient, ()>,
+    ) -> McpBridgeResult<usize> {
+        self.add(name.into(), false, None, client).await
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.iter().all(|server| server.tools.is_empty())
+    }
+
+    async fn add(
+        &mut self,
+        name: String,
+        namespaced: bool,
+        config: Option<McpServerConfig>,
+        client: RunningService<RoleClient, ()>,
+    ) -> McpBridgeResult<usize> {
+        let tools = client.list_all_tools().await?;
+        let mut server = BridgedServer {
+            name,
+            namespaced,
+            config,
+            client: tokio::sync::Mutex::new(client),
+            tools: Vec::new(),
+            restarts: AtomicU32::new(0),
+        };
+        for tool in tools {
+            if self.resolve(&server.function_name(&tool)).is_some() {
+                eprintln!(
+                    "⚠️ {} of {} is left out, another MCP server offers a tool of that name",
+                    tool.name, server.name
+                );
+            } else {
+                server.tools.push(tool);
+            }
+        }
+        let count = server.tools.len();
+        self.servers.push(server);
+        Ok(count)
+    }
+
+    /// The server and tool behind a function name
+    fn resolve(&self, function: &str) -> Option<(&BridgedServer, &Tool)> {
+        self.servers.iter().find_map(|server| {
+            server
+                .tools
+                .iter()
+                .find(|tool| server.function_name(tool) == function)
+                .map(|tool| (server, tool))
+        })
+    }
+}
+
+impl ToolHandler for McpBridge {
+    fn declarations(&self) -> Vec<Value> {
+        self.servers
+            .iter()
+            .flat_map(|server| {
+                server
+                    .tools
+                    .iter()
+                    .map(|tool| declaration(&server.function_name(tool), tool))
+            })
+            .collect()
+    }
+
+    fn handle<'a>(&'a self, call: &'a FunctionCall) -> BoxFuture<'a, Option<Value>> {
+        Box::pin(async move {
+            let (server, tool) = self.resolve(&call.name)?;
+            let arguments = match &call.args {
+                Some(Value::Object(args)) => Some(args.clone()),
+                _ => None,
+            };
+            let request = CallToolRequestParam {
+                name: tool.name.clone(),
+                arguments,
+            };
+
+            // A call that couldn't be written to the server is asked once more after a
+            // restart; one that went out isn't, as the tool may not be safe to run twice
+            let mut retried = false;
+            let result = loop {
+                let result = match server.peer().await {
+                    Ok(peer) => peer
+                        .call_tool(request.clone())
+                        .await
+                        .map_err(McpBridgeError::Call),
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Err(McpBridgeError::Call(ServiceError::TransportSend(_))) if !retried => {
+                        retried = true;
+                    }
+                    result => break result,
+                }
+            };
+            Some(match result {
+                Ok(result) => function_response(result),
+                Err(e) => json!({
+                    "error": format!("{} failed on {}: {}", tool.name, server.name, e)
+                }),
+            })
+        })
+    }
+}
+
+/// Starts `command` as an MCP server talking over stdio; it is killed when the returned
+/// client is dropped
+async fn launch(mut command: Command) -> McpBridgeResult<RunningService<RoleClient, ()>> {
+    command.kill_on_drop(true);
+    let transport = TokioChildProcess::new(command)?;
+    rmcp::serve_client((), transport)
+        .await
+        .map_err(|e| McpBridgeError::Initialize(e.to_string()))
+}
+
+/// Gemini function declaration of an MCP tool, under `name`
+fn declaration(name: &str, tool: &Tool) -> Value {
+    let description = tool
+        .description
+        .as_deref()
+        .or(tool.title.as_deref())
+        .unwrap_or_default();
+    let mut declaration = json!({
+        "name": name,
+        "description": description,
+    });
+    let parameters = gemini_schema(&Value::Object((*tool.input_schema).clone()));
+    // Gemini rejects an object without properties, so tools taking no arguments declare no
+    // parameters
+    if parameters["properties"]
+        .as_object()
+        .is_some_and(|properties| !properties.is_empty())
+    {
+        declaration["parameters"] = parameters;
+    }
+    declaration
+}
+
+/// Response body Gemini gets for an MCP tool result; only text is passed on
+fn function_response(result: CallToolResult) -> Value {
+    let text = result
+        .content
+        .iter()
+        .filter_map(|content| content.as_text())
+        .map(|content| content.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let output = result.structured_content.unwrap_or(Value::String(text));
+    if result.is_error == Some(true) {
+        json!({ "error": output })
+    } else {
+        json!({ "output": output })
+    }
+}
+
+/// Converts an MCP input JSON Schema into the OpenAPI subset Gemini accepts for function
+/// parameters, dropping keywords it would reject and inlining `$ref`s to the schema's own
+/// definitions
+fn gemini_schema(schema: &Value) -> Value {
+    convert_schema(schema, schema, 0)
+}
+
+/// `$ref`s followed within one another before giving up, so recursive schemas end
+const MAX_SCHEMA_REFS: usize = 8;
+
+fn convert_schema(schema: &Value, root: &Value, refs: usize) -> Value {
+    let Some(schema) = schema.as_object() else {
+        return json!({ "type": "STRING" });
+    };
+    let described = |mut converted: Value| {
+        if let Some(description) = schema.get("description") {
+            converted["description"] = description.clone();
+        }
+        converted
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let target = reference
+            .strip_prefix('#')
+            .filter(|_| refs < MAX_SCHEMA_REFS)
+            .and_then(|pointer| root.pointer(pointer));
+        return match target {
+            Some(target) => described(convert_schema(target, root, refs + 1)),
+            None => described(json!({ "type": "STRING" })),
+        };
+    }
+
+    // Null variants make the rest nullable, and a single other variant stands on its own
+    let variants = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array);
+    if let Some(variants) = variants {
+        let is_null =
+            |variant: &&Value| variant.get("type").and_then(Value::as_str) == Some("null");
+        let mut converted: Vec<Value> = variants
+            .iter()
+            .filter(|variant| !is_null(variant))
+            .map(|variant| convert_schema(variant, root, refs))
+            .collect();
+        let mut out = match converted.len() {
+            0 => json!({ "type": "STRING" }),
+            1 => converted.remove(0),
+            _ => json!({ "anyOf": converted }),
+        };
+        if variants.iter().any(|variant| is_null(&variant)) {
+            out["nullable"] = Value::Bool(true);
+        }
+        return described(out);
+    }
+
+    let mut out = Map::new();
+
+    // A type list like ["string", "null"] becomes a nullable string
+    let (kind, nullable) = match schema.get("type") {
+        Some(Value::String(kind)) => (Some(kind.as_str()), false),
+        Some(Value::Array(kinds)) => (
+            kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|kind| *kind != "null"),
+            kinds.iter().any(|kind| kind == "null"),
+        ),
+        _ => (None, false),
+    };
+    let kind = kind.unwrap_or(if schema.contains_key("properties") {
+        "object"
+    } else {
+        "string"
+    });
+    out.insert("type".into(), Value::String(kind.to_uppercase()));
+    if nullable {
+        out.insert("nullable".into(), Value::Bool(true));
+    }
+
+    // Formats other than Gemini's few, e.g. uri, would be rejected, so none are kept
+    for key in ["description", "enum", "required"] {
+        if let Some(value) = schema.get(key) {
+            out.insert(key.into(), value.clone());
+        }
+    }
+    if let Some(Value::Object(properties)) = schema.get("properties") {
+        let properties = properties
+            .iter()
+            .map(|(name, property)| (name.clone(), convert_schema(property, root, refs)))
+            .collect();
+        out.insert("properties".into(), Value::Object(properties));
+    }
+    if let Some(items) = schema.get("items") {
+        out.insert("items".into(), convert_schema(items, root, refs));
+    }
+    Value::Object(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nullable_types_and_formats() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": ["string", "null"], "format": "uri" },
+                "count": { "type": "integer", "description": "How many" }
+            },
+            "required": ["url"]
+        });
+        assert_eq!(
+            gemini_schema(&schema),
+            json!({
+                "type": "OBJECT",
+                "required": ["url"],
+                "properties": {
+                    "url": { "type": "STRING", "nullable": true },
+                    "count": { "type": "INTEGER", "description": "How many" }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn any_of_keeps_its_variants() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "id": { "anyOf": [{ "type": "string" }, { "type": "integer" }] },
+                "page": { "anyOf": [{ "type": "integer" }, { "type": "null" }] }
+            }
+        });
+        assert_eq!(
+            gemini_schema(&schema)["properties"],
+            json!({
+                "id": { "anyOf": [{ "type": "STRING" }, { "type": "INTEGER" }] },
+                "page": { "type": "INTEGER", "nullable": true }
+            })
+        );
+    }
+
+    #[test]
+    fn refs_are_inlined() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "point": { "$ref": "#/$defs/Point", "description": "Where to click" },
+                "missing": { "$ref": "#/$defs/Missing" }
+            },
+            "$defs": {
+                "Point": {
+                    "type": "object",
+                    "properties": { "x": { "type": "number" }, "y": { "type": "number" } }
+                }
+            }
+        });
+        assert_eq!(
+            gemini_schema(&schema)["properties"],
+            json!({
+                "point": {
+                    "type": "OBJECT",
+                    "description": "Where to click",
+                    "properties": { "x": { "type": "NUMBER" }, "y": { "type": "NUMBER" } }
+                },
+                "missing": { "type": "STRING" }
+            })
+        );
+    }
+
+    #[test]
+    fn recursive_refs_end() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "node": { "$ref": "#/$defs/Node" } },
+            "$defs": {
+                "Node": { "type": "object", "properties": { "next": { "$ref": "#/$defs/Node" } } }
+            }
+        });
+        let mut node = &gemini_schema(&schema)["properties"]["node"];
+        for _ in 0..MAX_SCHEMA_REFS - 1 {
+            node = &node["properties"]["next"];
+        }
+        assert_eq!(node["properties"]["next"], json!({ "type": "STRING" }));
+    }
+}
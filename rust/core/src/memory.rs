@@ -0,0 +1,230 @@
+use crate::{clock_time, unix_millis, Content, Part, RecordedAnswer, ResponsePrinter};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Turn sent to the model when it should compress the session so far into a summary
+pub const MEMORY_REFRESH_PROMPT: &str = "Pause the screen descriptions for a moment. \
+     Write a compact summary of everything you have observed in this session so far: \
+     the main activities, their order and anything the user is likely to come back to. \
+     Use at most 10 short bullet points and do not describe the latest screenshot separately.";
+
+/// Turn sent instead of `MEMORY_REFRESH_PROMPT` when every summary only covers the time since
+/// the previous one
+pub const ROLLING_SUMMARY_PROMPT: &str = "Pause the screen descriptions for a moment. \
+     Write a compact summary of what you have observed since your last summary, or since the \
+     session started if there was none: the main activities, their order and anything the \
+     user is likely to come back to. Use at most 5 short bullet points and do not describe \
+     the latest screenshot separately.";
+
+/// A summary produced by a memory refresh turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub timestamp_ms: u64,
+    /// Start of the period a rolling summary covers; other summaries cover the whole session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since_ms: Option<u64>,
+    pub text: String,
+}
+
+impl SessionSummary {
+    pub fn now(text: impl Into<String>) -> Self {
+        Self {
+            timestamp_ms: unix_millis(SystemTime::now()),
+            since_ms: None,
+            text: text.into(),
+        }
+    }
+
+    /// Summary of the period from `since` until now
+    pub fn since(since: SystemTime, text: impl Into<String>) -> Self {
+        Self {
+            since_ms: Some(unix_millis(since)),
+            ..Self::now(text)
+        }
+    }
+}
+
+/// Extends a system instruction with the summary of a previous session
+pub fn seeded_instruction(base: &str, summary: &str) -> String {
+    format!(
+        "{}\n\nThis session continues an earlier one. Summary of what happened so far:\n{}",
+        base, summary
+    )
+}
+
+/// Extends a system instruction with the summaries of the latest periods of a previous
+/// session, oldest first, and its last answers in full
+pub fn rolling_instruction(
+    base: &str,
+    summaries: &[SessionSummary],
+    recent: &[RecordedAnswer],
+) -> String {
+    let clock = |ms: u64| clock_time(UNIX_EPOCH + Duration::from_millis(ms));
+    let mut instruction = format!(
+        "{}\n\nThis session continues an earlier one. Summaries of what happened so far, \
+         one per period:",
+        base
+    );
+    for summary in summaries {
+        let period = match summary.since_ms {
+            Some(since) => format!("{}–{}", clock(since), clock(summary.timestamp_ms)),
+            None => format!("until {}", clock(summary.timestamp_ms)),
+        };
+        instruction.push_str(&format!("\n\n{}:\n{}", period, summary.text.trim()));
+    }
+    if !recent.is_empty() {
+        instruction.push_str("\n\nYour latest descriptions:");
+        for answer in recent {
+            instruction.push_str(&format!(
+                "\n{}: {}",
+                clock(answer.timestamp_ms),
+                answer.text.trim()
+            ));
+        }
+    }
+    instruction
+}
+
+/// Append-only JSONL log of session summaries
+pub struct SummaryLog {
+    path: PathBuf,
+}
+
+impl SummaryLog {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, summary: &SessionSummary) -> io::Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(summary)?)
+    }
+}
+
+#[derive(Default)]
+struct CaptureState {
+    pending: bool,
+    text: String,
+    /// An answer is streaming in
+    streaming: bool,
+    /// The answer streaming in when the refresh began belongs to an earlier turn
+    skip_turn: bool,
+}
+
+/// Printer wrapper that collects the model's answer to a memory refresh turn
+///
+/// Text of every response is still forwarded to the inner printer; while a refresh is
+/// pending it is additionally buffered until the turn completes. An answer already streaming
+/// in when the refresh begins is left out.
+pub struct SummaryCapture {
+    inner: Arc<dyn ResponsePrinter>,
+    state: parking_lot::Mutex<CaptureState>,
+    finished: tokio::sync::Notify,
+    completed: parking_lot::Mutex<Option<String>>,
+}
+
+impl SummaryCapture {
+    pub fn new(inner: Arc<dyn ResponsePrinter>) -> Self {
+        Self {
+            inner,
+            state: parking_lot::Mutex::new(CaptureState::default()),
+            finished: tokio::sync::Notify::new(),
+            completed: parking_lot::Mutex::new(None),
+        }
+    }
+
+    /// Starts buffering model text for a summary turn that is about to be sent
+    pub fn begin(&self) {
+        let mut state = self.state.lock();
+        *state = CaptureState {
+            pending: true,
+            text: String::new(),
+            streaming: state.streaming,
+            skip_turn: state.streaming,
+        };
+        *self.completed.lock() = None;
+    }
+
+    /// Waits for the summary turn to finish, returning `None` on timeout or empty answers
+    pub async fn wait(&self, timeout: Duration) -> Option<String> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let finished = self.finished.notified();
+            if let Some(text) = self.completed.lock().take() {
+                let text = text.trim().to_string();
+                return (!text.is_empty()).then_some(text);
+            }
+            if tokio::time::timeout_at(deadline, finished).await.is_err() {
+                break;
+            }
+        }
+        // The turn may have completed right at the deadline
+        self.state.lock().pending = false;
+        let text = self.completed.lock().take()?.trim().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+impl ResponsePrinter for SummaryCapture {
+    fn print_response(&self, content: &Content) {
+        {
+            let mut state = self.state.lock();
+            state.streaming = true;
+            if state.pending && !state.skip_turn {
+                for part in &content.parts {
+                    if let Part::Text { text } = part {
+                        state.text.push_str(text);
+                    }
+                }
+            }
+        }
+        self.inner.print_response(content);
+    }
+
+    fn print_status(&self, message: &str) {
+        self.inner.print_status(message);
+    }
+
+    fn print_error(&self, message: &str) {
+        self.inner.print_error(message);
+    }
+
+    fn print_turn_complete(&self) {
+        {
+            let mut state = self.state.lock();
+            state.streaming = false;
+            if state.skip_turn {
+                state.skip_turn = false;
+            } else if state.pending && !state.text.trim().is_empty() {
+                state.pending = false;
+                *self.completed.lock() = Some(std::mem::take(&mut state.text));
+                self.finished.notify_one();
+            }
+        }
+        self.inner.print_turn_complete();
+    }
+
+    fn frame_captured(&self, path: &Path) {
+        self.inner.frame_captured(path);
+    }
+
+    fn frame_sent(&self, frame_seq: usize) {
+        self.inner.frame_sent(frame_seq);
+    }
+}
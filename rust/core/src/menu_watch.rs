@@ -0,0 +1,522 @@
+use crate::{Anonymizer, FrameData, Rect};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+/// Per-capture prompt sent with the crop of a menu that was just opened
+pub const MENU_PROMPT: &str =
+    "This is a close-up of a menu the user just opened. List the visible menu items, top to bottom.";
+
+/// Margin in points kept around a menu when cropping it
+const MENU_PADDING: f64 = 8.0;
+
+#[derive(Debug, Error)]
+pub enum MenuWatchError {
+    #[error("Platform not supported")]
+    PlatformNotSupported,
+    #[error("Accessibility permission not granted")]
+    PermissionDenied,
+}
+
+pub type MenuWatchResult<T> = std::result::Result<T, MenuWatchError>;
+
+/// What happened to a menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuEventKind {
+    Opened,
+    ItemSelected,
+}
+
+/// Where a menu belongs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuOwner {
+    /// Menu bar or context menu of the frontmost application
+    Application,
+    /// Context menu of a Dock item
+    Dock,
+}
+
+/// Menu interaction reported by the accessibility API
+#[derive(Debug, Clone)]
+pub struct MenuEvent {
+    pub kind: MenuEventKind,
+    pub owner: MenuOwner,
+    pub pid: u32,
+    pub app_name: String,
+    /// Titles from the menu bar item down to the event element, e.g. `["File", "Save As…"]`
+    pub path: Vec<String>,
+    /// Global display coordinates of an opened menu
+    pub bounds: Option<Rect>,
+}
+
+impl MenuEvent {
+    /// Copy with the app name and menu titles passed through `anonymizer`
+    pub fn anonymized(&self, anonymizer: &dyn Anonymizer) -> MenuEvent {
+        MenuEvent {
+            app_name: anonymizer.app_name(&self.app_name),
+            path: self
+                .path
+                .iter()
+                .map(|title| anonymizer.title(title))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// One-line description used in the activity log
+    pub fn describe(&self) -> String {
+        let place = match self.owner {
+            MenuOwner::Application => format!("{} menu", self.app_name),
+            MenuOwner::Dock => "Dock menu".to_string(),
+        };
+        let path = if self.path.is_empty() {
+            "(untitled)".to_string()
+        } else {
+            self.path.join(" > ")
+        };
+        match self.kind {
+            MenuEventKind::Opened => format!("User opened {} {}", place, path),
+            MenuEventKind::ItemSelected => format!("User selected {} item {}", place, path),
+        }
+    }
+}
+
+/// Crops `bounds` (global points) out of a full-display frame of `display`, with a small
+/// margin; only the part on that display is kept
+pub fn crop_to_bounds(frame: &FrameData, bounds: Rect, display: Rect) -> Option<FrameData> {
+    let (x, y, width, height) = crop_rect(
+        bounds.expanded(MENU_PADDING),
+        display,
+        frame.width,
+        frame.height,
+    )?;
+    frame.crop(x, y, width, height).ok()
+}
+
+/// Pixel rectangle `x, y, width, height` of `bounds` in a `frame_width` x `frame_height`
+/// capture of `display`, both in global points
+///
+/// Works for displays left of or above the main one, whose origins are negative, and for
+/// bounds that span two displays, which are clipped to `display`. Points are scaled by the
+/// frame's own size, so displays with different scale factors each map correctly.
+fn crop_rect(
+    bounds: Rect,
+    display: Rect,
+    frame_width: u32,
+    frame_height: u32,
+) -> Option<(u32, u32, u32, u32)> {
+    if display.width <= 0.0 || display.height <= 0.0 || frame_width == 0 || frame_height == 0 {
+        return None;
+    }
+    let visible = bounds.intersection(&display)?;
+
+    let scale_x = frame_width as f64 / display.width;
+    let scale_y = frame_height as f64 / display.height;
+    let left = ((visible.x - display.x) * scale_x).floor().max(0.0) as u32;
+    let top = ((visible.y - display.y) * scale_y).floor().max(0.0) as u32;
+    let right = ((visible.x + visible.width - display.x) * scale_x)
+        .ceil()
+        .min(frame_width as f64) as u32;
+    let bottom = ((visible.y + visible.height - display.y) * scale_y)
+        .ceil()
+        .min(frame_height as f64) as u32;
+    (right > left && bottom > top).then(|| (left, top, right - left, bottom - top))
+}
+
+/// Watches menu activity of the frontmost application and the Dock on a background thread
+///
+/// Events are delivered on the returned channel until the watcher is dropped.
+pub struct MenuWatcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl MenuWatcher {
+    pub fn start() -> MenuWatchResult<(Self, mpsc::UnboundedReceiver<MenuEvent>)> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = platform::spawn(tx, Arc::clone(&stop))?;
+        Ok((
+            Self {
+                stop,
+                thread: Some(thread),
+            },
+            rx,
+        ))
+    }
+}
+
+impl Drop for MenuWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{MenuEvent, MenuEventKind, MenuOwner, MenuWatchError, MenuWatchResult};
+    use crate::{frontmost_window, list_windows, Rect};
+    use core_foundation::base::{CFType, CFTypeRef, TCFType};
+    use core_foundation::runloop::{kCFRunLoopDefaultMode, CFRunLoop, CFRunLoopSource};
+    use core_foundation::string::{CFString, CFStringRef};
+    use core_graphics::geometry::{CGPoint, CGSize};
+    use std::os::raw::c_void;
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc::UnboundedSender;
+
+    type AXUIElementRef = *const c_void;
+    type AXObserverRef = *const c_void;
+    type AXObserverCallback =
+        extern "C" fn(AXObserverRef, AXUIElementRef, CFStringRef, *mut c_void);
+
+    const AX_VALUE_CG_POINT: u32 = 1;
+    const AX_VALUE_CG_SIZE: u32 = 2;
+    const NOTIFICATIONS: [&str; 2] = ["AXMenuOpened", "AXMenuItemSelected"];
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    unsafe extern "C" {
+        fn AXIsProcessTrusted() -> bool;
+        fn AXUIElementCreateApplication(pid: i32) -> AXUIElementRef;
+        fn AXUIElementCopyAttributeValue(
+            element: AXUIElementRef,
+            attribute: CFStringRef,
+            value: *mut CFTypeRef,
+        ) -> i32;
+        fn AXObserverCreate(
+            pid: i32,
+            callback: AXObserverCallback,
+            observer: *mut AXObserverRef,
+        ) -> i32;
+        fn AXObserverAddNotification(
+            observer: AXObserverRef,
+            element: AXUIElementRef,
+            notification: CFStringRef,
+            refcon: *mut c_void,
+        ) -> i32;
+        fn AXObserverGetRunLoopSource(
+            observer: AXObserverRef,
+        ) -> core_foundation::runloop::CFRunLoopSourceRef;
+        fn AXValueGetValue(value: CFTypeRef, kind: u32, out: *mut c_void) -> bool;
+    }
+
+    struct ObserverContext {
+        owner: MenuOwner,
+        pid: u32,
+        app_name: String,
+        tx: UnboundedSender<MenuEvent>,
+    }
+
+    /// AX observer registered on the watcher's run loop; unregistered when dropped
+    struct Observation {
+        pid: u32,
+        run_loop: CFRunLoop,
+        source: CFRunLoopSource,
+        _observer: CFType,
+        _app: CFType,
+        // Referenced by the callback through a raw pointer, so it must outlive the observer
+        _context: Box<ObserverContext>,
+    }
+
+    impl Observation {
+        fn new(
+            owner: MenuOwner,
+            pid: u32,
+            app_name: String,
+            tx: &UnboundedSender<MenuEvent>,
+        ) -> Option<Self> {
+            let context = Box::new(ObserverContext {
+                owner,
+                pid,
+                app_name,
+                tx: tx.clone(),
+            });
+            let mut observer: AXObserverRef = ptr::null();
+            if unsafe { AXObserverCreate(pid as i32, on_notification, &mut observer) } != 0
+                || observer.is_null()
+            {
+                return None;
+            }
+            let observer = unsafe { CFType::wrap_under_create_rule(observer) };
+            let app =
+                unsafe { CFType::wrap_under_create_rule(AXUIElementCreateApplication(pid as i32)) };
+
+            let refcon = &*context as *const ObserverContext as *mut c_void;
+            let mut registered = false;
+            for name in NOTIFICATIONS {
+                let name = CFString::from_static_string(name);
+                registered |= unsafe {
+                    AXObserverAddNotification(
+                        observer.as_CFTypeRef(),
+                        app.as_CFTypeRef(),
+                        name.as_concrete_TypeRef(),
+                        refcon,
+                    )
+                } == 0;
+            }
+            if !registered {
+                return None;
+            }
+
+            let source = unsafe {
+                CFRunLoopSource::wrap_under_get_rule(AXObserverGetRunLoopSource(
+                    observer.as_CFTypeRef(),
+                ))
+            };
+            let run_loop = CFRunLoop::get_current();
+            run_loop.add_source(&source, unsafe { kCFRunLoopDefaultMode });
+            Some(Self {
+                pid,
+                run_loop,
+                source,
+                _observer: observer,
+                _app: app,
+                _context: context,
+            })
+        }
+    }
+
+    impl Drop for Observation {
+        fn drop(&mut self) {
+            self.run_loop
+                .remove_source(&self.source, unsafe { kCFRunLoopDefaultMode });
+        }
+    }
+
+    extern "C" fn on_notification(
+        _observer: AXObserverRef,
+        element: AXUIElementRef,
+        notification: CFStringRef,
+        refcon: *mut c_void,
+    ) {
+        let context = unsafe { &*(refcon as *const ObserverContext) };
+        let notification = unsafe { CFString::wrap_under_get_rule(notification) }.to_string();
+        let kind = match notification.as_str() {
+            "AXMenuOpened" => MenuEventKind::Opened,
+            "AXMenuItemSelected" => MenuEventKind::ItemSelected,
+            _ => return,
+        };
+        let bounds = match kind {
+            MenuEventKind::Opened => frame(element),
+            MenuEventKind::ItemSelected => None,
+        };
+        let _ = context.tx.send(MenuEvent {
+            kind,
+            owner: context.owner,
+            pid: context.pid,
+            app_name: context.app_name.clone(),
+            path: title_path(element),
+            bounds,
+        });
+    }
+
+    fn attribute(element: AXUIElementRef, name: &'static str) -> Option<CFType> {
+        let name = CFString::from_static_string(name);
+        let mut value: CFTypeRef = ptr::null();
+        let status = unsafe {
+            AXUIElementCopyAttributeValue(element, name.as_concrete_TypeRef(), &mut value)
+        };
+        (status == 0 && !value.is_null()).then(|| unsafe { CFType::wrap_under_create_rule(value) })
+    }
+
+    fn string_attribute(element: AXUIElementRef, name: &'static str) -> Option<String> {
+        let value = attribute(element, name)?
+            .downcast::<CFString>()?
+            .to_string();
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+
+    /// Titles of the element and its menu ancestors, outermost first
+    fn title_path(element: AXUIElementRef) -> Vec<String> {
+        let mut path = Vec::new();
+        let mut current = unsafe { CFType::wrap_under_get_rule(element) };
+        // Menus rarely nest deeper than a few submenus
+        for _ in 0..8 {
+            let role = string_attribute(current.as_CFTypeRef(), "AXRole");
+            if matches!(role.as_deref(), Some("AXMenuBar" | "AXApplication") | None) {
+                break;
+            }
+            if let Some(title) = string_attribute(current.as_CFTypeRef(), "AXTitle") {
+                path.push(title);
+            }
+            match attribute(current.as_CFTypeRef(), "AXParent") {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+        path.reverse();
+        path
+    }
+
+    fn frame(element: AXUIElementRef) -> Option<Rect> {
+        let mut origin = CGPoint::new(0.0, 0.0);
+        let mut size = CGSize::new(0.0, 0.0);
+        let position = attribute(element, "AXPosition")?;
+        let extent = attribute(element, "AXSize")?;
+        let ok = unsafe {
+            AXValueGetValue(
+                position.as_CFTypeRef(),
+                AX_VALUE_CG_POINT,
+                &mut origin as *mut CGPoint as *mut c_void,
+            ) && AXValueGetValue(
+                extent.as_CFTypeRef(),
+                AX_VALUE_CG_SIZE,
+                &mut size as *mut CGSize as *mut c_void,
+            )
+        };
+        (ok && size.width > 0.0 && size.height > 0.0).then_some(Rect {
+            x: origin.x,
+            y: origin.y,
+            width: size.width,
+            height: size.height,
+        })
+    }
+
+    /// Owner of the frontmost regular window, as (pid, app name)
+    fn frontmost_app() -> Option<(u32, String)> {
+        frontmost_window().map(|window| (window.pid, window.app_name))
+    }
+
+    fn dock_pid() -> Option<u32> {
+        list_windows()
+            .into_iter()
+            .find(|window| window.app_name == "Dock")
+            .map(|window| window.pid)
+    }
+
+    pub(super) fn spawn(
+        tx: UnboundedSender<MenuEvent>,
+        stop: Arc<AtomicBool>,
+    ) -> MenuWatchResult<std::thread::JoinHandle<()>> {
+        if !unsafe { AXIsProcessTrusted() } {
+            return Err(MenuWatchError::PermissionDenied);
+        }
+
+        Ok(std::thread::spawn(move || {
+            let _dock = dock_pid()
+                .and_then(|pid| Observation::new(MenuOwner::Dock, pid, "Dock".to_string(), &tx));
+            let mut app: Option<Observation> = None;
+
+            while !stop.load(Ordering::Relaxed) {
+                // Follow the frontmost application; the previous observer is removed first
+                if let Some((pid, name)) = frontmost_app()
+                    && app.as_ref().map(|app| app.pid) != Some(pid)
+                {
+                    drop(app.take());
+                    app = Observation::new(MenuOwner::Application, pid, name, &tx);
+                }
+                CFRunLoop::run_in_mode(
+                    unsafe { kCFRunLoopDefaultMode },
+                    Duration::from_millis(500),
+                    false,
+                );
+            }
+        }))
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::{MenuEvent, MenuWatchError, MenuWatchResult};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use tokio::sync::mpsc::UnboundedSender;
+
+    pub(super) fn spawn(
+        _tx: UnboundedSender<MenuEvent>,
+        _stop: Arc<AtomicBool>,
+    ) -> MenuWatchResult<std::thread::JoinHandle<()>> {
+        Err(MenuWatchError::PlatformNotSupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The main display, captured at 1x
+    const MAIN: Rect = Rect {
+        x: 0.0,
+        y: 0.0,
+        width: 1440.0,
+        height: 900.0,
+    };
+
+    #[test]
+    fn crops_on_the_main_display() {
+        let crop = crop_rect(rect(100.0, 50.0, 200.0, 300.0), MAIN, 1440, 900);
+        assert_eq!(crop, Some((100, 50, 200, 300)));
+    }
+
+    #[test]
+    fn scales_to_a_retina_frame() {
+        let crop = crop_rect(rect(100.0, 50.0, 200.0, 300.0), MAIN, 2880, 1800);
+        assert_eq!(crop, Some((200, 100, 400, 600)));
+    }
+
+    #[test]
+    fn handles_a_display_left_of_the_main_one() {
+        let left = rect(-1920.0, 0.0, 1920.0, 1080.0);
+        let crop = crop_rect(rect(-500.0, 100.0, 200.0, 100.0), left, 1920, 1080);
+        assert_eq!(crop, Some((1420, 100, 200, 100)));
+    }
+
+    #[test]
+    fn handles_a_display_above_the_main_one() {
+        let above = rect(200.0, -1080.0, 1920.0, 1080.0);
+        let crop = crop_rect(rect(300.0, -80.0, 100.0, 50.0), above, 3840, 2160);
+        assert_eq!(crop, Some((200, 2000, 200, 100)));
+    }
+
+    #[test]
+    fn clips_bounds_spanning_two_displays() {
+        let right = rect(1440.0, 0.0, 1920.0, 1080.0);
+        let bounds = rect(1340.0, 100.0, 200.0, 100.0);
+        assert_eq!(
+            crop_rect(bounds, MAIN, 2880, 1800),
+            Some((2680, 200, 200, 200))
+        );
+        assert_eq!(
+            crop_rect(bounds, right, 1920, 1080),
+            Some((0, 100, 100, 100))
+        );
+    }
+
+    #[test]
+    fn rounds_fractional_points_outwards() {
+        let crop = crop_rect(rect(10.25, 10.75, 5.5, 5.0), MAIN, 1440, 900);
+        assert_eq!(crop, Some((10, 10, 6, 6)));
+    }
+
+    #[test]
+    fn rejects_bounds_off_the_display_and_empty_frames() {
+        let elsewhere = rect(2000.0, 0.0, 100.0, 100.0);
+        assert_eq!(crop_rect(elsewhere, MAIN, 1440, 900), None);
+        assert_eq!(crop_rect(rect(0.0, 0.0, 10.0, 10.0), MAIN, 0, 900), None);
+        assert_eq!(
+            crop_rect(
+                rect(0.0, 0.0, 10.0, 10.0),
+                rect(0.0, 0.0, 0.0, 900.0),
+                1440,
+                900
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    main_display_id, BufferPool, CaptureError, CaptureOptions, CaptureResult, CaptureTarget,
+    FrameData, FrameSource,
+};
+use scap::Target as ScapTarget;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// A connected display that can be captured
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayInfo {
+    pub id: u32,
+    pub title: String,
+}
+
+/// Lists the connected displays in the order the system reports them
+pub fn list_displays() -> Vec<DisplayInfo> {
+    scap::get_all_targets()
+        .into_iter()
+        .filter_map(|target| match target {
+            ScapTarget::Display(display) => Some(DisplayInfo {
+                id: display.id,
+                title: display.title,
+            }),
+            ScapTarget::Window(_) => None,
+        })
+        .collect()
+}
+
+/// How a CaptureSession combines the frames of several displays
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DisplayMode {
+    /// Each frame comes from the next display in turn
+    #[default]
+    RoundRobin,
+    /// Each frame shows all displays side by side
+    Composite,
+}
+
+impl FromStr for DisplayMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "round-robin" | "roundrobin" | "rotate" => Ok(DisplayMode::RoundRobin),
+            "composite" | "side-by-side" => Ok(DisplayMode::Composite),
+            other => Err(format!(
+                "unknown display mode '{}' (expected round-robin or composite)",
+                other
+            )),
+        }
+    }
+}
+
+/// Starts one FrameSource per connected display, with `options` for everything but the target
+///
+/// The main display comes first, since cursor and menu coordinates refer to it. Only that
+/// source captures system audio, so it isn't sent once per display.
+pub fn display_sources(options: &CaptureOptions) -> CaptureResult<Vec<FrameSource>> {
+    let mut displays = list_displays();
+    let main = main_display_id();
+    displays.sort_by_key(|display| Some(display.id) != main);
+    if displays.is_empty() {
+        return Err(CaptureError::TargetNotFound("no displays".to_string()));
+    }
+    displays
+        .into_iter()
+        .enumerate()
+        .map(|(index, display)| {
+            FrameSource::from_options(CaptureOptions {
+                target: CaptureTarget::DisplayId(display.id),
+                captures_audio: options.captures_audio && index == 0,
+                ..options.clone()
+            })
+        })
+        .collect()
+}
+
+/// Places frames left to right, top-aligned on a black canvas
+pub fn composite_frames(frames: &[Arc<FrameData>], pool: &BufferPool) -> Option<FrameData> {
+    let width: u32 = frames.iter().map(|frame| frame.width).sum();
+    let height = frames.iter().map(|frame| frame.height).max()?;
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let stride = width as usize * 4;
+    let mut data = pool.take(stride * height as usize);
+    data.resize(stride * height as usize, 0);
+    let mut left = 0;
+    for frame in frames {
+        let row_bytes = frame.width as usize * 4;
+        let rows = frame
+            .data
+            .chunks_exact(row_bytes.max(1))
+            .take(frame.height as usize);
+        for (y, row) in rows.enumerate() {
+            let start = y * stride + left;
+            data[start..start + row_bytes].copy_from_slice(row);
+        }
+        left += row_bytes;
+    }
+    // The canvas is opaque, also where a shorter display leaves it empty
+    for alpha in data.iter_mut().skip(3).step_by(4) {
+        *alpha = 255;
+    }
+
+    Some(FrameData {
+        width,
+        height,
+        data,
+        display_id: None,
+        cursor: None,
+        // The frontmost window is the same for every display
+        active_window: frames[0].active_window.clone(),
+    })
+}
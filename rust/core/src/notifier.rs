@@ -0,0 +1,134 @@
+use crate::{Notification, WebhookConfig};
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+/// Deliveries of one notification to one webhook, including the first
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Doubled after every failed attempt, unless the webhook asks for longer
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest a rate limited webhook may make the next attempt wait
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Notifications waiting for delivery before new ones are dropped
+const QUEUE_CAPACITY: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum NotifierError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Webhook answered {0}")]
+    Status(StatusCode),
+}
+
+pub type NotifierResult<T> = std::result::Result<T, NotifierError>;
+
+/// Posts every notification to the configured webhooks as JSON, or as Slack messages
+///
+/// Deliveries happen in the background, one at a time, and are retried with a growing delay.
+pub struct WebhookNotifier {
+    queue: mpsc::Sender<Notification>,
+}
+
+impl WebhookNotifier {
+    /// Starts delivering to `webhooks`; `None` when there are none
+    pub fn spawn(webhooks: Vec<WebhookConfig>) -> NotifierResult<Option<Self>> {
+        if webhooks.is_empty() {
+            return Ok(None);
+        }
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        let (queue, pending) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(deliver(client, webhooks, pending));
+        Ok(Some(Self { queue }))
+    }
+
+    /// Queues `notification` for every webhook, dropping it when too many are waiting
+    pub fn send(&self, notification: &Notification) {
+        if self.queue.try_send(notification.clone()).is_err() {
+            eprintln!(
+                "⚠️ Too many notifications waiting, dropped {}",
+                notification.rule
+            );
+        }
+    }
+}
+
+fn payload(notification: &Notification, webhook: &WebhookConfig) -> Value {
+    if webhook.slack {
+        json!({ "text": notification.text() })
+    } else {
+        json!(notification)
+    }
+}
+
+async fn deliver(
+    client: reqwest::Client,
+    webhooks: Vec<WebhookConfig>,
+    mut pending: mpsc::Receiver<Notification>,
+) {
+    while let Some(notification) = pending.recv().await {
+        for webhook in &webhooks {
+            if let Err(e) = post(&client, &webhook.url, &payload(&notification, webhook)).await {
+                eprintln!(
+                    "❌ Failed to notify {} about {}: {}",
+                    host(&webhook.url),
+                    notification.rule,
+                    e
+                );
+            }
+        }
+    }
+}
+
+async fn post(client: &reqwest::Client, url: &str, payload: &Value) -> NotifierResult<()> {
+    let mut delay = FIRST_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let error = match client.post(url).json(payload).send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                // Other client errors, e.g. a revoked webhook, won't go away by retrying
+                if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                    return Err(NotifierError::Status(status));
+                }
+                if let Some(wait) = retry_after(&response) {
+                    delay = delay.max(wait);
+                }
+                NotifierError::Status(status)
+            }
+            // Webhook URLs often embed a secret token
+            Err(e) => NotifierError::Http(e.without_url()),
+        };
+        if attempt == MAX_ATTEMPTS {
+            return Err(error);
+        }
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// Seconds a rate limited webhook asks to wait before the next attempt
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let seconds = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    let wait = seconds.trim().parse().ok().map(Duration::from_secs)?;
+    Some(wait.min(MAX_RETRY_AFTER))
+}
+
+/// Names a webhook in messages without its secret path
+fn host(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from))
+        .unwrap_or_else(|| "webhook".to_string())
+}
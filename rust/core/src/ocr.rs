@@ -0,0 +1,189 @@
+use crate::{FrameData, Rect};
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Characters of recognized text added to a prompt; the rest is cut off
+pub const MAX_PROMPT_TEXT: usize = 4_000;
+
+#[derive(Debug, Error)]
+pub enum OcrError {
+    #[error("Text recognition is only supported on macOS")]
+    PlatformNotSupported,
+    #[error("Vision error: {0}")]
+    Vision(String),
+}
+
+pub type OcrResult<T> = std::result::Result<T, OcrError>;
+
+/// What the recognized text is used for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OcrMode {
+    /// Added to the prompt sent to Gemini with each screenshot
+    #[default]
+    Augment,
+    /// Printed and saved next to the frames, without connecting to Gemini
+    Offline,
+}
+
+impl FromStr for OcrMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "augment" | "prompt" => Ok(OcrMode::Augment),
+            "offline" | "local" => Ok(OcrMode::Offline),
+            other => Err(format!(
+                "unknown OCR mode '{}' (expected augment or offline)",
+                other
+            )),
+        }
+    }
+}
+
+/// One line of recognized text
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    pub text: String,
+    /// Between 0 and 1
+    pub confidence: f32,
+    /// Position in the frame, as fractions of its size from the top-left
+    pub bounds: Rect,
+}
+
+/// Recognizes the text visible in frames on-device with the Vision framework
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextRecognizer {
+    fast: bool,
+}
+
+impl TextRecognizer {
+    /// Accurate recognition with language correction, around a second per 720p frame
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trades accuracy for speed, e.g. for higher frame rates
+    pub fn fast() -> Self {
+        Self { fast: true }
+    }
+
+    /// Lines of text in reading order, top to bottom
+    pub fn recognize(&self, frame: &Arc<FrameData>) -> OcrResult<Vec<TextLine>> {
+        let mut lines = platform::recognize(frame, self.fast)?;
+        lines.sort_by(|a, b| {
+            a.bounds
+                .y
+                .total_cmp(&b.bounds.y)
+                .then(a.bounds.x.total_cmp(&b.bounds.x))
+        });
+        Ok(lines)
+    }
+}
+
+/// Recognized lines as plain text, one per line
+pub fn ocr_text(lines: &[TextLine]) -> String {
+    lines
+        .iter()
+        .map(|line| line.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Prompt section quoting the recognized text, cut to `MAX_PROMPT_TEXT` characters
+pub fn ocr_prompt(lines: &[TextLine]) -> Option<String> {
+    let text = ocr_text(lines);
+    if text.trim().is_empty() {
+        return None;
+    }
+    let text: String = match text.char_indices().nth(MAX_PROMPT_TEXT) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    };
+    Some(format!(
+        "Text recognized on screen, which is more reliable than reading the image:\n{}",
+        text
+    ))
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{OcrError, OcrResult, TextLine};
+    use crate::{FrameData, Rect};
+    use cidre::{cv, ns, vn};
+    use std::ffi::c_void;
+    use std::sync::Arc;
+
+    fn vision_error(error: impl std::fmt::Debug) -> OcrError {
+        OcrError::Vision(format!("{:?}", error))
+    }
+
+    /// Drops the frame reference handed to CoreVideo once the pixel buffer is released
+    extern "C" fn release_frame(frame: *mut c_void, _base_address: *const *const c_void) {
+        drop(unsafe { Box::from_raw(frame as *mut Arc<FrameData>) });
+    }
+
+    pub fn recognize(frame: &Arc<FrameData>, fast: bool) -> OcrResult<Vec<TextLine>> {
+        let (width, height) = (frame.width as usize, frame.height as usize);
+        let base_address = frame.data.as_ptr() as *mut c_void;
+        let owner = Box::into_raw(Box::new(Arc::clone(frame))) as *mut c_void;
+        // Vision only reads the pixels and CoreVideo calls release_frame when it is done
+        let buffer = cv::PixelBuf::with_bytes(
+            width,
+            height,
+            base_address,
+            width * 4,
+            release_frame,
+            owner,
+            cv::PixelFormat::_32_BGRA,
+            None,
+        )
+        .map_err(vision_error)?;
+
+        let mut request = vn::RecognizeTextRequest::new();
+        request.set_recognition_level(if fast {
+            vn::RequestTextRecognitionLevel::Fast
+        } else {
+            vn::RequestTextRecognitionLevel::Accurate
+        });
+        request.set_uses_lang_correction(!fast);
+        let handler = vn::ImageRequestHandler::with_cv_pixel_buf(&buffer, None)
+            .ok_or_else(|| OcrError::Vision("failed to create request handler".to_string()))?;
+        let requests = ns::Array::<vn::Request>::from_slice(&[&request]);
+        handler.perform(&requests).map_err(vision_error)?;
+
+        let Some(observations) = request.results() else {
+            return Ok(Vec::new());
+        };
+        Ok(observations
+            .iter()
+            .filter_map(|observation| {
+                let candidates = observation.top_candidates(1);
+                let candidate = candidates.iter().next()?;
+                let bounds = observation.bounding_box();
+                Some(TextLine {
+                    text: candidate.string().to_string(),
+                    confidence: candidate.confidence(),
+                    // Vision's normalized coordinates start at the bottom-left
+                    bounds: Rect {
+                        x: bounds.origin.x,
+                        y: 1.0 - bounds.origin.y - bounds.size.height,
+                        width: bounds.size.width,
+                        height: bounds.size.height,
+                    },
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::{OcrError, OcrResult, TextLine};
+    use crate::FrameData;
+    use std::sync::Arc;
+
+    pub fn recognize(_frame: &Arc<FrameData>, _fast: bool) -> OcrResult<Vec<TextLine>> {
+        Err(OcrError::PlatformNotSupported)
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{clock_time, unix_millis, ClientContent, Content, Part};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A turn that couldn't be sent because the connection was down
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedTurn {
+    pub queued_ms: u64,
+    /// Frame the turn carries, for pairing the answer with it
+    pub frame_seq: Option<usize>,
+    pub content: ClientContent,
+}
+
+impl QueuedTurn {
+    pub fn new(content: ClientContent, frame_seq: Option<usize>) -> Self {
+        Self {
+            queued_ms: unix_millis(SystemTime::now()),
+            frame_seq,
+            content,
+        }
+    }
+
+    /// The turn as sent after reconnecting, opening with when it happened, since the model
+    /// would otherwise take it for the present
+    pub fn into_content(self) -> ClientContent {
+        let at = UNIX_EPOCH + Duration::from_millis(self.queued_ms);
+        let note = Part::text(format!(
+            "Sent late, as the connection was down; this happened at {}:",
+            clock_time(at)
+        ));
+        let mut content = self.content;
+        match content.turns.first_mut() {
+            Some(turn) => turn.parts.insert(0, note),
+            None => content.turns.push(Content {
+                role: Some("user".to_string()),
+                parts: vec![note],
+            }),
+        }
+        content
+    }
+}
+
+/// Turns kept on disk while the connection is down, one JSON file each, so a long outage
+/// doesn't hold its frames in memory
+///
+/// Holds at most `capacity` turns; the oldest are dropped to make room. Retention limits of
+/// the output directory don't cover the queue, which empties as the turns are sent.
+pub struct OfflineQueue {
+    dir: PathBuf,
+    capacity: usize,
+    state: parking_lot::Mutex<QueueState>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    /// Number of the next file, which sorts after all queued ones
+    next: u64,
+    /// Queued files, oldest first, kept so sending doesn't list the directory
+    files: VecDeque<PathBuf>,
+}
+
+impl OfflineQueue {
+    /// Queue in `dir`, which is created if needed; turns left there by an earlier run are
+    /// discarded, as their context is gone
+    pub fn open(dir: impl Into<PathBuf>, capacity: usize) -> io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        let queue = Self {
+            dir,
+            capacity: capacity.max(1),
+            state: parking_lot::Mutex::default(),
+        };
+        for entry in std::fs::read_dir(&queue.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(queue)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds `turn` after the others, returning how many of the oldest were dropped for it
+    pub fn push(&self, turn: &QueuedTurn) -> io::Result<usize> {
+        let mut state = self.state.lock();
+        let path = self.dir.join(format!("{:010}.json", state.next));
+        std::fs::write(&path, serde_json::to_vec(turn)?)?;
+        state.next += 1;
+        state.files.push_back(path);
+
+        let excess = state.files.len().saturating_sub(self.capacity);
+        for oldest in state.files.drain(..excess) {
+            remove_if_exists(&oldest)?;
+        }
+        Ok(excess)
+    }
+
+    /// Oldest queued turn and the file holding it, to be removed with `remove` once sent
+    pub fn front(&self) -> io::Result<Option<(PathBuf, QueuedTurn)>> {
+        let mut state = self.state.lock();
+        while let Some(path) = state.files.front().cloned() {
+            match serde_json::from_slice(&std::fs::read(&path)?) {
+                Ok(turn) => return Ok(Some((path, turn))),
+                // Cut short, e.g. by a full disk
+                Err(_) => {
+                    state.files.pop_front();
+                    remove_if_exists(&path)?;
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn remove(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state.lock();
+        state.files.retain(|queued| queued != path);
+        remove_if_exists(path)
+    }
+}
+
+/// Removes a queued file; one already gone, e.g. dropped for a newer turn, is fine
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,134 @@
+use crate::chat_session::{
+    backend_error, instruction_text, json_schema, trim_history, ChatAnswer, ChatModel, Deltas,
+};
+use crate::gemini::{GeminiError, Result};
+use crate::{Blob, ChatSession, Setup};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Loading a model into memory can take minutes before the first answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+impl ChatSession {
+    /// Screen watching with a vision model served by a local Ollama, so frames never leave
+    /// the machine; checks that the server at `url` has `model` first
+    pub async fn ollama(url: &str, model: &str, setup: &Setup) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(backend_error)?;
+        let url = url.trim_end_matches('/');
+        let response = client
+            .post(format!("{}/api/show", url))
+            .json(&json!({ "model": model }))
+            .send()
+            .await
+            .map_err(|e| {
+                GeminiError::Backend(format!("Ollama isn't reachable at {}: {}", url, e))
+            })?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(GeminiError::Backend(format!(
+                "Ollama has no model {}; run `ollama pull {}`",
+                model, model
+            )));
+        }
+        response.error_for_status().map_err(backend_error)?;
+
+        let config = setup.generation_config.as_ref();
+        Ok(Self::spawn(OllamaChat {
+            client,
+            url: format!("{}/api/chat", url),
+            model: model.to_string(),
+            system: setup
+                .system_instruction
+                .as_ref()
+                .map(instruction_text)
+                .filter(|text| !text.is_empty()),
+            format: config
+                .and_then(|config| config.response_schema.as_ref())
+                .map(json_schema),
+            temperature: config.and_then(|config| config.temperature),
+            history: VecDeque::new(),
+        }))
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: ChatMessage,
+    prompt_eval_count: Option<i32>,
+    eval_count: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: String,
+}
+
+/// `/api/chat` of an Ollama server
+struct OllamaChat {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+    system: Option<String>,
+    /// JSON schema answers must follow
+    format: Option<Value>,
+    temperature: Option<f32>,
+    /// Earlier user and assistant messages, oldest first, without images
+    history: VecDeque<Value>,
+}
+
+impl ChatModel for OllamaChat {
+    const NAME: &'static str = "Ollama";
+
+    fn ask<'a>(
+        &'a mut self,
+        text: &'a str,
+        images: Vec<Blob>,
+        _deltas: &'a mut Deltas,
+    ) -> BoxFuture<'a, Result<ChatAnswer>> {
+        Box::pin(async move {
+            let images: Vec<String> = images.into_iter().map(|blob| blob.data).collect();
+            let mut messages: Vec<Value> = self
+                .system
+                .iter()
+                .map(|system| json!({ "role": "system", "content": system }))
+                .collect();
+            messages.extend(self.history.iter().cloned());
+            messages.push(json!({ "role": "user", "content": text, "images": images }));
+
+            let mut body = json!({ "model": self.model, "messages": messages, "stream": false });
+            if let Some(format) = &self.format {
+                body["format"] = format.clone();
+            }
+            if let Some(temperature) = self.temperature {
+                body["options"] = json!({ "temperature": temperature });
+            }
+            let response: ChatResponse = self
+                .client
+                .post(&self.url)
+                .json(&body)
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .map_err(backend_error)?
+                .json()
+                .await
+                .map_err(backend_error)?;
+
+            self.history
+                .push_back(json!({ "role": "user", "content": text }));
+            self.history
+                .push_back(json!({ "role": "assistant", "content": response.message.content }));
+            trim_history(&mut self.history);
+            Ok(ChatAnswer {
+                text: response.message.content,
+                prompt_tokens: response.prompt_eval_count,
+                response_tokens: response.eval_count,
+            })
+        })
+    }
+}
@@ -0,0 +1,201 @@
+use crate::chat_session::{
+    backend_error, instruction_text, json_schema, trim_history, ChatAnswer, ChatModel, Deltas,
+};
+use crate::gemini::{GeminiError, Result};
+use crate::{Blob, ChatSession, Setup};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Longest part of an error response shown to the user
+const MAX_ERROR_CHARS: usize = 500;
+
+impl ChatSession {
+    /// Screen watching with a vision model behind an OpenAI-compatible chat completions API,
+    /// e.g. `https://api.openai.com/v1`; checks that `model` exists and `api_key` is accepted
+    /// first
+    pub async fn openai(
+        url: &str,
+        model: &str,
+        api_key: Option<&str>,
+        setup: &Setup,
+    ) -> Result<Self> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(key) = api_key {
+            let value = format!("Bearer {}", key)
+                .parse()
+                .map_err(|_| GeminiError::Backend("Invalid OpenAI API key".to_string()))?;
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .default_headers(headers)
+            .build()
+            .map_err(backend_error)?;
+        let url = url.trim_end_matches('/');
+        let response = client
+            .get(format!("{}/models/{}", url, model))
+            .send()
+            .await
+            .map_err(|e| GeminiError::Backend(format!("{} isn't reachable: {}", url, e)))?;
+        match response.status() {
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+                return Err(GeminiError::Backend(format!(
+                    "{} rejected the API key",
+                    url
+                )));
+            }
+            reqwest::StatusCode::NOT_FOUND => {
+                return Err(GeminiError::Backend(format!(
+                    "{} has no model {}",
+                    url, model
+                )));
+            }
+            // Compatible servers don't all list their models
+            _ => {}
+        }
+
+        let config = setup.generation_config.as_ref();
+        Ok(Self::spawn(OpenAiChat {
+            client,
+            url: format!("{}/chat/completions", url),
+            model: model.to_string(),
+            system: setup
+                .system_instruction
+                .as_ref()
+                .map(instruction_text)
+                .filter(|text| !text.is_empty()),
+            schema: config
+                .and_then(|config| config.response_schema.as_ref())
+                .map(json_schema),
+            temperature: config.and_then(|config| config.temperature),
+            history: VecDeque::new(),
+        }))
+    }
+}
+
+/// `response` if it succeeded, otherwise an error with the message the server sent, e.g.
+/// why a request was rejected
+async fn error_for_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<Value>(&body)
+        .ok()
+        .and_then(|body| body["error"]["message"].as_str().map(String::from))
+        .unwrap_or(body);
+    let message: String = message.trim().chars().take(MAX_ERROR_CHARS).collect();
+    Err(GeminiError::Backend(format!("{}: {}", status, message)))
+}
+
+#[derive(Deserialize)]
+struct CompletionResponse {
+    choices: Vec<Choice>,
+    usage: Option<CompletionUsage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CompletionUsage {
+    prompt_tokens: Option<i32>,
+    completion_tokens: Option<i32>,
+}
+
+/// `/chat/completions` of the OpenAI API or a server mimicking it
+struct OpenAiChat {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+    system: Option<String>,
+    /// JSON schema answers must follow
+    schema: Option<Value>,
+    temperature: Option<f32>,
+    /// Earlier user and assistant messages, oldest first, without images
+    history: VecDeque<Value>,
+}
+
+impl ChatModel for OpenAiChat {
+    const NAME: &'static str = "OpenAI";
+
+    fn ask<'a>(
+        &'a mut self,
+        text: &'a str,
+        images: Vec<Blob>,
+        _deltas: &'a mut Deltas,
+    ) -> BoxFuture<'a, Result<ChatAnswer>> {
+        Box::pin(async move {
+            let mut content: Vec<Value> = Vec::new();
+            if !text.is_empty() {
+                content.push(json!({ "type": "text", "text": text }));
+            }
+            content.extend(images.into_iter().map(|blob| {
+                let mime = blob.mime_type.as_deref().unwrap_or("image/jpeg");
+                let url = format!("data:{};base64,{}", mime, blob.data);
+                json!({ "type": "image_url", "image_url": { "url": url } })
+            }));
+            let mut messages: Vec<Value> = self
+                .system
+                .iter()
+                .map(|system| json!({ "role": "system", "content": system }))
+                .collect();
+            messages.extend(self.history.iter().cloned());
+            messages.push(json!({ "role": "user", "content": content }));
+
+            let mut body = json!({ "model": self.model, "messages": messages });
+            if let Some(schema) = &self.schema {
+                body["response_format"] = json!({
+                    "type": "json_schema",
+                    "json_schema": { "name": "answer", "schema": schema },
+                });
+            }
+            if let Some(temperature) = self.temperature {
+                body["temperature"] = json!(temperature);
+            }
+            let response = self
+                .client
+                .post(&self.url)
+                .json(&body)
+                .send()
+                .await
+                .map_err(backend_error)?;
+            let response: CompletionResponse = error_for_status(response)
+                .await?
+                .json()
+                .await
+                .map_err(backend_error)?;
+
+            let answer = response
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|choice| choice.message.content)
+                .unwrap_or_default();
+            self.history
+                .push_back(json!({ "role": "user", "content": text }));
+            self.history
+                .push_back(json!({ "role": "assistant", "content": answer }));
+            trim_history(&mut self.history);
+            let usage = response.usage;
+            Ok(ChatAnswer {
+                text: answer,
+                prompt_tokens: usage.as_ref().and_then(|usage| usage.prompt_tokens),
+                response_tokens: usage.as_ref().and_then(|usage| usage.completion_tokens),
+            })
+        })
+    }
+}
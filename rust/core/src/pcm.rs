@@ -0,0 +1,74 @@
+/// Converts 16-bit little-endian PCM bytes to `f32` samples in `[-1.0, 1.0]`
+pub fn pcm16_to_f32(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / i16::MAX as f32)
+        .collect()
+}
+
+/// Converts `f32` samples to 16-bit little-endian PCM bytes, clamping out-of-range values
+pub fn f32_to_pcm16(samples: impl IntoIterator<Item = f32>) -> Vec<u8> {
+    samples
+        .into_iter()
+        .flat_map(|sample| ((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+        .collect()
+}
+
+/// Extracts the sample rate from a MIME type such as `audio/pcm;rate=24000`, ignoring zero
+pub fn pcm_sample_rate(mime_type: &str) -> Option<u32> {
+    mime_type
+        .split(';')
+        .filter_map(|param| param.trim().strip_prefix("rate="))
+        .find_map(|rate| rate.parse().ok())
+        .filter(|&rate| rate > 0)
+}
+
+/// Converts interleaved audio at any rate/channel count to mono at a target rate
+pub struct Resampler {
+    channels: usize,
+    step: f64,
+    position: f64,
+    previous: f32,
+}
+
+impl Resampler {
+    /// Returns `None` if either rate is zero
+    pub fn new(input_rate: u32, channels: u16, output_rate: u32) -> Option<Self> {
+        if input_rate == 0 || output_rate == 0 {
+            return None;
+        }
+        Some(Self {
+            channels: channels.max(1) as usize,
+            step: input_rate as f64 / output_rate as f64,
+            position: 0.0,
+            previous: 0.0,
+        })
+    }
+
+    /// Downmixes and linearly resamples one callback's worth of samples, appending to `out`
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        let frames: Vec<f32> = input
+            .chunks(self.channels)
+            .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+            .collect();
+
+        // `position` is relative to the last frame of the previous buffer (index -1)
+        while self.position < frames.len() as f64 {
+            let index = self.position.floor();
+            let fraction = (self.position - index) as f32;
+            let before = if index < 1.0 {
+                self.previous
+            } else {
+                frames[index as usize - 1]
+            };
+            let after = frames[index as usize];
+            out.push(before + (after - before) * fraction);
+            self.position += self.step;
+        }
+
+        self.position -= frames.len() as f64;
+        if let Some(last) = frames.last() {
+            self.previous = *last;
+        }
+    }
+}
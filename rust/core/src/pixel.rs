@@ -0,0 +1,243 @@
+/// Drops the alpha channel of a BGRA buffer and reorders it to packed RGB
+///
+/// Uses NEON on Apple Silicon and SSSE3 on Intel when available; a trailing partial
+/// block and other targets go through the scalar loop.
+pub fn bgra_to_rgb(bgra: &[u8]) -> Vec<u8> {
+    let pixels = bgra.len() / 4;
+    let mut rgb = vec![0u8; pixels * 3];
+    bgra_to_rgb_into(bgra, &mut rgb);
+    rgb
+}
+
+/// Same as [`bgra_to_rgb`], writing into `rgb`, which must hold 3 bytes per source pixel
+pub fn bgra_to_rgb_into(bgra: &[u8], rgb: &mut [u8]) {
+    let pixels = (bgra.len() / 4).min(rgb.len() / 3);
+    let done = convert_blocks(&bgra[..pixels * 4], &mut rgb[..pixels * 3]);
+    convert_scalar(&bgra[done * 4..pixels * 4], &mut rgb[done * 3..pixels * 3]);
+}
+
+fn convert_scalar(bgra: &[u8], rgb: &mut [u8]) {
+    for (src, dst) in bgra.chunks_exact(4).zip(rgb.chunks_exact_mut(3)) {
+        dst[0] = src[2];
+        dst[1] = src[1];
+        dst[2] = src[0];
+    }
+}
+
+/// Converts whole 16-pixel blocks and returns the number of pixels written
+#[cfg(target_arch = "aarch64")]
+fn convert_blocks(bgra: &[u8], rgb: &mut [u8]) -> usize {
+    use std::arch::aarch64::{uint8x16x3_t, vld4q_u8, vst3q_u8};
+
+    let blocks = bgra.len() / 64;
+    for block in 0..blocks {
+        // SAFETY: NEON is always present on aarch64, and `block` stays within both the
+        // 64-byte source and the 48-byte destination chunks
+        unsafe {
+            let pixel = vld4q_u8(bgra.as_ptr().add(block * 64));
+            vst3q_u8(
+                rgb.as_mut_ptr().add(block * 48),
+                uint8x16x3_t(pixel.2, pixel.1, pixel.0),
+            );
+        }
+    }
+    blocks * 16
+}
+
+#[cfg(target_arch = "x86_64")]
+fn convert_blocks(bgra: &[u8], rgb: &mut [u8]) -> usize {
+    if !is_x86_feature_detected!("ssse3") {
+        return 0;
+    }
+    // SAFETY: SSSE3 support was checked above
+    unsafe { convert_blocks_ssse3(bgra, rgb) }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+unsafe fn convert_blocks_ssse3(bgra: &[u8], rgb: &mut [u8]) -> usize {
+    use std::arch::x86_64::{
+        __m128i, _mm_loadu_si128, _mm_or_si128, _mm_setr_epi8, _mm_shuffle_epi8, _mm_slli_si128,
+        _mm_srli_si128, _mm_storeu_si128,
+    };
+
+    // Four BGRA pixels -> twelve RGB bytes, upper four lanes zeroed
+    let mask = _mm_setr_epi8(2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, -1, -1, -1, -1);
+    let blocks = bgra.len() / 64;
+    for block in 0..blocks {
+        // SAFETY: each iteration reads 64 bytes and writes 48 bytes inside the slices
+        unsafe {
+            let src = bgra.as_ptr().add(block * 64) as *const __m128i;
+            let dst = rgb.as_mut_ptr().add(block * 48) as *mut __m128i;
+            let s0 = _mm_shuffle_epi8(_mm_loadu_si128(src), mask);
+            let s1 = _mm_shuffle_epi8(_mm_loadu_si128(src.add(1)), mask);
+            let s2 = _mm_shuffle_epi8(_mm_loadu_si128(src.add(2)), mask);
+            let s3 = _mm_shuffle_epi8(_mm_loadu_si128(src.add(3)), mask);
+            _mm_storeu_si128(dst, _mm_or_si128(s0, _mm_slli_si128(s1, 12)));
+            _mm_storeu_si128(
+                dst.add(1),
+                _mm_or_si128(_mm_srli_si128(s1, 4), _mm_slli_si128(s2, 8)),
+            );
+            _mm_storeu_si128(
+                dst.add(2),
+                _mm_or_si128(_mm_srli_si128(s2, 8), _mm_slli_si128(s3, 4)),
+            );
+        }
+    }
+    blocks * 16
+}
+
+#[cfg(not(any(target_arch = "aarch64", target_arch = "x86_64")))]
+fn convert_blocks(_bgra: &[u8], _rgb: &mut [u8]) -> usize {
+    0
+}
+
+/// Byte order of packed pixels a capturer may deliver instead of BGRA
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackedLayout {
+    Rgb,
+    Bgr,
+    Rgbx,
+    Bgrx,
+    Xbgr,
+}
+
+impl PackedLayout {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PackedLayout::Rgb | PackedLayout::Bgr => 3,
+            PackedLayout::Rgbx | PackedLayout::Bgrx | PackedLayout::Xbgr => 4,
+        }
+    }
+
+    /// Offsets of blue, green and red within a pixel
+    fn offsets(self) -> [usize; 3] {
+        match self {
+            PackedLayout::Rgb | PackedLayout::Rgbx => [2, 1, 0],
+            PackedLayout::Bgr | PackedLayout::Bgrx => [0, 1, 2],
+            PackedLayout::Xbgr => [1, 2, 3],
+        }
+    }
+}
+
+/// Reorders packed pixels to BGRA with opaque alpha, or `None` when `data` doesn't hold
+/// exactly `width * height` pixels, e.g. because its rows are padded
+pub fn packed_to_bgra(
+    data: &[u8],
+    layout: PackedLayout,
+    width: usize,
+    height: usize,
+) -> Option<Vec<u8>> {
+    let bytes_per_pixel = layout.bytes_per_pixel();
+    if data.len() != width * height * bytes_per_pixel {
+        return None;
+    }
+    let [b, g, r] = layout.offsets();
+    let mut bgra = vec![0u8; width * height * 4];
+    for (src, dst) in data
+        .chunks_exact(bytes_per_pixel)
+        .zip(bgra.chunks_exact_mut(4))
+    {
+        dst[0] = src[b];
+        dst[1] = src[g];
+        dst[2] = src[r];
+        dst[3] = 255;
+    }
+    Some(bgra)
+}
+
+/// Converts bi-planar 4:2:0 video-range YCbCr (NV12), what ScreenCaptureKit delivers as
+/// YUV, to BGRA with the BT.709 matrix; `None` for an empty frame or when the planes are
+/// too small for `width` and `height`
+pub fn nv12_to_bgra(
+    luma: &[u8],
+    luma_stride: usize,
+    chroma: &[u8],
+    chroma_stride: usize,
+    width: usize,
+    height: usize,
+) -> Option<Vec<u8>> {
+    let chroma_rows = height.div_ceil(2);
+    let chroma_width = width.div_ceil(2) * 2;
+    if width == 0
+        || height == 0
+        || luma_stride < width
+        || chroma_stride < chroma_width
+        || luma.len() < (height - 1) * luma_stride + width
+        || chroma.len() < (chroma_rows - 1) * chroma_stride + chroma_width
+    {
+        return None;
+    }
+
+    let mut bgra = vec![0u8; width * height * 4];
+    for (y, row) in bgra.chunks_exact_mut(width * 4).enumerate() {
+        let luma_row = &luma[y * luma_stride..y * luma_stride + width];
+        let chroma_row = &chroma[(y / 2) * chroma_stride..(y / 2) * chroma_stride + chroma_width];
+        for (x, dst) in row.chunks_exact_mut(4).enumerate() {
+            // Fixed point with 8 fractional bits
+            let l = 298 * (luma_row[x] as i32 - 16);
+            let cb = chroma_row[x & !1] as i32 - 128;
+            let cr = chroma_row[(x & !1) + 1] as i32 - 128;
+            dst[0] = ((l + 541 * cb + 128) >> 8).clamp(0, 255) as u8;
+            dst[1] = ((l - 55 * cb - 136 * cr + 128) >> 8).clamp(0, 255) as u8;
+            dst[2] = ((l + 459 * cr + 128) >> 8).clamp(0, 255) as u8;
+            dst[3] = 255;
+        }
+    }
+    Some(bgra)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Distinct bytes, so a swapped or shifted channel changes the output
+    fn pattern(pixels: usize) -> Vec<u8> {
+        (0..pixels * 4).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn scalar(bgra: &[u8]) -> Vec<u8> {
+        let mut rgb = vec![0u8; bgra.len() / 4 * 3];
+        convert_scalar(bgra, &mut rgb);
+        rgb
+    }
+
+    #[test]
+    fn vectorized_matches_scalar() {
+        // Below one block, whole blocks, and blocks with a partial tail
+        for pixels in [0, 1, 15, 16, 17, 32, 47, 64, 1000, 2560 * 3 + 5] {
+            let bgra = pattern(pixels);
+            assert_eq!(bgra_to_rgb(&bgra), scalar(&bgra), "{} pixels", pixels);
+        }
+    }
+
+    #[test]
+    fn converts_channel_order() {
+        assert_eq!(bgra_to_rgb(&[1, 2, 3, 4, 5, 6, 7, 8]), [3, 2, 1, 7, 6, 5]);
+    }
+
+    #[test]
+    fn ignores_trailing_partial_pixel() {
+        assert_eq!(bgra_to_rgb(&[1, 2, 3, 4, 5, 6]), [3, 2, 1]);
+    }
+
+    #[test]
+    fn into_stops_at_destination_length() {
+        let bgra = pattern(20);
+        let mut rgb = vec![0u8; 18 * 3];
+        bgra_to_rgb_into(&bgra, &mut rgb);
+        assert_eq!(rgb, scalar(&bgra[..18 * 4]));
+    }
+
+    #[test]
+    fn nv12_rejects_empty_frames() {
+        assert_eq!(nv12_to_bgra(&[16; 4], 2, &[128; 2], 2, 0, 2), None);
+        assert_eq!(nv12_to_bgra(&[16; 4], 2, &[128; 2], 2, 2, 0), None);
+    }
+
+    #[test]
+    fn nv12_converts_black_and_white() {
+        let bgra = nv12_to_bgra(&[16, 235], 2, &[128, 128], 2, 2, 1).unwrap();
+        assert_eq!(bgra, [0, 0, 0, 255, 255, 255, 255, 255]);
+    }
+}
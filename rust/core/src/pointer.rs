@@ -0,0 +1,237 @@
+use crate::{Point, Rect};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClickWatchError {
+    #[error("Platform not supported")]
+    PlatformNotSupported,
+    #[error("Input Monitoring permission not granted")]
+    PermissionDenied,
+}
+
+pub type ClickWatchResult<T> = std::result::Result<T, ClickWatchError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+}
+
+/// A mouse click in global display coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Click {
+    pub at: Instant,
+    pub position: Point,
+    pub button: MouseButton,
+}
+
+type ClickLog = Arc<parking_lot::Mutex<VecDeque<Click>>>;
+
+/// Records mouse clicks system-wide through a listen-only event tap on a background thread
+pub struct ClickWatcher {
+    clicks: ClickLog,
+    stop: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl ClickWatcher {
+    pub fn start() -> ClickWatchResult<Self> {
+        let clicks = ClickLog::default();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = platform::spawn(Arc::clone(&clicks), Arc::clone(&stop))?;
+        Ok(Self {
+            clicks,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Clicks at or after `since`, oldest first
+    pub fn since(&self, since: Instant) -> Vec<Click> {
+        self.clicks
+            .lock()
+            .iter()
+            .filter(|click| click.at >= since)
+            .copied()
+            .collect()
+    }
+}
+
+impl Drop for ClickWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Describes the pointer for each screenshot, with the clicks since the previous one
+pub struct PointerTracker {
+    clicks: Option<ClickWatcher>,
+    last_frame: parking_lot::Mutex<Instant>,
+}
+
+impl PointerTracker {
+    /// Reports only the pointer location when `clicks` is `None`
+    pub fn new(clicks: Option<ClickWatcher>) -> Self {
+        Self {
+            clicks,
+            last_frame: parking_lot::Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Prompt sentence for the next screenshot of `display`
+    pub fn describe(&self, cursor: Option<Point>, display: Rect) -> Option<String> {
+        let since = std::mem::replace(&mut *self.last_frame.lock(), Instant::now());
+        let clicks = self
+            .clicks
+            .as_ref()
+            .map(|clicks| clicks.since(since))
+            .unwrap_or_default();
+        describe_pointer(cursor, &clicks, display)
+    }
+}
+
+/// Position within `display` as percentages from its top-left, which stay valid however
+/// the screenshot is scaled
+fn relative_position(point: Point, display: Rect) -> Option<(f64, f64)> {
+    if display.width <= 0.0 || display.height <= 0.0 {
+        return None;
+    }
+    let x = (point.x - display.x) / display.width * 100.0;
+    let y = (point.y - display.y) / display.height * 100.0;
+    ((0.0..=100.0).contains(&x) && (0.0..=100.0).contains(&y)).then_some((x, y))
+}
+
+/// Prompt sentence telling the model where the pointer is and where the user clicked in a
+/// screenshot of `display`; `None` when there is nothing on screen to report
+pub fn describe_pointer(cursor: Option<Point>, clicks: &[Click], display: Rect) -> Option<String> {
+    let mut sentences = Vec::new();
+    if let Some((x, y)) = cursor.and_then(|cursor| relative_position(cursor, display)) {
+        sentences.push(format!(
+            "The mouse pointer is {:.0}% from the left and {:.0}% from the top of the screenshot.",
+            x, y
+        ));
+    }
+    let clicks: Vec<String> = clicks
+        .iter()
+        .filter_map(|click| {
+            let (x, y) = relative_position(click.position, display)?;
+            let button = match click.button {
+                MouseButton::Left => "",
+                MouseButton::Right => "right-",
+            };
+            Some(format!("{}clicked at {:.0}%, {:.0}%", button, x, y))
+        })
+        .collect();
+    if !clicks.is_empty() {
+        sentences.push(format!(
+            "Since the previous screenshot the user {}.",
+            clicks.join(", then ")
+        ));
+    }
+    (!sentences.is_empty()).then(|| sentences.join(" "))
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{Click, ClickLog, ClickWatchError, ClickWatchResult, MouseButton};
+    use crate::Point;
+    use core_foundation::runloop::{kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop};
+    use core_graphics::event::{
+        CGEventTap, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement, CGEventType,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// Clicks kept for `ClickWatcher::since`
+    const MAX_CLICKS: usize = 32;
+
+    fn record_click(clicks: &ClickLog, click: Click) {
+        let mut clicks = clicks.lock();
+        if clicks.len() == MAX_CLICKS {
+            clicks.pop_front();
+        }
+        clicks.push_back(click);
+    }
+
+    pub(super) fn spawn(
+        clicks: ClickLog,
+        stop: Arc<AtomicBool>,
+    ) -> ClickWatchResult<std::thread::JoinHandle<()>> {
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            let tap = CGEventTap::new(
+                CGEventTapLocation::Session,
+                CGEventTapPlacement::TailAppendEventTap,
+                CGEventTapOptions::ListenOnly,
+                vec![CGEventType::LeftMouseDown, CGEventType::RightMouseDown],
+                move |_, event_type, event| {
+                    let button = match event_type {
+                        CGEventType::RightMouseDown => MouseButton::Right,
+                        _ => MouseButton::Left,
+                    };
+                    let location = event.location();
+                    record_click(
+                        &clicks,
+                        Click {
+                            at: Instant::now(),
+                            position: Point {
+                                x: location.x,
+                                y: location.y,
+                            },
+                            button,
+                        },
+                    );
+                    None
+                },
+            );
+            // Creating the tap fails without Input Monitoring permission
+            let Some((tap, source)) = tap.ok().and_then(|tap| {
+                let source = tap.mach_port.create_runloop_source(0).ok()?;
+                Some((tap, source))
+            }) else {
+                let _ = ready_tx.send(false);
+                return;
+            };
+            CFRunLoop::get_current().add_source(&source, unsafe { kCFRunLoopCommonModes });
+            tap.enable();
+            let _ = ready_tx.send(true);
+
+            while !stop.load(Ordering::Relaxed) {
+                CFRunLoop::run_in_mode(
+                    unsafe { kCFRunLoopDefaultMode },
+                    Duration::from_millis(500),
+                    false,
+                );
+            }
+        });
+
+        if ready_rx.recv() == Ok(true) {
+            Ok(thread)
+        } else {
+            let _ = thread.join();
+            Err(ClickWatchError::PermissionDenied)
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::{ClickLog, ClickWatchError, ClickWatchResult};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    pub(super) fn spawn(
+        _clicks: ClickLog,
+        _stop: Arc<AtomicBool>,
+    ) -> ClickWatchResult<std::thread::JoinHandle<()>> {
+        Err(ClickWatchError::PlatformNotSupported)
+    }
+}
@@ -0,0 +1,169 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Separates the JPEG parts of the MJPEG stream
+const BOUNDARY: &str = "watcher-frame";
+
+const INDEX_HTML: &str = "<!doctype html><html><head><title>Watcher preview</title></head>\
+<body style=\"margin:0;background:#111\"><img src=\"/stream.mjpg\" \
+style=\"display:block;max-width:100vw;max-height:100vh;margin:auto\"></body></html>";
+
+/// Latest JPEG the watcher sent to the model, shared with the preview server
+#[derive(Clone)]
+pub struct PreviewFeed {
+    latest: Arc<watch::Sender<Option<Arc<[u8]>>>>,
+}
+
+impl Default for PreviewFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PreviewFeed {
+    pub fn new() -> Self {
+        Self {
+            latest: Arc::new(watch::Sender::new(None)),
+        }
+    }
+
+    /// Replaces the frame shown to preview clients
+    pub fn publish(&self, jpeg_bytes: &[u8]) {
+        self.latest.send_replace(Some(Arc::from(jpeg_bytes)));
+    }
+
+    pub fn latest(&self) -> Option<Arc<[u8]>> {
+        self.latest.borrow().clone()
+    }
+
+    pub(crate) fn subscribe(&self) -> watch::Receiver<Option<Arc<[u8]>>> {
+        self.latest.subscribe()
+    }
+}
+
+/// Serves the preview feed over HTTP: `/` shows the stream in a page, `/stream.mjpg` is the
+/// MJPEG stream and `/snapshot.jpg` the latest frame
+pub struct PreviewServer {
+    listener: TcpListener,
+    feed: PreviewFeed,
+}
+
+impl PreviewServer {
+    pub async fn bind(addr: SocketAddr, feed: PreviewFeed) -> std::io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            feed,
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections until the task is aborted
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = self.listener.accept().await else {
+                    // e.g. out of file descriptors; avoid spinning until some are freed
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    continue;
+                };
+                let feed = self.feed.clone();
+                tokio::spawn(async move {
+                    // Clients going away mid-response are expected
+                    let _ = serve_connection(stream, feed).await;
+                });
+            }
+        })
+    }
+}
+
+async fn serve_connection(stream: TcpStream, feed: PreviewFeed) -> std::io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let mut request_line = String::new();
+    stream.read_line(&mut request_line).await?;
+    // Headers are not needed, but must be consumed before responding
+    loop {
+        let mut header = String::new();
+        if stream.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = path.split('?').next().unwrap_or("");
+    let stream = stream.get_mut();
+    if method != "GET" {
+        return respond(stream, "405 Method Not Allowed", "text/plain", b"GET only").await;
+    }
+    match path {
+        "/" | "/index.html" => respond(stream, "200 OK", "text/html", INDEX_HTML.as_bytes()).await,
+        "/snapshot.jpg" => match feed.latest() {
+            Some(jpeg) => respond(stream, "200 OK", "image/jpeg", &jpeg).await,
+            None => {
+                respond(
+                    stream,
+                    "503 Service Unavailable",
+                    "text/plain",
+                    b"No frame yet",
+                )
+                .await
+            }
+        },
+        "/stream.mjpg" => stream_frames(stream, feed).await,
+        _ => respond(stream, "404 Not Found", "text/plain", b"Not found").await,
+    }
+}
+
+async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> std::io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\n\
+         Connection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await
+}
+
+/// Writes every published frame as one part of a multipart/x-mixed-replace response
+async fn stream_frames(stream: &mut TcpStream, feed: PreviewFeed) -> std::io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary={}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        BOUNDARY
+    );
+    stream.write_all(head.as_bytes()).await?;
+
+    let mut latest = feed.subscribe();
+    // Start with the current frame, so the page isn't blank until the next capture
+    latest.mark_changed();
+    while latest.changed().await.is_ok() {
+        let Some(jpeg) = latest.borrow_and_update().clone() else {
+            continue;
+        };
+        let part = format!(
+            "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            BOUNDARY,
+            jpeg.len()
+        );
+        stream.write_all(part.as_bytes()).await?;
+        stream.write_all(&jpeg).await?;
+        stream.write_all(b"\r\n").await?;
+        stream.flush().await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use crate::{config_home, GenerationConfig};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Profiles available without a file, by name
+pub const BUILTIN_PROFILES: [&str; 3] = ["productivity-coach", "security-audit", "ux-research"];
+
+#[derive(Debug, Error)]
+pub enum ProfileError {
+    #[error("Failed to read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid profile in {path}: {source}")]
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("Invalid profile in {path}: {source}")]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("Unknown profile '{0}' (built in: {list})", list = BUILTIN_PROFILES.join(", "))]
+    NotFound(String),
+}
+
+pub type ProfileResult<T> = std::result::Result<T, ProfileError>;
+
+/// What the model is told to look for in the screenshots and how it answers, e.g. in a TOML
+/// file:
+///
+/// ```toml
+/// name = "focus"
+/// system_instruction = "You watch a developer's screen and point out distractions."
+/// prompt = "Is the user still working on the task at {time}?"
+///
+/// [schema]
+/// type = "object"
+/// properties = { focused = { type = "boolean" }, reason = { type = "string" } }
+/// required = ["focused", "reason"]
+/// ```
+///
+/// JSON files hold the same fields.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptProfile {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub system_instruction: String,
+    /// Question sent with each frame; see `SessionOptions::prompt_template`
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// OpenAPI schema answers must follow; free text without one
+    #[serde(default)]
+    pub schema: Option<Value>,
+}
+
+impl PromptProfile {
+    /// A profile file, `<name>.toml` or `<name>.json` in the `profiles` directory next to
+    /// the config file, or a built-in profile, in that order, so users can override built-ins
+    pub fn resolve(name: &str) -> ProfileResult<Self> {
+        let path = Path::new(name);
+        if path.is_file() {
+            return Self::from_file(path);
+        }
+        let found = Self::profile_dir().and_then(|dir| {
+            ["toml", "json"]
+                .iter()
+                .map(|ext| dir.join(format!("{}.{}", name, ext)))
+                .find(|path| path.is_file())
+        });
+        if let Some(path) = found {
+            return Self::from_file(&path);
+        }
+        Self::builtin(name).ok_or_else(|| ProfileError::NotFound(name.to_string()))
+    }
+
+    /// `$XDG_CONFIG_HOME/watcher/profiles`, falling back to `~/.config/watcher/profiles`
+    pub fn profile_dir() -> Option<PathBuf> {
+        Some(config_home()?.join("watcher").join("profiles"))
+    }
+
+    /// Reads a profile from JSON if the file ends in `.json` and from TOML otherwise
+    pub fn from_file(path: &Path) -> ProfileResult<Self> {
+        let text = std::fs::read_to_string(path).map_err(|source| ProfileError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text).map_err(|source| ProfileError::Json {
+                path: path.to_path_buf(),
+                source,
+            })
+        } else {
+            toml::from_str(&text).map_err(|source| ProfileError::Toml {
+                path: path.to_path_buf(),
+                source,
+            })
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        let profile = match name {
+            "productivity-coach" => Self {
+                name: name.to_string(),
+                description: Some("Notices focus, context switches and distractions".to_string()),
+                system_instruction: "You are a productivity coach watching screenshots of a \
+                    user's computer screen. For each screenshot, say in one or two sentences what \
+                    the user is working on and whether they are focused, switching between tasks \
+                    or distracted. Only suggest something when the same distraction keeps \
+                    coming back."
+                    .to_string(),
+                prompt: Some("What is the user working on at {time}?".to_string()),
+                schema: None,
+            },
+            "security-audit" => Self {
+                name: name.to_string(),
+                description: Some(
+                    "Flags secrets, personal data and risky actions on screen".to_string(),
+                ),
+                system_instruction: "You are a security auditor reviewing screenshots of a \
+                    user's computer screen. For each screenshot, report anything that puts the \
+                    user or their organization at risk: visible passwords, API keys or tokens, \
+                    personal or customer data, phishing pages, security warnings being \
+                    dismissed, or commands that disable protections. Report nothing when the \
+                    screen is harmless."
+                    .to_string(),
+                prompt: Some("Does frame {index} show anything risky?".to_string()),
+                schema: Some(json!({
+                    "type": "object",
+                    "properties": {
+                        "risk": {"type": "string", "enum": ["none", "low", "medium", "high"]},
+                        "findings": {"type": "array", "items": {"type": "string"}},
+                        "summary": {"type": "string"},
+                    },
+                    "required": ["risk", "findings", "summary"],
+                    "propertyOrdering": ["risk", "findings", "summary"],
+                })),
+            },
+            "ux-research" => Self {
+                name: name.to_string(),
+                description: Some("Observes tasks and friction like a usability study".to_string()),
+                system_instruction: "You are a UX researcher observing a usability session \
+                    through screenshots of a user's screen. For each screenshot, describe the \
+                    task the user is attempting, the step they are on, and any friction you can \
+                    see: hesitation, repeated attempts, error messages, confusing layouts or \
+                    going back and forth between screens."
+                    .to_string(),
+                prompt: Some(
+                    "What is the user trying to do, and what gets in their way?".to_string(),
+                ),
+                schema: Some(json!({
+                    "type": "object",
+                    "properties": {
+                        "app": {"type": "string"},
+                        "task": {"type": "string"},
+                        "step": {"type": "string"},
+                        "friction": {"type": "array", "items": {"type": "string"}},
+                        "summary": {"type": "string"},
+                    },
+                    "required": ["app", "task", "friction", "summary"],
+                    "propertyOrdering": ["app", "task", "step", "friction", "summary"],
+                })),
+            },
+            _ => return None,
+        };
+        Some(profile)
+    }
+
+    /// `config` with JSON output constrained to the schema of the profile, if it has one
+    pub fn generation_config(&self, config: GenerationConfig) -> GenerationConfig {
+        match &self.schema {
+            Some(schema) => GenerationConfig {
+                response_mime_type: Some("application/json".to_string()),
+                response_schema: Some(schema.clone()),
+                ..config
+            },
+            None => config,
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use crate::{FrameData, FrameSource};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Frames buffered for the encoder before new ones are dropped
+const FRAME_QUEUE: usize = 8;
+
+#[derive(Debug, Error)]
+pub enum RecordingError {
+    #[error("Video recording is only supported on macOS")]
+    PlatformNotSupported,
+    #[error("Failed to prepare the recording file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Video writer error: {0}")]
+    Writer(String),
+    #[error("The recording thread exited unexpectedly")]
+    ThreadExited,
+}
+
+pub type RecordingResult<T> = std::result::Result<T, RecordingError>;
+
+/// What a finished recording contains
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingSummary {
+    pub path: PathBuf,
+    pub frames: u64,
+    /// Frames left out because the encoder was busy or the resolution changed
+    pub dropped: u64,
+    pub duration: Duration,
+}
+
+enum Message {
+    Frame(Instant, Arc<FrameData>),
+    Finish,
+}
+
+/// Encodes every captured frame into an H.264 MP4 file, in parallel with the snapshots
+/// sent to Gemini
+pub struct Recorder {
+    messages: SyncSender<Message>,
+    thread: Option<JoinHandle<RecordingResult<RecordingSummary>>>,
+}
+
+impl Recorder {
+    /// Starts recording the frames of `source` to `path`, replacing an existing file
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn start(source: &FrameSource, path: impl AsRef<Path>) -> RecordingResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        let mut writer = platform::Writer::new(&path)?;
+
+        let (messages, queue) = mpsc::sync_channel(FRAME_QUEUE);
+        let thread = std::thread::spawn(move || -> RecordingResult<RecordingSummary> {
+            let mut dropped = 0;
+            while let Ok(Message::Frame(captured_at, frame)) = queue.recv() {
+                if !writer.append(captured_at, frame)? {
+                    dropped += 1;
+                }
+            }
+            let (frames, duration) = writer.finish()?;
+            Ok(RecordingSummary {
+                path,
+                frames,
+                dropped,
+                duration,
+            })
+        });
+
+        // Stamped on arrival, so the video plays back at the pace frames were captured
+        let mut frames = source.frame_channel(FRAME_QUEUE);
+        let forward = messages.clone();
+        tokio::spawn(async move {
+            while let Some(frame) = frames.recv().await {
+                match forward.try_send(Message::Frame(Instant::now(), frame)) {
+                    Ok(()) | Err(TrySendError::Full(_)) => {}
+                    Err(TrySendError::Disconnected(_)) => break,
+                }
+            }
+        });
+
+        Ok(Self {
+            messages,
+            thread: Some(thread),
+        })
+    }
+
+    /// Finalizes the file, blocking until the encoder has written everything
+    pub fn finish(mut self) -> RecordingResult<RecordingSummary> {
+        self.finish_thread()
+            .unwrap_or(Err(RecordingError::ThreadExited))
+    }
+
+    fn finish_thread(&mut self) -> Option<RecordingResult<RecordingSummary>> {
+        let thread = self.thread.take()?;
+        let _ = self.messages.send(Message::Finish);
+        Some(thread.join().unwrap_or(Err(RecordingError::ThreadExited)))
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Some(Err(e)) = self.finish_thread() {
+            eprintln!("❌ Recording error: {}", e);
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{RecordingError, RecordingResult};
+    use crate::FrameData;
+    use cidre::objc::Obj;
+    use cidre::{arc, av, cm, cv, ns};
+    use std::ffi::c_void;
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// Presentation timestamps are in milliseconds
+    const TIMESCALE: i32 = 1000;
+
+    struct Session {
+        writer: arc::R<av::AssetWriter>,
+        input: arc::R<av::AssetWriterInput>,
+        adaptor: arc::R<av::asset::WriterInputPixelBufAdaptor>,
+        size: (u32, u32),
+        started_at: Instant,
+        last_pts: cm::Time,
+    }
+
+    /// AVAssetWriter that is set up once the first frame tells the video size
+    pub struct Writer {
+        url: arc::R<ns::Url>,
+        session: Option<Session>,
+        frames: u64,
+    }
+
+    fn writer_error(error: impl std::fmt::Debug) -> RecordingError {
+        RecordingError::Writer(format!("{:?}", error))
+    }
+
+    /// Drops the frame reference handed to CoreVideo once the pixel buffer is released
+    extern "C" fn release_frame(frame: *mut c_void, _base_address: *const *const c_void) {
+        drop(unsafe { Box::from_raw(frame as *mut Arc<FrameData>) });
+    }
+
+    impl Writer {
+        pub fn new(path: &Path) -> RecordingResult<Self> {
+            let path = path.to_str().ok_or_else(|| {
+                RecordingError::Writer(format!("path is not valid UTF-8: {}", path.display()))
+            })?;
+            Ok(Self {
+                url: ns::Url::with_fs_path_str(path, false),
+                session: None,
+                frames: 0,
+            })
+        }
+
+        fn start_session(&self, width: u32, height: u32) -> RecordingResult<Session> {
+            let mut writer =
+                av::AssetWriter::with_url_and_file_type(&self.url, av::FileType::mp4())
+                    .map_err(writer_error)?;
+
+            // 4:2:0 H.264 needs even dimensions; AVFoundation scales the odd pixel away
+            let width_value = ns::Number::with_u32(width & !1);
+            let height_value = ns::Number::with_u32(height & !1);
+            let settings = ns::Dictionary::with_keys_values(
+                &[
+                    av::video_settings_keys::codec(),
+                    av::video_settings_keys::width(),
+                    av::video_settings_keys::height(),
+                ],
+                &[
+                    av::VideoCodec::h264().as_id_ref(),
+                    width_value.as_id_ref(),
+                    height_value.as_id_ref(),
+                ],
+            );
+            let mut input = av::AssetWriterInput::with_media_type_and_output_settings(
+                av::MediaType::video(),
+                Some(&settings),
+            )
+            .map_err(writer_error)?;
+            input.set_expects_media_data_in_real_time(true);
+            let adaptor = av::asset::WriterInputPixelBufAdaptor::with_input_writer(&input, None)
+                .map_err(writer_error)?;
+            writer.add_input(&input).map_err(writer_error)?;
+
+            if !writer.start_writing() {
+                return Err(writer_error(writer.error()));
+            }
+            writer.start_session_at_src_time(cm::Time::zero());
+            Ok(Session {
+                writer,
+                input,
+                adaptor,
+                size: (width, height),
+                started_at: Instant::now(),
+                last_pts: cm::Time::zero(),
+            })
+        }
+
+        /// Appends a frame, returning false when it had to be dropped
+        pub fn append(
+            &mut self,
+            captured_at: Instant,
+            frame: Arc<FrameData>,
+        ) -> RecordingResult<bool> {
+            if self.session.is_none() {
+                self.session = Some(self.start_session(frame.width, frame.height)?);
+            }
+            let Some(session) = self.session.as_mut() else {
+                return Ok(false);
+            };
+            if session.size != (frame.width, frame.height)
+                || !session.input.is_ready_for_more_media_data()
+            {
+                return Ok(false);
+            }
+
+            let elapsed = captured_at.saturating_duration_since(session.started_at);
+            let pts = cm::Time::new(elapsed.as_millis() as i64, TIMESCALE);
+            if pts.value <= session.last_pts.value && self.frames > 0 {
+                return Ok(false);
+            }
+
+            let (width, height) = (frame.width as usize, frame.height as usize);
+            let base_address = frame.data.as_ptr() as *mut c_void;
+            let frame = Box::into_raw(Box::new(frame)) as *mut c_void;
+            // CoreVideo only reads the pixels and calls release_frame when it is done
+            let buffer = cv::PixelBuf::with_bytes(
+                width,
+                height,
+                base_address,
+                width * 4,
+                release_frame,
+                frame,
+                cv::PixelFormat::_32_BGRA,
+                None,
+            )
+            .map_err(writer_error)?;
+
+            let appended = session
+                .adaptor
+                .append_pixel_buf_with_pts(&buffer, pts)
+                .map_err(writer_error)?;
+            if appended {
+                session.last_pts = pts;
+                self.frames += 1;
+            }
+            Ok(appended)
+        }
+
+        /// Closes the file, returning the number of frames and the video duration
+        pub fn finish(mut self) -> RecordingResult<(u64, Duration)> {
+            let Some(mut session) = self.session.take() else {
+                return Ok((0, Duration::ZERO));
+            };
+            session.input.mark_as_finished();
+            session.writer.end_session_at_src_time(session.last_pts);
+            session.writer.finish_writing();
+            if session.writer.status() != av::AssetWriterStatus::Completed {
+                return Err(writer_error(session.writer.error()));
+            }
+            let duration = Duration::from_millis(session.last_pts.value.max(0) as u64);
+            Ok((self.frames, duration))
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::{RecordingError, RecordingResult};
+    use crate::FrameData;
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    pub struct Writer;
+
+    impl Writer {
+        pub fn new(_path: &Path) -> RecordingResult<Self> {
+            Err(RecordingError::PlatformNotSupported)
+        }
+
+        pub fn append(
+            &mut self,
+            _captured_at: Instant,
+            _frame: Arc<FrameData>,
+        ) -> RecordingResult<bool> {
+            Err(RecordingError::PlatformNotSupported)
+        }
+
+        pub fn finish(self) -> RecordingResult<(u64, Duration)> {
+            Err(RecordingError::PlatformNotSupported)
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{list_windows, FrameData, Rect, WindowInfo};
+use std::str::FromStr;
+
+/// Edge in pixels of the blocks a pixelated area is averaged over
+const PIXELATE_BLOCK: u32 = 24;
+
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+/// What gets masked in every frame before it is encoded
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedactionRule {
+    /// Windows of apps whose name contains the text (case-insensitive), e.g. "1Password"
+    App(String),
+    /// Windows whose title contains the text (case-insensitive), e.g. a bank's name
+    Title(String),
+    /// A fixed rectangle in global display points
+    Region(Rect),
+}
+
+impl RedactionRule {
+    fn matches(&self, window: &WindowInfo) -> bool {
+        let contains =
+            |haystack: &str, needle: &str| haystack.to_lowercase().contains(&needle.to_lowercase());
+        match self {
+            RedactionRule::App(name) => contains(&window.app_name, name),
+            RedactionRule::Title(title) => window
+                .title
+                .as_deref()
+                .is_some_and(|window_title| contains(window_title, title)),
+            RedactionRule::Region(_) => false,
+        }
+    }
+}
+
+/// How a redacted area is masked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedactionStyle {
+    /// Solid black
+    #[default]
+    BlackOut,
+    /// Coarse blocks that keep the layout recognizable but not the text
+    Pixelate,
+}
+
+impl FromStr for RedactionStyle {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "black" | "blackout" | "black-out" => Ok(RedactionStyle::BlackOut),
+            "pixelate" | "blur" => Ok(RedactionStyle::Pixelate),
+            other => Err(format!(
+                "unknown redaction style '{}' (expected black or pixelate)",
+                other
+            )),
+        }
+    }
+}
+
+/// Masks configured apps, window titles and screen regions in display frames
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    rules: Vec<RedactionRule>,
+    style: RedactionStyle,
+    capture_region: Option<Rect>,
+}
+
+impl Redactor {
+    pub fn new(rules: Vec<RedactionRule>, style: RedactionStyle) -> Self {
+        Self {
+            rules,
+            style,
+            capture_region: None,
+        }
+    }
+
+    /// Frames only show this region, in points from the top-left of the display, as set
+    /// with `CaptureOptions::region`
+    pub fn with_capture_region(mut self, region: Rect) -> Self {
+        self.capture_region = Some(region);
+        self
+    }
+
+    /// Global rectangle shown by a frame of `display`
+    pub fn capture_area(&self, display: Rect) -> Rect {
+        match self.capture_region {
+            Some(region) => Rect {
+                x: display.x + region.x,
+                y: display.y + region.y,
+                ..region
+            },
+            None => display,
+        }
+    }
+
+    /// Whether a rule hides this window, which then mustn't be named in prompts either
+    pub fn hides(&self, window: &WindowInfo) -> bool {
+        self.rules.iter().any(|rule| rule.matches(window))
+    }
+
+    /// Whether a title rule matches `title`, e.g. of a calendar event
+    pub fn hides_title(&self, title: &str) -> bool {
+        self.rules.iter().any(|rule| match rule {
+            RedactionRule::Title(needle) => title.to_lowercase().contains(&needle.to_lowercase()),
+            _ => false,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Screen rectangles to mask right now: the configured regions and the bounds of every
+    /// on-screen window matching a rule, even where other windows cover it
+    pub fn areas(&self) -> Vec<Rect> {
+        let mut areas: Vec<Rect> = self
+            .rules
+            .iter()
+            .filter_map(|rule| match rule {
+                RedactionRule::Region(region) => Some(*region),
+                _ => None,
+            })
+            .collect();
+        if self
+            .rules
+            .iter()
+            .any(|rule| !matches!(rule, RedactionRule::Region(_)))
+        {
+            areas.extend(
+                list_windows()
+                    .into_iter()
+                    .filter(|window| self.hides(window))
+                    .map(|window| window.bounds),
+            );
+        }
+        areas
+    }
+
+    /// Masks what is hidden in a frame showing the global rectangle `area`, in place
+    pub fn apply(&self, frame: &mut FrameData, area: Rect) {
+        if area.width <= 0.0 || area.height <= 0.0 {
+            return;
+        }
+        let scale_x = frame.width as f64 / area.width;
+        let scale_y = frame.height as f64 / area.height;
+
+        for rect in self.areas() {
+            let left = ((rect.x - area.x) * scale_x).floor().max(0.0);
+            let top = ((rect.y - area.y) * scale_y).floor().max(0.0);
+            let right = ((rect.x + rect.width - area.x) * scale_x)
+                .ceil()
+                .min(frame.width as f64);
+            let bottom = ((rect.y + rect.height - area.y) * scale_y)
+                .ceil()
+                .min(frame.height as f64);
+            if right <= left || bottom <= top {
+                continue;
+            }
+            let (x, y) = (left as u32, top as u32);
+            let (width, height) = (right as u32 - x, bottom as u32 - y);
+            match self.style {
+                RedactionStyle::BlackOut => fill(frame, x, y, width, height, BLACK),
+                RedactionStyle::Pixelate => pixelate(frame, x, y, width, height),
+            }
+        }
+    }
+}
+
+/// Fills a rectangle with a BGRA color
+fn fill(frame: &mut FrameData, x: u32, y: u32, width: u32, height: u32, color: [u8; 4]) {
+    let stride = frame.width as usize * 4;
+    for row in y..y + height {
+        let start = row as usize * stride + x as usize * 4;
+        let Some(line) = frame.data.get_mut(start..start + width as usize * 4) else {
+            break;
+        };
+        for pixel in line.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+}
+
+/// Replaces each block of a rectangle with its average color
+fn pixelate(frame: &mut FrameData, x: u32, y: u32, width: u32, height: u32) {
+    let stride = frame.width as usize * 4;
+    for block_y in (y..y + height).step_by(PIXELATE_BLOCK as usize) {
+        let block_height = PIXELATE_BLOCK.min(y + height - block_y);
+        for block_x in (x..x + width).step_by(PIXELATE_BLOCK as usize) {
+            let block_width = PIXELATE_BLOCK.min(x + width - block_x);
+            let mut sums = [0u64; 4];
+            for row in block_y..block_y + block_height {
+                let start = row as usize * stride + block_x as usize * 4;
+                let Some(line) = frame.data.get(start..start + block_width as usize * 4) else {
+                    continue;
+                };
+                for pixel in line.chunks_exact(4) {
+                    for (sum, channel) in sums.iter_mut().zip(pixel) {
+                        *sum += *channel as u64;
+                    }
+                }
+            }
+            let count = (block_width as u64 * block_height as u64).max(1);
+            let color = sums.map(|sum| (sum / count) as u8);
+            fill(frame, block_x, block_y, block_width, block_height, color);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    read_manifest, CaptureError, CaptureResult, FrameData, FrameProducer, FrameSource,
+    ProducedFrame,
+};
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Pause between replayed images unless configured otherwise
+pub const DEFAULT_REPLAY_INTERVAL: Duration = Duration::from_secs(1);
+
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// Feeds the images of a directory, e.g. a recorded session or a folder of screenshots,
+/// through the analysis pipeline as if they were being captured
+///
+/// Images listed in the directory's manifest are replayed in the order they were saved,
+/// others in the order of their file names. Unlike live capture, no image is skipped: the
+/// next one waits until the consumer has taken the previous one, so the interval is only
+/// the shortest time between them.
+#[derive(Debug)]
+pub struct DirectoryFrameSource {
+    images: VecDeque<PathBuf>,
+    pacer: Pacer,
+}
+
+impl DirectoryFrameSource {
+    pub fn open<P: AsRef<Path>>(dir: P) -> CaptureResult<Self> {
+        let dir = dir.as_ref();
+        let images = replay_order(dir)
+            .map_err(|e| CaptureError::FrameError(format!("{}: {}", dir.display(), e)))?;
+        if images.is_empty() {
+            return Err(CaptureError::TargetNotFound(format!(
+                "no images in {}",
+                dir.display()
+            )));
+        }
+        Ok(Self {
+            images: images.into(),
+            pacer: Pacer::new(DEFAULT_REPLAY_INTERVAL),
+        })
+    }
+
+    /// Time between images
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.pacer = Pacer::new(interval);
+        self
+    }
+
+    /// Images left to replay
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// Starts replaying on a FrameSource, which stops after the last image
+    pub fn into_frame_source(self) -> FrameSource {
+        FrameSource::from_producer(self)
+    }
+}
+
+impl FrameProducer for DirectoryFrameSource {
+    fn lossless(&self) -> bool {
+        true
+    }
+
+    fn next_frame(&mut self) -> Option<ProducedFrame> {
+        let path = self.images.pop_front()?;
+        self.pacer.wait();
+
+        match image::open(&path) {
+            Ok(image) => {
+                let image = image.to_rgba8();
+                let (width, height) = image.dimensions();
+                let mut data = image.into_raw();
+                for pixel in data.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+                Some(ProducedFrame::Video(FrameData {
+                    width,
+                    height,
+                    data,
+                    display_id: None,
+                    cursor: None,
+                    active_window: None,
+                }))
+            }
+            Err(e) => {
+                eprintln!("❌ Skipping {}: {}", path.display(), e);
+                Some(ProducedFrame::Skipped)
+            }
+        }
+    }
+}
+
+/// Spaces out the frames of a producer running on a FrameSource thread
+#[derive(Debug)]
+pub(crate) struct Pacer {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl Pacer {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+        }
+    }
+
+    /// Waits until `interval` has passed since the previous call
+    pub(crate) fn wait(&mut self) {
+        if let Some(last) = self.last {
+            // Stopping unparks the thread, so it doesn't wait out the interval
+            std::thread::park_timeout(self.interval.saturating_sub(last.elapsed()));
+        }
+        self.last = Some(Instant::now());
+    }
+}
+
+/// Images of `dir` in the order they should be replayed
+fn replay_order(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut images: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && is_image(path))
+        .collect();
+    images.sort();
+
+    let Ok(archived) = read_manifest(dir) else {
+        return Ok(images);
+    };
+    // Saved frames in manifest order, then whatever the manifest doesn't list
+    let present: HashSet<PathBuf> = images.iter().cloned().collect();
+    let mut listed = HashSet::new();
+    let mut ordered: Vec<PathBuf> = archived
+        .iter()
+        .map(|archived| dir.join(&archived.frame.file))
+        .filter(|path| present.contains(path) && listed.insert(path.clone()))
+        .collect();
+    images.retain(|path| !listed.contains(path));
+    ordered.append(&mut images);
+    Ok(ordered)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|image| extension.eq_ignore_ascii_case(image))
+        })
+}
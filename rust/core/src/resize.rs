@@ -0,0 +1,113 @@
+use crate::FrameData;
+use image::imageops::FilterType;
+use image::{ImageBuffer, Rgba};
+use std::str::FromStr;
+
+/// Resampling filter used when downscaling frames
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResizeFilter {
+    /// Fastest, blocky; fine for large UI elements
+    Nearest,
+    Bilinear,
+    /// Sharpest text, slowest
+    #[default]
+    Lanczos,
+}
+
+impl ResizeFilter {
+    fn filter_type(self) -> FilterType {
+        match self {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Bilinear => FilterType::Triangle,
+            ResizeFilter::Lanczos => FilterType::Lanczos3,
+        }
+    }
+}
+
+impl FromStr for ResizeFilter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "nearest" => Ok(ResizeFilter::Nearest),
+            "bilinear" | "triangle" => Ok(ResizeFilter::Bilinear),
+            "lanczos" | "lanczos3" => Ok(ResizeFilter::Lanczos),
+            other => Err(format!(
+                "unknown resize filter '{}' (expected nearest, bilinear or lanczos)",
+                other
+            )),
+        }
+    }
+}
+
+/// Size limit applied to frames before encoding; frames are never upscaled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeTarget {
+    /// Fit inside a `width` x `height` box, keeping the aspect ratio
+    Fit { width: u32, height: u32 },
+    /// Limit the longer side to this many pixels
+    MaxLongEdge(u32),
+}
+
+/// Pre-encode downscaling step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeOptions {
+    pub target: ResizeTarget,
+    pub filter: ResizeFilter,
+}
+
+impl ResizeOptions {
+    pub fn new(target: ResizeTarget) -> Self {
+        Self {
+            target,
+            filter: ResizeFilter::default(),
+        }
+    }
+
+    pub fn with_filter(mut self, filter: ResizeFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Dimensions a `width` x `height` frame is scaled to
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if width == 0 || height == 0 {
+            return (width, height);
+        }
+        let scale = match self.target {
+            ResizeTarget::Fit {
+                width: max_width,
+                height: max_height,
+            } => (max_width as f64 / width as f64).min(max_height as f64 / height as f64),
+            ResizeTarget::MaxLongEdge(edge) => edge as f64 / width.max(height) as f64,
+        };
+        if scale >= 1.0 {
+            return (width, height);
+        }
+        (
+            ((width as f64 * scale).round() as u32).max(1),
+            ((height as f64 * scale).round() as u32).max(1),
+        )
+    }
+
+    /// Downscales a BGRA frame, returning `None` when it already fits
+    pub fn apply(&self, frame: &FrameData) -> Option<FrameData> {
+        let (width, height) = self.output_size(frame.width, frame.height);
+        if (width, height) == (frame.width, frame.height) {
+            return None;
+        }
+
+        // Channel order does not matter for resampling, so BGRA goes through as RGBA
+        let image: ImageBuffer<Rgba<u8>, &[u8]> =
+            ImageBuffer::from_raw(frame.width, frame.height, frame.data.as_slice())?;
+        let resized = image::imageops::resize(&image, width, height, self.filter.filter_type());
+        Some(FrameData {
+            width,
+            height,
+            data: resized.into_raw(),
+            display_id: frame.display_id,
+            cursor: frame.cursor,
+            active_window: frame.active_window.clone(),
+        })
+    }
+}
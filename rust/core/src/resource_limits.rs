@@ -0,0 +1,196 @@
+use std::time::{Duration, Instant};
+
+/// How often the governor samples its own usage
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Consecutive samples well below the ceilings before throttling is eased again
+const RECOVERY_SAMPLES: u32 = 3;
+
+/// Fraction of a ceiling usage must drop below to count as recovered
+const RECOVERY_RATIO: f64 = 0.7;
+
+/// Ceilings on the watcher's own resource usage
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// CPU usage in percent of one core
+    pub max_cpu_percent: Option<f64>,
+    /// Resident memory in bytes
+    pub max_rss_bytes: Option<u64>,
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        self.max_cpu_percent.is_none() && self.max_rss_bytes.is_none()
+    }
+}
+
+/// Process usage measured between two samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceUsage {
+    pub cpu_percent: f64,
+    /// `None` where resident memory can't be queried
+    pub rss_bytes: Option<u64>,
+}
+
+impl ResourceUsage {
+    fn describe(&self) -> String {
+        match self.rss_bytes {
+            Some(rss) => format!(
+                "CPU {:.1}%, RSS {} MB",
+                self.cpu_percent,
+                rss / (1024 * 1024)
+            ),
+            None => format!("CPU {:.1}%", self.cpu_percent),
+        }
+    }
+}
+
+/// Capture settings applied at a throttle level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttle {
+    pub level: u8,
+    /// Only every n-th captured frame is sent
+    pub frame_stride: u32,
+    /// Longer side frames are downscaled to
+    pub max_edge: Option<u32>,
+    pub jpeg_quality: u8,
+}
+
+impl Throttle {
+    pub const MAX_LEVEL: u8 = 3;
+
+    pub fn for_level(level: u8) -> Self {
+        let (frame_stride, max_edge, jpeg_quality) = match level {
+            0 => (1, None, 90),
+            1 => (1, Some(1280), 75),
+            2 => (2, Some(960), 60),
+            _ => (4, Some(640), 50),
+        };
+        Self {
+            level: level.min(Self::MAX_LEVEL),
+            frame_stride,
+            max_edge,
+            jpeg_quality,
+        }
+    }
+}
+
+/// Total user + system CPU time consumed by this process
+fn process_cpu_time() -> Duration {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) } != 0 {
+        return Duration::ZERO;
+    }
+    let usage = unsafe { usage.assume_init() };
+    let time = |value: libc::timeval| {
+        Duration::from_secs(value.tv_sec as u64) + Duration::from_micros(value.tv_usec as u64)
+    };
+    time(usage.ru_utime) + time(usage.ru_stime)
+}
+
+/// Current resident memory of this process
+#[cfg(target_os = "macos")]
+fn process_rss_bytes() -> Option<u64> {
+    let mut info = std::mem::MaybeUninit::<libc::proc_taskinfo>::uninit();
+    let size = std::mem::size_of::<libc::proc_taskinfo>() as libc::c_int;
+    let written = unsafe {
+        libc::proc_pidinfo(
+            libc::getpid(),
+            libc::PROC_PIDTASKINFO,
+            0,
+            info.as_mut_ptr() as *mut libc::c_void,
+            size,
+        )
+    };
+    if written != size {
+        return None;
+    }
+    Some(unsafe { info.assume_init() }.pti_resident_size)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn process_rss_bytes() -> Option<u64> {
+    None
+}
+
+/// Samples the watcher's own CPU and memory and steps capture quality down when a ceiling
+/// is exceeded, back up once usage has settled
+pub struct ResourceGovernor {
+    limits: ResourceLimits,
+    level: u8,
+    calm_samples: u32,
+    last_sample: (Instant, Duration),
+}
+
+impl ResourceGovernor {
+    pub fn new(limits: ResourceLimits) -> Self {
+        Self {
+            limits,
+            level: 0,
+            calm_samples: 0,
+            last_sample: (Instant::now(), process_cpu_time()),
+        }
+    }
+
+    pub fn throttle(&self) -> Throttle {
+        Throttle::for_level(self.level)
+    }
+
+    /// Takes a sample if one is due, returning a status message when the level changed
+    pub fn update(&mut self) -> Option<String> {
+        let (last_wall, last_cpu) = self.last_sample;
+        let elapsed = last_wall.elapsed();
+        if elapsed < SAMPLE_INTERVAL {
+            return None;
+        }
+        let cpu = process_cpu_time();
+        self.last_sample = (Instant::now(), cpu);
+        let usage = ResourceUsage {
+            cpu_percent: cpu.saturating_sub(last_cpu).as_secs_f64() / elapsed.as_secs_f64() * 100.0,
+            rss_bytes: process_rss_bytes(),
+        };
+
+        let ratio = self.usage_ratio(&usage);
+        if ratio > 1.0 {
+            self.calm_samples = 0;
+            if self.level < Throttle::MAX_LEVEL {
+                self.level += 1;
+                return Some(format!(
+                    "🐢 Resource limit exceeded ({}), throttling capture to level {}",
+                    usage.describe(),
+                    self.level
+                ));
+            }
+        } else if ratio < RECOVERY_RATIO && self.level > 0 {
+            self.calm_samples += 1;
+            if self.calm_samples >= RECOVERY_SAMPLES {
+                self.calm_samples = 0;
+                self.level -= 1;
+                return Some(format!(
+                    "🐇 Resource usage back to normal ({}), throttle level {}",
+                    usage.describe(),
+                    self.level
+                ));
+            }
+        } else {
+            self.calm_samples = 0;
+        }
+        None
+    }
+
+    /// Highest usage relative to its ceiling; above 1.0 means a limit is exceeded
+    fn usage_ratio(&self, usage: &ResourceUsage) -> f64 {
+        let cpu = self
+            .limits
+            .max_cpu_percent
+            .filter(|max| *max > 0.0)
+            .map_or(0.0, |max| usage.cpu_percent / max);
+        let rss = self
+            .limits
+            .max_rss_bytes
+            .filter(|max| *max > 0)
+            .zip(usage.rss_bytes)
+            .map_or(0.0, |(max, rss)| rss as f64 / max as f64);
+        cpu.max(rss)
+    }
+}
@@ -1,9 +1,36 @@
-use crate::{Content, GeminiSession, Part, ServerEvent};
+use crate::{
+    dispatch_tool_calls, AnalysisEvents, AnalysisResult, Content, Part, SentTurn, ServerEvent,
+    Stage, Telemetry, ToolHandler, TurnTracker,
+};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::mpsc::UnboundedSender;
 
 /// Trait for printing Gemini responses
 pub trait ResponsePrinter: Send + Sync {
     fn print_response(&self, content: &Content);
+
+    /// Prints a progress line from the capture pipeline
+    fn print_status(&self, message: &str) {
+        println!("{}", message);
+    }
+
+    /// Prints an error from the capture pipeline or a background task
+    fn print_error(&self, message: &str) {
+        eprintln!("{}", message);
+    }
+
+    /// Called once the model has finished generating a turn
+    fn print_turn_complete(&self) {
+        println!();
+    }
+
+    /// Called after a frame has been written to disk, before it is sent to the model
+    fn frame_captured(&self, _path: &Path) {}
+
+    /// Called for every frame on its way to the model, saved or not
+    fn frame_sent(&self, _frame_seq: usize) {}
 }
 
 /// CLI implementation that prints responses to stdout
@@ -28,9 +55,14 @@ impl ResponsePrinter for CliResponsePrinter {
                 Part::Text { text } => {
                     println!("🤖 Gemini: {}", text);
                 }
-                Part::Json(value) => {
-                    println!("🤖 Gemini (json): {}", value);
-                }
+                Part::Json(value) => match part.inline_data() {
+                    Some(blob) => println!(
+                        "🤖 Gemini ({}, {} base64 bytes)",
+                        blob.mime_type.as_deref().unwrap_or("inline data"),
+                        blob.data.len()
+                    ),
+                    None => println!("🤖 Gemini (json): {}", value),
+                },
             }
         }
     }
@@ -39,29 +71,156 @@ impl ResponsePrinter for CliResponsePrinter {
 /// Processes Gemini session output by receiving events and printing responses
 pub struct OutputProcessor {
     printer: Arc<dyn ResponsePrinter>,
+    tool_handlers: Vec<Arc<dyn ToolHandler>>,
+    results: Option<(Arc<TurnTracker>, UnboundedSender<AnalysisResult>)>,
+    telemetry: Option<Arc<Telemetry>>,
+    #[cfg(feature = "playback")]
+    audio_sink: Option<Arc<crate::AudioSink>>,
 }
 
 impl OutputProcessor {
     pub fn new(printer: Arc<dyn ResponsePrinter>) -> Self {
-        Self { printer }
+        Self {
+            printer,
+            tool_handlers: Vec::new(),
+            results: None,
+            telemetry: None,
+            #[cfg(feature = "playback")]
+            audio_sink: None,
+        }
+    }
+
+    /// Answers the model's function calls with `handlers`
+    pub fn with_tool_handlers(mut self, handlers: Vec<Arc<dyn ToolHandler>>) -> Self {
+        self.tool_handlers = handlers;
+        self
+    }
+
+    /// Also delivers each complete answer to `results`, paired with the turn recorded in
+    /// `turns` that it answers
+    pub fn with_results(
+        mut self,
+        turns: Arc<TurnTracker>,
+        results: UnboundedSender<AnalysisResult>,
+    ) -> Self {
+        self.results = Some((turns, results));
+        self
     }
 
-    /// Spawns a task to process Gemini session events
-    pub fn spawn(self, session: GeminiSession) {
+    /// Times the first part and the end of every answer about a frame into `telemetry`;
+    /// requires `with_results` to tell which frame an answer is about
+    pub fn with_telemetry(mut self, telemetry: Arc<Telemetry>) -> Self {
+        self.telemetry = Some(telemetry);
+        self
+    }
+
+    /// Plays audio parts of model turns through `sink`, stopping when the model is interrupted
+    #[cfg(feature = "playback")]
+    pub fn with_audio_sink(mut self, sink: Arc<crate::AudioSink>) -> Self {
+        self.audio_sink = Some(sink);
+        self
+    }
+
+    /// Adds the time since the turn about a frame was sent to the telemetry; `turn` is the
+    /// one the answer that started at `started` is about unless given
+    fn time_answer(&self, turn: Option<SentTurn>, started: SystemTime, stage: Stage) {
+        let (Some(telemetry), Some((turns, _))) = (&self.telemetry, &self.results) else {
+            return;
+        };
+        let Some(turn) = turn.or_else(|| turns.oldest(started)) else {
+            return;
+        };
+        if let Some(frame_seq) = turn.frame_seq {
+            let elapsed = turn.sent_at.elapsed().unwrap_or_default();
+            telemetry.record(frame_seq, stage, elapsed);
+        }
+    }
+
+    /// Spawns a task to process the events of a Gemini session or another backend
+    pub fn spawn(self, events: impl AnalysisEvents + 'static) {
         tokio::spawn(async move {
-            let mut session = session;
+            let mut session = events;
+            let mut answer = String::new();
+            let mut answer_usage = None;
+            // When the first part of the answer being received arrived
+            let mut answer_started = None;
             loop {
                 match session.recv().await {
-                    Ok(Some(ServerEvent::ServerContent { content, .. })) => {
+                    Ok(Some(ServerEvent::ServerContent {
+                        content,
+                        usage_metadata,
+                    })) => {
+                        #[cfg(feature = "playback")]
+                        if let Some(sink) = &self.audio_sink {
+                            if content.interrupted.unwrap_or(false) {
+                                sink.interrupt();
+                            }
+                            if let Some(model_turn) = &content.model_turn {
+                                for part in &model_turn.parts {
+                                    sink.play_part(part);
+                                }
+                            }
+                        }
+                        if content.is_blocked() {
+                            self.printer.print_status(&format!(
+                                "⚠️ Response blocked (finish reason: {:?})",
+                                content.finish_reason
+                            ));
+                        }
+                        // The interrupted answer never completes, so its turn goes unanswered
+                        if content.interrupted.unwrap_or(false)
+                            && let Some(started) = answer_started.take()
+                        {
+                            answer.clear();
+                            answer_usage = None;
+                            if let Some((turns, _)) = &self.results {
+                                turns.answered(started);
+                            }
+                        }
                         if let Some(model_turn) = content.model_turn {
+                            if answer_started.is_none() {
+                                let now = SystemTime::now();
+                                answer_started = Some(now);
+                                self.time_answer(None, now, Stage::FirstToken);
+                            }
+                            if self.results.is_some() {
+                                for part in &model_turn.parts {
+                                    if let Part::Text { text } = part {
+                                        answer.push_str(text);
+                                    }
+                                }
+                            }
                             self.printer.print_response(&model_turn);
                         }
+                        if usage_metadata.is_some() {
+                            answer_usage = usage_metadata;
+                        }
                         if content.generation_complete.unwrap_or(false) {
-                            println!();
+                            self.printer.print_turn_complete();
+                        }
+                        if content.turn_complete.unwrap_or(false) {
+                            let started = answer_started.take().unwrap_or_else(SystemTime::now);
+                            if let Some((turns, results)) = &self.results {
+                                let turn = turns.answered(started);
+                                self.time_answer(turn, started, Stage::Complete);
+                                let _ = results.send(AnalysisResult::new(
+                                    turn,
+                                    std::mem::take(&mut answer),
+                                    answer_usage.take(),
+                                ));
+                            }
+                        }
+                    }
+                    Ok(Some(ServerEvent::ToolCall { tool_call, .. })) => {
+                        let response =
+                            dispatch_tool_calls(&self.tool_handlers, &tool_call.function_calls)
+                                .await;
+                        if let Err(err) = session.send_tool_response(response).await {
+                            eprintln!("❌ Error sending tool response: {}", err);
                         }
                     }
                     Ok(Some(ServerEvent::SetupComplete { .. })) => {
-                        println!("✅ Gemini session ready");
+                        self.printer.print_status("✅ Gemini session ready");
                     }
                     Ok(Some(ServerEvent::Error { error, .. })) => {
                         eprintln!("❌ Gemini error: {}", error);
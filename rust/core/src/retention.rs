@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Extensions of the files a RetentionManager prunes; indexes, markers and notes are kept
+const FRAME_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// How much of the frame archive is kept; unset limits don't apply
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_files: Option<usize>,
+    pub max_bytes: Option<u64>,
+    pub max_age: Option<Duration>,
+}
+
+impl RetentionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.max_files.is_none() && self.max_bytes.is_none() && self.max_age.is_none()
+    }
+}
+
+/// Files deleted by one pruning pass
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneStats {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+struct ArchivedFile {
+    path: PathBuf,
+    bytes: u64,
+    modified: SystemTime,
+}
+
+/// Keeps a directory of saved frames within a RetentionPolicy by deleting the oldest frames,
+/// including those of earlier sessions, as new ones are written
+#[derive(Debug)]
+pub struct RetentionManager {
+    dir: PathBuf,
+    policy: RetentionPolicy,
+    /// Oldest first
+    files: VecDeque<ArchivedFile>,
+    total_bytes: u64,
+}
+
+impl RetentionManager {
+    /// Creates `dir` if needed and indexes the frames already in it
+    pub fn open(dir: impl Into<PathBuf>, policy: RetentionPolicy) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() || !is_frame_file(&entry.path()) {
+                continue;
+            }
+            files.push(ArchivedFile {
+                path: entry.path(),
+                bytes: metadata.len(),
+                modified: metadata.modified().unwrap_or_else(|_| SystemTime::now()),
+            });
+        }
+        files.sort_by(|a, b| a.modified.cmp(&b.modified).then(a.path.cmp(&b.path)));
+        let total_bytes = files.iter().map(|file| file.bytes).sum();
+        Ok(Self {
+            dir,
+            policy,
+            files: files.into(),
+            total_bytes,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn policy(&self) -> RetentionPolicy {
+        self.policy
+    }
+
+    /// Number of frames in the directory
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Adds a frame just written to the directory, then prunes
+    pub fn record(&mut self, path: impl AsRef<Path>) -> io::Result<PruneStats> {
+        let path = path.as_ref();
+        let bytes = fs::metadata(path)?.len();
+        if let Some(position) = self.files.iter().position(|file| file.path == path)
+            && let Some(replaced) = self.files.remove(position)
+        {
+            self.total_bytes -= replaced.bytes;
+        }
+        self.files.push_back(ArchivedFile {
+            path: path.to_path_buf(),
+            bytes,
+            modified: SystemTime::now(),
+        });
+        self.total_bytes += bytes;
+        self.prune()
+    }
+
+    /// Deletes the oldest frames until the policy holds again; the newest frame is always
+    /// kept, even when it alone is over the size limit
+    pub fn prune(&mut self) -> io::Result<PruneStats> {
+        let mut stats = PruneStats::default();
+        let now = SystemTime::now();
+        while self.files.len() > 1 {
+            let Some(oldest) = self.files.front() else {
+                break;
+            };
+            let expired = self.policy.max_age.is_some_and(|max_age| {
+                now.duration_since(oldest.modified)
+                    .is_ok_and(|age| age > max_age)
+            });
+            let over = expired
+                || self
+                    .policy
+                    .max_files
+                    .is_some_and(|max_files| self.files.len() > max_files)
+                || self
+                    .policy
+                    .max_bytes
+                    .is_some_and(|max_bytes| self.total_bytes > max_bytes);
+            if !over {
+                break;
+            }
+
+            match fs::remove_file(&oldest.path) {
+                Ok(()) => {
+                    stats.files += 1;
+                    stats.bytes += oldest.bytes;
+                }
+                // Already deleted by someone else
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+            if let Some(removed) = self.files.pop_front() {
+                self.total_bytes -= removed.bytes;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+fn is_frame_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            FRAME_EXTENSIONS
+                .iter()
+                .any(|frame| extension.eq_ignore_ascii_case(frame))
+        })
+}
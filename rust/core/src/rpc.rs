@@ -0,0 +1,159 @@
+use crate::{Content, Part, ResponsePrinter};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+/// JSON-RPC error code for malformed JSON
+pub const RPC_PARSE_ERROR: i64 = -32700;
+/// JSON-RPC error code for a message that is not a valid request
+pub const RPC_INVALID_REQUEST: i64 = -32600;
+/// JSON-RPC error code for an unknown method
+pub const RPC_METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for parameters that don't match the method
+pub const RPC_INVALID_PARAMS: i64 = -32602;
+/// JSON-RPC error code for failures while handling a request
+pub const RPC_INTERNAL_ERROR: i64 = -32603;
+
+/// Largest message body accepted, so a bad header can't allocate unbounded memory
+pub const MAX_RPC_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum RpcError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Missing Content-Length header")]
+    MissingContentLength,
+    #[error("Invalid header: {0}")]
+    InvalidHeader(String),
+    #[error("Message of {0} bytes exceeds the {MAX_RPC_MESSAGE_BYTES} byte limit")]
+    MessageTooLarge(usize),
+}
+
+pub type RpcResult<T> = std::result::Result<T, RpcError>;
+
+/// An incoming JSON-RPC request; requests without an id are notifications
+#[derive(Debug, Clone, Deserialize)]
+pub struct RpcRequest {
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// Reads one `Content-Length` framed message, returning `None` at end of input
+pub async fn read_rpc_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> RpcResult<Option<Value>> {
+    let mut content_length = None;
+    let mut saw_header = false;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return if saw_header {
+                Err(RpcError::MissingContentLength)
+            } else {
+                Ok(None)
+            };
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            if saw_header {
+                break;
+            }
+            continue;
+        }
+        saw_header = true;
+
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| RpcError::InvalidHeader(line.to_string()))?;
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            let length = value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| RpcError::InvalidHeader(line.to_string()))?;
+            content_length = Some(length);
+        }
+    }
+
+    let length = content_length.ok_or(RpcError::MissingContentLength)?;
+    if length > MAX_RPC_MESSAGE_BYTES {
+        return Err(RpcError::MessageTooLarge(length));
+    }
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Writes `Content-Length` framed JSON-RPC messages, shareable across tasks
+#[derive(Clone)]
+pub struct RpcWriter {
+    out: Arc<parking_lot::Mutex<Box<dyn Write + Send>>>,
+}
+
+impl RpcWriter {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            out: Arc::new(parking_lot::Mutex::new(Box::new(writer))),
+        }
+    }
+
+    /// Creates a writer over the process stdout
+    pub fn stdout() -> Self {
+        Self::new(std::io::stdout())
+    }
+
+    /// Sends a successful response to the request with the given id
+    pub fn respond(&self, id: Value, result: Value) -> RpcResult<()> {
+        self.write(&json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+
+    /// Sends an error response to the request with the given id
+    pub fn respond_error(&self, id: Value, code: i64, message: impl Into<String>) -> RpcResult<()> {
+        self.write(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message.into() }
+        }))
+    }
+
+    /// Emits an event notification
+    pub fn notify(&self, method: &str, params: Value) -> RpcResult<()> {
+        self.write(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn write(&self, message: &Value) -> RpcResult<()> {
+        let payload = serde_json::to_string(message)?;
+        let mut out = self.out.lock();
+        write!(out, "Content-Length: {}\r\n\r\n{}", payload.len(), payload)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Emits model output as `model/*` notifications instead of printing it
+impl ResponsePrinter for RpcWriter {
+    fn print_response(&self, content: &Content) {
+        for part in &content.parts {
+            let params = match part {
+                Part::Text { text } => json!({ "text": text }),
+                Part::Json(value) => json!({ "json": value }),
+            };
+            let _ = self.notify("model/response", params);
+        }
+    }
+
+    fn print_status(&self, message: &str) {
+        let _ = self.notify("log", json!({ "message": message }));
+    }
+
+    fn print_turn_complete(&self) {
+        let _ = self.notify("model/turnComplete", json!({}));
+    }
+}
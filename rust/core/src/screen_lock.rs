@@ -0,0 +1,102 @@
+use crate::{clock_time, CaptureSession, ResponsePrinter};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// How often the lock watch checks the screen lock
+pub const LOCK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Whether the login session is behind the lock screen or the login window, e.g. after
+/// switching to another user; `None` where it can't be told
+#[cfg(target_os = "macos")]
+pub fn is_screen_locked() -> Option<bool> {
+    use core_foundation::base::{CFType, TCFType};
+    use core_foundation::boolean::CFBoolean;
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::string::CFString;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    unsafe extern "C" {
+        fn CGSessionCopyCurrentDictionary() -> CFDictionaryRef;
+    }
+
+    let dict = unsafe { CGSessionCopyCurrentDictionary() };
+    // No window server session, e.g. when run over SSH
+    if dict.is_null() {
+        return None;
+    }
+    let dict: CFDictionary<CFString, CFType> =
+        unsafe { CFDictionary::wrap_under_create_rule(dict) };
+    let flag = |key: &str| {
+        dict.find(&CFString::new(key))
+            .and_then(|value| value.downcast::<CFBoolean>())
+            .map(bool::from)
+    };
+
+    // The lock key is only present while locked
+    Some(
+        flag("CGSSessionScreenIsLocked").unwrap_or(false)
+            || flag("kCGSSessionOnConsoleKey") == Some(false),
+    )
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn is_screen_locked() -> Option<bool> {
+    None
+}
+
+/// Suspends capture and Gemini sends while the screen is locked and picks them up again on
+/// unlock, noting how long it was locked in the activity log
+pub struct ScreenLockWatch {
+    printer: Arc<dyn ResponsePrinter>,
+}
+
+impl ScreenLockWatch {
+    pub fn new(printer: Arc<dyn ResponsePrinter>) -> Self {
+        Self { printer }
+    }
+
+    /// Polls the screen lock in the background
+    ///
+    /// A session that was already paused, e.g. over RPC, stays paused after unlocking.
+    pub fn spawn(self, session: Arc<CaptureSession>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(LOCK_POLL_INTERVAL);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // When the screen was locked and whether locking paused the session, while locked
+            let mut locked: Option<(SystemTime, bool)> = None;
+            loop {
+                ticker.tick().await;
+                let Some(is_locked) = is_screen_locked() else {
+                    continue;
+                };
+                match locked {
+                    None if is_locked => {
+                        let since = SystemTime::now();
+                        self.printer.print_status(&format!(
+                            "🔒 Screen locked at {}, pausing until it is unlocked",
+                            clock_time(since)
+                        ));
+                        let pausing = !session.is_paused();
+                        session.pause();
+                        session.set_locked(true);
+                        locked = Some((since, pausing));
+                    }
+                    Some((since, paused_by_lock)) if !is_locked => {
+                        locked = None;
+                        session.set_locked(false);
+                        if paused_by_lock {
+                            session.resume();
+                        }
+                        if let Err(e) = session.record_lock_gap(since, SystemTime::now()).await {
+                            self.printer
+                                .print_error(&format!("❌ Error sending lock gap: {}", e));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        })
+    }
+}
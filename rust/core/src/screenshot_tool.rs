@@ -0,0 +1,68 @@
+use crate::{CaptureSession, FunctionCall, ToolHandler};
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+use std::sync::{Arc, OnceLock, Weak};
+
+pub const SCREENSHOT_TOOL_NAME: &str = "take_screenshot";
+
+/// Lets the model take a fresh screenshot mid-conversation, e.g. to look at a dialog again
+/// or at another app, through the `take_screenshot` tool
+///
+/// The tool is declared in the session setup, before the capture session exists, so the
+/// session is attached once it does; calls before that fail. The screenshot is redacted and
+/// left out for excluded apps like every other frame.
+#[derive(Default)]
+pub struct ScreenshotTool {
+    session: OnceLock<Weak<CaptureSession>>,
+}
+
+impl ScreenshotTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes screenshots through `session` from now on
+    pub fn attach(&self, session: &Arc<CaptureSession>) {
+        let _ = self.session.set(Arc::downgrade(session));
+    }
+}
+
+impl ToolHandler for ScreenshotTool {
+    fn declarations(&self) -> Vec<Value> {
+        vec![json!({
+            "name": SCREENSHOT_TOOL_NAME,
+            "description": "Takes a screenshot of the user's screen right now and shows it to \
+                you in the next user turn. Use it when the latest screenshot is not enough, \
+                e.g. to look at a dialog again or check whether something changed.",
+            "parameters": {
+                "type": "OBJECT",
+                "properties": {
+                    "app": {
+                        "type": "STRING",
+                        "description": "Name of an app to crop the screenshot to its \
+                            frontmost window, e.g. \"Safari\"; the whole screen when omitted",
+                    }
+                }
+            }
+        })]
+    }
+
+    fn handle<'a>(&'a self, call: &'a FunctionCall) -> BoxFuture<'a, Option<Value>> {
+        Box::pin(async move {
+            if call.name != SCREENSHOT_TOOL_NAME {
+                return None;
+            }
+            let Some(session) = self.session.get().and_then(Weak::upgrade) else {
+                return Some(json!({ "error": "Capture has not started yet" }));
+            };
+            let app = call
+                .args
+                .as_ref()
+                .and_then(|args| args.get("app"))
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|app| !app.is_empty());
+            Some(session.send_requested_screenshot(app).await)
+        })
+    }
+}
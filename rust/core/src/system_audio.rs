@@ -0,0 +1,83 @@
+use crate::{f32_to_pcm16, Resampler};
+use scap::frame::{AudioFormat, AudioFrame};
+
+/// Sample rate expected by Gemini for realtime audio input
+pub const SYSTEM_AUDIO_SAMPLE_RATE: u32 = 16_000;
+
+/// Number of 16 kHz samples buffered before a chunk is emitted (100 ms)
+const CHUNK_SAMPLES: usize = 1_600;
+
+/// Turns the audio frames of a screen capturer into 16 kHz mono PCM chunks
+#[derive(Default)]
+pub struct SystemAudioChunker {
+    /// Input rate and channel count the resampler was built for
+    resampler: Option<(u32, u16, Resampler)>,
+    converted: Vec<f32>,
+    pending: Vec<f32>,
+}
+
+impl SystemAudioChunker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a captured frame, returning the 16-bit little-endian chunks it completed
+    pub fn push(&mut self, frame: &AudioFrame) -> Vec<Vec<u8>> {
+        let channels = frame.channels().max(1);
+        let rate = frame.rate();
+        if !matches!(&self.resampler, Some((r, c, _)) if (*r, *c) == (rate, channels)) {
+            // Frames without a sample rate can't be resampled
+            let Some(resampler) = Resampler::new(rate, channels, SYSTEM_AUDIO_SAMPLE_RATE) else {
+                return Vec::new();
+            };
+            self.resampler = Some((rate, channels, resampler));
+        }
+
+        self.converted.clear();
+        interleaved_samples(frame, &mut self.converted);
+        if let Some((_, _, resampler)) = self.resampler.as_mut() {
+            resampler.process(&self.converted, &mut self.pending);
+        }
+
+        let mut chunks = Vec::new();
+        while self.pending.len() >= CHUNK_SAMPLES {
+            chunks.push(f32_to_pcm16(self.pending.drain(..CHUNK_SAMPLES)));
+        }
+        chunks
+    }
+}
+
+/// Interleaved `f32` samples of a frame; planar frames are interleaved here
+fn interleaved_samples(frame: &AudioFrame, out: &mut Vec<f32>) {
+    let channels = frame.channels().max(1) as usize;
+    let samples = frame.sample_count();
+    let decode: fn(&[u8]) -> f32 = match frame.format() {
+        AudioFormat::F32 => |bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        AudioFormat::I16 => |bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+        AudioFormat::I32 => |bytes| {
+            i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2147483648.0
+        },
+        // ScreenCaptureKit only delivers float samples
+        _ => return,
+    };
+    let size = frame.format().sample_size();
+
+    // scap concatenates ScreenCaptureKit's per-channel buffers, so stereo system audio
+    // arrives as one plane per channel even though the frame claims to be interleaved
+    let planar = frame.is_planar() || cfg!(target_os = "macos");
+    let data = frame.raw_data();
+    if data.len() < samples * channels * size {
+        return;
+    }
+    out.reserve(samples * channels);
+    for sample in 0..samples {
+        for channel in 0..channels {
+            let index = if planar {
+                channel * samples + sample
+            } else {
+                sample * channels + channel
+            };
+            out.push(decode(&data[index * size..(index + 1) * size]));
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use crate::ResponsePrinter;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Frames whose timings a Telemetry keeps unless configured otherwise
+pub const DEFAULT_TELEMETRY_FRAMES: usize = 256;
+
+/// A step of the pipeline between capturing a frame and the model's answer about it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Waiting for the frame source to deliver the frame
+    CaptureWait,
+    /// Reordering BGRA pixels to what the encoder takes
+    Convert,
+    Encode,
+    /// Base64-encoding the image into the turn
+    Base64,
+    /// Writing the turn to the socket
+    Send,
+    /// From sending the turn to the first part of the answer
+    FirstToken,
+    /// From sending the turn to the end of the answer
+    Complete,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 7] = [
+        Stage::CaptureWait,
+        Stage::Convert,
+        Stage::Encode,
+        Stage::Base64,
+        Stage::Send,
+        Stage::FirstToken,
+        Stage::Complete,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::CaptureWait => "capture_wait",
+            Stage::Convert => "convert",
+            Stage::Encode => "encode",
+            Stage::Base64 => "base64",
+            Stage::Send => "send",
+            Stage::FirstToken => "first_token",
+            Stage::Complete => "complete",
+        }
+    }
+
+    /// Index into `ALL`, which lists the stages in declaration order
+    fn position(self) -> usize {
+        self as usize
+    }
+}
+
+/// How long each stage took for one frame; stages the frame didn't go through are `None`,
+/// e.g. the model stages of a frame skipped as unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTimings {
+    pub frame_seq: usize,
+    stages: [Option<Duration>; Stage::ALL.len()],
+}
+
+impl FrameTimings {
+    pub fn new(frame_seq: usize) -> Self {
+        Self {
+            frame_seq,
+            stages: [None; Stage::ALL.len()],
+        }
+    }
+
+    pub fn get(&self, stage: Stage) -> Option<Duration> {
+        self.stages[stage.position()]
+    }
+
+    pub fn set(&mut self, stage: Stage, duration: Duration) {
+        self.stages[stage.position()] = Some(duration);
+    }
+}
+
+impl fmt::Display for FrameTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "frame {}:", self.frame_seq)?;
+        for stage in Stage::ALL {
+            if let Some(duration) = self.get(stage) {
+                write!(f, " {} {:.1}ms", stage.as_str(), millis(duration))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Distribution of one stage's timings over the frames kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageSummary {
+    pub stage: Stage,
+    pub count: usize,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub max: Duration,
+}
+
+/// Per-stage summary of the frames a Telemetry kept, in pipeline order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TelemetrySummary {
+    pub frames: usize,
+    /// Only stages with at least one timing
+    pub stages: Vec<StageSummary>,
+}
+
+impl fmt::Display for TelemetrySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Stage timings over {} frames (ms):", self.frames)?;
+        writeln!(
+            f,
+            "{:<12} {:>6} {:>9} {:>9} {:>9} {:>9}",
+            "stage", "count", "mean", "p50", "p95", "max"
+        )?;
+        for stage in &self.stages {
+            writeln!(
+                f,
+                "{:<12} {:>6} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+                stage.stage.as_str(),
+                stage.count,
+                millis(stage.mean),
+                millis(stage.p50),
+                millis(stage.p95),
+                millis(stage.max)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Records how long every pipeline stage takes per frame, so latency can be traced to the
+/// stage it goes to
+///
+/// Shared between the CaptureSession, which times capture, encoding and sending, and the
+/// OutputProcessor, which times the model's answers.
+pub struct Telemetry {
+    /// Oldest first
+    frames: parking_lot::Mutex<VecDeque<FrameTimings>>,
+    capacity: usize,
+    log: Option<Arc<dyn ResponsePrinter>>,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self::new(DEFAULT_TELEMETRY_FRAMES)
+    }
+}
+
+impl Telemetry {
+    /// Keeps the timings of the `capacity` most recent frames
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: parking_lot::Mutex::new(VecDeque::new()),
+            capacity: capacity.max(1),
+            log: None,
+        }
+    }
+
+    /// Also prints each frame's timings through `printer` once its answer is complete
+    pub fn with_logging(mut self, printer: Arc<dyn ResponsePrinter>) -> Self {
+        self.log = Some(printer);
+        self
+    }
+
+    pub fn record(&self, frame_seq: usize, stage: Stage, duration: Duration) {
+        let mut frames = self.frames.lock();
+        let timings = match frames.iter().rposition(|t| t.frame_seq == frame_seq) {
+            Some(position) => &mut frames[position],
+            None => {
+                if frames.len() == self.capacity {
+                    frames.pop_front();
+                }
+                frames.push_back(FrameTimings::new(frame_seq));
+                let last = frames.len() - 1;
+                &mut frames[last]
+            }
+        };
+        timings.set(stage, duration);
+        if let Some(printer) = &self.log
+            && stage == Stage::Complete
+        {
+            printer.print_status(&format!("⏱️ {}", timings));
+        }
+    }
+
+    /// Timings of frame `frame_seq`, if it is still kept
+    pub fn frame(&self, frame_seq: usize) -> Option<FrameTimings> {
+        let frames = self.frames.lock();
+        frames
+            .iter()
+            .rev()
+            .find(|t| t.frame_seq == frame_seq)
+            .copied()
+    }
+
+    pub fn summary(&self) -> TelemetrySummary {
+        let frames = self.frames.lock();
+        let stages = Stage::ALL
+            .into_iter()
+            .filter_map(|stage| {
+                let mut durations: Vec<Duration> =
+                    frames.iter().filter_map(|t| t.get(stage)).collect();
+                if durations.is_empty() {
+                    return None;
+                }
+                durations.sort();
+                let count = durations.len();
+                let total: Duration = durations.iter().sum();
+                let percentile = |p: usize| durations[((count - 1) * p).div_ceil(100)];
+                Some(StageSummary {
+                    stage,
+                    count,
+                    mean: total / count as u32,
+                    p50: percentile(50),
+                    p95: percentile(95),
+                    max: durations[count - 1],
+                })
+            })
+            .collect();
+        TelemetrySummary {
+            frames: frames.len(),
+            stages,
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,265 @@
+use crate::{StoredFrame, FRAME_INDEX_FILE};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::imageops::FilterType;
+use image::{Delay, Frame, RgbaImage};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TimelapseError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Failed to process frame: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Failed to encode WebP frame: {0}")]
+    WebP(#[from] image_webp::EncodingError),
+    #[error("No frames found in {0}")]
+    NoFrames(PathBuf),
+}
+
+pub type TimelapseResult<T> = std::result::Result<T, TimelapseError>;
+
+/// Animated image container a timelapse is written as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimelapseFormat {
+    #[default]
+    Gif,
+    /// Lossless animated WebP; larger than GIF but without palette banding
+    WebP,
+}
+
+impl TimelapseFormat {
+    /// Picks the format from a `.gif` or `.webp` file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for TimelapseFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "gif" => Ok(TimelapseFormat::Gif),
+            "webp" => Ok(TimelapseFormat::WebP),
+            other => Err(format!(
+                "unknown timelapse format '{}' (expected gif or webp)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimelapseOptions {
+    pub format: TimelapseFormat,
+    /// How long each frame is shown
+    pub frame_delay: Duration,
+    /// Longer side of the animation; frames are never upscaled
+    pub max_edge: Option<u32>,
+    /// Use every n-th saved frame
+    pub step: usize,
+}
+
+impl Default for TimelapseOptions {
+    fn default() -> Self {
+        Self {
+            format: TimelapseFormat::Gif,
+            frame_delay: Duration::from_millis(200),
+            max_edge: Some(640),
+            step: 1,
+        }
+    }
+}
+
+/// Written animation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelapseReport {
+    pub frames: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Saved frames of an output directory in capture order: the index of a store session, or
+/// the `frame_NNNN.jpg` and `frame_<session>_NNNN.jpg` files of the flat layout
+pub fn timelapse_frames<P: AsRef<Path>>(dir: P) -> io::Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    let index = dir.join(FRAME_INDEX_FILE);
+    if index.is_file() {
+        let mut frames = Vec::new();
+        for line in BufReader::new(File::open(index)?).lines() {
+            // Skip entries torn by a crash mid-write rather than failing the export
+            if let Ok(entry) = serde_json::from_str::<StoredFrame>(&line?) {
+                frames.push(dir.join(entry.file));
+            }
+        }
+        return Ok(frames);
+    }
+
+    // Session names sort in time order, and frames without one are from before sessions
+    let mut frames: Vec<((String, u64), PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            let stem = name.strip_prefix("frame_")?.strip_suffix(".jpg")?;
+            let (session, sequence) = stem.rsplit_once('_').unwrap_or(("", stem));
+            Some(((session.to_string(), sequence.parse().ok()?), path))
+        })
+        .collect();
+    frames.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(frames.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Loads a frame scaled to the animation canvas; the first frame decides the canvas size
+fn load_frame(
+    path: &Path,
+    canvas: &mut Option<(u32, u32)>,
+    max_edge: Option<u32>,
+) -> TimelapseResult<RgbaImage> {
+    let image = image::open(path)?.to_rgba8();
+    let (width, height) = *canvas.get_or_insert_with(|| {
+        let (width, height) = image.dimensions();
+        let longest = width.max(height).max(1);
+        match max_edge {
+            Some(edge) if edge < longest => (
+                (width as u64 * edge as u64 / longest as u64).max(1) as u32,
+                (height as u64 * edge as u64 / longest as u64).max(1) as u32,
+            ),
+            _ => (width, height),
+        }
+    });
+    if image.dimensions() == (width, height) {
+        return Ok(image);
+    }
+    Ok(image::imageops::resize(
+        &image,
+        width,
+        height,
+        FilterType::Triangle,
+    ))
+}
+
+/// Assembles the saved frames of `dir` into an animated GIF or WebP at `output`
+pub fn export_timelapse(
+    dir: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: TimelapseOptions,
+) -> TimelapseResult<TimelapseReport> {
+    let dir = dir.as_ref();
+    let paths: Vec<PathBuf> = timelapse_frames(dir)?
+        .into_iter()
+        .step_by(options.step.max(1))
+        .collect();
+    if paths.is_empty() {
+        return Err(TimelapseError::NoFrames(dir.to_path_buf()));
+    }
+
+    let mut canvas = None;
+    let mut frames = paths
+        .iter()
+        .map(|path| load_frame(path, &mut canvas, options.max_edge));
+    let writer = BufWriter::new(File::create(output)?);
+    let count = match options.format {
+        TimelapseFormat::Gif => write_gif(writer, &mut frames, options.frame_delay)?,
+        TimelapseFormat::WebP => write_webp(writer, &mut frames, options.frame_delay)?,
+    };
+    let (width, height) = canvas.unwrap_or_default();
+    Ok(TimelapseReport {
+        frames: count,
+        width,
+        height,
+    })
+}
+
+fn write_gif(
+    writer: impl Write,
+    frames: &mut dyn Iterator<Item = TimelapseResult<RgbaImage>>,
+    frame_delay: Duration,
+) -> TimelapseResult<usize> {
+    let mut encoder = GifEncoder::new_with_speed(writer, 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+    let delay = Delay::from_saturating_duration(frame_delay);
+    let mut count = 0;
+    for frame in frames {
+        encoder.encode_frame(Frame::from_parts(frame?, 0, 0, delay))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Appends a RIFF chunk, padded to an even length
+fn push_chunk(out: &mut Vec<u8>, name: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(name);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Little-endian 24-bit field of the WebP extended format
+fn push_u24(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes()[..3]);
+}
+
+/// Writes an animated WebP: every frame is encoded lossless on its own and wrapped in an
+/// ANMF chunk, since the encoder only produces still images
+fn write_webp(
+    mut writer: impl Write,
+    frames: &mut dyn Iterator<Item = TimelapseResult<RgbaImage>>,
+    frame_delay: Duration,
+) -> TimelapseResult<usize> {
+    let duration = frame_delay.as_millis().min(0xFF_FFFF) as u32;
+    let mut body = Vec::new();
+    let mut canvas = (0, 0);
+    let mut count = 0;
+    for frame in frames {
+        let frame = image::DynamicImage::ImageRgba8(frame?).to_rgb8();
+        let (width, height) = frame.dimensions();
+        canvas = (width, height);
+
+        let mut still = Vec::new();
+        image_webp::WebPEncoder::new(&mut still).encode(
+            frame.as_raw(),
+            width,
+            height,
+            image_webp::ColorType::Rgb8,
+        )?;
+        // Simple format: "RIFF" size "WEBP" followed by the VP8L chunk
+        let bitstream = still.get(12..).unwrap_or_default();
+
+        let mut anmf = Vec::with_capacity(16 + bitstream.len());
+        push_u24(&mut anmf, 0);
+        push_u24(&mut anmf, 0);
+        push_u24(&mut anmf, width - 1);
+        push_u24(&mut anmf, height - 1);
+        push_u24(&mut anmf, duration);
+        // Frames cover the whole canvas: no blending, no disposal
+        anmf.push(0b10);
+        anmf.extend_from_slice(bitstream);
+        push_chunk(&mut body, b"ANMF", &anmf);
+        count += 1;
+    }
+
+    let mut vp8x = Vec::with_capacity(10);
+    // Animation flag
+    vp8x.extend_from_slice(&[0b10, 0, 0, 0]);
+    push_u24(&mut vp8x, canvas.0.saturating_sub(1));
+    push_u24(&mut vp8x, canvas.1.saturating_sub(1));
+    let mut header = Vec::new();
+    push_chunk(&mut header, b"VP8X", &vp8x);
+    // Black background, loop forever
+    push_chunk(&mut header, b"ANIM", &[0, 0, 0, 0xFF, 0, 0]);
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&((4 + header.len() + body.len()) as u32).to_le_bytes())?;
+    writer.write_all(b"WEBP")?;
+    writer.write_all(&header)?;
+    writer.write_all(&body)?;
+    writer.flush()?;
+    Ok(count)
+}
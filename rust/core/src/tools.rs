@@ -0,0 +1,46 @@
+use crate::{FunctionCall, FunctionResponse, ToolResponse};
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Local implementation of one or more functions the model may call
+pub trait ToolHandler: Send + Sync {
+    /// Function declarations advertised in the session setup
+    fn declarations(&self) -> Vec<Value>;
+
+    /// Runs `call`, returning `None` when the function is not handled here
+    fn handle<'a>(&'a self, call: &'a FunctionCall) -> BoxFuture<'a, Option<Value>>;
+}
+
+/// Builds the `tools` entry of a session setup from the registered handlers
+pub fn tool_declarations(handlers: &[Arc<dyn ToolHandler>]) -> Vec<Value> {
+    let declarations: Vec<Value> = handlers
+        .iter()
+        .flat_map(|handler| handler.declarations())
+        .collect();
+    if declarations.is_empty() {
+        return Vec::new();
+    }
+    vec![json!({ "functionDeclarations": declarations })]
+}
+
+/// Answers every call of a tool-call message, reporting unknown functions back to the model
+pub async fn dispatch_tool_calls(
+    handlers: &[Arc<dyn ToolHandler>],
+    calls: &[FunctionCall],
+) -> ToolResponse {
+    let mut function_responses = Vec::with_capacity(calls.len());
+    for call in calls {
+        let mut response = None;
+        for handler in handlers {
+            response = handler.handle(call).await;
+            if response.is_some() {
+                break;
+            }
+        }
+        let response = response
+            .unwrap_or_else(|| json!({ "error": format!("Unknown function: {}", call.name) }));
+        function_responses.push(FunctionResponse::new(&call.id, &call.name, response));
+    }
+    ToolResponse { function_responses }
+}
@@ -1,6 +1,7 @@
 use std::fs;
 use std::io;
 use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Ensures a directory exists and is empty.
 /// If the directory exists, all its contents are removed.
@@ -18,3 +19,115 @@ pub fn ensure_clean_directory<P: AsRef<Path>>(path: P) -> io::Result<()> {
 
     Ok(())
 }
+
+/// Local wall-clock time as HH:MM
+pub fn clock_time(time: SystemTime) -> String {
+    match local_time(time) {
+        Some(local) => format!("{:02}:{:02}", local.tm_hour, local.tm_min),
+        None => "--:--".to_string(),
+    }
+}
+
+/// Local date and time as YYYYMMDD-HHMMSS, which sorts in time order, e.g. to name a session
+pub fn session_stamp(time: SystemTime) -> String {
+    match local_time(time) {
+        Some(local) => format!(
+            "{:04}{:02}{:02}-{:02}{:02}{:02}",
+            local.tm_year + 1900,
+            local.tm_mon + 1,
+            local.tm_mday,
+            local.tm_hour,
+            local.tm_min,
+            local.tm_sec
+        ),
+        None => time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            .to_string(),
+    }
+}
+
+/// Local date and time as YYYY-MM-DD HH:MM:SS, which spreadsheets read as a date
+pub fn date_time(time: SystemTime) -> String {
+    match local_time(time) {
+        Some(local) => format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            local.tm_year + 1900,
+            local.tm_mon + 1,
+            local.tm_mday,
+            local.tm_hour,
+            local.tm_min,
+            local.tm_sec
+        ),
+        None => String::new(),
+    }
+}
+
+/// Reads YYYY-MM-DD, YYYY-MM-DD HH:MM or YYYY-MM-DD HH:MM:SS as local time (a T may
+/// separate the date and time), or milliseconds since the Unix epoch
+pub fn parse_local_time(text: &str) -> Option<SystemTime> {
+    let text = text.trim();
+    if !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit()) {
+        return Some(UNIX_EPOCH + Duration::from_millis(text.parse().ok()?));
+    }
+    let (date, clock) = match text.split_once([' ', 'T']) {
+        Some((date, clock)) => (date, Some(clock)),
+        None => (text, None),
+    };
+    let mut date = date.splitn(3, '-').map(str::parse::<i32>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut clock = clock.unwrap_or("0:0").splitn(3, ':').map(str::parse::<i32>);
+    let (hour, minute) = (clock.next()?.ok()?, clock.next()?.ok()?);
+    let second = clock.next().transpose().ok()?.unwrap_or(0);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || !(0..24).contains(&hour)
+        || !(0..60).contains(&minute)
+        || !(0..=60).contains(&second)
+    {
+        return None;
+    }
+
+    let mut local: libc::tm = unsafe { std::mem::zeroed() };
+    local.tm_year = year - 1900;
+    local.tm_mon = month - 1;
+    local.tm_mday = day;
+    local.tm_hour = hour;
+    local.tm_min = minute;
+    local.tm_sec = second;
+    // Lets mktime work out whether daylight saving time applies
+    local.tm_isdst = -1;
+    let seconds = unsafe { libc::mktime(&mut local) };
+    u64::try_from(seconds)
+        .ok()
+        .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Start of the local calendar day `time` falls on
+pub fn local_midnight(time: SystemTime) -> SystemTime {
+    match local_time(time) {
+        Some(local) => {
+            let since_midnight =
+                local.tm_hour as u64 * 3600 + local.tm_min as u64 * 60 + local.tm_sec as u64;
+            let seconds = time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            UNIX_EPOCH + Duration::from_secs(seconds.saturating_sub(since_midnight))
+        }
+        None => time,
+    }
+}
+
+pub(crate) fn local_time(time: SystemTime) -> Option<libc::tm> {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as libc::time_t;
+    let mut local = std::mem::MaybeUninit::<libc::tm>::uninit();
+    if unsafe { libc::localtime_r(&seconds, local.as_mut_ptr()) }.is_null() {
+        return None;
+    }
+    Some(unsafe { local.assume_init() })
+}
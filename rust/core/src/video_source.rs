@@ -0,0 +1,271 @@
+use crate::replay::Pacer;
+use crate::{CaptureResult, FrameProducer, FrameSource, ProducedFrame};
+use std::path::Path;
+use std::time::Duration;
+
+/// Recording time between sampled frames unless configured otherwise
+pub const DEFAULT_VIDEO_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Pause between emitted frames unless configured otherwise
+pub const DEFAULT_VIDEO_PACE: Duration = Duration::from_secs(1);
+
+/// Feeds a screen recording, e.g. an MP4 or MOV saved by QuickTime, through the analysis
+/// pipeline as if it were being captured
+///
+/// Every frame is decoded, but only the first one of each sample interval of recording time
+/// is analyzed; the frames in between are dropped on purpose, so an hour of video can be
+/// analyzed in minutes. A zero interval analyzes every frame. Sampled frames are emitted at
+/// most once every pace interval of wall-clock time, and none is skipped: the next one
+/// waits until the consumer has taken the previous one.
+pub struct VideoFrameSource {
+    reader: platform::Reader,
+    sample_interval: Duration,
+    next_sample: Duration,
+    pacer: Pacer,
+}
+
+impl VideoFrameSource {
+    pub fn open<P: AsRef<Path>>(path: P) -> CaptureResult<Self> {
+        Ok(Self {
+            reader: platform::Reader::open(path.as_ref())?,
+            sample_interval: DEFAULT_VIDEO_SAMPLE_INTERVAL,
+            next_sample: Duration::ZERO,
+            pacer: Pacer::new(DEFAULT_VIDEO_PACE),
+        })
+    }
+
+    /// Recording time between sampled frames
+    pub fn with_sample_interval(mut self, interval: Duration) -> Self {
+        self.sample_interval = interval;
+        self
+    }
+
+    /// Wall-clock time between emitted frames
+    pub fn with_pace(mut self, pace: Duration) -> Self {
+        self.pacer = Pacer::new(pace);
+        self
+    }
+
+    /// Length of the recording
+    pub fn duration(&self) -> Duration {
+        self.reader.duration()
+    }
+
+    /// Frames the recording yields at the sample interval
+    pub fn sample_count(&self) -> usize {
+        if self.sample_interval.is_zero() {
+            return usize::MAX;
+        }
+        (self.duration().as_secs_f64() / self.sample_interval.as_secs_f64()).floor() as usize + 1
+    }
+
+    /// Starts decoding on a FrameSource, which stops at the end of the recording
+    pub fn into_frame_source(self) -> FrameSource {
+        FrameSource::from_producer(self)
+    }
+}
+
+impl FrameProducer for VideoFrameSource {
+    fn lossless(&self) -> bool {
+        true
+    }
+
+    fn next_frame(&mut self) -> Option<ProducedFrame> {
+        loop {
+            let pts = match self.reader.advance() {
+                Ok(Some(pts)) => pts,
+                Ok(None) => return None,
+                Err(e) => {
+                    eprintln!("❌ Stopped reading the video: {}", e);
+                    return None;
+                }
+            };
+            // Every frame is decoded, but only the sampled ones are copied out
+            if pts < self.next_sample {
+                continue;
+            }
+            self.next_sample = pts + self.sample_interval;
+
+            self.pacer.wait();
+            return Some(match self.reader.frame() {
+                Ok(frame) => ProducedFrame::Video(frame),
+                Err(e) => {
+                    eprintln!("❌ Skipping the frame at {:.1}s: {}", pts.as_secs_f64(), e);
+                    ProducedFrame::Skipped
+                }
+            });
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use crate::{CaptureError, CaptureResult, FrameData};
+    use cidre::objc::Obj;
+    use cidre::{arc, av, cm, cv, ns};
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[link(name = "CoreVideo", kind = "framework")]
+    unsafe extern "C" {
+        fn CVPixelBufferGetBaseAddress(pixel_buffer: &cv::PixelBuf) -> *const u8;
+        fn CVPixelBufferGetBytesPerRow(pixel_buffer: &cv::PixelBuf) -> usize;
+    }
+
+    fn reader_error(error: impl std::fmt::Debug) -> CaptureError {
+        CaptureError::FrameError(format!("{:?}", error))
+    }
+
+    /// AVAssetReader decoding the first video track to BGRA
+    pub struct Reader {
+        _reader: arc::R<av::AssetReader>,
+        output: arc::R<av::AssetReaderTrackOutput>,
+        duration: Duration,
+        sample: Option<arc::R<cm::SampleBuf>>,
+    }
+
+    impl Reader {
+        pub fn open(path: &Path) -> CaptureResult<Self> {
+            if !path.is_file() {
+                return Err(CaptureError::TargetNotFound(path.display().to_string()));
+            }
+            let path = path.to_str().ok_or_else(|| {
+                CaptureError::FrameError(format!("path is not valid UTF-8: {}", path.display()))
+            })?;
+            let url = ns::Url::with_fs_path_str(path, false);
+            let asset = av::UrlAsset::with_url(&url, None)
+                .ok_or_else(|| CaptureError::FrameError(format!("cannot open {}", path)))?;
+            let tracks = futures::executor::block_on(
+                asset.load_tracks_with_media_type(av::MediaType::video()),
+            )
+            .map_err(reader_error)?;
+            let track = tracks.iter().next().ok_or_else(|| {
+                CaptureError::TargetNotFound(format!("no video track in {}", path))
+            })?;
+
+            let settings = ns::Dictionary::with_keys_values(
+                &[cv::pixel_buffer_keys::pixel_format().as_ns()],
+                &[cv::PixelFormat::_32_BGRA.to_ns_number().as_id_ref()],
+            );
+            let mut output = av::AssetReaderTrackOutput::with_track(track, Some(&settings))
+                .map_err(reader_error)?;
+            // Sample buffers are only read, so the decoder's own can be handed out
+            output.set_always_copies_sample_data(false);
+            let mut reader = av::AssetReader::with_asset(&asset).map_err(reader_error)?;
+            reader.add_output(&output).map_err(reader_error)?;
+            if !reader.start_reading().map_err(reader_error)? {
+                return Err(reader_error(reader.error()));
+            }
+
+            let duration = asset.duration();
+            let duration = if duration.is_valid() {
+                Duration::from_secs_f64(duration.as_secs().max(0.0))
+            } else {
+                Duration::ZERO
+            };
+            Ok(Self {
+                _reader: reader,
+                output,
+                duration,
+                sample: None,
+            })
+        }
+
+        pub fn duration(&self) -> Duration {
+            self.duration
+        }
+
+        /// Decodes the next frame, returning its presentation time, or `None` at the end
+        pub fn advance(&mut self) -> CaptureResult<Option<Duration>> {
+            loop {
+                let Some(sample) = self.output.next_sample_buf().map_err(reader_error)? else {
+                    self.sample = None;
+                    return Ok(None);
+                };
+                // Buffers without an image only carry timing or metadata
+                if sample.image_buf().is_none() {
+                    continue;
+                }
+                let pts = sample.pts();
+                let pts = if pts.is_valid() {
+                    Duration::from_secs_f64(pts.as_secs().max(0.0))
+                } else {
+                    Duration::ZERO
+                };
+                self.sample = Some(sample);
+                return Ok(Some(pts));
+            }
+        }
+
+        /// Copies out the frame decoded last
+        pub fn frame(&mut self) -> CaptureResult<FrameData> {
+            let buffer = self
+                .sample
+                .as_mut()
+                .and_then(|sample| sample.image_buf_mut())
+                .ok_or(CaptureError::NoFrameAvailable)?;
+            let (width, height) = (buffer.width(), buffer.height());
+            unsafe {
+                buffer
+                    .lock_base_addr(cv::pixel_buffer::LockFlags::READ_ONLY)
+                    .result()
+                    .map_err(reader_error)?;
+            }
+            let base_address = unsafe { CVPixelBufferGetBaseAddress(buffer) };
+            let bytes_per_row = unsafe { CVPixelBufferGetBytesPerRow(buffer) };
+            let mut data = Vec::with_capacity(width * height * 4);
+            if !base_address.is_null() {
+                for row in 0..height {
+                    // Rows may be padded past the visible width
+                    let row = unsafe {
+                        std::slice::from_raw_parts(base_address.add(row * bytes_per_row), width * 4)
+                    };
+                    data.extend_from_slice(row);
+                }
+            }
+            unsafe {
+                buffer.unlock_lock_base_addr(cv::pixel_buffer::LockFlags::READ_ONLY);
+            }
+            if data.is_empty() {
+                return Err(CaptureError::NoFrameAvailable);
+            }
+            Ok(FrameData {
+                width: width as u32,
+                height: height as u32,
+                data,
+                display_id: None,
+                cursor: None,
+                active_window: None,
+            })
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use crate::{CaptureError, CaptureResult, FrameData};
+    use std::path::Path;
+    use std::time::Duration;
+
+    pub struct Reader;
+
+    impl Reader {
+        pub fn open(_path: &Path) -> CaptureResult<Self> {
+            Err(CaptureError::FrameError(
+                "Video files can only be read on macOS".to_string(),
+            ))
+        }
+
+        pub fn duration(&self) -> Duration {
+            Duration::ZERO
+        }
+
+        pub fn advance(&mut self) -> CaptureResult<Option<Duration>> {
+            Ok(None)
+        }
+
+        pub fn frame(&mut self) -> CaptureResult<FrameData> {
+            Err(CaptureError::NoFrameAvailable)
+        }
+    }
+}
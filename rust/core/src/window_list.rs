@@ -0,0 +1,99 @@
+use crate::Rect;
+
+/// On-screen window as reported by the window server
+#[derive(Debug, Clone)]
+pub struct WindowInfo {
+    pub id: u32,
+    pub pid: u32,
+    pub app_name: String,
+    /// Only available once screen recording permission has been granted
+    pub title: Option<String>,
+    /// Window level; regular application windows are on layer 0
+    pub layer: i32,
+    /// Global display coordinates in points
+    pub bounds: Rect,
+}
+
+impl WindowInfo {
+    /// Returns true for regular application windows (not menus, panels or overlays)
+    pub fn is_normal(&self) -> bool {
+        self.layer == 0
+    }
+}
+
+/// Lists the windows currently on screen, front to back
+#[cfg(target_os = "macos")]
+pub fn list_windows() -> Vec<WindowInfo> {
+    use core_foundation::base::{CFType, CFTypeRef, TCFType};
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::number::CFNumber;
+    use core_foundation::string::CFString;
+    use core_graphics::window::{
+        copy_window_info, kCGNullWindowID, kCGWindowBounds, kCGWindowLayer,
+        kCGWindowListExcludeDesktopElements, kCGWindowListOptionOnScreenOnly, kCGWindowName,
+        kCGWindowNumber, kCGWindowOwnerName, kCGWindowOwnerPID,
+    };
+    use std::os::raw::c_void;
+
+    fn value(dict: &CFDictionary, key: *const c_void) -> Option<CFType> {
+        dict.find(key)
+            .map(|value| unsafe { CFType::wrap_under_get_rule(*value as CFTypeRef) })
+    }
+
+    fn number(dict: &CFDictionary, key: *const c_void) -> Option<f64> {
+        value(dict, key)?.downcast::<CFNumber>()?.to_f64()
+    }
+
+    fn string(dict: &CFDictionary, key: *const c_void) -> Option<String> {
+        let value = value(dict, key)?.downcast::<CFString>()?.to_string();
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+
+    fn bounds(dict: &CFDictionary) -> Option<Rect> {
+        let bounds =
+            value(dict, unsafe { kCGWindowBounds } as *const c_void)?.downcast::<CFDictionary>()?;
+        let field = |name: &'static str| {
+            let key = CFString::from_static_string(name);
+            number(&bounds, key.as_concrete_TypeRef() as *const c_void)
+        };
+        Some(Rect {
+            x: field("X")?,
+            y: field("Y")?,
+            width: field("Width")?,
+            height: field("Height")?,
+        })
+    }
+
+    let options = kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements;
+    let Some(info) = copy_window_info(options, kCGNullWindowID) else {
+        return Vec::new();
+    };
+
+    info.iter()
+        .filter_map(|item| {
+            let dict: CFDictionary =
+                unsafe { CFDictionary::wrap_under_get_rule(*item as CFDictionaryRef) };
+            Some(WindowInfo {
+                id: number(&dict, unsafe { kCGWindowNumber } as *const c_void)? as u32,
+                pid: number(&dict, unsafe { kCGWindowOwnerPID } as *const c_void)? as u32,
+                app_name: string(&dict, unsafe { kCGWindowOwnerName } as *const c_void)
+                    .unwrap_or_default(),
+                title: string(&dict, unsafe { kCGWindowName } as *const c_void),
+                layer: number(&dict, unsafe { kCGWindowLayer } as *const c_void).unwrap_or(0.0)
+                    as i32,
+                bounds: bounds(&dict)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn list_windows() -> Vec<WindowInfo> {
+    Vec::new()
+}
+
+/// The frontmost regular window, which belongs to the active app
+pub fn frontmost_window() -> Option<WindowInfo> {
+    list_windows().into_iter().find(WindowInfo::is_normal)
+}
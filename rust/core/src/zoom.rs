@@ -0,0 +1,70 @@
+use crate::{FrameData, Point, Rect};
+
+/// System instruction used when narrating zoom-follow crops
+pub const ZOOM_NARRATION_INSTRUCTION: &str = "You are narrating a live software demo. \
+     Each image is a zoomed region around the presenter's mouse cursor. \
+     Describe what the presenter is pointing at or doing, as a short voice-over line (1 sentence).";
+
+/// Per-frame prompt used when narrating zoom-follow crops
+pub const ZOOM_NARRATION_PROMPT: &str =
+    "Narrate what the presenter is showing in this zoomed region.";
+
+/// Crops frames to a magnified region that follows the mouse cursor, like a presenter zoom
+#[derive(Debug, Clone)]
+pub struct ZoomFollow {
+    zoom: f64,
+    smoothing: f64,
+    center: Option<(f64, f64)>,
+}
+
+impl ZoomFollow {
+    /// Creates a zoom-follow tracker; `zoom` is the magnification (values below 1.0 are clamped)
+    pub fn new(zoom: f64) -> Self {
+        Self {
+            zoom: zoom.max(1.0),
+            smoothing: 0.5,
+            center: None,
+        }
+    }
+
+    /// Sets how much the crop lags behind the cursor (0.0 snaps instantly, 0.9 glides slowly)
+    pub fn with_smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing.clamp(0.0, 0.95);
+        self
+    }
+
+    /// Returns the configured magnification
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    /// Crops `frame` around `cursor`, mapping global `display` points into frame pixels
+    pub fn apply(&mut self, frame: &FrameData, cursor: Point, display: Rect) -> Option<FrameData> {
+        if display.width <= 0.0 || display.height <= 0.0 {
+            return None;
+        }
+
+        let scale_x = frame.width as f64 / display.width;
+        let scale_y = frame.height as f64 / display.height;
+        let target_x = (cursor.x - display.x) * scale_x;
+        let target_y = (cursor.y - display.y) * scale_y;
+
+        let (center_x, center_y) = match self.center {
+            Some((x, y)) => (
+                x + (target_x - x) * (1.0 - self.smoothing),
+                y + (target_y - y) * (1.0 - self.smoothing),
+            ),
+            None => (target_x, target_y),
+        };
+        self.center = Some((center_x, center_y));
+
+        let crop_width = ((frame.width as f64 / self.zoom).round() as u32).max(1);
+        let crop_height = ((frame.height as f64 / self.zoom).round() as u32).max(1);
+        let max_x = frame.width.saturating_sub(crop_width) as f64;
+        let max_y = frame.height.saturating_sub(crop_height) as f64;
+        let x = (center_x - crop_width as f64 / 2.0).clamp(0.0, max_x) as u32;
+        let y = (center_y - crop_height as f64 / 2.0).clamp(0.0, max_y) as u32;
+
+        frame.crop(x, y, crop_width, crop_height).ok()
+    }
+}
@@ -0,0 +1,118 @@
+mod server;
+
+use clap::Parser;
+use rmcp::transport::stdio;
+use rmcp::ServiceExt;
+use server::Privacy;
+use std::path::PathBuf;
+use std::sync::Arc;
+use watcher_core::{
+    AnonymizeMode, Anonymizer, AppExclusion, CategoryAnonymizer, HashAnonymizer, Passthrough,
+    RedactionRule, RedactionStyle, Redactor, WatcherConfig,
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Serve this machine's screen state to MCP clients over stdio",
+    version
+)]
+struct Cli {
+    /// Output directory of the capture binary, whose manifest holds the activity log
+    #[arg(long, default_value = "output")]
+    output_dir: PathBuf,
+
+    /// Black out the windows of apps whose name contains this text, e.g. 1Password;
+    /// repeatable, defaults to privacy.redact_apps in the config file
+    #[arg(long, value_name = "APP")]
+    redact_app: Vec<String>,
+
+    /// Black out windows whose title contains this text; repeatable, defaults to
+    /// privacy.redact_titles
+    #[arg(long, value_name = "TEXT")]
+    redact_title: Vec<String>,
+
+    /// How redacted areas are masked: black or pixelate
+    #[arg(long, default_value = "black")]
+    redact_style: RedactionStyle,
+
+    /// Refuse screenshots and leave out the windows of apps with this bundle ID or name;
+    /// repeatable, defaults to privacy.exclude_apps
+    #[arg(long, value_name = "APP")]
+    exclude_app: Vec<String>,
+
+    /// How app names and window titles are rewritten: passthrough, hash or category;
+    /// defaults to privacy.anonymize
+    #[arg(long)]
+    anonymize: Option<AnonymizeMode>,
+
+    /// Salt mixed into hashed names; defaults to privacy.anonymize_salt
+    #[arg(long)]
+    anonymize_salt: Option<String>,
+
+    /// JSON file with category rules used by --anonymize category
+    #[arg(long, value_name = "FILE")]
+    anonymize_categories: Option<PathBuf>,
+}
+
+impl Cli {
+    /// The privacy settings given on the command line, or else in the config file
+    fn privacy(self) -> Result<Privacy, Box<dyn std::error::Error>> {
+        let config = WatcherConfig::load()?.privacy;
+        let or_config = |args: Vec<String>, configured: Vec<String>| {
+            if args.is_empty() {
+                configured
+            } else {
+                args
+            }
+        };
+        let rules: Vec<RedactionRule> = or_config(self.redact_app, config.redact_apps)
+            .into_iter()
+            .map(RedactionRule::App)
+            .chain(
+                or_config(self.redact_title, config.redact_titles)
+                    .into_iter()
+                    .map(RedactionRule::Title),
+            )
+            .collect();
+        let exclude = or_config(self.exclude_app, config.exclude_apps);
+        let mode = match (self.anonymize, &config.anonymize) {
+            (Some(mode), _) => mode,
+            (None, Some(mode)) => mode
+                .parse()
+                .map_err(|e| format!("privacy.anonymize: {}", e))?,
+            (None, None) => AnonymizeMode::Passthrough,
+        };
+        let salt = self
+            .anonymize_salt
+            .or(config.anonymize_salt)
+            .unwrap_or_default();
+        let anonymizer: Arc<dyn Anonymizer> = match mode {
+            AnonymizeMode::Passthrough => Arc::new(Passthrough),
+            AnonymizeMode::Hash => Arc::new(HashAnonymizer::new(salt)),
+            AnonymizeMode::Category => match &self.anonymize_categories {
+                Some(path) => Arc::new(CategoryAnonymizer::from_file(path)?),
+                None => Arc::new(CategoryAnonymizer::default()),
+            },
+        };
+        Ok(Privacy {
+            redactor: (!rules.is_empty()).then(|| Redactor::new(rules, self.redact_style)),
+            exclusion: (!exclude.is_empty()).then(|| AppExclusion::new(exclude)),
+            anonymizer,
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Cli::parse();
+    let output_dir = args.output_dir.clone();
+    let privacy = args.privacy()?;
+
+    // stdout carries the protocol, so anything for people goes to stderr
+    eprintln!("🔌 Serving watcher tools over stdio");
+    let service = server::WatcherServer::new(output_dir, privacy)
+        .serve(stdio())
+        .await?;
+    service.waiting().await?;
+    Ok(())
+}
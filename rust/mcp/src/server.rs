@@ -0,0 +1,297 @@
+use base64::Engine;
+use rmcp::handler::server::router::tool::ToolRouter;
+use rmcp::handler::server::wrapper::Parameters;
+use rmcp::model::{CallToolResult, Content, Implementation, ServerCapabilities, ServerInfo};
+use rmcp::{schemars, tool, tool_handler, tool_router, ErrorData as McpError, ServerHandler};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use watcher_core::{
+    clock_time, display_bounds, encode_bgra_to_jpeg_bytes, frontmost_window, is_screen_locked,
+    list_windows, passthrough, read_manifest, user_idle_time, Anonymizer, AppExclusion,
+    ArchivedFrame, CaptureOptions, CaptureTarget, FrameData, FrameSource, Redactor, ResizeOptions,
+    ResizeTarget, WindowInfo,
+};
+
+/// JPEG quality of screenshots returned to clients
+const SCREENSHOT_QUALITY: u8 = 80;
+
+/// Entries `get_activity_log` returns when no limit is given
+const DEFAULT_LOG_LIMIT: usize = 20;
+
+#[derive(Debug, Default, Deserialize, schemars::JsonSchema)]
+pub struct ScreenshotParams {
+    /// Window server id of a window to capture, as listed by list_windows
+    pub window_id: Option<u32>,
+    /// CoreGraphics id of a display to capture; the main display when neither is given
+    pub display_id: Option<u32>,
+    /// Scale the screenshot down so its longer side is at most this many pixels
+    pub max_edge: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize, schemars::JsonSchema)]
+pub struct LogParams {
+    /// How many of the most recent entries to return
+    pub limit: Option<usize>,
+}
+
+/// What is kept from clients, configured like the capture binary: redacted windows are
+/// masked and unnamed, excluded apps leave nothing at all, and names are anonymized
+pub struct Privacy {
+    pub redactor: Option<Redactor>,
+    pub exclusion: Option<AppExclusion>,
+    pub anonymizer: Arc<dyn Anonymizer>,
+}
+
+impl Default for Privacy {
+    fn default() -> Self {
+        Self {
+            redactor: None,
+            exclusion: None,
+            anonymizer: passthrough(),
+        }
+    }
+}
+
+impl Privacy {
+    fn excludes(&self, window: &WindowInfo) -> bool {
+        self.exclusion
+            .as_ref()
+            .is_some_and(|exclusion| exclusion.excludes(window))
+    }
+
+    fn hides(&self, window: &WindowInfo) -> bool {
+        self.redactor
+            .as_ref()
+            .is_some_and(|redactor| redactor.hides(window))
+    }
+
+    /// Whether an excluded app is in front or on the display `frame` shows
+    fn blocks(&self, frame: &FrameData) -> bool {
+        let display = frame.display_id.and_then(display_bounds);
+        self.exclusion.as_ref().is_some_and(|exclusion| {
+            exclusion
+                .blocking_app(frame.active_window.as_ref(), display.as_slice())
+                .is_some()
+        })
+    }
+
+    /// Anonymized app name and window title; the title of a redacted window is left out
+    fn names(&self, window: &WindowInfo) -> (String, String) {
+        let title = match &window.title {
+            _ if self.hides(window) => "(redacted)".to_string(),
+            Some(title) => self.anonymizer.title(title),
+            None => "(untitled)".to_string(),
+        };
+        (self.anonymizer.app_name(&window.app_name), title)
+    }
+}
+
+/// Exposes what is on this machine's screen as MCP tools: screenshots on demand, the open
+/// windows, and the activity log the capture binary keeps in its output directory
+#[derive(Clone)]
+pub struct WatcherServer {
+    output_dir: PathBuf,
+    privacy: Arc<Privacy>,
+    tool_router: ToolRouter<Self>,
+}
+
+#[tool_router]
+impl WatcherServer {
+    pub fn new(output_dir: PathBuf, privacy: Privacy) -> Self {
+        Self {
+            output_dir,
+            privacy: Arc::new(privacy),
+            tool_router: Self::tool_router(),
+        }
+    }
+
+    #[tool(description = "Take a JPEG screenshot of the main display, another display or a window")]
+    async fn capture_screenshot(
+        &self,
+        Parameters(params): Parameters<ScreenshotParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let target = match (params.window_id, params.display_id) {
+            (Some(id), _) => CaptureTarget::Window(id),
+            (_, Some(id)) => CaptureTarget::DisplayId(id),
+            _ => CaptureTarget::Display,
+        };
+        if let CaptureTarget::Window(id) = target
+            && list_windows()
+                .iter()
+                .find(|window| window.id == id)
+                .is_some_and(|window| self.privacy.excludes(window) || self.privacy.hides(window))
+        {
+            return Err(McpError::invalid_params(
+                "This window is redacted and can't be captured",
+                None,
+            ));
+        }
+        let options = CaptureOptions::builder()
+            .target(target.clone())
+            // The highlight border would end up in the screenshot
+            .show_highlight(!target.is_window())
+            .build()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        let source = FrameSource::from_options(options)
+            .map_err(|e| McpError::internal_error(format!("Capture failed: {}", e), None))?;
+        // Windows have no known position on screen to mask
+        if !target.is_window() {
+            source.set_redactor(self.privacy.redactor.clone());
+        }
+        let frame = source.subscribe().next_frame().await;
+        // Stopping waits for the capturer to shut down
+        let _ = tokio::task::spawn_blocking(move || source.stop()).await;
+        let frame =
+            frame.map_err(|e| McpError::internal_error(format!("Capture failed: {}", e), None))?;
+        if self.privacy.blocks(&frame) {
+            return Err(McpError::invalid_request(
+                "An excluded app is on screen, no screenshot was taken",
+                None,
+            ));
+        }
+        let frame = match params.max_edge {
+            Some(edge) => ResizeOptions::new(ResizeTarget::MaxLongEdge(edge))
+                .apply(&frame)
+                .unwrap_or_else(|| (*frame).clone()),
+            None => (*frame).clone(),
+        };
+
+        let jpeg =
+            encode_bgra_to_jpeg_bytes(&frame.data, frame.width, frame.height, SCREENSHOT_QUALITY)
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        Ok(CallToolResult::success(vec![Content::image(
+            base64::engine::general_purpose::STANDARD.encode(jpeg),
+            "image/jpeg",
+        )]))
+    }
+
+    #[tool(description = "List the windows on screen, front to back, with their ids")]
+    async fn list_windows(&self) -> Result<CallToolResult, McpError> {
+        let windows: Vec<String> = list_windows()
+            .into_iter()
+            .filter(|window| window.is_normal() && !self.privacy.excludes(window))
+            .map(|window| {
+                let (app, title) = self.privacy.names(&window);
+                format!(
+                    "{} (pid {}): {} [window_id {}, {}x{} at {},{}]",
+                    app,
+                    window.pid,
+                    title,
+                    window.id,
+                    window.bounds.width,
+                    window.bounds.height,
+                    window.bounds.x,
+                    window.bounds.y
+                )
+            })
+            .collect();
+        let text = if windows.is_empty() {
+            "No windows on screen".to_string()
+        } else {
+            windows.join("\n")
+        };
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        description = "Describe what the user is doing right now: the frontmost app and window, \
+                       how long since their last input, and the latest description of the screen"
+    )]
+    async fn describe_current_activity(&self) -> Result<CallToolResult, McpError> {
+        let mut lines = Vec::new();
+        if is_screen_locked() == Some(true) {
+            lines.push("The screen is locked.".to_string());
+        }
+        match frontmost_window() {
+            Some(window) if self.privacy.excludes(&window) => {
+                lines.push("An excluded app is in front.".to_string())
+            }
+            Some(window) => {
+                let (app, title) = self.privacy.names(&window);
+                lines.push(format!("Frontmost app: {}, window: {}", app, title))
+            }
+            None => lines.push("No app window is in front.".to_string()),
+        }
+        if let Some(idle) = user_idle_time() {
+            lines.push(format!(
+                "Last keyboard or mouse input: {}s ago",
+                idle.as_secs()
+            ));
+        }
+        if let Some(latest) = self
+            .activity_log()
+            .ok()
+            .and_then(|frames| frames.into_iter().rev().find_map(describe_entry))
+        {
+            lines.push(format!("Latest description of the screen: {}", latest));
+        }
+        Ok(CallToolResult::success(vec![Content::text(
+            lines.join("\n"),
+        )]))
+    }
+
+    #[tool(
+        description = "The most recent entries of the activity log, one described screenshot per line"
+    )]
+    async fn get_activity_log(
+        &self,
+        Parameters(params): Parameters<LogParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let frames = self.activity_log().map_err(|e| {
+            McpError::internal_error(
+                format!("No activity log in {}: {}", self.output_dir.display(), e),
+                None,
+            )
+        })?;
+        let limit = params.limit.unwrap_or(DEFAULT_LOG_LIMIT);
+        let mut entries: Vec<String> = frames
+            .into_iter()
+            .rev()
+            .filter_map(describe_entry)
+            .take(limit)
+            .collect();
+        entries.reverse();
+        let text = if entries.is_empty() {
+            "The activity log is empty".to_string()
+        } else {
+            entries.join("\n")
+        };
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    fn activity_log(&self) -> watcher_core::StoreResult<Vec<ArchivedFrame>> {
+        read_manifest(&self.output_dir)
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for WatcherServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            server_info: Implementation::from_build_env(),
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            instructions: Some(
+                "Screen state of the user's Mac: take screenshots, list windows, and read \
+                 what the watcher has seen the user doing."
+                    .to_string(),
+            ),
+            ..Default::default()
+        }
+    }
+}
+
+/// Log line for a frame the model answered about, e.g. "14:05 Safari — Docs: reading..."
+fn describe_entry(archived: ArchivedFrame) -> Option<String> {
+    let response = archived.response?;
+    let frame = archived.frame;
+    let time = clock_time(UNIX_EPOCH + Duration::from_millis(frame.timestamp_ms));
+    let place = match (frame.app, frame.window_title) {
+        (Some(app), Some(title)) => format!(" {} — {}", app, title),
+        (Some(app), None) => format!(" {}", app),
+        _ => String::new(),
+    };
+    Some(format!("{}{}: {}", time, place, response.text.trim()))
+}
@@ -13,8 +13,7 @@ async fn main() -> Result<()> {
 
     let options = ConnectionOptions::builder()
         .api_key(api_key)
-        .build()
-        .expect("connection options builder should set all fields");
+        .build()?;
 
     let setup = Setup::builder("models/gemini-live-2.5-flash-preview")
         .system_instruction(Content::system(
@@ -24,8 +23,7 @@ async fn main() -> Result<()> {
             response_modalities: vec!["TEXT".to_string()],
             ..Default::default()
         })
-        .build()
-        .expect("setup builder should initialize required fields");
+        .build()?;
 
     let session = GeminiSession::connect(setup, options).await?;
     let sender = session.sender_handle();